@@ -0,0 +1,417 @@
+//! GraphQL subscriptions over API Gateway WebSocket API, graphql-transport-ws protocol
+//!
+//! Each Lambda invocation handles a single WebSocket frame, so the frame is driven through
+//! async-graphql's `WebSocket` after replaying the `connection_init` of the connection.
+//! The `connection_init` frame and the subscriptions are kept in the DynamoDB connection store,
+//! and the subscriptions are executed again for each published event,
+//! pushing results to the clients with ConnectionsClient.
+//!
+//! Subscription resolvers must end after yielding the items of the published event,
+//! as each invocation runs them to completion.
+//!
+//! Build with `cargo build --example graphql_ws --features graphql-ws-example`,
+//! and set `CONNECTIONS_TABLE` to the DynamoDB table of `DynamoDbConnectionStore`.
+use async_graphql::futures_util::stream::{self, Stream, StreamExt};
+use async_graphql::http::{Protocols, WebSocket, WsMessage};
+use async_graphql::{Context, Data, EmptyMutation, Object, Schema, Subscription};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::post;
+use axum::{Extension, Router};
+use lambda_web::{
+    run_hyper_on_lambda_with_options, AwsTransport, ConnectionStore, ConnectionsClient,
+    ConnectionsError, DynamoDbConnectionStore, LambdaError, RequestContext, RunOptions,
+    SignedRequest, StoreFuture, TransportResponse, WebSocketRoutes, WEBSOCKET_CONNECTION_ID_HEADER,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+struct Query;
+
+#[Object]
+impl Query {
+    async fn hello(&self) -> &str {
+        "Hello, World!"
+    }
+}
+
+/// Event published by `POST /publish`, given to the subscription resolvers
+struct Published(String);
+
+struct Subscription;
+
+#[Subscription]
+impl Subscription {
+    /// Published messages, one item per execution
+    async fn messages(&self, ctx: &Context<'_>) -> impl Stream<Item = String> {
+        let message = ctx.data_opt::<Published>().map(|p| p.0.clone());
+        stream::iter(message)
+    }
+}
+
+type AppSchema = Schema<Query, EmptyMutation, Subscription>;
+
+/// Accept `connection_init` with `{"token": ...}` payload matching `GRAPHQL_WS_TOKEN`, when set.
+/// Called again for each frame, as `connection_init` is replayed.
+async fn on_connection_init(payload: Value) -> async_graphql::Result<Data> {
+    if let Ok(token) = std::env::var("GRAPHQL_WS_TOKEN") {
+        if payload.get("token").and_then(Value::as_str) != Some(token.as_str()) {
+            return Err(async_graphql::Error::new("Forbidden"));
+        }
+    }
+    Ok(Data::default())
+}
+
+/// Frame from the client, only the fields used to keep the connection state
+#[derive(Default, Deserialize)]
+struct ClientFrame {
+    #[serde(rename = "type")]
+    kind: String,
+    id: Option<String>,
+    payload: Option<Value>,
+}
+
+/// State of the connection, saved in the connection store
+#[derive(Default, Serialize, Deserialize)]
+struct ConnectionState {
+    /// Accepted `connection_init` frame, replayed before each frame
+    init: Option<String>,
+    /// `subscribe` payloads by ID
+    subscriptions: BTreeMap<String, Value>,
+}
+
+impl ConnectionState {
+    async fn load(store: &dyn ConnectionStore, connection_id: &str) -> Result<Self, LambdaError> {
+        match store.load_state(connection_id).await? {
+            Some(state) => Ok(serde_json::from_value(state)?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    async fn save(
+        &self,
+        store: &dyn ConnectionStore,
+        connection_id: &str,
+    ) -> Result<(), LambdaError> {
+        store
+            .save_state(connection_id, &serde_json::to_value(self)?)
+            .await
+    }
+}
+
+/// JSON of a server message, null for `Close`
+fn server_message(message: &WsMessage) -> Value {
+    match message {
+        WsMessage::Text(text) => serde_json::from_str(text).unwrap_or_default(),
+        WsMessage::Close(_, _) => Value::Null,
+    }
+}
+
+/// Drive async-graphql's WebSocket with the frames, and collect the messages to the client.
+///
+/// Frames other than `complete` and `pong` are answered with one last message,
+/// `connection_ack`, `complete` (after `next` messages) or `pong`,
+/// unless the connection is closed, e.g. for invalid frames.
+async fn drive(schema: &AppSchema, frames: Vec<String>, data: Data) -> Vec<WsMessage> {
+    let mut unanswered = frames
+        .iter()
+        .filter(|frame| {
+            let frame = serde_json::from_str::<Value>(frame).unwrap_or_default();
+            !matches!(frame["type"].as_str(), Some("complete" | "pong"))
+        })
+        .count();
+    // Not closed by the end of frames, until the answers of subscriptions are taken
+    let frames = stream::iter(frames).chain(stream::pending());
+    let mut websocket = Box::pin(
+        WebSocket::new(schema.clone(), frames, Protocols::GraphQLWS)
+            .connection_data(data)
+            .on_connection_init(on_connection_init),
+    );
+    let mut messages = Vec::new();
+    while unanswered > 0 {
+        let message = match websocket.next().await {
+            Some(message) => message,
+            None => break,
+        };
+        match server_message(&message)["type"].as_str() {
+            Some("next") => {}
+            Some(_) => unanswered -= 1,
+            // Closed
+            None => unanswered = 0,
+        }
+        messages.push(message);
+    }
+    messages
+}
+
+/// Replay `connection_init` of the connection before the frame,
+/// returns messages answering the frame
+async fn replay(
+    schema: &AppSchema,
+    state: &ConnectionState,
+    frame: String,
+    data: Data,
+) -> Vec<WsMessage> {
+    let frames = state.init.iter().cloned().chain(Some(frame)).collect();
+    let mut messages = drive(schema, frames, data).await;
+    let acked = messages
+        .first()
+        .map(|message| server_message(message)["type"] == "connection_ack")
+        .unwrap_or(false);
+    if state.init.is_some() && acked {
+        // Acknowledged already
+        messages.remove(0);
+    }
+    messages
+}
+
+/// `next` without data, i.e. failed validation, as `error` message of graphql-transport-ws
+fn into_error(messages: Vec<WsMessage>, id: &str) -> Vec<WsMessage> {
+    if let Some(next) = messages.first().map(server_message) {
+        if next["type"] == "next" && next["payload"]["data"].is_null() {
+            let error = json!({"type": "error", "id": id, "payload": next["payload"]["errors"]});
+            return vec![WsMessage::Text(error.to_string())];
+        }
+    }
+    messages
+}
+
+/// Send the messages to the client, the connection is deleted on `Close`
+/// as API Gateway sends no close frame from the backend
+async fn send(client: &ConnectionsClient, connection_id: &str, messages: Vec<WsMessage>) {
+    for message in messages {
+        let result = match message {
+            WsMessage::Text(text) => client.post_to_connection(connection_id, text).await,
+            WsMessage::Close(code, reason) => {
+                eprintln!("closing {}: {} {}", connection_id, code, reason);
+                client.delete_connection(connection_id).await
+            }
+        };
+        match result {
+            Ok(()) => {}
+            Err(ConnectionsError::Gone) => return,
+            Err(err) => {
+                eprintln!("failed to send to {}: {}", connection_id, err);
+                return;
+            }
+        }
+    }
+}
+
+/// HTTPS client for `@connections` and DynamoDB requests
+#[derive(Clone)]
+struct HttpsTransport(
+    hyper::Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>, hyper::Body>,
+);
+
+impl HttpsTransport {
+    fn new() -> Self {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_only()
+            .enable_http1()
+            .build();
+        Self(hyper::Client::builder().build(connector))
+    }
+}
+
+impl AwsTransport for HttpsTransport {
+    fn send(
+        &self,
+        request: SignedRequest,
+    ) -> StoreFuture<'_, Result<TransportResponse, LambdaError>> {
+        Box::pin(async move {
+            let mut builder = hyper::Request::builder()
+                .method(request.method)
+                .uri(&request.url);
+            for (name, value) in &request.headers {
+                builder = builder.header(name, value);
+            }
+            let response = self
+                .0
+                .request(builder.body(hyper::Body::from(request.body))?)
+                .await?;
+            let status = response.status().as_u16();
+            let body = hyper::body::to_bytes(response.into_body()).await?.to_vec();
+            Ok(TransportResponse { status, body })
+        })
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    schema: AppSchema,
+    store: DynamoDbConnectionStore,
+    transport: HttpsTransport,
+}
+
+/// `$connect`, accept clients speaking graphql-transport-ws
+async fn connect(headers: HeaderMap) -> impl IntoResponse {
+    let protocol = Protocols::GraphQLWS.sec_websocket_protocol();
+    let protocols = headers
+        .get("sec-websocket-protocol")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if protocols.split(',').any(|p| p.trim() == protocol) {
+        Ok([("sec-websocket-protocol", protocol)])
+    } else {
+        Err(StatusCode::BAD_REQUEST)
+    }
+}
+
+/// `$disconnect`, the connection and its state are deleted from the store by lambda_web
+async fn disconnect() -> StatusCode {
+    StatusCode::OK
+}
+
+/// `$default`, a graphql-transport-ws frame
+async fn message(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    headers: HeaderMap,
+    frame: String,
+) -> StatusCode {
+    let connection_id = match headers
+        .get(WEBSOCKET_CONNECTION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(connection_id) => connection_id,
+        None => return StatusCode::BAD_REQUEST,
+    };
+    let client = match ConnectionsClient::from_context(&context) {
+        Some(client) => client.transport(state.transport.clone()),
+        None => return StatusCode::BAD_REQUEST,
+    };
+    match handle_frame(&state, &client, connection_id, frame).await {
+        Ok(()) => StatusCode::OK,
+        Err(err) => {
+            eprintln!("failed to handle frame of {}: {}", connection_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn handle_frame(
+    state: &AppState,
+    client: &ConnectionsClient,
+    connection_id: &str,
+    frame: String,
+) -> Result<(), LambdaError> {
+    let mut connection = ConnectionState::load(&state.store, connection_id).await?;
+    // Invalid frames are closed by async-graphql
+    let ClientFrame { kind, id, payload } = serde_json::from_str(&frame).unwrap_or_default();
+    let id = id.unwrap_or_default();
+    if kind == "subscribe" && connection.subscriptions.contains_key(&id) {
+        let reason = format!("Subscriber for {} already exists", id);
+        send(client, connection_id, vec![WsMessage::Close(4409, reason)]).await;
+        return Ok(());
+    }
+
+    let messages = replay(&state.schema, &connection, frame.clone(), Data::default()).await;
+    let accepted = !messages
+        .iter()
+        .any(|message| matches!(message, WsMessage::Close(_, _)));
+    let messages = match kind.as_str() {
+        "connection_init" if accepted => {
+            connection.init = Some(frame);
+            connection.save(&state.store, connection_id).await?;
+            messages
+        }
+        "subscribe" if accepted => {
+            let waiting = match messages.as_slice() {
+                [message] => server_message(message) == json!({"type": "complete", "id": id}),
+                _ => false,
+            };
+            if waiting {
+                // Subscription waiting for published events, completed by the client
+                connection
+                    .subscriptions
+                    .insert(id, payload.unwrap_or_default());
+                connection.save(&state.store, connection_id).await?;
+                Vec::new()
+            } else {
+                // Queries, or errors
+                into_error(messages, &id)
+            }
+        }
+        "complete" => {
+            if connection.subscriptions.remove(&id).is_some() {
+                connection.save(&state.store, connection_id).await?;
+            }
+            messages
+        }
+        _ => messages,
+    };
+    send(client, connection_id, messages).await;
+    Ok(())
+}
+
+/// Execute the subscriptions with the published message, and push `next` to the clients.
+/// Expose this route only to publishers, e.g. with IAM authorization.
+async fn publish(State(state): State<AppState>, message: String) -> StatusCode {
+    let connections = match state.store.list().await {
+        Ok(connections) => connections,
+        Err(err) => {
+            eprintln!("failed to list connections: {}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+    for connection in connections {
+        let id = &connection.connection_id;
+        let connection_state = match ConnectionState::load(&state.store, id).await {
+            Ok(connection_state) => connection_state,
+            Err(err) => {
+                eprintln!("failed to load state of {}: {}", id, err);
+                continue;
+            }
+        };
+        let client = connection.client().transport(state.transport.clone());
+        for (subscription_id, payload) in &connection_state.subscriptions {
+            let subscribe = json!({"type": "subscribe", "id": subscription_id, "payload": payload});
+            let mut data = Data::default();
+            data.insert(Published(message.clone()));
+            let messages = replay(
+                &state.schema,
+                &connection_state,
+                subscribe.to_string(),
+                data,
+            )
+            .await
+            .into_iter()
+            // The subscription is completed by the client, not by each event
+            .filter(|message| server_message(message)["type"] != "complete")
+            .collect();
+            send(&client, id, messages).await;
+        }
+    }
+    StatusCode::NO_CONTENT
+}
+
+#[tokio::main]
+async fn main() -> Result<(), LambdaError> {
+    let transport = HttpsTransport::new();
+    let table = std::env::var("CONNECTIONS_TABLE")?;
+    let store = DynamoDbConnectionStore::new(table).transport(transport.clone());
+    let state = AppState {
+        schema: Schema::new(Query, EmptyMutation, Subscription),
+        store: store.clone(),
+        transport,
+    };
+    let app = Router::new()
+        .route("/graphql/connections", post(connect).delete(disconnect))
+        .route("/graphql/messages", post(message))
+        .route("/publish", post(publish))
+        .with_state(state);
+
+    let routes = WebSocketRoutes::new()
+        .route("$connect", "POST", "/graphql/connections")
+        .route("$disconnect", "DELETE", "/graphql/connections")
+        .route("$default", "POST", "/graphql/messages");
+    let options = RunOptions::default()
+        .websocket_routes(routes)
+        .websocket_connection_store(store);
+    run_hyper_on_lambda_with_options(app, options).await?;
+    Ok(())
+}
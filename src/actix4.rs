@@ -2,11 +2,21 @@
 //! Run Actix Web on AWS Lambda
 //!
 //!
-use crate::request::LambdaHttpEvent;
+use crate::bridge::WebEvent;
+#[cfg(feature = "html-rewrite")]
+use crate::html_rewrite::HtmlRewriter;
+use crate::options::RunOptions;
+use crate::request::{DecodeError, LambdaHttpEvent};
+use crate::response::{
+    finish_response, ready_response, HandlerFuture, LambdaResponse, RequestInfo, ResponseFormat,
+};
 use core::convert::TryFrom;
 use core::future::Future;
+use futures_util::future::Either;
 use lambda_runtime::{Error as LambdaError, LambdaEvent, Service as LambdaService};
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Run Actix web application on AWS Lambda
 ///
@@ -36,6 +46,49 @@ use std::pin::Pin;
 /// ```
 ///
 pub async fn run_actix_on_lambda<F, I, S, B>(factory: F) -> Result<(), LambdaError>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: actix_service::IntoServiceFactory<S, actix_http::Request>,
+    S: actix_service::ServiceFactory<
+            actix_http::Request,
+            Config = actix_web::dev::AppConfig,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    S::InitError: std::fmt::Debug,
+    B: actix_web::body::MessageBody,
+    B::Error: std::fmt::Display,
+    <B as actix_web::body::MessageBody>::Error: std::fmt::Debug,
+{
+    run_actix_on_lambda_with_options(factory, RunOptions::default()).await
+}
+
+/// Run Actix web application on AWS Lambda with options
+///
+/// ```no_run
+/// use lambda_web::actix_web::{self, get, App, Responder};
+/// use lambda_web::{run_actix_on_lambda_with_options, LambdaError, PeerPort, RunOptions};
+///
+/// #[get("/")]
+/// async fn hello() -> impl Responder {
+///     format!("Hello")
+/// }
+///
+/// #[actix_web::main]
+/// async fn main() -> Result<(),LambdaError> {
+///     let factory = move || {
+///         App::new().service(hello)
+///     };
+///     let options = RunOptions::default().peer_port(PeerPort::Forwarded);
+///     run_actix_on_lambda_with_options(factory, options).await?;
+///     Ok(())
+/// }
+/// ```
+///
+pub async fn run_actix_on_lambda_with_options<F, I, S, B>(
+    factory: F,
+    options: RunOptions,
+) -> Result<(), LambdaError>
 where
     F: Fn() -> I + Send + Clone + 'static,
     I: actix_service::IntoServiceFactory<S, actix_http::Request>,
@@ -55,15 +108,76 @@ where
     let new_svc = srv
         .new_service(actix_web::dev::AppConfig::default())
         .await
-        .unwrap();
+        .map_err(|err| format!("Actix Web app initialization failed: {:?}", err))?;
 
-    lambda_runtime::run(ActixHandler(new_svc)).await?;
+    run_actix_service_on_lambda_with_options(new_svc, options).await
+}
+
+/// Run already initialized Actix web service on AWS Lambda
+///
+/// Build the app once with async setup, e.g. awaiting connection pools, then hand it over.
+///
+/// ```no_run
+/// use lambda_web::actix_web::{self, get, test, web, App, Responder};
+/// use lambda_web::{run_actix_service_on_lambda, LambdaError};
+///
+/// #[get("/")]
+/// async fn hello(greeting: web::Data<String>) -> impl Responder {
+///     greeting.to_string()
+/// }
+///
+/// #[actix_web::main]
+/// async fn main() -> Result<(),LambdaError> {
+///     // Async setup before the app is built
+///     let greeting = web::Data::new(String::from("Hello"));
+///     let svc = test::init_service(App::new().app_data(greeting).service(hello)).await;
+///     run_actix_service_on_lambda(svc).await?;
+///     Ok(())
+/// }
+/// ```
+///
+pub async fn run_actix_service_on_lambda<S, B>(svc: S) -> Result<(), LambdaError>
+where
+    S: actix_service::Service<
+            actix_http::Request,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    B: actix_web::body::MessageBody,
+    B::Error: std::fmt::Display,
+    <B as actix_web::body::MessageBody>::Error: std::fmt::Debug,
+{
+    run_actix_service_on_lambda_with_options(svc, RunOptions::default()).await
+}
+
+/// Run already initialized Actix web service on AWS Lambda with options
+pub async fn run_actix_service_on_lambda_with_options<S, B>(
+    svc: S,
+    options: RunOptions,
+) -> Result<(), LambdaError>
+where
+    S: actix_service::Service<
+            actix_http::Request,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    B: actix_web::body::MessageBody,
+    B::Error: std::fmt::Display,
+    <B as actix_web::body::MessageBody>::Error: std::fmt::Debug,
+{
+    options.snapshot_hooks.run()?;
+    crate::capture::install(options.failure_capture.clone());
+    crate::bridge::install_raw_event(options.raw_event);
+    lambda_runtime::run(ActixHandler::new(svc, options)).await?;
 
     Ok(())
 }
 
 /// Lambda_runtime handler for Actix Web
-struct ActixHandler<S, B>(S)
+///
+/// Service of `LambdaEvent<WebEvent>` used by `run_actix_service_on_lambda`,
+/// to be run by your own `lambda_runtime` setup.
+pub struct ActixHandler<S, B>(S, Arc<RunOptions>)
 where
     S: actix_service::Service<
             actix_http::Request,
@@ -74,7 +188,25 @@ where
     B::Error: std::fmt::Display,
     <B as actix_web::body::MessageBody>::Error: std::fmt::Debug;
 
-impl<S, B> LambdaService<LambdaEvent<LambdaHttpEvent<'_>>> for ActixHandler<S, B>
+impl<S, B> ActixHandler<S, B>
+where
+    S: actix_service::Service<
+            actix_http::Request,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    B: actix_web::body::MessageBody,
+    B::Error: std::fmt::Display,
+    <B as actix_web::body::MessageBody>::Error: std::fmt::Debug,
+{
+    /// Handler of initialized Actix Web service with options,
+    /// e.g. made by `actix_web::test::init_service`
+    pub fn new(svc: S, options: RunOptions) -> Self {
+        Self(svc, Arc::new(options))
+    }
+}
+
+impl<S, B> LambdaService<LambdaEvent<WebEvent<'_>>> for ActixHandler<S, B>
 where
     S: actix_service::Service<
             actix_http::Request,
@@ -85,9 +217,9 @@ where
     B::Error: std::fmt::Display,
     <B as actix_web::body::MessageBody>::Error: std::fmt::Debug,
 {
-    type Response = serde_json::Value;
+    type Response = LambdaResponse;
     type Error = actix_web::Error;
-    type Future = Pin<Box<dyn Future<Output = Result<serde_json::Value, Self::Error>>>>;
+    type Future = HandlerFuture<Pin<Box<dyn Future<Output = Result<LambdaResponse, Self::Error>>>>>;
 
     /// Returns Poll::Ready when servie can process more requrests.
     fn poll_ready(
@@ -100,22 +232,36 @@ where
     /// Lambda handler function
     /// Parse Lambda event as Actix-web request,
     /// serialize Actix-web response to Lambda JSON response
-    fn call(&mut self, req: LambdaEvent<LambdaHttpEvent<'_>>) -> Self::Future {
-        use serde_json::json;
-
-        let event = req.payload;
-        let _context = req.context;
+    fn call(&mut self, req: LambdaEvent<WebEvent<'_>>) -> Self::Future {
+        let event = match req.payload.into_http(&self.1) {
+            Ok(event) => event,
+            // Conversational event without route, or invalid header names
+            Err(request_err) => return ready_response(request_err.response()),
+        };
+        if let Some(builtin) = crate::builtin::builtin_response(&event, &self.1) {
+            let request_info = RequestInfo::new(&event, &self.1);
+            let fut = async move { Ok(builtin.await) };
+            return Either::Right(Box::pin(finish_response(self.1.clone(), request_info, fut)));
+        }
+        let deadline = Some(crate::brotli::deadline_from_millis(req.context.deadline));
 
         // check if web client supports content-encoding: br
         let client_br = event.client_supports_brotli();
         // multi-value-headers response format
         let multi_value = event.multi_value();
+        // HTML rewriting for the stage prefix
+        #[cfg(feature = "html-rewrite")]
+        let html_rewriter = HtmlRewriter::for_request(&event, &self.1);
+
+        // Request information for response post-processing
+        let request_info = RequestInfo::new(&event, &self.1);
 
         // Parse request
-        let actix_request = actix_http::Request::try_from(event);
+        let actix_request = actix_request_from_event(event, &self.1);
 
         // Call Actix service when request parsing succeeded
         let svc_call = actix_request.map(|req| self.0.call(req));
+        let options = self.1.clone();
 
         let fut = async move {
             match svc_call {
@@ -123,70 +269,145 @@ where
                     // Request parsing succeeded
                     if let Ok(response) = svc_fut.await {
                         // Returns as API Gateway response
-                        api_gateway_response_from_actix_web(response, client_br, multi_value)
-                            .await
-                            .or_else(|_err| {
-                                Ok(json!({
-                                    "isBase64Encoded": false,
-                                    "statusCode": 500u16,
-                                    "headers": { "content-type": "text/plain"},
-                                    "body": "Internal Server Error"
-                                }))
-                            })
+                        api_gateway_response_from_actix_web(
+                            response,
+                            client_br,
+                            multi_value,
+                            deadline,
+                            #[cfg(feature = "html-rewrite")]
+                            html_rewriter.as_ref(),
+                            &options,
+                        )
+                        .await
+                        .or_else(|_err| Ok(LambdaResponse::text(500, "Internal Server Error")))
                     } else {
                         // Some Actix web error -> 500 Internal Server Error
-                        Ok(json!({
-                            "isBase64Encoded": false,
-                            "statusCode": 500u16,
-                            "headers": { "content-type": "text/plain"},
-                            "body": "Internal Server Error"
-                        }))
+                        Ok(LambdaResponse::text(500, "Internal Server Error"))
                     }
                 }
-                Err(_request_err) => {
-                    // Request parsing error
-                    Ok(json!({
-                        "isBase64Encoded": false,
-                        "statusCode": 400u16,
-                        "headers": { "content-type": "text/plain"},
-                        "body": "Bad Request"
-                    }))
+                Err(request_err) => {
+                    // Request parsing error, 4xx by cause
+                    Ok(request_err.response())
                 }
             }
         };
-        Box::pin(fut)
+        Either::Right(Box::pin(finish_response(self.1.clone(), request_info, fut)))
     }
 }
 
 impl TryFrom<LambdaHttpEvent<'_>> for actix_http::Request {
-    type Error = LambdaError;
+    type Error = DecodeError;
 
     /// Actix-web Request from API Gateway event
     fn try_from(event: LambdaHttpEvent) -> Result<Self, Self::Error> {
-        use actix_web::http::Method;
-
-        // Construct actix_web request
-        let method = Method::try_from(event.method())?;
-        let req = actix_web::test::TestRequest::with_uri(&event.path_query()).method(method);
+        actix_request_from_event(event, &RunOptions::default())
+    }
+}
 
-        // Source IP
-        let req = if let Some(source_ip) = event.source_ip() {
-            req.peer_addr(std::net::SocketAddr::from((source_ip, 0u16)))
-        } else {
-            req
-        };
+/// Actix-web Request from API Gateway event with options
+fn actix_request_from_event(
+    event: LambdaHttpEvent,
+    options: &RunOptions,
+) -> Result<actix_http::Request, DecodeError> {
+    use actix_web::http::{Method, Version};
+    use actix_web::HttpMessage;
+
+    // Extensions, as same as hyper request
+    let tenant = options
+        .tenant_routing
+        .as_ref()
+        .zip(event.hostname())
+        .and_then(|(routing, hostname)| routing.tenant(hostname));
+    let assignments = if options.experiments.is_empty() {
+        None
+    } else {
+        Some(crate::experiment::Assignments::from_event(
+            &event,
+            &options.experiments,
+        ))
+    };
+    let sampled = options
+        .access_log
+        .as_ref()
+        .map(|sampling| sampling.sample(&event));
+    let client_hints = event.client_hints();
+    let viewer_info = event.viewer_info();
+    let container_affinity = crate::ContainerAffinity::from_event(&event);
+
+    // Construct actix_web request
+    let method = Method::try_from(event.method()).map_err(|_| DecodeError::InvalidMethod)?;
+    let req = crate::scratch::with_scratch(|buf| {
+        event.write_path_query(buf);
+        crate::request::check_uri_length(buf)?;
+        Ok::<_, DecodeError>(actix_web::test::TestRequest::with_uri(buf).method(method))
+    })?;
+
+    // HTTP version, HTTP/1.1 when unknown (ALB)
+    let req = req.version(match event.protocol() {
+        Some("HTTP/1.0") => Version::HTTP_10,
+        Some(protocol) if protocol.starts_with("HTTP/2") => Version::HTTP_2,
+        Some(protocol) if protocol.starts_with("HTTP/3") => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    });
+
+    // Source IP
+    let req = if let Some(source_ip) = event.source_ip() {
+        let source_port = options.peer_port.port(&event);
+        req.peer_addr(std::net::SocketAddr::from((source_ip, source_port)))
+    } else {
+        req
+    };
 
-        // Headers
-        let req = event
-            .headers()
-            .into_iter()
-            .fold(req, |req, (k, v)| req.insert_header((k, &v as &str)));
+    // Headers
+    let mut req = req;
+    event.for_each_header(|k, v| req = std::mem::take(&mut req).insert_header((k, v)));
 
-        // Body
-        let req = req.set_payload(event.body()?);
+    // Body, without content-length when empty as same as GET requests from real HTTP clients
+    let body = event.body()?;
+    let req = if body.is_empty() {
+        req
+    } else {
+        req.insert_header((actix_web::http::header::CONTENT_LENGTH, body.len()))
+    };
+    let (req, _) = req
+        .to_request()
+        .replace_payload(payload_stream(body, options.actix_payload_chunk_size));
+
+    // Extensions, taken by HttpRequest::extensions()
+    {
+        let mut extensions = req.extensions_mut();
+        extensions.insert(client_hints);
+        extensions.insert(viewer_info);
+        extensions.insert(container_affinity);
+        if let Some(sampled) = sampled {
+            extensions.insert(sampled);
+        }
+        if let Some(assignments) = assignments {
+            extensions.insert(assignments);
+        }
+        if let Some(tenant) = tenant {
+            extensions.insert(tenant);
+        }
+    }
+    Ok(req)
+}
 
-        Ok(req.to_request())
+/// Streaming payload from buffered request body,
+/// as same as the payload from real HTTP server.
+/// Split into chunks of `chunk_size` bytes, or a single chunk when None.
+fn payload_stream(body: Vec<u8>, chunk_size: Option<usize>) -> actix_http::Payload {
+    use actix_http::error::PayloadError;
+    use actix_web::web::Bytes;
+
+    let mut body = Bytes::from(body);
+    let chunk_size = chunk_size.unwrap_or_else(|| body.len()).max(1);
+    let mut chunks = Vec::with_capacity((body.len() + chunk_size - 1) / chunk_size);
+    while !body.is_empty() {
+        let chunk = body.split_to(chunk_size.min(body.len()));
+        chunks.push(Ok::<_, PayloadError>(chunk));
     }
+    let stream: actix_http::BoxedPayloadStream = Box::pin(futures_util::stream::iter(chunks));
+    actix_http::Payload::from(stream)
 }
 
 impl<B> crate::brotli::ResponseCompression for actix_web::dev::ServiceResponse<B> {
@@ -210,69 +431,53 @@ async fn api_gateway_response_from_actix_web<B: actix_web::body::MessageBody>(
     response: actix_web::dev::ServiceResponse<B>,
     client_support_br: bool,
     multi_value: bool,
-) -> Result<serde_json::Value, B::Error> {
+    deadline: Option<SystemTime>,
+    #[cfg(feature = "html-rewrite")] html_rewriter: Option<&HtmlRewriter>,
+    options: &RunOptions,
+) -> Result<LambdaResponse, B::Error> {
     use crate::brotli::ResponseCompression;
-    use actix_web::http::header::SET_COOKIE;
-    use serde_json::json;
 
     // HTTP status
-    let status_code = response.status().as_u16();
+    let mut lambda_response = LambdaResponse::new(
+        response.status().as_u16(),
+        ResponseFormat::from_multi_value(multi_value),
+    );
 
-    // Convert header to JSON map
-    let mut cookies = Vec::<String>::new();
-    let mut headers = serde_json::Map::new();
+    // Convert headers,
+    // REST API returns multiValueHeaders, HTTP API v2 returns headers and cookies
     for (k, v) in response.headers() {
+        if options.is_blocked_response_header(k.as_str()) {
+            // Stripped by options
+            continue;
+        }
         if let Ok(value_str) = v.to_str() {
-            if multi_value {
-                // REST API format, returns multiValueHeaders
-                if let Some(values) = headers.get_mut(k.as_str()) {
-                    if let Some(value_ary) = values.as_array_mut() {
-                        value_ary.push(json!(value_str));
-                    }
-                } else {
-                    headers.insert(k.as_str().to_string(), json!([value_str]));
-                }
-            } else {
-                // HTTP API v2 format, returns headers
-                if k == SET_COOKIE {
-                    cookies.push(value_str.to_string());
-                } else {
-                    headers.insert(k.as_str().to_string(), json!(value_str));
-                }
-            }
+            lambda_response.add_header(k.as_str(), value_str);
         }
     }
 
     // check if response should be compressed
     let compress = client_support_br && response.can_brotli_compress();
+    #[cfg(feature = "html-rewrite")]
+    let html_rewriter = HtmlRewriter::for_response(html_rewriter, &response);
+    let content_type = response.content_type().map(|ctype| ctype.to_string());
     let body_bytes = actix_web::body::to_bytes(response.into_body()).await?;
-    let body_base64 = if compress {
-        if multi_value {
-            headers.insert("content-encoding".to_string(), json!(["br"]));
-        } else {
-            headers.insert("content-encoding".to_string(), json!("br"));
-        }
-        crate::brotli::compress_response_body(&body_bytes)
-    } else {
-        base64::encode(body_bytes)
+    #[cfg(feature = "html-rewrite")]
+    let body_bytes = match html_rewriter {
+        Some(rewriter) => rewriter.rewrite(body_bytes),
+        None => body_bytes,
     };
-
-    if multi_value {
-        Ok(json!({
-            "isBase64Encoded": true,
-            "statusCode": status_code,
-            "multiValueHeaders": headers,
-            "body": body_base64
-        }))
-    } else {
-        Ok(json!({
-            "isBase64Encoded": true,
-            "statusCode": status_code,
-            "cookies": cookies,
-            "headers": headers,
-            "body": body_base64
-        }))
-    }
+    let lambda_response = crate::response::encode_response_body(
+        lambda_response,
+        body_bytes,
+        client_support_br,
+        compress,
+        content_type,
+        deadline,
+        options,
+    )
+    .await;
+
+    Ok(lambda_response)
 }
 
 #[cfg(test)]
@@ -379,20 +584,200 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_remote_port() {
+        use crate::options::PeerPort;
+
+        let req = prepare_request(API_GATEWAY_V2_GET_ROOT_ONEQUERY);
+        assert_eq!(req.peer_addr().unwrap().port(), 0);
+
+        let options = RunOptions::default().peer_port(PeerPort::Fixed(12345));
+        let reqjson: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_GET_ROOT_ONEQUERY).unwrap();
+        let req = actix_request_from_event(reqjson, &options).unwrap();
+        assert_eq!(req.peer_addr().unwrap().port(), 12345);
+
+        let options = RunOptions::default().peer_port(PeerPort::Forwarded);
+        let reqjson: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_ROOT_ONEQUERY).unwrap();
+        let req = actix_request_from_event(reqjson, &options).unwrap();
+        assert_eq!(req.peer_addr().unwrap().port(), 443);
+    }
+
+    #[test]
+    fn test_tenant_extension() {
+        use crate::{Tenant, TenantRouting};
+        use actix_web::HttpMessage;
+
+        let options = RunOptions::default().tenant_routing(TenantRouting::subdomain_of(
+            "execute-api.ap-northeast-1.amazonaws.com",
+        ));
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let req = actix_request_from_event(event, &options).unwrap();
+        assert_eq!(
+            req.extensions().get::<Tenant>().map(Tenant::name),
+            Some("yyyyyyyyyy")
+        );
+
+        let event: LambdaHttpEvent = serde_json::from_str(ALB_GET_MIXEDCASE_HEADERS).unwrap();
+        let req = actix_request_from_event(event, &options).unwrap();
+        assert!(req.extensions().get::<Tenant>().is_none());
+    }
+
+    #[test]
+    fn test_extensions() {
+        use crate::{ClientHints, ContainerAffinity, Sampled, ViewerInfo};
+        use actix_web::HttpMessage;
+
+        let options = RunOptions::default().access_log(crate::LogSampling::all());
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let req = actix_request_from_event(event, &options).unwrap();
+        let extensions = req.extensions();
+        assert!(extensions.get::<ClientHints>().is_some());
+        assert!(extensions.get::<ViewerInfo>().is_some());
+        assert!(extensions.get::<ContainerAffinity>().is_some());
+        assert_eq!(
+            extensions.get::<Sampled>().map(Sampled::is_sampled),
+            Some(true)
+        );
+    }
+
+    // Read whole payload stream
+    async fn read_payload(req: &mut actix_http::Request) -> Vec<u8> {
+        use futures_util::StreamExt;
+
+        let mut payload = Box::pin(req.take_payload());
+        let mut body = Vec::new();
+        while let Some(chunk) = payload.next().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        body
+    }
+
     #[tokio::test]
     async fn test_form_post() {
         use actix_web::http::Method;
 
-        let req = prepare_request(API_GATEWAY_V2_POST_FORM_URLENCODED);
+        let mut req = prepare_request(API_GATEWAY_V2_POST_FORM_URLENCODED);
         assert_eq!(req.method(), Method::POST);
-        let req = prepare_request(API_GATEWAY_REST_POST_FORM_URLENCODED);
+        assert_eq!(
+            read_payload(&mut req).await,
+            b"key1=value1&key2=value2&Ok=Ok"
+        );
+        let mut req = prepare_request(API_GATEWAY_REST_POST_FORM_URLENCODED);
         assert_eq!(req.method(), Method::POST);
+        assert_eq!(
+            read_payload(&mut req).await,
+            b"key1=value1&key2=value2&Ok=Ok"
+        );
 
         // Base64 encoded
-        let req = prepare_request(API_GATEWAY_V2_POST_FORM_URLENCODED_B64);
+        let mut req = prepare_request(API_GATEWAY_V2_POST_FORM_URLENCODED_B64);
         assert_eq!(req.method(), Method::POST);
-        let req = prepare_request(API_GATEWAY_REST_POST_FORM_URLENCODED_B64);
+        assert_eq!(
+            read_payload(&mut req).await,
+            b"key1=value1&key2=value2&Ok=Ok"
+        );
+        let mut req = prepare_request(API_GATEWAY_REST_POST_FORM_URLENCODED_B64);
         assert_eq!(req.method(), Method::POST);
+        assert_eq!(
+            read_payload(&mut req).await,
+            b"key1=value1&key2=value2&Ok=Ok"
+        );
+
+        // No body
+        let mut req = prepare_request(API_GATEWAY_V2_GET_ROOT_NOQUERY);
+        assert!(req.headers().get("content-length").is_none());
+        assert!(read_payload(&mut req).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_payload_chunks() {
+        use futures_util::StreamExt;
+
+        let event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_POST_FORM_URLENCODED).unwrap();
+        let options = RunOptions::default().actix_payload_chunk_size(8);
+        let mut req = actix_request_from_event(event, &options).unwrap();
+        let chunks: Vec<_> = Box::pin(req.take_payload())
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            vec![8, 8, 8, 5]
+        );
+        assert_eq!(chunks.concat(), b"key1=value1&key2=value2&Ok=Ok");
+        assert_eq!(req.headers().get("content-length").unwrap(), "29");
+    }
+
+    #[tokio::test]
+    async fn test_set_cookie_attributes() {
+        use actix_web::{test::TestRequest, HttpResponse};
+
+        let options = RunOptions::default();
+        let response = || {
+            TestRequest::default().to_srv_response(
+                HttpResponse::Ok()
+                    .append_header(("set-cookie", SET_COOKIES_WITH_ATTRIBUTES[0]))
+                    .append_header(("set-cookie", SET_COOKIES_WITH_ATTRIBUTES[1]))
+                    .finish(),
+            )
+        };
+
+        let res = api_gateway_response_from_actix_web(
+            response(),
+            false,
+            false,
+            None,
+            #[cfg(feature = "html-rewrite")]
+            None,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.cookies, SET_COOKIES_WITH_ATTRIBUTES);
+        let res = api_gateway_response_from_actix_web(
+            response(),
+            false,
+            true,
+            None,
+            #[cfg(feature = "html-rewrite")]
+            None,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            res.multi_value_headers["set-cookie"],
+            SET_COOKIES_WITH_ATTRIBUTES
+        );
+    }
+
+    #[test]
+    fn test_extension_method() {
+        for method in ["PROPFIND", "REPORT", "PURGE", "MKCOL"] {
+            let event_str =
+                API_GATEWAY_V2_GET_ROOT_NOQUERY.replace("\"GET\"", &format!("\"{}\"", method));
+            let req = prepare_request(&event_str);
+            assert_eq!(req.method().as_str(), method);
+            let event_str =
+                API_GATEWAY_REST_GET_ROOT_NOQUERY.replace("\"GET\"", &format!("\"{}\"", method));
+            let req = prepare_request(&event_str);
+            assert_eq!(req.method().as_str(), method);
+        }
+    }
+
+    #[test]
+    fn test_http_version() {
+        use actix_web::http::Version;
+
+        let req = prepare_request(API_GATEWAY_REST_GET_WITH_CONTEXT);
+        assert_eq!(req.version(), Version::HTTP_11);
+
+        let event_str = API_GATEWAY_REST_GET_WITH_CONTEXT.replace(r#""HTTP/1.1""#, r#""HTTP/2.0""#);
+        let req = prepare_request(&event_str);
+        assert_eq!(req.version(), Version::HTTP_2);
     }
 
     #[test]
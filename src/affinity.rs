@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Container affinity
+//! Tells which Lambda container handled the request
+//!
+use crate::request::LambdaHttpEvent;
+use std::sync::OnceLock;
+
+/// Response header containing the container ID
+pub const CONTAINER_ID_HEADER: &str = "x-lambda-container-id";
+
+/// Cookie name containing the container ID
+pub const CONTAINER_ID_COOKIE: &str = "lambda-container-id";
+
+/// Random ID of this Lambda container (process),
+/// unique while the container is warm.
+pub fn container_id() -> &'static str {
+    static CONTAINER_ID: OnceLock<String> = OnceLock::new();
+    CONTAINER_ID.get_or_init(|| {
+        use std::collections::hash_map::RandomState;
+        use std::hash::{BuildHasher, Hasher};
+
+        // RandomState is randomly seeded per process
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        format!("{:016x}", hasher.finish())
+    })
+}
+
+/// Set-Cookie header value containing the container ID
+pub(crate) fn container_cookie(container_id: &str) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax",
+        CONTAINER_ID_COOKIE, container_id
+    )
+}
+
+/// Container affinity of the request
+///
+/// Attached to hyper and Actix-web request extensions, and to
+/// Rocket request local cache as `Option<ContainerAffinity>`.
+/// Enable `RunOptions::container_affinity` to send the container ID cookie to clients,
+/// then `previous_container_id` tells the container which handled the previous request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerAffinity {
+    /// ID of the container handling this request
+    pub container_id: &'static str,
+    /// ID of the container which handled the previous request from this client
+    pub previous_container_id: Option<String>,
+}
+
+impl ContainerAffinity {
+    /// Container affinity from the container ID cookie in the request
+    pub(crate) fn from_event(event: &LambdaHttpEvent) -> Self {
+        let previous_container_id = crate::CookieJar::from_event(event)
+            .get_raw(CONTAINER_ID_COOKIE)
+            .map(String::from);
+        Self {
+            container_id: container_id(),
+            previous_container_id,
+        }
+    }
+
+    /// Has the container changed since the previous request from this client?
+    /// Returns false for the first request.
+    pub fn changed(&self) -> bool {
+        match &self.previous_container_id {
+            Some(previous) => previous != self.container_id,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_container_id() {
+        assert_eq!(container_id().len(), 16);
+        assert_eq!(container_id(), container_id());
+    }
+
+    #[test]
+    fn test_from_event() {
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_TWO_COOKIES).unwrap();
+        let affinity = ContainerAffinity::from_event(&event);
+        assert_eq!(affinity.previous_container_id, None);
+        assert!(!affinity.changed());
+
+        let event_str = API_GATEWAY_REST_GET_ONE_COOKIE.replace(
+            "cookie1=value1",
+            "cookie1=value1; lambda-container-id=0123456789abcdef",
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let affinity = ContainerAffinity::from_event(&event);
+        assert_eq!(
+            affinity.previous_container_id.as_deref(),
+            Some("0123456789abcdef")
+        );
+        assert!(affinity.changed());
+    }
+}
@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: MIT
+//!
+//! ALB OIDC authentication
+//! Verifies `x-amzn-oidc-data` JWT signed by ALB, and exposes its claims in `CallerIdentity`
+//!
+use crate::context::CallerIdentity;
+use crate::hyper014::HyperRequest;
+use core::convert::TryFrom;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Header of user claims JWT, added by ALB with OIDC authentication
+pub const OIDC_DATA_HEADER: &str = "x-amzn-oidc-data";
+
+/// Failed key fetches are not retried for this duration
+const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Key IDs cached or being fetched, ALB rotates only a few keys
+const MAX_KEY_IDS: usize = 16;
+
+type KeyFetcher = Arc<dyn Fn(&str) -> Result<String, String> + Send + Sync>;
+
+/// Public key of a key ID, or the state of fetching it
+#[derive(Clone, Copy)]
+enum CachedKey {
+    Key(VerifyingKey),
+    Fetching,
+    Failed(Instant),
+}
+
+/// ALB OIDC token verification, set by `RunOptions::alb_oidc`
+///
+/// Verified claims of `x-amzn-oidc-data` are set to `CallerIdentity::oidc_claims`
+/// in hyper request extensions. Requests without valid tokens are passed to the app
+/// without claims, so the app decides whether to reject them.
+///
+/// ALB signs tokens with ES256, its public keys are at
+/// `https://public-keys.auth.elb.{region}.amazonaws.com/{key-id}`.
+/// Only tokens signed by the ALB of the ARN are accepted.
+/// This crate has no HTTPS client, so the key fetcher is given by the app,
+/// e.g. by blocking `ureq` client. It runs on the blocking thread pool of tokio,
+/// once for each key ID in a container, and requests signed by the key are passed
+/// without claims until it is fetched. Failed fetches are retried after 60 seconds.
+/// Keys given by `public_key` before running the app are used without fetching.
+///
+/// ```
+/// use lambda_web::{AlbOidc, RunOptions};
+///
+/// let oidc = AlbOidc::new(
+///     "ap-northeast-1",
+///     "arn:aws:elasticloadbalancing:ap-northeast-1:123456789012:loadbalancer/app/web/0123456789abcdef",
+/// )
+/// .key_fetcher(|url| {
+///     // e.g. ureq::get(url).call().and_then(|res| res.into_string())
+///     Err(format!("no HTTP client to fetch {}", url))
+/// });
+/// let options = RunOptions::default().alb_oidc(oidc);
+/// ```
+#[derive(Clone)]
+pub struct AlbOidc {
+    region: String,
+    signer: String,
+    fetcher: Option<KeyFetcher>,
+    keys: Arc<Mutex<HashMap<String, CachedKey>>>,
+}
+
+/// JWT header of ALB, `exp` is in the header not in claims
+#[derive(Deserialize)]
+struct AlbJwtHeader {
+    alg: String,
+    kid: String,
+    #[serde(default)]
+    signer: Option<String>,
+    #[serde(default)]
+    exp: Option<u64>,
+}
+
+impl AlbOidc {
+    /// Verification of tokens signed by the ALB of the ARN in the region
+    pub fn new<R: Into<String>, S: Into<String>>(region: R, alb_arn: S) -> Self {
+        Self {
+            region: region.into(),
+            signer: alb_arn.into(),
+            fetcher: None,
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Public key in PEM of the key ID, e.g. fetched before running the app
+    pub fn public_key(self, kid: &str, pem: &str) -> Result<Self, String> {
+        let key = VerifyingKey::from_public_key_pem(pem).map_err(|err| err.to_string())?;
+        if let Ok(mut keys) = self.keys.lock() {
+            keys.insert(kid.to_string(), CachedKey::Key(key));
+        }
+        Ok(self)
+    }
+
+    /// Fetch public key PEM from the URL, for key IDs not given by `public_key`
+    pub fn key_fetcher<F>(mut self, fetcher: F) -> Self
+    where
+        F: Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.fetcher = Some(Arc::new(fetcher));
+        self
+    }
+
+    /// URL of the ALB public key
+    pub fn public_key_url(&self, kid: &str) -> String {
+        format!(
+            "https://public-keys.auth.elb.{}.amazonaws.com/{}",
+            self.region, kid
+        )
+    }
+
+    /// Verify the token and return its claims
+    fn verify(&self, token: &str, now: SystemTime) -> Result<Map<String, Value>, String> {
+        let segments = token.split('.').collect::<Vec<_>>();
+        let (header_b64, payload_b64, signature_b64) = match segments[..] {
+            [header, payload, signature] => (header, payload, signature),
+            _ => return Err("malformed token".to_string()),
+        };
+
+        let header: AlbJwtHeader =
+            serde_json::from_slice(&decode_segment(header_b64)?).map_err(|err| err.to_string())?;
+        if header.alg != "ES256" {
+            return Err(format!("unsupported algorithm {}", header.alg));
+        }
+        if header.signer.as_deref() != Some(self.signer.as_str()) {
+            return Err("unexpected signer".to_string());
+        }
+        match header.exp {
+            Some(exp) if now < SystemTime::UNIX_EPOCH + Duration::from_secs(exp) => {}
+            Some(_) => return Err("expired token".to_string()),
+            None => return Err("token without expiration".to_string()),
+        }
+
+        let key = self.key(&header.kid)?;
+        let signature = Signature::try_from(decode_segment(signature_b64)?.as_slice())
+            .map_err(|_| "malformed signature".to_string())?;
+        let signing_input = &token[..header_b64.len() + 1 + payload_b64.len()];
+        key.verify(signing_input.as_bytes(), &signature)
+            .map_err(|_| "invalid signature".to_string())?;
+
+        serde_json::from_slice(&decode_segment(payload_b64)?).map_err(|err| err.to_string())
+    }
+
+    /// Cached public key, or start fetching it without blocking the request
+    fn key(&self, kid: &str) -> Result<VerifyingKey, String> {
+        let mut keys = self
+            .keys
+            .lock()
+            .map_err(|_| "key cache poisoned".to_string())?;
+        match keys.get(kid) {
+            Some(CachedKey::Key(key)) => return Ok(*key),
+            Some(CachedKey::Fetching) => return Err(format!("key ID {} is being fetched", kid)),
+            Some(CachedKey::Failed(at)) if at.elapsed() < NEGATIVE_CACHE_TTL => {
+                return Err(format!("key ID {} failed to fetch", kid))
+            }
+            _ => {}
+        }
+        // Key ID goes to the URL path
+        let valid_kid = !kid.is_empty()
+            && kid.len() <= 64
+            && kid
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        let fetcher = match &self.fetcher {
+            Some(fetcher) if valid_kid => fetcher.clone(),
+            _ => return Err(format!("unknown key ID {}", kid)),
+        };
+        // Unknown key IDs in forged tokens must not grow the cache or flood the fetcher
+        if keys.len() >= MAX_KEY_IDS {
+            keys.retain(|_, cached| match cached {
+                CachedKey::Failed(at) => at.elapsed() < NEGATIVE_CACHE_TTL,
+                _ => true,
+            });
+            if keys.len() >= MAX_KEY_IDS {
+                return Err(format!("too many key IDs, {} is not fetched", kid));
+            }
+        }
+        keys.insert(kid.to_string(), CachedKey::Fetching);
+        drop(keys);
+
+        let err = format!("key ID {} is being fetched", kid);
+        let keys = self.keys.clone();
+        let kid = kid.to_string();
+        let url = self.public_key_url(&kid);
+        tokio::task::spawn_blocking(move || {
+            let cached = fetcher(&url)
+                .and_then(|pem| {
+                    VerifyingKey::from_public_key_pem(&pem).map_err(|err| err.to_string())
+                })
+                .map(CachedKey::Key)
+                .unwrap_or_else(|err| {
+                    tracing::warn!("ALB public key {} not fetched, {}", url, err);
+                    CachedKey::Failed(Instant::now())
+                });
+            if let Ok(mut keys) = keys.lock() {
+                keys.insert(kid, cached);
+            }
+        });
+        Err(err)
+    }
+
+    /// Set verified claims to CallerIdentity in the request extensions
+    pub(crate) fn apply(&self, req: &mut HyperRequest) {
+        let token = match req
+            .headers()
+            .get(OIDC_DATA_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(token) => token.to_string(),
+            None => return,
+        };
+        match self.verify(&token, SystemTime::now()) {
+            Ok(claims) => {
+                if let Some(identity) = req.extensions_mut().get_mut::<CallerIdentity>() {
+                    identity.oidc_subject =
+                        claims.get("sub").and_then(Value::as_str).map(String::from);
+                    identity.oidc_claims = Some(claims);
+                }
+            }
+            Err(err) => tracing::warn!("ALB OIDC token rejected, {}", err),
+        }
+    }
+}
+
+/// Decode base64url JWT segment, ALB pads it with '='
+fn decode_segment(segment: &str) -> Result<Vec<u8>, String> {
+    base64::decode_config(segment.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+
+    const SIGNER: &str =
+        "arn:aws:elasticloadbalancing:ap-northeast-1:123456789012:loadbalancer/app/web/0123456789abcdef";
+
+    fn token(signing_key: &SigningKey, exp: u64) -> String {
+        signed_token(
+            signing_key,
+            serde_json::json!({
+                "alg": "ES256",
+                "kid": "key-1",
+                "signer": SIGNER,
+                "exp": exp,
+            }),
+        )
+    }
+
+    fn signed_token(signing_key: &SigningKey, header: Value) -> String {
+        let encode = |json: Value| base64::encode_config(json.to_string(), base64::URL_SAFE);
+        let header = encode(header);
+        let payload = encode(serde_json::json!({ "sub": "user-1", "email": "user@example.com" }));
+        let signing_input = format!("{}.{}", header, payload);
+        let signature: Signature = signing_key.sign(signing_input.as_bytes());
+        format!(
+            "{}.{}",
+            signing_input,
+            base64::encode_config(signature.as_ref(), base64::URL_SAFE)
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let oidc = AlbOidc::new("ap-northeast-1", SIGNER);
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        // Unknown key without fetcher
+        assert!(oidc.verify(&token(&signing_key, 2_000), now).is_err());
+
+        oidc.keys.lock().unwrap().insert(
+            "key-1".to_string(),
+            CachedKey::Key(VerifyingKey::from(&signing_key)),
+        );
+        let claims = oidc.verify(&token(&signing_key, 2_000), now).unwrap();
+        assert_eq!(claims["sub"], "user-1");
+
+        // Expired
+        assert!(oidc.verify(&token(&signing_key, 500), now).is_err());
+        // Without expiration
+        let no_exp = serde_json::json!({ "alg": "ES256", "kid": "key-1", "signer": SIGNER });
+        assert!(oidc
+            .verify(&signed_token(&signing_key, no_exp), now)
+            .is_err());
+        // Signed by other key
+        let other_key = SigningKey::from_bytes(&[9u8; 32]).unwrap();
+        assert!(oidc.verify(&token(&other_key, 2_000), now).is_err());
+        // Other ALB
+        let other_alb = AlbOidc::new("ap-northeast-1", "arn:other");
+        other_alb.keys.lock().unwrap().insert(
+            "key-1".to_string(),
+            CachedKey::Key(VerifyingKey::from(&signing_key)),
+        );
+        assert!(other_alb.verify(&token(&signing_key, 2_000), now).is_err());
+    }
+
+    #[test]
+    fn test_public_key_url() {
+        let oidc = AlbOidc::new("ap-northeast-1", SIGNER);
+        assert_eq!(
+            oidc.public_key_url("key-1"),
+            "https://public-keys.auth.elb.ap-northeast-1.amazonaws.com/key-1"
+        );
+        // Key IDs which can change the URL are not fetched
+        let oidc = oidc.key_fetcher(|url| panic!("fetched {}", url));
+        assert!(oidc.key("../key").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_key_negative_cache() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        let oidc = AlbOidc::new("ap-northeast-1", SIGNER).key_fetcher(move |_url| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err("not found".to_string())
+        });
+
+        // Fetched in background, the request is not blocked
+        assert!(oidc.key("key-2").is_err());
+        while !matches!(
+            oidc.keys.lock().unwrap().get("key-2"),
+            Some(CachedKey::Failed(_))
+        ) {
+            tokio::task::yield_now().await;
+        }
+        // Failure is cached
+        assert!(oidc.key("key-2").is_err());
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+
+        // Number of key IDs is bounded
+        for i in 0..MAX_KEY_IDS * 2 {
+            let _ = oidc.key(&format!("forged-{}", i));
+        }
+        assert!(oidc.keys.lock().unwrap().len() <= MAX_KEY_IDS);
+    }
+}
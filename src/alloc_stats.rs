@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Allocation statistics
+//! Global allocator counting heap allocations, to compare allocations per invocation
+//!
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static ALLOCATED_BYTES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Global allocator counting allocations of each thread, wrapping another allocator
+///
+/// ```
+/// use lambda_web::{allocation_stats, CountingAllocator};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator::system();
+///
+/// let before = allocation_stats();
+/// let v = vec![0u8; 1024];
+/// let stats = allocation_stats().since(&before);
+/// assert!(1 <= stats.allocations);
+/// # drop(v);
+/// ```
+#[derive(Debug, Default)]
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl CountingAllocator<System> {
+    /// Count allocations by the system allocator
+    pub const fn system() -> Self {
+        Self { inner: System }
+    }
+}
+
+impl<A> CountingAllocator<A> {
+    /// Count allocations by `inner` allocator
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn count(size: usize) {
+    // Ignore after the thread local is destroyed
+    let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+    let _ = ALLOCATED_BYTES.try_with(|n| n.set(n.get() + size as u64));
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count(new_size);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Allocations counted by `CountingAllocator`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Number of allocations and reallocations
+    pub allocations: u64,
+    /// Total requested bytes
+    pub bytes: u64,
+}
+
+impl AllocationStats {
+    /// Allocations after `earlier` stats
+    pub fn since(&self, earlier: &AllocationStats) -> AllocationStats {
+        AllocationStats {
+            allocations: self.allocations.saturating_sub(earlier.allocations),
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+        }
+    }
+}
+
+/// Allocations on the current thread so far,
+/// always zero unless `CountingAllocator` is the global allocator
+pub fn allocation_stats() -> AllocationStats {
+    AllocationStats {
+        allocations: ALLOCATIONS.with(|n| n.get()),
+        bytes: ALLOCATED_BYTES.with(|n| n.get()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counting_allocator() {
+        let allocator = CountingAllocator::system();
+        let layout = Layout::from_size_align(100, 8).unwrap();
+
+        let before = allocation_stats();
+        unsafe {
+            let ptr = allocator.alloc(layout);
+            assert!(!ptr.is_null());
+            let ptr = allocator.realloc(ptr, layout, 200);
+            assert!(!ptr.is_null());
+            allocator.dealloc(ptr, Layout::from_size_align(200, 8).unwrap());
+        }
+        assert_eq!(
+            allocation_stats().since(&before),
+            AllocationStats {
+                allocations: 2,
+                bytes: 300
+            }
+        );
+    }
+}
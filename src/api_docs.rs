@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: MIT
+//!
+//! API documentation endpoint
+//! Serves OpenAPI JSON by this crate, even when routing of the web application is broken
+//!
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use crate::response::{LambdaResponse, ResponseFormat};
+use std::sync::{Arc, OnceLock};
+
+/// Default path of the API documentation
+pub const DEFAULT_API_DOCS_PATH: &str = "/openapi.json";
+
+type GenerateFn = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// OpenAPI document served by this crate, set by `RunOptions::api_docs`
+///
+/// `GET` or `HEAD` to the path returns the document with `ETag` and `Cache-Control`,
+/// Brotli compressed when the client accepts it. The stage prefix of REST API is ignored.
+///
+/// ```
+/// use lambda_web::{ApiDocs, RunOptions};
+///
+/// // Static document
+/// let options = RunOptions::default()
+///     .api_docs(ApiDocs::document(r#"{"openapi":"3.0.3","info":{"title":"Hello","version":"1"},"paths":{}}"#));
+///
+/// // Generated on the first request, e.g. by utoipa
+/// let options = RunOptions::default().api_docs(
+///     ApiDocs::generator(|| serde_json::json!({ "openapi": "3.0.3" }).to_string())
+///         .path("/docs/openapi.json"),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ApiDocs {
+    path: String,
+    max_age: u32,
+    generate: GenerateFn,
+    document: Arc<OnceLock<Document>>,
+}
+
+/// Document made once in a container
+struct Document {
+    body: String,
+    etag: String,
+    #[cfg(feature = "br")]
+    compressed: Option<String>,
+}
+
+impl ApiDocs {
+    /// Serve the JSON document
+    pub fn document<S: Into<String>>(json: S) -> Self {
+        let json = json.into();
+        Self::generator(move || json.clone())
+    }
+
+    /// Serve the JSON document made by the generator, called once on the first request
+    pub fn generator<F>(generate: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Self {
+            path: DEFAULT_API_DOCS_PATH.to_string(),
+            max_age: 300,
+            generate: Arc::new(generate),
+            document: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Path of the document, `/openapi.json` by default
+    pub fn path<S: Into<String>>(mut self, path: S) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// `Cache-Control: max-age` in seconds, 300 by default
+    pub fn max_age(mut self, seconds: u32) -> Self {
+        self.max_age = seconds;
+        self
+    }
+
+    fn get_document(&self) -> &Document {
+        self.document.get_or_init(|| {
+            use sha2::{Digest, Sha256};
+
+            let body = (self.generate)();
+            let digest = Sha256::digest(body.as_bytes());
+            let etag = format!(
+                "\"{}\"",
+                digest[..8]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            );
+            #[cfg(feature = "br")]
+            let compressed = Some(crate::brotli::brotli_base64(
+                body.as_bytes(),
+                Some("application/json"),
+                crate::brotli::DEFAULT_QUALITY,
+                false,
+            ));
+            Document {
+                body,
+                etag,
+                #[cfg(feature = "br")]
+                compressed,
+            }
+        })
+    }
+}
+
+/// API documentation response answered by this crate,
+/// None when the request is not to the document path
+pub(crate) fn api_docs_response(
+    event: &LambdaHttpEvent,
+    options: &RunOptions,
+) -> Option<LambdaResponse> {
+    let docs = options.api_docs.as_ref()?;
+    if !crate::health::is_request_to(event, &docs.path) {
+        return None;
+    }
+    let document = docs.get_document();
+
+    let not_modified = event
+        .header("if-none-match")
+        .map(|tags| tags.split(',').any(|tag| tag.trim() == document.etag))
+        .unwrap_or(false);
+    let mut response = LambdaResponse::new(
+        if not_modified { 304 } else { 200 },
+        ResponseFormat::Headers,
+    );
+    response.add_header("etag", &document.etag);
+    response.add_header(
+        "cache-control",
+        &format!("public, max-age={}", docs.max_age),
+    );
+    response.add_header("vary", "accept-encoding");
+    if not_modified {
+        return Some(response);
+    }
+    response.add_header("content-type", "application/json");
+
+    #[cfg(feature = "br")]
+    if let Some(compressed) = document
+        .compressed
+        .as_ref()
+        .filter(|_| event.client_supports_brotli())
+    {
+        response.add_header("content-encoding", "br");
+        response.body = compressed.clone();
+        response.is_base64 = true;
+        return Some(response);
+    }
+    response.body = document.body.clone();
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    const OPENAPI: &str =
+        r#"{"openapi":"3.0.3","info":{"title":"Hello","version":"1"},"paths":{}}"#;
+
+    #[test]
+    fn test_api_docs_response() {
+        let options = RunOptions::default().api_docs(ApiDocs::document(OPENAPI));
+        let event_str = API_GATEWAY_REST_GET_ROOT_NOQUERY
+            .replace(r#""path":"/stage/""#, r#""path":"/stage/openapi.json""#);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let res = api_docs_response(&event, &options).unwrap();
+        assert_eq!(res.status, 200);
+        assert_eq!(res.header("content-type"), Some("application/json"));
+        assert_eq!(res.header("cache-control"), Some("public, max-age=300"));
+        assert_eq!(res.body, OPENAPI);
+
+        // Conditional request
+        let etag = res.header("etag").unwrap().to_string();
+        let event_str = event_str.replace(
+            r#""multiValueHeaders":{"#,
+            &format!(
+                r#""multiValueHeaders":{{"if-none-match":[{}],"#,
+                serde_json::json!(etag)
+            ),
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let res = api_docs_response(&event, &options).unwrap();
+        assert_eq!(res.status, 304);
+        assert!(res.body.is_empty());
+
+        // Other paths and disabled
+        let root: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_ROOT_NOQUERY).unwrap();
+        assert!(api_docs_response(&root, &options).is_none());
+        assert!(api_docs_response(&event, &RunOptions::default()).is_none());
+    }
+
+    #[cfg(feature = "br")]
+    #[test]
+    fn test_api_docs_compressed() {
+        let options = RunOptions::default()
+            .api_docs(ApiDocs::generator(|| OPENAPI.to_string()).path("/docs"));
+        let event_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(r#""rawPath":"/""#, r#""rawPath":"/docs""#)
+            .replace(
+                r#""headers":{"#,
+                r#""headers":{"accept-encoding":"gzip, br","#,
+            );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let res = api_docs_response(&event, &options).unwrap();
+        assert_eq!(res.header("content-encoding"), Some("br"));
+        assert!(res.is_base64);
+    }
+}
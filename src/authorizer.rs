@@ -0,0 +1,418 @@
+// SPDX-License-Identifier: MIT
+//!
+//! API Gateway Lambda authorizer
+//! Events and responses of a sibling authorizer function, deserialized with the same
+//! header and query parsing as requests to web frameworks.
+//! Available without web framework features.
+//!
+//! ```no_run
+//! use lambda_runtime::{service_fn, LambdaEvent};
+//! use lambda_web::authorizer::{AuthorizerEvent, PolicyResponse};
+//! use lambda_web::LambdaError;
+//!
+//! async fn authorize(event: LambdaEvent<AuthorizerEvent>) -> Result<PolicyResponse, LambdaError> {
+//!     let event = event.payload;
+//!     let token = event.token().or_else(|| event.header("x-api-key"));
+//!     let response = if token == Some("secret") {
+//!         PolicyResponse::allow("user", event.arn()).context("tier", "gold")
+//!     } else {
+//!         PolicyResponse::deny("anonymous", event.arn())
+//!     };
+//!     Ok(response)
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), LambdaError> {
+//!     lambda_runtime::run(service_fn(authorize)).await
+//! }
+//! ```
+//!
+use crate::de::{
+    deserialize_lowercase_headers, deserialize_lowercase_multi_value_headers,
+    deserialize_null_default,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Lambda authorizer event of HTTP API, REST API or WebSocket API
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum AuthorizerEvent {
+    /// HTTP API `REQUEST` authorizer, payload format 2.0
+    HttpApiV2(HttpApiV2AuthorizerEvent),
+    /// REST API `TOKEN` authorizer
+    Token(TokenAuthorizerEvent),
+    /// REST API `REQUEST` authorizer, HTTP API payload format 1.0 and WebSocket `$connect`
+    Request(RequestAuthorizerEvent),
+}
+
+/// HTTP API `REQUEST` authorizer event, payload format 2.0
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpApiV2AuthorizerEvent {
+    route_arn: String,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    identity_source: Vec<String>,
+    #[serde(default)]
+    raw_path: String,
+    #[serde(default)]
+    raw_query_string: String,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    cookies: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_lowercase_headers")]
+    headers: HashMap<String, String>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    query_string_parameters: HashMap<String, String>,
+    request_context: HttpApiV2AuthorizerContext,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HttpApiV2AuthorizerContext {
+    http: HttpApiV2AuthorizerHttp,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HttpApiV2AuthorizerHttp {
+    method: String,
+    source_ip: String,
+}
+
+/// REST API `REQUEST` authorizer event
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestAuthorizerEvent {
+    method_arn: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    http_method: String,
+    #[serde(default, deserialize_with = "deserialize_lowercase_headers")]
+    headers: HashMap<String, String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lowercase_multi_value_headers"
+    )]
+    multi_value_headers: HashMap<String, Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    multi_value_query_string_parameters: HashMap<String, Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    query_string_parameters: HashMap<String, String>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    request_context: RequestAuthorizerContext,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct RequestAuthorizerContext {
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    identity: RequestAuthorizerIdentity,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct RequestAuthorizerIdentity {
+    source_ip: Option<String>,
+}
+
+/// REST API `TOKEN` authorizer event
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenAuthorizerEvent {
+    authorization_token: String,
+    method_arn: String,
+}
+
+impl AuthorizerEvent {
+    /// `methodArn` of REST API or `routeArn` of HTTP API, resource of the policy
+    pub fn arn(&self) -> &str {
+        match self {
+            Self::HttpApiV2(event) => &event.route_arn,
+            Self::Request(event) => &event.method_arn,
+            Self::Token(event) => &event.method_arn,
+        }
+    }
+
+    /// Token of `TOKEN` authorizer, usually `Authorization` header value
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            Self::Token(event) => Some(event.authorization_token.as_str()),
+            _ => None,
+        }
+    }
+
+    /// First value of the header, name is case-insensitive.
+    /// Not available to `TOKEN` authorizer.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        match self {
+            Self::HttpApiV2(event) => event.headers.get(&name).map(String::as_str),
+            Self::Request(event) => event
+                .multi_value_headers
+                .get(&name)
+                .and_then(|values| values.first())
+                .or_else(|| event.headers.get(&name))
+                .map(String::as_str),
+            Self::Token(_) => None,
+        }
+    }
+
+    /// First value of the query string parameter
+    pub fn query(&self, name: &str) -> Option<&str> {
+        match self {
+            Self::HttpApiV2(event) => event.query_string_parameters.get(name),
+            Self::Request(event) => event
+                .multi_value_query_string_parameters
+                .get(name)
+                .and_then(|values| values.first())
+                .or_else(|| event.query_string_parameters.get(name)),
+            Self::Token(_) => None,
+        }
+        .map(String::as_str)
+    }
+
+    /// Cookie value by name, as it is sent without percent-decoding
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        match self {
+            Self::HttpApiV2(event) => event
+                .cookies
+                .iter()
+                .find_map(|pair| cookie_value(pair, name)),
+            Self::Request(_) => self
+                .header("cookie")?
+                .split(';')
+                .find_map(|pair| cookie_value(pair, name)),
+            Self::Token(_) => None,
+        }
+    }
+
+    /// HTTP method, not available to `TOKEN` authorizer
+    pub fn method(&self) -> Option<&str> {
+        match self {
+            Self::HttpApiV2(event) => Some(&event.request_context.http.method),
+            Self::Request(event) => Some(&event.http_method).filter(|m| !m.is_empty()),
+            Self::Token(_) => None,
+        }
+        .map(String::as_str)
+    }
+
+    /// Request path, not available to `TOKEN` authorizer
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::HttpApiV2(event) => Some(&event.raw_path),
+            Self::Request(event) => Some(&event.path),
+            Self::Token(_) => None,
+        }
+        .map(String::as_str)
+    }
+
+    /// Raw query string of HTTP API
+    pub fn raw_query_string(&self) -> Option<&str> {
+        match self {
+            Self::HttpApiV2(event) => Some(event.raw_query_string.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Identity sources of HTTP API, e.g. `Authorization` header value
+    pub fn identity_source(&self) -> &[String] {
+        match self {
+            Self::HttpApiV2(event) => &event.identity_source,
+            _ => &[],
+        }
+    }
+
+    /// Client IP address
+    pub fn source_ip(&self) -> Option<std::net::IpAddr> {
+        match self {
+            Self::HttpApiV2(event) => Some(&event.request_context.http.source_ip),
+            Self::Request(event) => event.request_context.identity.source_ip.as_ref(),
+            Self::Token(_) => None,
+        }
+        .and_then(|ip| ip.parse().ok())
+    }
+}
+
+/// Value of `name=value` pair with the name
+fn cookie_value<'a>(pair: &'a str, name: &str) -> Option<&'a str> {
+    let (key, value) = pair.trim().split_once('=')?;
+    Some(value).filter(|_| key == name)
+}
+
+/// IAM policy response, accepted by REST, HTTP and WebSocket APIs
+///
+/// The context is passed to the integration as `requestContext.authorizer`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyResponse {
+    principal_id: String,
+    policy_document: PolicyDocument,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    context: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+struct PolicyDocument {
+    version: &'static str,
+    statement: Vec<PolicyStatement>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+struct PolicyStatement {
+    action: &'static str,
+    effect: &'static str,
+    resource: Vec<String>,
+}
+
+impl PolicyResponse {
+    fn new(principal_id: &str, effect: &'static str, resource: &str) -> Self {
+        Self {
+            principal_id: principal_id.to_string(),
+            policy_document: PolicyDocument {
+                version: "2012-10-17",
+                statement: vec![PolicyStatement {
+                    action: "execute-api:Invoke",
+                    effect,
+                    resource: vec![resource.to_string()],
+                }],
+            },
+            context: BTreeMap::new(),
+        }
+    }
+
+    /// Allow invoking the resource, e.g. `AuthorizerEvent::arn`
+    pub fn allow(principal_id: &str, resource: &str) -> Self {
+        Self::new(principal_id, "Allow", resource)
+    }
+
+    /// Deny invoking the resource, 403 Forbidden to the client
+    pub fn deny(principal_id: &str, resource: &str) -> Self {
+        Self::new(principal_id, "Deny", resource)
+    }
+
+    /// Add a context value passed to the integration
+    pub fn context(mut self, key: &str, value: &str) -> Self {
+        self.context.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Simple response of HTTP API authorizer, payload format 2.0 with simple responses enabled
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SimpleResponse {
+    is_authorized: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    context: BTreeMap<String, String>,
+}
+
+impl SimpleResponse {
+    /// Authorized or not
+    pub fn new(is_authorized: bool) -> Self {
+        Self {
+            is_authorized,
+            context: BTreeMap::new(),
+        }
+    }
+
+    /// Add a context value passed to the integration
+    pub fn context(mut self, key: &str, value: &str) -> Self {
+        self.context.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HTTP_API_V2_AUTHORIZER: &str = r###"{
+        "version":"2.0",
+        "type":"REQUEST",
+        "routeArn":"arn:aws:execute-api:ap-northeast-1:123456789012:abcdef1234/$default/GET/items",
+        "identitySource":["Bearer abc"],
+        "routeKey":"GET /items",
+        "rawPath":"/items",
+        "rawQueryString":"page=2",
+        "cookies":["session=xyz","theme=dark"],
+        "headers":{"Authorization":"Bearer abc","x-api-key":"secret"},
+        "queryStringParameters":{"page":"2"},
+        "requestContext":{
+            "http":{"method":"GET","path":"/items","protocol":"HTTP/1.1","sourceIp":"1.2.3.4","userAgent":"curl"},
+            "requestId":"id"
+        }
+    }"###;
+
+    const REST_REQUEST_AUTHORIZER: &str = r###"{
+        "type":"REQUEST",
+        "methodArn":"arn:aws:execute-api:ap-northeast-1:123456789012:abcdef1234/prod/GET/items",
+        "resource":"/items",
+        "path":"/items",
+        "httpMethod":"GET",
+        "headers":{"Cookie":"session=xyz; theme=dark","X-Api-Key":"secret"},
+        "multiValueHeaders":{"Cookie":["session=xyz; theme=dark"],"X-Api-Key":["secret"]},
+        "queryStringParameters":{"page":"2"},
+        "multiValueQueryStringParameters":{"page":["2"]},
+        "pathParameters":null,
+        "stageVariables":null,
+        "requestContext":{"identity":{"sourceIp":"1.2.3.4"},"stage":"prod"}
+    }"###;
+
+    const REST_TOKEN_AUTHORIZER: &str = r###"{
+        "type":"TOKEN",
+        "authorizationToken":"Bearer abc",
+        "methodArn":"arn:aws:execute-api:ap-northeast-1:123456789012:abcdef1234/prod/GET/items"
+    }"###;
+
+    #[test]
+    fn test_authorizer_event() {
+        for event_str in [HTTP_API_V2_AUTHORIZER, REST_REQUEST_AUTHORIZER] {
+            let event: AuthorizerEvent = serde_json::from_str(event_str).unwrap();
+            assert!(event.arn().ends_with("/GET/items"));
+            assert_eq!(event.header("X-API-KEY"), Some("secret"));
+            assert_eq!(event.query("page"), Some("2"));
+            assert_eq!(event.cookie("theme"), Some("dark"));
+            assert_eq!(event.method(), Some("GET"));
+            assert_eq!(event.path(), Some("/items"));
+            assert_eq!(event.source_ip(), Some([1, 2, 3, 4].into()));
+            assert_eq!(event.token(), None);
+        }
+
+        let event: AuthorizerEvent = serde_json::from_str(HTTP_API_V2_AUTHORIZER).unwrap();
+        assert_eq!(event.identity_source(), &["Bearer abc".to_string()]);
+
+        let event: AuthorizerEvent = serde_json::from_str(REST_TOKEN_AUTHORIZER).unwrap();
+        assert!(matches!(event, AuthorizerEvent::Token(_)));
+        assert_eq!(event.token(), Some("Bearer abc"));
+        assert_eq!(event.header("authorization"), None);
+    }
+
+    #[test]
+    fn test_authorizer_response() {
+        let response = PolicyResponse::allow("user", "arn:resource").context("tier", "gold");
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({
+                "principalId": "user",
+                "policyDocument": {
+                    "Version": "2012-10-17",
+                    "Statement": [{
+                        "Action": "execute-api:Invoke",
+                        "Effect": "Allow",
+                        "Resource": ["arn:resource"]
+                    }]
+                },
+                "context": {"tier": "gold"}
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(&SimpleResponse::new(false)).unwrap(),
+            json!({"isAuthorized": false})
+        );
+    }
+}
@@ -0,0 +1,400 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Conversational bridge
+//! Translates Alexa Skills Kit and Lex V2 events into HTTP POST requests,
+//! so conversational backends share the same web application and middleware.
+//!
+use crate::options::RunOptions;
+use crate::request::{DecodeError, LambdaHttpEvent};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Request header telling the kind of bridged event, `alexa` or `lex-v2`
+pub const BRIDGE_HEADER: &str = "x-lambda-web-bridge";
+
+/// Routes of bridged conversational events
+///
+/// Events are POSTed to the route with the event JSON (including the session) as body,
+/// and the JSON body of the response is returned to Alexa or Lex as it is.
+///
+/// ```
+/// use lambda_web::{ConversationalBridge, RunOptions};
+///
+/// let options = RunOptions::default()
+///     .conversational_bridge(ConversationalBridge::new().alexa("/alexa").lex_v2("/lex"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversationalBridge {
+    alexa_route: Option<String>,
+    lex_v2_route: Option<String>,
+}
+
+impl ConversationalBridge {
+    /// Bridge without routes, no events are accepted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route of Alexa Skills Kit requests
+    pub fn alexa<S: Into<String>>(mut self, route: S) -> Self {
+        self.alexa_route = Some(route.into());
+        self
+    }
+
+    /// Route of Lex V2 code hook events
+    pub fn lex_v2<S: Into<String>>(mut self, route: S) -> Self {
+        self.lex_v2_route = Some(route.into());
+        self
+    }
+
+    fn route(&self, kind: ConversationalKind) -> Option<&str> {
+        match kind {
+            ConversationalKind::Alexa => self.alexa_route.as_deref(),
+            ConversationalKind::LexV2 => self.lex_v2_route.as_deref(),
+        }
+    }
+}
+
+/// Kind of conversational event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConversationalKind {
+    Alexa,
+    LexV2,
+}
+
+impl ConversationalKind {
+    /// Detect from event JSON
+    fn detect(event: &Value) -> Option<Self> {
+        let has = |key: &str| event.get(key).map(|v| v.is_object()).unwrap_or(false);
+        if event.get("version").is_some() && has("request") && has("context") {
+            Some(Self::Alexa)
+        } else if has("sessionState") && has("bot") {
+            Some(Self::LexV2)
+        } else {
+            None
+        }
+    }
+
+    fn header_value(&self) -> &'static str {
+        match self {
+            Self::Alexa => "alexa",
+            Self::LexV2 => "lex-v2",
+        }
+    }
+}
+
+/// Alexa Skills Kit or Lex V2 event
+#[derive(Debug)]
+pub(crate) struct ConversationalEvent {
+    kind: ConversationalKind,
+    event: Value,
+}
+
+impl<'de> Deserialize<'de> for ConversationalEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let event = Value::deserialize(deserializer)?;
+        match ConversationalKind::detect(&event) {
+            Some(kind) => Ok(Self { kind, event }),
+            None => Err(serde::de::Error::custom(
+                "neither HTTP nor conversational event",
+            )),
+        }
+    }
+}
+
+/// Keep the event JSON as `RawEvent`, installed by launchers
+static RAW_EVENT: AtomicBool = AtomicBool::new(false);
+
+/// Install `RunOptions::raw_event`, called by launchers
+pub(crate) fn install_raw_event(enabled: bool) {
+    RAW_EVENT.store(enabled, Ordering::Relaxed);
+}
+
+/// Lambda event accepted by handlers
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum InvocationEvent<'a> {
+    Http(LambdaHttpEvent<'a>),
+    #[cfg(feature = "cloudfront")]
+    Edge(crate::edge::EdgeEvent),
+    WebSocket(crate::websocket::WebSocketEvent),
+    Sqs(crate::sqs::SqsEvent),
+    Stream(crate::streams::StreamEvent),
+    Sns(crate::sns::SnsEvent),
+    EventBridge(crate::eventbridge::EventBridgeEvent),
+    Conversational(ConversationalEvent),
+}
+
+/// Lambda event accepted by `HyperHandler`, `ActixHandler` and `RocketHandler`
+///
+/// API Gateway REST or HTTP API, ALB and function URL events,
+/// Lambda@Edge events with `cloudfront` feature,
+/// WebSocket API events mapped by `RunOptions::websocket_routes`,
+/// SQS events bridged by `RunOptions::sqs_bridge`,
+/// Kinesis and DynamoDB Streams events bridged by `RunOptions::stream_bridge`,
+/// SNS events bridged by `RunOptions::sns_bridge`,
+/// EventBridge events mapped by `RunOptions::eventbridge_routes`,
+/// and Alexa or Lex events bridged by `RunOptions::conversational_bridge`.
+#[derive(Debug)]
+pub struct WebEvent<'a>(
+    pub(crate) InvocationEvent<'a>,
+    /// Event JSON kept by `RunOptions::capture_failures`
+    pub(crate) Option<Arc<Value>>,
+);
+
+impl<'de, 'a> Deserialize<'de> for WebEvent<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let capture = crate::capture::enabled();
+        let raw_event = RAW_EVENT.load(Ordering::Relaxed);
+        if !capture && !raw_event {
+            return InvocationEvent::deserialize(deserializer).map(|event| Self(event, None));
+        }
+        // Keep the raw JSON to capture failures, or to attach as RawEvent
+        let value = Value::deserialize(deserializer)?;
+        match InvocationEvent::deserialize(&value) {
+            Ok(mut event) => {
+                if let (true, InvocationEvent::Http(http)) = (raw_event, &mut event) {
+                    http.set_raw_event(crate::RawEvent::new(value.clone()));
+                }
+                let captured = if capture { Some(Arc::new(value)) } else { None };
+                Ok(Self(event, captured))
+            }
+            Err(err) => {
+                if capture {
+                    crate::capture::capture_parse_error(value, err.to_string());
+                }
+                Err(serde::de::Error::custom(err))
+            }
+        }
+    }
+}
+
+impl<'a> WebEvent<'a> {
+    /// HTTP event normalized by options, see `InvocationEvent::into_http`.
+    /// The captured event JSON is moved to the request, or captured now when rejected.
+    pub(crate) fn into_http(
+        self,
+        options: &RunOptions,
+    ) -> Result<LambdaHttpEvent<'a>, DecodeError> {
+        let Self(event, captured) = self;
+        let mut event = event.into_http(options);
+        if let Some(captured) = captured {
+            crate::capture::attach(&mut event, captured);
+        }
+        event
+    }
+}
+
+impl<'a> InvocationEvent<'a> {
+    /// SQS messages or stream records as POST requests normalized by options,
+    /// with `RunOptions::sqs_bridge` or `RunOptions::stream_bridge`.
+    /// Other events are returned as they are.
+    #[cfg(feature = "hyper")]
+    pub(crate) fn into_batch_messages(
+        self,
+        options: &RunOptions,
+    ) -> Result<Vec<crate::sqs::SqsMessage<'static>>, Self> {
+        match (self, options.stream_bridge.as_ref()) {
+            // Stream records are processed in order as same as FIFO queues
+            (Self::Stream(event), Some(bridge)) => Ok(event
+                .into_http(bridge)
+                .into_iter()
+                .map(|(sequence_number, event)| {
+                    crate::sqs::SqsMessage::new(
+                        sequence_number,
+                        true,
+                        InvocationEvent::Http(event).into_http(options),
+                    )
+                })
+                .collect()),
+            (event, _) => event.into_sqs_messages(options),
+        }
+    }
+
+    /// SQS messages as POST requests normalized by options, with `RunOptions::sqs_bridge`
+    #[cfg(feature = "hyper")]
+    fn into_sqs_messages(
+        self,
+        options: &RunOptions,
+    ) -> Result<Vec<crate::sqs::SqsMessage<'static>>, Self> {
+        match (self, options.sqs_bridge.as_ref()) {
+            (Self::Sqs(event), Some(bridge)) => Ok(event
+                .into_http(bridge)
+                .into_iter()
+                .map(|(message_id, fifo, event)| {
+                    crate::sqs::SqsMessage::new(
+                        message_id,
+                        fifo,
+                        InvocationEvent::Http(event).into_http(options),
+                    )
+                })
+                .collect()),
+            (event, _) => Err(event),
+        }
+    }
+
+    /// SNS message as POST request normalized by options, with `RunOptions::sns_bridge`.
+    /// Other events are returned as they are.
+    #[cfg(feature = "hyper")]
+    pub(crate) fn into_sns_message(
+        self,
+        options: &RunOptions,
+    ) -> Result<crate::sns::SnsMessage<'a>, Self> {
+        match (self, options.sns_bridge.as_ref()) {
+            (Self::Sns(event), Some(bridge)) => {
+                let (message_id, event) = event.into_http(bridge);
+                Ok(crate::sns::SnsMessage::new(
+                    message_id,
+                    Self::Http(event).into_http(options),
+                ))
+            }
+            (event, _) => Err(event),
+        }
+    }
+
+    /// HTTP event normalized by options,
+    /// conversational events are translated by the bridge in options
+    pub(crate) fn into_http(
+        self,
+        options: &RunOptions,
+    ) -> Result<LambdaHttpEvent<'a>, DecodeError> {
+        match self {
+            Self::Http(mut event) => {
+                event.normalize(options);
+                crate::request::check_header_limits(
+                    &event,
+                    options
+                        .max_header_count
+                        .unwrap_or(crate::request::DEFAULT_MAX_HEADER_COUNT),
+                    options
+                        .max_header_bytes
+                        .unwrap_or(crate::request::DEFAULT_MAX_HEADER_BYTES),
+                )?;
+                if options.strict_header_names {
+                    crate::request::check_header_names(&event)?;
+                }
+                if !options.method_override.is_empty() {
+                    crate::request::apply_method_override(&mut event, &options.method_override)?;
+                }
+                #[cfg(feature = "decompress")]
+                if options.decompress_requests {
+                    crate::decompress::decompress_request(&mut event)?;
+                }
+                Ok(event)
+            }
+            #[cfg(feature = "cloudfront")]
+            Self::Edge(event) => Self::Http(event.into_http()).into_http(options),
+            Self::WebSocket(event) => {
+                let event = event.into_http(options.websocket_routes.as_ref())?;
+                Self::Http(event).into_http(options)
+            }
+            Self::EventBridge(event) => {
+                let event = event.into_http(options.eventbridge_routes.as_ref())?;
+                Self::Http(event).into_http(options)
+            }
+            // Batches are handled by `into_batch_messages`
+            Self::Sqs(_) => Err(DecodeError::Malformed(
+                "SQS events are bridged only by hyper handlers with sqs_bridge".to_string(),
+            )),
+            Self::Stream(_) => Err(DecodeError::Malformed(
+                "stream events are bridged only by hyper handlers with stream_bridge".to_string(),
+            )),
+            // Notifications are handled by `into_sns_message`
+            Self::Sns(_) => Err(DecodeError::Malformed(
+                "SNS events are bridged only by hyper handlers with sns_bridge".to_string(),
+            )),
+            Self::Conversational(ConversationalEvent { kind, event }) => {
+                let route = options
+                    .conversational_bridge
+                    .as_ref()
+                    .and_then(|bridge| bridge.route(kind))
+                    .ok_or_else(|| {
+                        DecodeError::Malformed(format!(
+                            "no route for {} events",
+                            kind.header_value()
+                        ))
+                    })?;
+                Ok(LambdaHttpEvent::bridged_post(
+                    route,
+                    kind.header_value(),
+                    event.to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_detect() {
+        let event: InvocationEvent = serde_json::from_str(ALEXA_LAUNCH_REQUEST).unwrap();
+        assert!(matches!(
+            event,
+            InvocationEvent::Conversational(ConversationalEvent {
+                kind: ConversationalKind::Alexa,
+                ..
+            })
+        ));
+        let event: InvocationEvent = serde_json::from_str(LEX_V2_DIALOG_CODE_HOOK).unwrap();
+        assert!(matches!(
+            event,
+            InvocationEvent::Conversational(ConversationalEvent {
+                kind: ConversationalKind::LexV2,
+                ..
+            })
+        ));
+        let event: InvocationEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        assert!(matches!(event, InvocationEvent::Http(_)));
+
+        // Other events are still rejected
+        assert!(serde_json::from_str::<InvocationEvent>(r#"{"Records":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_into_http() {
+        let options = RunOptions::default()
+            .conversational_bridge(ConversationalBridge::new().alexa("/alexa"));
+
+        let event: InvocationEvent = serde_json::from_str(ALEXA_LAUNCH_REQUEST).unwrap();
+        let event = event.into_http(&options).unwrap();
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/alexa");
+        assert_eq!(event.header(BRIDGE_HEADER), Some("alexa"));
+        assert_eq!(event.header("content-type"), Some("application/json"));
+        assert!(event.is_bridged());
+        let body: Value = serde_json::from_slice(&event.body().unwrap()).unwrap();
+        assert_eq!(body["session"]["sessionId"], "amzn1.echo-api.session.0000");
+
+        // No route for Lex
+        let event: InvocationEvent = serde_json::from_str(LEX_V2_DIALOG_CODE_HOOK).unwrap();
+        assert!(matches!(
+            event.into_http(&options),
+            Err(DecodeError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_captured_event() {
+        let options = RunOptions::default();
+        let json: Value = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let event = InvocationEvent::deserialize(&json).unwrap();
+
+        // Moved to the request, not shared with other invocations
+        let event = WebEvent(event, Some(Arc::new(json.clone())))
+            .into_http(&options)
+            .unwrap();
+        assert_eq!(event.captured_event().map(|e| e.as_ref()), Some(&json));
+    }
+}
@@ -4,6 +4,12 @@
 //! Supports
 //!   Content-Encoding: br
 //!
+use crate::options::RunOptions;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+#[cfg(feature = "br")]
+use std::time::Instant;
+use std::time::{Duration, SystemTime};
 
 ///
 /// Trait to check if reponse should be compressed
@@ -26,15 +32,7 @@ pub(crate) trait ResponseCompression {
 
         // Get Content-type header value
         if let Some(header_val) = self.content_type() {
-            let ctype = header_val.trim().to_ascii_lowercase();
-
-            // Compress when text types
-            ctype.starts_with("text/")
-                || ctype.starts_with("application/json")
-                || ctype.starts_with("application/xhtml")
-                || ctype.starts_with("application/xml")
-                || ctype.starts_with("application/wasm")
-                || ctype.starts_with("image/svg")
+            is_compressible_type(header_val)
         } else {
             // No content-type
             false
@@ -48,25 +46,372 @@ pub(crate) trait ResponseCompression {
     }
 }
 
+/// Is the Content-Type worth compressing?
+#[cfg(feature = "br")]
+pub(crate) fn is_compressible_type(content_type: &str) -> bool {
+    let ctype = content_type.trim().to_ascii_lowercase();
+
+    // Compress when text types
+    ctype.starts_with("text/")
+        || ctype.starts_with("application/json")
+        || is_ndjson(&ctype)
+        || ctype.starts_with("application/xhtml")
+        || ctype.starts_with("application/xml")
+        || ctype.starts_with("application/wasm")
+        || ctype.starts_with("image/svg")
+}
+
+// Without Brotli support, always returns false
+#[cfg(not(feature = "br"))]
+pub(crate) fn is_compressible_type(_content_type: &str) -> bool {
+    false
+}
+
+/// Is the Content-Type newline delimited JSON?
+#[cfg(any(feature = "br", feature = "hyper"))]
+pub(crate) fn is_ndjson(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    mime.eq_ignore_ascii_case("application/x-ndjson")
+        || mime.eq_ignore_ascii_case("application/jsonl")
+}
+
+/// Brotli quality of normal responses
+pub(crate) const DEFAULT_QUALITY: i32 = 4;
+
+/// Brotli quality near the invocation deadline
+pub(crate) const FAST_QUALITY: i32 = 1;
+
+/// Compression by remaining invocation time, set by `RunOptions::deadline_aware_compression`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DeadlineCompression {
+    /// Use `FAST_QUALITY` when remaining time is less than this
+    pub(crate) fast_within: Duration,
+    /// Do not compress when remaining time is less than this
+    pub(crate) skip_within: Duration,
+}
+
+/// Invocation deadline from Lambda context (milliseconds since UNIX epoch)
+pub(crate) fn deadline_from_millis(deadline_ms: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_millis(deadline_ms)
+}
+
+/// Brotli quality for the response, `None` to skip compression.
+/// Called just before compression, since the handler consumed some of the time.
+pub(crate) fn compression_quality(
+    options: &RunOptions,
+    deadline: Option<SystemTime>,
+) -> Option<i32> {
+    let (policy, deadline) = match (options.deadline_compression, deadline) {
+        (Some(policy), Some(deadline)) => (policy, deadline),
+        // No deadline awareness
+        _ => return Some(DEFAULT_QUALITY),
+    };
+    let remaining = deadline
+        .duration_since(SystemTime::now())
+        .unwrap_or(Duration::ZERO);
+    if remaining < policy.skip_within {
+        None
+    } else if remaining < policy.fast_within {
+        Some(FAST_QUALITY)
+    } else {
+        Some(DEFAULT_QUALITY)
+    }
+}
+
+/// Bodies larger than this are compressed in a blocking thread,
+/// not to stall the async runtime. Smaller bodies are compressed in place
+/// since a thread handoff costs more than the compression itself.
+#[cfg(feature = "br")]
+const BLOCKING_COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
 /// Compress response using Brotli, base64 encode it, and return encoded string.
+/// When the compression task failed, e.g. on runtime shutdown,
+/// returns uncompressed base64 string as `Err`.
 #[cfg(feature = "br")]
-pub(crate) fn compress_response_body<'a>(body: &[u8]) -> String {
+pub(crate) async fn compress_response_body<T>(
+    body: T,
+    content_type: Option<String>,
+    quality: i32,
+    options: &RunOptions,
+) -> Result<String, String>
+where
+    T: AsRef<[u8]> + Send + Sync + 'static,
+{
+    let log = options.log_compression_stats;
+    if body.as_ref().len() < BLOCKING_COMPRESSION_THRESHOLD {
+        // Small body
+        Ok(brotli_base64(
+            body.as_ref(),
+            content_type.as_deref(),
+            quality,
+            log,
+        ))
+    } else {
+        // Large body, tens of milliseconds at quality 4
+        let body = std::sync::Arc::new(body);
+        let shared = body.clone();
+        tokio::task::spawn_blocking(move || {
+            brotli_base64((*shared).as_ref(), content_type.as_deref(), quality, log)
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!("Brotli compression task failed, sent uncompressed, {}", err);
+            base64::encode((*body).as_ref())
+        })
+    }
+}
+
+/// Brotli compression and base64 encoding
+#[cfg(feature = "br")]
+pub(crate) fn brotli_base64(
+    body: &[u8],
+    content_type: Option<&str>,
+    quality: i32,
+    log: bool,
+) -> String {
     // Compress parameter
     let cfg = brotli::enc::BrotliEncoderParams {
-        quality: 4,
+        quality,
         ..Default::default()
     };
 
     // Do Brotli compression
+    let started = Instant::now();
     let mut body_reader = std::io::Cursor::new(body);
     let mut compressed_base64 = base64::write::EncoderStringWriter::new(base64::STANDARD);
-    let _sz = brotli::BrotliCompress(&mut body_reader, &mut compressed_base64, &cfg);
+    let compressed_len =
+        brotli::BrotliCompress(&mut body_reader, &mut compressed_base64, &cfg).unwrap_or(0);
+    let elapsed = started.elapsed();
+
+    record_compression(content_type, body.len(), compressed_len, elapsed, log);
 
     compressed_base64.into_inner()
 }
 
 // No Brotli compression, only base64 encoding
 #[cfg(not(feature = "br"))]
-pub(crate) fn compress_response_body<'a>(body: &[u8]) -> String {
-    base64::encode(body)
+pub(crate) async fn compress_response_body<T>(
+    body: T,
+    _content_type: Option<String>,
+    _quality: i32,
+    _options: &RunOptions,
+) -> Result<String, String>
+where
+    T: AsRef<[u8]> + Send + Sync + 'static,
+{
+    Err(base64::encode(body))
+}
+
+//
+// Compression statistics
+//
+
+/// Statistics of compressed responses, per content type
+static COMPRESSION_STATS: Mutex<BTreeMap<String, ContentTypeStats>> = Mutex::new(BTreeMap::new());
+
+/// Snapshot of compression statistics in this Lambda container
+///
+/// ```
+/// let stats = lambda_web::compression_stats();
+/// for (content_type, stats) in stats.by_content_type() {
+///     println!("{}: {:.2}", content_type, stats.ratio());
+/// }
+/// ```
+pub fn compression_stats() -> CompressionStats {
+    let by_content_type = COMPRESSION_STATS
+        .lock()
+        .map(|stats| stats.clone())
+        .unwrap_or_default();
+    CompressionStats { by_content_type }
+}
+
+/// Compression statistics, returned by `compression_stats()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    by_content_type: BTreeMap<String, ContentTypeStats>,
+}
+
+impl CompressionStats {
+    /// Statistics per content type (media type without parameters, lowercase)
+    pub fn by_content_type(&self) -> &BTreeMap<String, ContentTypeStats> {
+        &self.by_content_type
+    }
+
+    /// Statistics of all content types
+    pub fn total(&self) -> ContentTypeStats {
+        self.by_content_type
+            .values()
+            .fold(ContentTypeStats::default(), |mut total, stats| {
+                total.add(stats);
+                total
+            })
+    }
+}
+
+/// Compression statistics of one content type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentTypeStats {
+    /// Number of compressed responses
+    pub responses: u64,
+    /// Total bytes before compression
+    pub input_bytes: u64,
+    /// Total bytes after compression (before base64 encoding)
+    pub output_bytes: u64,
+    /// Total time spent compressing
+    pub compress_time: Duration,
+}
+
+impl ContentTypeStats {
+    /// Compressed size / original size, smaller is better.
+    /// 1.0 when nothing compressed.
+    pub fn ratio(&self) -> f64 {
+        if self.input_bytes == 0 {
+            1.0
+        } else {
+            self.output_bytes as f64 / self.input_bytes as f64
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.responses += other.responses;
+        self.input_bytes += other.input_bytes;
+        self.output_bytes += other.output_bytes;
+        self.compress_time += other.compress_time;
+    }
+}
+
+/// Add compressed response to statistics
+#[cfg_attr(not(feature = "br"), allow(dead_code))]
+fn record_compression(
+    content_type: Option<&str>,
+    input_len: usize,
+    output_len: usize,
+    compress_time: Duration,
+    log: bool,
+) {
+    // Media type without parameters, e.g. "text/html; charset=utf-8" -> "text/html"
+    let media_type = content_type
+        .and_then(|ctype| ctype.split(';').next())
+        .map(|ctype| ctype.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if log {
+        tracing::info!(
+            "brotli {} {} -> {} bytes ({:.1}%) in {} us",
+            media_type,
+            input_len,
+            output_len,
+            if input_len == 0 {
+                100.0
+            } else {
+                output_len as f64 * 100.0 / input_len as f64
+            },
+            compress_time.as_micros()
+        );
+    }
+
+    if let Ok(mut stats) = COMPRESSION_STATS.lock() {
+        stats.entry(media_type).or_default().add(&ContentTypeStats {
+            responses: 1,
+            input_bytes: input_len as u64,
+            output_bytes: output_len as u64,
+            compress_time,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "br")]
+    #[tokio::test]
+    async fn test_compress_response_body() {
+        let options = RunOptions::default();
+        let small = "Hello, World!".repeat(10);
+        let large = "Hello, World!".repeat(10000);
+
+        for body in [small, large] {
+            let compressed = compress_response_body(
+                body.clone().into_bytes(),
+                Some("text/plain".to_string()),
+                DEFAULT_QUALITY,
+                &options,
+            )
+            .await
+            .unwrap();
+            let compressed = base64::decode(compressed).unwrap();
+            let mut decompressed = Vec::new();
+            brotli::BrotliDecompress(&mut std::io::Cursor::new(compressed), &mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, body.as_bytes());
+        }
+    }
+
+    #[cfg(any(feature = "br", feature = "hyper"))]
+    #[test]
+    fn test_is_ndjson() {
+        assert!(is_ndjson("application/x-ndjson"));
+        assert!(is_ndjson("Application/X-NDJSON; charset=utf-8"));
+        assert!(is_ndjson("application/jsonl"));
+        assert!(!is_ndjson("application/json"));
+    }
+
+    #[test]
+    fn test_compression_quality() {
+        let now = SystemTime::now();
+        let deadline = |secs| Some(now + Duration::from_secs(secs));
+
+        // Not deadline aware
+        let options = RunOptions::default();
+        assert_eq!(
+            compression_quality(&options, deadline(0)),
+            Some(DEFAULT_QUALITY)
+        );
+
+        let options = RunOptions::default()
+            .deadline_aware_compression(Duration::from_secs(5), Duration::from_secs(1));
+        assert_eq!(
+            compression_quality(&options, deadline(60)),
+            Some(DEFAULT_QUALITY)
+        );
+        assert_eq!(
+            compression_quality(&options, deadline(3)),
+            Some(FAST_QUALITY)
+        );
+        assert_eq!(compression_quality(&options, deadline(0)), None);
+        assert_eq!(compression_quality(&options, None), Some(DEFAULT_QUALITY));
+        // Deadline already passed
+        assert_eq!(
+            compression_quality(&options, Some(now - Duration::from_secs(1))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compression_stats() {
+        record_compression(
+            Some("Text/X-Stats-Test; charset=utf-8"),
+            1000,
+            250,
+            Duration::from_micros(100),
+            false,
+        );
+        record_compression(
+            Some("text/x-stats-test"),
+            1000,
+            150,
+            Duration::from_micros(100),
+            false,
+        );
+
+        let stats = compression_stats();
+        let text = stats.by_content_type()["text/x-stats-test"];
+        assert_eq!(text.responses, 2);
+        assert_eq!(text.input_bytes, 2000);
+        assert_eq!(text.output_bytes, 400);
+        assert_eq!(text.compress_time, Duration::from_micros(200));
+        assert!((text.ratio() - 0.2).abs() < 1e-9);
+        assert!(stats.total().responses >= 2);
+    }
 }
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Build information
+//! Identifies the deployed application build from HTTP responses
+//!
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use crate::response::{LambdaResponse, ResponseFormat};
+use serde::Serialize;
+
+/// Path of the version endpoint enabled by `RunOptions::version_endpoint`
+pub const VERSION_PATH: &str = "/__lambda_web/version";
+
+/// Compile-time build metadata of the application
+///
+/// Made by `build_info!()` macro in the application crate.
+/// Attached to hyper request extensions, and reported by the health check
+/// and version endpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// Package name, `CARGO_PKG_NAME`
+    pub name: &'static str,
+    /// Package version, `CARGO_PKG_VERSION`
+    pub version: &'static str,
+    /// Git commit hash, `GIT_SHA` environment variable at build time
+    pub git_sha: Option<&'static str>,
+    /// Build timestamp, `BUILD_TIMESTAMP` environment variable at build time
+    pub build_timestamp: Option<&'static str>,
+}
+
+/// Build metadata of the crate calling this macro
+///
+/// Set `GIT_SHA` and `BUILD_TIMESTAMP` environment variables when building, e.g.
+/// `GIT_SHA=$(git rev-parse HEAD) BUILD_TIMESTAMP=$(date -u +%FT%TZ) cargo lambda build`
+///
+/// ```
+/// use lambda_web::{build_info, RunOptions};
+///
+/// let options = RunOptions::default()
+///     .build_info(build_info!())
+///     .version_endpoint(true);
+/// ```
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::BuildInfo {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: option_env!("GIT_SHA"),
+            build_timestamp: option_env!("BUILD_TIMESTAMP"),
+        }
+    };
+}
+
+/// Version endpoint response, None when disabled or the request is to another path
+pub(crate) fn version_response(
+    event: &LambdaHttpEvent,
+    options: &RunOptions,
+) -> Option<LambdaResponse> {
+    if !options.version_endpoint || !crate::health::is_request_to(event, VERSION_PATH) {
+        return None;
+    }
+    let body = serde_json::json!({
+        "build": options.build_info,
+        "lambda_web": env!("CARGO_PKG_VERSION"),
+        "function_version": std::env::var("AWS_LAMBDA_FUNCTION_VERSION").ok(),
+    });
+    let mut response = LambdaResponse::new(200, ResponseFormat::Headers);
+    response.add_header("content-type", "application/json");
+    response.add_header("cache-control", "no-store");
+    response.body = body.to_string();
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_version_response() {
+        let event_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(r#""rawPath":"/""#, r#""rawPath":"/__lambda_web/version""#);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+
+        // Disabled by default
+        assert_eq!(version_response(&event, &RunOptions::default()), None);
+
+        let options = RunOptions::default()
+            .build_info(crate::build_info!())
+            .version_endpoint(true);
+        let res = version_response(&event, &options).unwrap();
+        assert_eq!(res.status, 200);
+        let body: serde_json::Value = serde_json::from_str(&res.body).unwrap();
+        assert_eq!(body["build"]["name"], "lambda-web");
+        assert_eq!(body["build"]["version"], env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Built-in endpoints
+//! Requests answered by this crate before web frameworks, in the order they are checked
+//!
+use crate::idempotency::StoreFuture;
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use crate::response::LambdaResponse;
+
+/// Health check, HTTPS redirect, version, API docs, route listing or OPTIONS response
+/// answered by this crate, None when the request goes to the web framework
+pub(crate) fn builtin_response(
+    event: &LambdaHttpEvent,
+    options: &RunOptions,
+) -> Option<StoreFuture<'static, LambdaResponse>> {
+    // Health checks from ALB or Route 53 may be plain HTTP, so they are not redirected
+    if let Some(health) = crate::health::health_response(event, options) {
+        return Some(health);
+    }
+    let response = options
+        .https_redirect
+        .as_ref()
+        .and_then(|redirect| crate::https::redirect_response(event, redirect))
+        .or_else(|| crate::build_info::version_response(event, options))
+        .or_else(|| crate::api_docs::api_docs_response(event, options))
+        .or_else(|| crate::routes::routes_response(event, options))?;
+    Some(Box::pin(async move { response }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[tokio::test]
+    async fn test_builtin_response() {
+        let health_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(r#""rawPath":"/""#, r#""rawPath":"/__lambda_web/health""#);
+        let health: LambdaHttpEvent = serde_json::from_str(&health_str).unwrap();
+        let root: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+
+        // Nothing answered by default
+        assert!(builtin_response(&health, &RunOptions::default()).is_none());
+        assert!(builtin_response(&root, &RunOptions::default()).is_none());
+
+        let options = RunOptions::default()
+            .health_check(crate::HealthCheck::new())
+            .version_endpoint(true);
+        let res = builtin_response(&health, &options).unwrap().await;
+        assert_eq!(res.status, 200);
+        assert!(builtin_response(&root, &options).is_none());
+    }
+}
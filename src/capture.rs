@@ -0,0 +1,443 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Failure capture
+//! Dumps events failing to parse or answered with 4xx/5xx status,
+//! and converts them into `test_consts.rs` style fixtures
+//!
+use crate::request::{DecodeError, LambdaHttpEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Headers redacted by default, including bearer tokens added by ALB and SigV4
+const DEFAULT_REDACT_HEADERS: [&str; 7] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "x-amzn-oidc-accesstoken",
+    "x-amzn-oidc-data",
+    "x-amz-security-token",
+];
+
+/// Replacement of redacted header values
+const REDACTED: &str = "[REDACTED]";
+
+/// Why the event is captured
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureReason {
+    /// Not a supported event, the invocation failed without calling the web application
+    ParseError { message: String },
+    /// Rejected by this crate before the web application, e.g. invalid header names
+    Rejected { status: u16, message: String },
+    /// Error status of the response
+    Status { status: u16 },
+}
+
+impl CaptureReason {
+    fn status(&self) -> Option<u16> {
+        match self {
+            Self::ParseError { .. } => None,
+            Self::Rejected { status, .. } | Self::Status { status } => Some(*status),
+        }
+    }
+}
+
+impl std::fmt::Display for CaptureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ParseError { message } => write!(f, "parse error, {}", message),
+            Self::Rejected { status, message } => {
+                write!(f, "rejected with {}, {}", status, message)
+            }
+            Self::Status { status } => write!(f, "responded with {}", status),
+        }
+    }
+}
+
+/// Event captured by `FailureCapture`, serialized as JSON artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedEvent {
+    pub reason: CaptureReason,
+    /// Unix time in milliseconds
+    pub captured_at: u64,
+    /// Lambda event with sensitive headers, and bodies unless `FailureCapture::capture_body`, redacted
+    pub event: Value,
+}
+
+type Sink = Arc<dyn Fn(&CapturedEvent) + Send + Sync>;
+
+/// Capture of failing events, set by `RunOptions::capture_failures`
+///
+/// Events failing to parse, rejected by this crate or answered with status 400 or above
+/// are passed to the sink, with `authorization`, `proxy-authorization`, `cookie`, `x-api-key`,
+/// `x-amzn-oidc-accesstoken`, `x-amzn-oidc-data` and `x-amz-security-token` headers redacted.
+/// Request bodies, e.g. passwords of failed logins, are redacted unless `capture_body` is set.
+/// Sinks run synchronously before the response is returned,
+/// so a sink uploading to S3 should queue the upload instead of blocking.
+/// Convert the artifacts into test fixtures by `fixture`.
+///
+/// ```no_run
+/// use lambda_web::{FailureCapture, RunOptions};
+///
+/// // Local JSON files, collected by `docker cp` or `cargo lambda watch`
+/// let options = RunOptions::default().capture_failures(FailureCapture::dir("/tmp/captured"));
+///
+/// // Other sinks, e.g. S3 via AwsTransport
+/// let capture = FailureCapture::new(|captured| {
+///     eprintln!("{}", serde_json::to_string(captured).unwrap());
+/// })
+/// .min_status(500);
+/// ```
+#[derive(Clone)]
+pub struct FailureCapture {
+    sink: Sink,
+    min_status: u16,
+    redact_headers: Vec<String>,
+    capture_body: bool,
+}
+
+impl std::fmt::Debug for FailureCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FailureCapture")
+            .field("min_status", &self.min_status)
+            .field("redact_headers", &self.redact_headers)
+            .field("capture_body", &self.capture_body)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FailureCapture {
+    /// Pass captured events to the sink
+    pub fn new<F>(sink: F) -> Self
+    where
+        F: Fn(&CapturedEvent) + Send + Sync + 'static,
+    {
+        Self {
+            sink: Arc::new(sink),
+            min_status: 400,
+            redact_headers: DEFAULT_REDACT_HEADERS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            capture_body: false,
+        }
+    }
+
+    /// Write captured events as `{captured_at}-{seq}.json` files in the directory,
+    /// `/tmp` is the only writable directory on Lambda
+    pub fn dir<P: Into<PathBuf>>(dir: P) -> Self {
+        let dir = dir.into();
+        let seq = AtomicU64::new(0);
+        Self::new(move |captured| {
+            let path = dir.join(format!(
+                "{}-{}.json",
+                captured.captured_at,
+                seq.fetch_add(1, Ordering::Relaxed)
+            ));
+            let result = std::fs::create_dir_all(&dir).and_then(|_| {
+                std::fs::write(
+                    &path,
+                    serde_json::to_vec_pretty(captured).unwrap_or_default(),
+                )
+            });
+            if let Err(err) = result {
+                tracing::warn!("failed to capture event to {}: {}", path.display(), err);
+            }
+        })
+    }
+
+    /// Capture responses with the status or above, 400 by default
+    pub fn min_status(mut self, status: u16) -> Self {
+        self.min_status = status;
+        self
+    }
+
+    /// Redact one more header, case-insensitive
+    pub fn redact_header<S: Into<String>>(mut self, name: S) -> Self {
+        self.redact_headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Keep request bodies as they are, false by default.
+    /// Bodies may contain credentials, e.g. passwords of failed logins.
+    pub fn capture_body(mut self, enabled: bool) -> Self {
+        self.capture_body = enabled;
+        self
+    }
+
+    fn capture(&self, reason: CaptureReason, mut event: Value) {
+        if reason
+            .status()
+            .map(|status| status < self.min_status)
+            .unwrap_or(false)
+        {
+            return;
+        }
+        redact(&mut event, &self.redact_headers);
+        if !self.capture_body {
+            redact_body(&mut event);
+        }
+        let captured_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        (self.sink)(&CapturedEvent {
+            reason,
+            captured_at,
+            event,
+        });
+    }
+}
+
+/// Replace values of the headers in `headers`, `multiValueHeaders` and `cookies`
+fn redact(event: &mut Value, names: &[String]) {
+    let is_redacted = |name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+    for key in ["headers", "multiValueHeaders"] {
+        if let Some(headers) = event.get_mut(key).and_then(Value::as_object_mut) {
+            for (name, value) in headers.iter_mut() {
+                if !is_redacted(name) {
+                    continue;
+                }
+                match value {
+                    Value::Array(values) => {
+                        values.iter_mut().for_each(|v| *v = Value::from(REDACTED))
+                    }
+                    value => *value = Value::from(REDACTED),
+                }
+            }
+        }
+    }
+    // Lambda@Edge `{"cf":{"request":{"headers":{"name":[{"key":..,"value":..}]}}}}`
+    if let Some(headers) = event
+        .pointer_mut("/Records/0/cf/request/headers")
+        .and_then(Value::as_object_mut)
+    {
+        for (name, values) in headers.iter_mut() {
+            if is_redacted(name) {
+                for value in values.as_array_mut().into_iter().flatten() {
+                    value["value"] = Value::from(REDACTED);
+                }
+            }
+        }
+    }
+    if is_redacted("cookie") {
+        if let Some(cookies) = event.get_mut("cookies").and_then(Value::as_array_mut) {
+            cookies
+                .iter_mut()
+                .for_each(|cookie| *cookie = Value::from(REDACTED));
+        }
+    }
+}
+
+/// Replace request bodies of HTTP, Lambda@Edge and SQS events
+fn redact_body(event: &mut Value) {
+    fn redact_string(value: Option<&mut Value>) {
+        if let Some(value) = value.filter(|value| value.is_string()) {
+            *value = Value::from(REDACTED);
+        }
+    }
+
+    redact_string(event.get_mut("body"));
+    redact_string(event.pointer_mut("/Records/0/cf/request/body/data"));
+    if let Some(records) = event.get_mut("Records").and_then(Value::as_array_mut) {
+        for record in records.iter_mut() {
+            redact_string(record.get_mut("body"));
+        }
+    }
+}
+
+/// Capture installed by the launcher, as events are parsed before handlers see options
+static CAPTURE: Mutex<Option<FailureCapture>> = Mutex::new(None);
+
+/// Install the capture of RunOptions, called by launchers
+pub(crate) fn install(capture: Option<FailureCapture>) {
+    *CAPTURE.lock().unwrap() = capture;
+}
+
+pub(crate) fn enabled() -> bool {
+    CAPTURE.lock().unwrap().is_some()
+}
+
+fn capture(reason: CaptureReason, event: Value) {
+    let capture = CAPTURE.lock().unwrap().clone();
+    if let Some(capture) = capture {
+        capture.capture(reason, event);
+    }
+}
+
+/// Capture the event failed to parse
+pub(crate) fn capture_parse_error(event: Value, message: String) {
+    capture(CaptureReason::ParseError { message }, event);
+}
+
+/// Keep the event JSON in the request to capture its error response,
+/// or capture it now when rejected by this crate
+pub(crate) fn attach(event: &mut Result<LambdaHttpEvent<'_>, DecodeError>, captured: Arc<Value>) {
+    match event {
+        Ok(event) => event.set_captured_event(captured),
+        Err(err) => capture(
+            CaptureReason::Rejected {
+                status: err.status_code(),
+                message: err.to_string(),
+            },
+            unwrap_or_clone(captured),
+        ),
+    }
+}
+
+/// Capture the event of the error response
+pub(crate) fn capture_status(event: Option<Arc<Value>>, status: u16) {
+    if let Some(event) = event {
+        if status >= 400 {
+            capture(CaptureReason::Status { status }, unwrap_or_clone(event));
+        }
+    }
+}
+
+/// The event JSON, cloned only when shared with other requests of the batch
+fn unwrap_or_clone(event: Arc<Value>) -> Value {
+    Arc::try_unwrap(event).unwrap_or_else(|event| (*event).clone())
+}
+
+/// `test_consts.rs` style fixture of the captured event, the name is converted to
+/// SCREAMING_SNAKE_CASE
+///
+/// ```
+/// let captured: lambda_web::CapturedEvent = serde_json::from_str(r#"{
+///     "reason": {"kind": "status", "status": 500},
+///     "captured_at": 1700000000000,
+///     "event": {"rawPath": "/"}
+/// }"#).unwrap();
+/// let fixture = lambda_web::fixture("api gateway v2 get root 500", &captured);
+/// assert!(fixture.contains("pub(crate) const API_GATEWAY_V2_GET_ROOT_500: &str = r###\"{"));
+/// ```
+pub fn fixture(name: &str, captured: &CapturedEvent) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let mut json = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+    captured
+        .event
+        .serialize(&mut serializer)
+        .expect("JSON value is always serialized");
+    let json = String::from_utf8(json).expect("serde_json writes UTF-8");
+
+    // Raw string delimiter not appearing in the JSON
+    let mut hashes = "###".to_string();
+    while json.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!(
+        "// Captured: {}\npub(crate) const {}: &str = r{}\"{}\"{};\n",
+        captured.reason, name, hashes, json, hashes
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn collect() -> (FailureCapture, Arc<Mutex<Vec<CapturedEvent>>>) {
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink = captured.clone();
+        let capture = FailureCapture::new(move |event| sink.lock().unwrap().push(event.clone()));
+        (capture, captured)
+    }
+
+    #[test]
+    fn test_capture() {
+        let (capture, captured) = collect();
+        let capture = capture.min_status(500).redact_header("X-Session");
+        let event = json!({
+            "headers": {"Authorization": "Bearer abc", "x-session": "s", "accept": "*/*"},
+            "multiValueHeaders": {"cookie": ["a=1", "b=2"], "x-amzn-oidc-data": ["eyJ"]},
+            "cookies": ["a=1"],
+            "body": "password=secret"
+        });
+
+        // Below min_status
+        capture.capture(CaptureReason::Status { status: 404 }, event.clone());
+        assert!(captured.lock().unwrap().is_empty());
+
+        capture.capture(CaptureReason::Status { status: 502 }, event.clone());
+        assert_eq!(
+            captured.lock().unwrap()[0].event,
+            json!({
+                "headers": {"Authorization": REDACTED, "x-session": REDACTED, "accept": "*/*"},
+                "multiValueHeaders": {
+                    "cookie": [REDACTED, REDACTED],
+                    "x-amzn-oidc-data": [REDACTED]
+                },
+                "cookies": [REDACTED],
+                "body": REDACTED
+            })
+        );
+
+        // Opted in to bodies
+        let capture = capture.capture_body(true);
+        capture.capture(CaptureReason::Status { status: 502 }, event);
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[1].event["body"], "password=secret");
+    }
+
+    #[test]
+    fn test_parse_error_always_captured() {
+        let (capture, captured) = collect();
+        let capture = capture.min_status(500);
+        capture.capture(
+            CaptureReason::ParseError {
+                message: "missing field".to_string(),
+            },
+            json!({"foo": 1}),
+        );
+        assert_eq!(captured.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_fixture() {
+        let captured = CapturedEvent {
+            reason: CaptureReason::Rejected {
+                status: 431,
+                message: "Request headers too large".to_string(),
+            },
+            captured_at: 0,
+            event: json!({"body": "\"###"}),
+        };
+        let fixture = fixture("rest api too many headers", &captured);
+        assert_eq!(
+            fixture,
+            concat!(
+                "// Captured: rejected with 431, Request headers too large\n",
+                "pub(crate) const REST_API_TOO_MANY_HEADERS: &str = r####\"{\n",
+                "    \"body\": \"\\\"###\"\n",
+                "}\"####;\n"
+            )
+        );
+
+        // Artifact JSON round trip
+        let artifact = serde_json::to_string(&captured).unwrap();
+        assert!(artifact.contains(r#""kind":"rejected""#));
+        assert_eq!(
+            serde_json::from_str::<CapturedEvent>(&artifact).unwrap(),
+            captured
+        );
+    }
+}
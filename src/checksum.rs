@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Response body checksum
+//! Attaches checksum of the final (compressed) body, to verify integrity on clients
+//!
+use crate::response::LambdaResponse;
+
+/// Checksum algorithm of `RunOptions::body_checksum`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyChecksum {
+    /// SHA-256 in `x-content-sha256` header, lowercase hex
+    Sha256,
+    /// CRC32C (Castagnoli) in `x-content-crc32c` header, lowercase hex, cheaper than SHA-256
+    Crc32c,
+}
+
+impl BodyChecksum {
+    /// Response header name
+    pub fn header_name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "x-content-sha256",
+            Self::Crc32c => "x-content-crc32c",
+        }
+    }
+
+    /// Checksum of the body in hex
+    pub fn hex_digest(&self, body: &[u8]) -> String {
+        match self {
+            Self::Sha256 => {
+                use sha2::{Digest, Sha256};
+                Sha256::digest(body)
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect()
+            }
+            Self::Crc32c => format!("{:08x}", crc32c(body)),
+        }
+    }
+}
+
+/// CRC32C, bitwise since bodies are hashed once per response
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Attach checksum header of the body as sent to clients,
+/// that is after Brotli compression and before base64 encoding.
+pub(crate) fn attach_checksum(response: &mut LambdaResponse, checksum: BodyChecksum) {
+    let digest = if response.is_base64 {
+        match base64::decode(&response.body) {
+            Ok(body) => checksum.hex_digest(&body),
+            Err(_) => return,
+        }
+    } else {
+        checksum.hex_digest(response.body.as_bytes())
+    };
+    response.add_header(checksum.header_name(), &digest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_digest() {
+        assert_eq!(
+            BodyChecksum::Sha256.hex_digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(BodyChecksum::Crc32c.hex_digest(b"123456789"), "e3069283");
+        assert_eq!(BodyChecksum::Crc32c.hex_digest(b""), "00000000");
+    }
+
+    #[test]
+    fn test_attach_checksum() {
+        let mut res = LambdaResponse::text(200, "123456789");
+        attach_checksum(&mut res, BodyChecksum::Crc32c);
+        assert_eq!(res.header("x-content-crc32c"), Some("e3069283"));
+
+        let mut res = LambdaResponse::text(200, "");
+        res.body = base64::encode("abc");
+        res.is_base64 = true;
+        attach_checksum(&mut res, BodyChecksum::Sha256);
+        assert_eq!(
+            res.header("x-content-sha256"),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+    }
+}
@@ -0,0 +1,576 @@
+// SPDX-License-Identifier: MIT
+//!
+//! WebSocket connection store
+//! Tracks connection IDs of `$connect` and `$disconnect` events,
+//! to broadcast messages by `ConnectionsClient`
+//!
+use crate::idempotency::StoreFuture;
+use crate::request::LambdaHttpEvent;
+use crate::response::LambdaResponse;
+use lambda_runtime::Error as LambdaError;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Connected WebSocket client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketConnection {
+    pub connection_id: String,
+    /// Domain name of the API, to post messages by `ConnectionsClient`
+    pub domain_name: String,
+    pub stage: Option<String>,
+    /// Unix time in milliseconds
+    pub connected_at: u64,
+}
+
+impl WebSocketConnection {
+    /// `@connections` client of the API stage the client is connected to
+    pub fn client(&self) -> crate::ConnectionsClient {
+        match &self.stage {
+            Some(stage) => {
+                crate::ConnectionsClient::new(format!("https://{}/{}", self.domain_name, stage))
+            }
+            None => crate::ConnectionsClient::new(format!("https://{}", self.domain_name)),
+        }
+    }
+}
+
+/// Storage of WebSocket connections, set by `RunOptions::websocket_connection_store`
+///
+/// Connections are put when the app accepts `$connect` with 2xx response,
+/// and deleted on `$disconnect`. Implement this trait for other databases,
+/// or use `MemoryConnectionStore` for tests.
+///
+/// Stores may also keep protocol state of each connection, e.g. GraphQL subscriptions,
+/// as each Lambda invocation handles a single WebSocket frame.
+pub trait ConnectionStore: Send + Sync {
+    /// Store the connection
+    fn put<'a>(
+        &'a self,
+        connection: &'a WebSocketConnection,
+    ) -> StoreFuture<'a, Result<(), LambdaError>>;
+
+    /// Remove the connection, Ok when not stored
+    fn delete<'a>(&'a self, connection_id: &'a str) -> StoreFuture<'a, Result<(), LambdaError>>;
+
+    /// All stored connections
+    fn list(&self) -> StoreFuture<'_, Result<Vec<WebSocketConnection>, LambdaError>>;
+
+    /// Protocol state of the connection saved by `save_state`, None when not saved.
+    /// Not supported by default.
+    fn load_state<'a>(
+        &'a self,
+        _connection_id: &'a str,
+    ) -> StoreFuture<'a, Result<Option<Value>, LambdaError>> {
+        Box::pin(async { Err("connection state is not supported by this store".into()) })
+    }
+
+    /// Replace the protocol state of the stored connection, Err when the connection is not stored.
+    /// The state is deleted with the connection. Not supported by default.
+    fn save_state<'a>(
+        &'a self,
+        _connection_id: &'a str,
+        _state: &'a Value,
+    ) -> StoreFuture<'a, Result<(), LambdaError>> {
+        Box::pin(async { Err("connection state is not supported by this store".into()) })
+    }
+}
+
+/// In-memory connection store
+///
+/// Connections live only in a warm Lambda container,
+/// clients connected via other containers are not listed.
+#[derive(Default)]
+pub struct MemoryConnectionStore {
+    connections: Mutex<BTreeMap<String, (WebSocketConnection, Option<Value>)>>,
+}
+
+impl MemoryConnectionStore {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConnectionStore for MemoryConnectionStore {
+    fn put<'a>(
+        &'a self,
+        connection: &'a WebSocketConnection,
+    ) -> StoreFuture<'a, Result<(), LambdaError>> {
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(connection.connection_id.clone(), (connection.clone(), None));
+        Box::pin(async { Ok(()) })
+    }
+
+    fn delete<'a>(&'a self, connection_id: &'a str) -> StoreFuture<'a, Result<(), LambdaError>> {
+        self.connections.lock().unwrap().remove(connection_id);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn list(&self) -> StoreFuture<'_, Result<Vec<WebSocketConnection>, LambdaError>> {
+        let connections = self
+            .connections
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(connection, _)| connection.clone())
+            .collect();
+        Box::pin(async { Ok(connections) })
+    }
+
+    fn load_state<'a>(
+        &'a self,
+        connection_id: &'a str,
+    ) -> StoreFuture<'a, Result<Option<Value>, LambdaError>> {
+        let state = self
+            .connections
+            .lock()
+            .unwrap()
+            .get(connection_id)
+            .and_then(|(_, state)| state.clone());
+        Box::pin(async { Ok(state) })
+    }
+
+    fn save_state<'a>(
+        &'a self,
+        connection_id: &'a str,
+        state: &'a Value,
+    ) -> StoreFuture<'a, Result<(), LambdaError>> {
+        let saved = match self.connections.lock().unwrap().get_mut(connection_id) {
+            Some((_, saved)) => {
+                *saved = Some(state.clone());
+                Ok(())
+            }
+            None => Err(format!("WebSocket connection {} is not stored", connection_id).into()),
+        };
+        Box::pin(async { saved })
+    }
+}
+
+/// Change of the connection by the WebSocket event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConnectionUpdate {
+    Connect(WebSocketConnection),
+    Disconnect(String),
+}
+
+impl ConnectionUpdate {
+    /// Update by `$connect` or `$disconnect` event, None for messages
+    pub(crate) fn from_event(event: &LambdaHttpEvent) -> Option<Self> {
+        if !event.is_websocket() {
+            return None;
+        }
+        let connection_id = event
+            .header(crate::WEBSOCKET_CONNECTION_ID_HEADER)?
+            .to_string();
+        match event.header(crate::WEBSOCKET_EVENT_TYPE_HEADER)? {
+            "CONNECT" => Some(Self::Connect(WebSocketConnection {
+                connection_id,
+                domain_name: event.hostname()?.to_string(),
+                stage: event.request_context().stage,
+                connected_at: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0),
+            })),
+            "DISCONNECT" => Some(Self::Disconnect(connection_id)),
+            _ => None,
+        }
+    }
+
+    /// Apply to the store after the app responded.
+    /// Connections failed to be stored are rejected with 500.
+    pub(crate) async fn apply(
+        self,
+        store: &Arc<dyn ConnectionStore>,
+        response: &mut LambdaResponse,
+    ) {
+        match self {
+            Self::Connect(connection) if (200..300).contains(&response.status) => {
+                if let Err(err) = store.put(&connection).await {
+                    tracing::error!(
+                        "failed to store WebSocket connection {}: {}",
+                        connection.connection_id,
+                        err
+                    );
+                    *response = LambdaResponse::text(500, "Internal Server Error");
+                }
+            }
+            Self::Connect(_) => {}
+            Self::Disconnect(connection_id) => {
+                if let Err(err) = store.delete(&connection_id).await {
+                    tracing::warn!(
+                        "failed to delete WebSocket connection {}: {}",
+                        connection_id,
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "dynamodb")]
+pub use dynamodb::DynamoDbConnectionStore;
+
+#[cfg(feature = "dynamodb")]
+mod dynamodb {
+    use super::*;
+    use crate::sigv4::{AwsTransport, Credentials, SignedRequest};
+    use serde_json::json;
+    use std::time::Duration;
+
+    /// Connection store of a DynamoDB table, signed by SigV4 and sent by `AwsTransport`
+    ///
+    /// The table has `connectionId` string partition key. Items have `domainName`, `stage`,
+    /// `connectedAt` and optionally `expiresAt` (for DynamoDB TTL) attributes,
+    /// and `state` JSON string saved by `save_state`.
+    /// The execution role needs `dynamodb:PutItem`, `DeleteItem` and `Scan` permissions,
+    /// and `GetItem` and `UpdateItem` to keep connection state.
+    ///
+    /// ```no_run
+    /// use lambda_web::{AwsTransport, DynamoDbConnectionStore, RunOptions};
+    /// use std::time::Duration;
+    /// # fn example(transport: impl AwsTransport + 'static) {
+    /// let store = DynamoDbConnectionStore::new("websocket-connections")
+    ///     .ttl(Duration::from_secs(2 * 60 * 60))
+    ///     .transport(transport);
+    /// let options = RunOptions::default().websocket_connection_store(store);
+    /// # }
+    /// ```
+    #[derive(Clone)]
+    pub struct DynamoDbConnectionStore {
+        table: String,
+        region: String,
+        credentials: Option<Credentials>,
+        transport: Option<Arc<dyn AwsTransport>>,
+        ttl: Option<Duration>,
+    }
+
+    impl DynamoDbConnectionStore {
+        /// Store in the table, in `AWS_REGION` with the execution role credentials
+        pub fn new<S: Into<String>>(table: S) -> Self {
+            Self {
+                table: table.into(),
+                region: std::env::var("AWS_REGION").unwrap_or_default(),
+                credentials: Credentials::from_env(),
+                transport: None,
+                ttl: None,
+            }
+        }
+
+        /// Override the region
+        pub fn region<S: Into<String>>(mut self, region: S) -> Self {
+            self.region = region.into();
+            self
+        }
+
+        /// Override the credentials
+        pub fn credentials(mut self, credentials: Credentials) -> Self {
+            self.credentials = Some(credentials);
+            self
+        }
+
+        /// HTTPS client to send requests
+        pub fn transport<T: AwsTransport + 'static>(mut self, transport: T) -> Self {
+            self.transport = Some(Arc::new(transport));
+            self
+        }
+
+        /// Set `expiresAt` (Unix time in seconds) on items, for connections whose
+        /// `$disconnect` is lost. API Gateway closes connections after 2 hours.
+        pub fn ttl(mut self, ttl: Duration) -> Self {
+            self.ttl = Some(ttl);
+            self
+        }
+
+        fn item(&self, connection: &WebSocketConnection) -> Value {
+            let mut item = json!({
+                "connectionId": {"S": connection.connection_id},
+                "domainName": {"S": connection.domain_name},
+                "connectedAt": {"N": connection.connected_at.to_string()}
+            });
+            if let Some(stage) = &connection.stage {
+                item["stage"] = json!({ "S": stage });
+            }
+            if let Some(ttl) = self.ttl {
+                let expires_at = connection.connected_at / 1000 + ttl.as_secs();
+                item["expiresAt"] = json!({"N": expires_at.to_string()});
+            }
+            item
+        }
+
+        fn sign(
+            &self,
+            target: &str,
+            body: &Value,
+            now: SystemTime,
+        ) -> Result<SignedRequest, LambdaError> {
+            let credentials = self
+                .credentials
+                .as_ref()
+                .ok_or("no AWS credentials to sign DynamoDB request")?;
+            let host = format!("dynamodb.{}.amazonaws.com", self.region);
+            let mut request = crate::sigv4::sign(
+                "POST",
+                &host,
+                "/",
+                body.to_string().into_bytes(),
+                &self.region,
+                "dynamodb",
+                credentials,
+                now,
+            );
+            request.headers.push((
+                "content-type".to_string(),
+                "application/x-amz-json-1.0".to_string(),
+            ));
+            request.headers.push((
+                "x-amz-target".to_string(),
+                format!("DynamoDB_20120810.{}", target),
+            ));
+            Ok(request)
+        }
+
+        async fn call(&self, target: &str, body: Value) -> Result<Value, LambdaError> {
+            let transport = self
+                .transport
+                .as_ref()
+                .ok_or("no AwsTransport is configured")?;
+            let response = transport
+                .send(self.sign(target, &body, SystemTime::now())?)
+                .await?;
+            if !(200..300).contains(&response.status) {
+                return Err(format!(
+                    "DynamoDB {} failed with {}: {}",
+                    target,
+                    response.status,
+                    String::from_utf8_lossy(&response.body)
+                )
+                .into());
+            }
+            Ok(serde_json::from_slice(&response.body)?)
+        }
+    }
+
+    /// Connection of the DynamoDB item, None without required attributes
+    fn connection_from_item(item: &Value) -> Option<WebSocketConnection> {
+        Some(WebSocketConnection {
+            connection_id: item["connectionId"]["S"].as_str()?.to_string(),
+            domain_name: item["domainName"]["S"].as_str()?.to_string(),
+            stage: item["stage"]["S"].as_str().map(String::from),
+            connected_at: item["connectedAt"]["N"]
+                .as_str()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0),
+        })
+    }
+
+    impl ConnectionStore for DynamoDbConnectionStore {
+        fn put<'a>(
+            &'a self,
+            connection: &'a WebSocketConnection,
+        ) -> StoreFuture<'a, Result<(), LambdaError>> {
+            Box::pin(async move {
+                let body = json!({ "TableName": self.table, "Item": self.item(connection) });
+                self.call("PutItem", body).await.map(|_| ())
+            })
+        }
+
+        fn delete<'a>(
+            &'a self,
+            connection_id: &'a str,
+        ) -> StoreFuture<'a, Result<(), LambdaError>> {
+            Box::pin(async move {
+                let body = json!({
+                    "TableName": self.table,
+                    "Key": {"connectionId": {"S": connection_id}}
+                });
+                self.call("DeleteItem", body).await.map(|_| ())
+            })
+        }
+
+        fn load_state<'a>(
+            &'a self,
+            connection_id: &'a str,
+        ) -> StoreFuture<'a, Result<Option<Value>, LambdaError>> {
+            Box::pin(async move {
+                let body = json!({
+                    "TableName": self.table,
+                    "Key": {"connectionId": {"S": connection_id}},
+                    "ProjectionExpression": "#state",
+                    "ExpressionAttributeNames": {"#state": "state"},
+                    "ConsistentRead": true
+                });
+                let response = self.call("GetItem", body).await?;
+                match response["Item"]["state"]["S"].as_str() {
+                    Some(state) => Ok(Some(serde_json::from_str(state)?)),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        fn save_state<'a>(
+            &'a self,
+            connection_id: &'a str,
+            state: &'a Value,
+        ) -> StoreFuture<'a, Result<(), LambdaError>> {
+            Box::pin(async move {
+                // Not to revive connections deleted by $disconnect
+                let body = json!({
+                    "TableName": self.table,
+                    "Key": {"connectionId": {"S": connection_id}},
+                    "UpdateExpression": "SET #state = :state",
+                    "ConditionExpression": "attribute_exists(connectionId)",
+                    "ExpressionAttributeNames": {"#state": "state"},
+                    "ExpressionAttributeValues": {":state": {"S": state.to_string()}}
+                });
+                self.call("UpdateItem", body).await.map(|_| ())
+            })
+        }
+
+        fn list(&self) -> StoreFuture<'_, Result<Vec<WebSocketConnection>, LambdaError>> {
+            Box::pin(async move {
+                let mut connections = Vec::new();
+                let mut start_key = None;
+                loop {
+                    let mut body = json!({ "TableName": self.table });
+                    if let Some(key) = start_key.take() {
+                        body["ExclusiveStartKey"] = key;
+                    }
+                    let mut page = self.call("Scan", body).await?;
+                    if let Some(items) = page["Items"].as_array() {
+                        connections.extend(items.iter().filter_map(connection_from_item));
+                    }
+                    match page.get_mut("LastEvaluatedKey").map(Value::take) {
+                        Some(key) if !key.is_null() => start_key = Some(key),
+                        _ => return Ok(connections),
+                    }
+                }
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_item() {
+            let store = DynamoDbConnectionStore::new("connections")
+                .region("ap-northeast-1")
+                .ttl(Duration::from_secs(7200));
+            let connection = WebSocketConnection {
+                connection_id: "L0SM9cOFvHcCIhw=".to_string(),
+                domain_name: "abcdef1234.execute-api.ap-northeast-1.amazonaws.com".to_string(),
+                stage: Some("prod".to_string()),
+                connected_at: 1760000000000,
+            };
+            let item = store.item(&connection);
+            assert_eq!(item["expiresAt"]["N"], "1760007200");
+            assert_eq!(connection_from_item(&item), Some(connection));
+        }
+
+        #[test]
+        fn test_sign() {
+            let store = DynamoDbConnectionStore::new("connections")
+                .region("ap-northeast-1")
+                .credentials(Credentials {
+                    access_key_id: "AKIDEXAMPLE".to_string(),
+                    secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                    session_token: None,
+                });
+            let request = store
+                .sign(
+                    "Scan",
+                    &json!({"TableName": "connections"}),
+                    SystemTime::now(),
+                )
+                .unwrap();
+            assert_eq!(
+                request.url,
+                "https://dynamodb.ap-northeast-1.amazonaws.com/"
+            );
+            assert!(request.headers.contains(&(
+                "x-amz-target".to_string(),
+                "DynamoDB_20120810.Scan".to_string()
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::test_consts::*;
+
+    fn websocket_event(event_str: &str) -> LambdaHttpEvent<'static> {
+        let routes = crate::WebSocketRoutes::new()
+            .route("$connect", "POST", "/ws/connections")
+            .route("sendmessage", "POST", "/ws/messages");
+        let options = crate::RunOptions::default().websocket_routes(routes);
+        let event: InvocationEvent = serde_json::from_str(event_str).unwrap();
+        event.into_http(&options).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connection_update() {
+        let store: Arc<dyn ConnectionStore> = Arc::new(MemoryConnectionStore::new());
+
+        let update = ConnectionUpdate::from_event(&websocket_event(WEBSOCKET_CONNECT)).unwrap();
+        // Rejected by the app
+        let mut response = LambdaResponse::text(401, "Unauthorized");
+        update.clone().apply(&store, &mut response).await;
+        assert!(store.list().await.unwrap().is_empty());
+
+        let mut response = LambdaResponse::text(200, "OK");
+        update.apply(&store, &mut response).await;
+        let connections = store.list().await.unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].connection_id, "L0SM9cOFvHcCIhw=");
+        assert_eq!(
+            connections[0].domain_name,
+            "abcdef1234.execute-api.ap-northeast-1.amazonaws.com"
+        );
+        assert_eq!(connections[0].stage.as_deref(), Some("prod"));
+
+        // Messages do not change connections
+        assert_eq!(
+            ConnectionUpdate::from_event(&websocket_event(WEBSOCKET_MESSAGE)),
+            None
+        );
+
+        let mut response = LambdaResponse::text(200, "OK");
+        ConnectionUpdate::Disconnect("L0SM9cOFvHcCIhw=".to_string())
+            .apply(&store, &mut response)
+            .await;
+        assert!(store.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_state() {
+        let store = MemoryConnectionStore::new();
+        let state = serde_json::json!({"subscriptions": {"1": {"query": "subscription { a }"}}});
+        assert!(store.save_state("L0SM9cOFvHcCIhw=", &state).await.is_err());
+
+        let update = ConnectionUpdate::from_event(&websocket_event(WEBSOCKET_CONNECT)).unwrap();
+        let store: Arc<dyn ConnectionStore> = Arc::new(store);
+        update
+            .apply(&store, &mut LambdaResponse::text(200, "OK"))
+            .await;
+        assert_eq!(store.load_state("L0SM9cOFvHcCIhw=").await.unwrap(), None);
+        store.save_state("L0SM9cOFvHcCIhw=", &state).await.unwrap();
+        assert_eq!(
+            store.load_state("L0SM9cOFvHcCIhw=").await.unwrap(),
+            Some(state)
+        );
+
+        // Deleted with the connection
+        store.delete("L0SM9cOFvHcCIhw=").await.unwrap();
+        assert_eq!(store.load_state("L0SM9cOFvHcCIhw=").await.unwrap(), None);
+    }
+}
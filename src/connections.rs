@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MIT
+//!
+//! WebSocket connections
+//! API Gateway Management API `@connections` client, to push messages back to WebSocket clients
+//!
+use crate::context::RequestContext;
+use crate::sigv4::{AwsTransport, Credentials, SignedRequest};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Error of `@connections` requests
+#[derive(Debug)]
+pub enum ConnectionsError {
+    /// 410 Gone, the client has disconnected
+    Gone,
+    /// Other error status code, e.g. 403 without `execute-api:ManageConnections` permission
+    Status(u16),
+    /// No credentials in the environment
+    NoCredentials,
+    /// Error of `AwsTransport`
+    Transport(lambda_runtime::Error),
+}
+
+impl std::fmt::Display for ConnectionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gone => write!(f, "WebSocket connection is gone"),
+            Self::Status(status) => write!(f, "@connections request failed with {}", status),
+            Self::NoCredentials => write!(f, "no AWS credentials to sign @connections request"),
+            Self::Transport(err) => write!(f, "@connections request failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionsError {}
+
+/// API Gateway Management API client of a WebSocket API stage
+///
+/// ```no_run
+/// use lambda_web::{AwsTransport, ConnectionsClient, RequestContext};
+/// # fn example(context: &RequestContext, connection_id: &str, transport: impl AwsTransport + 'static) {
+/// let client = ConnectionsClient::from_context(context)
+///     .expect("WebSocket request context")
+///     .transport(transport);
+/// # async {
+/// client.post_to_connection(connection_id, "hello").await.unwrap();
+/// # };
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ConnectionsClient {
+    endpoint: String,
+    region: String,
+    credentials: Option<Credentials>,
+    transport: Option<Arc<dyn AwsTransport>>,
+}
+
+impl ConnectionsClient {
+    /// Client of `https://{domain}/{stage}` endpoint,
+    /// signed by the execution role credentials in the region of the domain or `AWS_REGION`
+    pub fn new<S: Into<String>>(endpoint: S) -> Self {
+        let endpoint = endpoint.into().trim_end_matches('/').to_string();
+        let region = region_of_endpoint(&endpoint)
+            .map(|region| region.to_string())
+            .or_else(|| std::env::var("AWS_REGION").ok())
+            .unwrap_or_default();
+        Self {
+            endpoint,
+            region,
+            credentials: Credentials::from_env(),
+            transport: None,
+        }
+    }
+
+    /// Client of the API stage which received the WebSocket request
+    pub fn from_context(context: &RequestContext) -> Option<Self> {
+        let domain_name = context.domain_name.as_ref()?;
+        let stage = context.stage.as_ref()?;
+        Some(Self::new(format!("https://{}/{}", domain_name, stage)))
+    }
+
+    /// Override the region, e.g. for custom domain names
+    pub fn region<S: Into<String>>(mut self, region: S) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Override the credentials
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// HTTPS client to send requests
+    pub fn transport<T: AwsTransport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Signed PostToConnection request
+    pub fn sign_post<B: Into<Vec<u8>>>(
+        &self,
+        connection_id: &str,
+        data: B,
+    ) -> Result<SignedRequest, ConnectionsError> {
+        self.sign("POST", connection_id, data.into(), SystemTime::now())
+    }
+
+    /// Signed DeleteConnection request
+    pub fn sign_delete(&self, connection_id: &str) -> Result<SignedRequest, ConnectionsError> {
+        self.sign("DELETE", connection_id, Vec::new(), SystemTime::now())
+    }
+
+    /// Send the message to the client
+    pub async fn post_to_connection<B: Into<Vec<u8>>>(
+        &self,
+        connection_id: &str,
+        data: B,
+    ) -> Result<(), ConnectionsError> {
+        self.send(self.sign_post(connection_id, data)?).await
+    }
+
+    /// Disconnect the client
+    pub async fn delete_connection(&self, connection_id: &str) -> Result<(), ConnectionsError> {
+        self.send(self.sign_delete(connection_id)?).await
+    }
+
+    async fn send(&self, request: SignedRequest) -> Result<(), ConnectionsError> {
+        let transport = self
+            .transport
+            .as_ref()
+            .ok_or_else(|| ConnectionsError::Transport("no AwsTransport is configured".into()))?;
+        match transport
+            .send(request)
+            .await
+            .map_err(ConnectionsError::Transport)?
+            .status
+        {
+            200..=299 => Ok(()),
+            410 => Err(ConnectionsError::Gone),
+            status => Err(ConnectionsError::Status(status)),
+        }
+    }
+
+    fn sign(
+        &self,
+        method: &'static str,
+        connection_id: &str,
+        body: Vec<u8>,
+        now: SystemTime,
+    ) -> Result<SignedRequest, ConnectionsError> {
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or(ConnectionsError::NoCredentials)?;
+        let (host, stage_path) = split_endpoint(&self.endpoint);
+        let path = format!(
+            "{}/@connections/{}",
+            stage_path,
+            crate::sigv4::uri_encode(connection_id)
+        );
+        let mut request = crate::sigv4::sign(
+            method,
+            host,
+            &path,
+            body,
+            &self.region,
+            "execute-api",
+            credentials,
+            now,
+        );
+        if method == "POST" {
+            request.headers.push((
+                "content-type".to_string(),
+                "application/octet-stream".to_string(),
+            ));
+        }
+        Ok(request)
+    }
+}
+
+/// `abcdef1234.execute-api.ap-northeast-1.amazonaws.com` to `ap-northeast-1`
+fn region_of_endpoint(endpoint: &str) -> Option<&str> {
+    let (host, _) = split_endpoint(endpoint);
+    let (_, rest) = host.split_once(".execute-api.")?;
+    rest.split('.').next()
+}
+
+/// `https://{host}/{stage}` to host and `/{stage}`
+fn split_endpoint(endpoint: &str) -> (&str, &str) {
+    let without_scheme = endpoint.strip_prefix("https://").unwrap_or(endpoint);
+    match without_scheme.find('/') {
+        Some(slash) => without_scheme.split_at(slash),
+        None => (without_scheme, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn client() -> ConnectionsClient {
+        ConnectionsClient::new("https://abcdef1234.execute-api.ap-northeast-1.amazonaws.com/prod/")
+            .credentials(Credentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                session_token: None,
+            })
+    }
+
+    #[test]
+    fn test_sign() {
+        let client = client();
+        assert_eq!(client.region, "ap-northeast-1");
+
+        // 2015-08-30T12:36:00Z
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1440938160);
+        let request = client
+            .sign("POST", "L0SM9cOFvHcCIhw=", b"hello".to_vec(), now)
+            .unwrap();
+        assert_eq!(
+            request.url,
+            "https://abcdef1234.execute-api.ap-northeast-1.amazonaws.com/prod/@connections/L0SM9cOFvHcCIhw%3D"
+        );
+        let header = |name: &str| {
+            request
+                .headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(header("x-amz-date"), Some("20150830T123600Z"));
+        let authorization = header("authorization").unwrap();
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/ap-northeast-1/execute-api/aws4_request, SignedHeaders=host;x-amz-date, Signature="
+        ));
+        assert_eq!(request.body, b"hello");
+
+        let request = client
+            .sign("DELETE", "L0SM9cOFvHcCIhw=", Vec::new(), now)
+            .unwrap();
+        assert_eq!(request.method, "DELETE");
+        assert!(!request.headers.iter().any(|(n, _)| n == "content-type"));
+    }
+
+    #[test]
+    fn test_from_context() {
+        let context = RequestContext {
+            request_id: None,
+            domain_name: Some("abcdef1234.execute-api.us-east-1.amazonaws.com".to_string()),
+            stage: Some("dev".to_string()),
+        };
+        let client = ConnectionsClient::from_context(&context).unwrap();
+        assert_eq!(
+            client.endpoint,
+            "https://abcdef1234.execute-api.us-east-1.amazonaws.com/dev"
+        );
+        assert_eq!(client.region, "us-east-1");
+    }
+}
@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Request context and caller identity passed to web frameworks
+//!
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// API Gateway request context
+///
+/// Attached to hyper request extensions, so tower layers and
+/// warp filters (`warp::ext::get::<RequestContext>()`) can read it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    /// The ID that API Gateway assigns to the API request.
+    pub request_id: Option<String>,
+    /// The full domain name used to invoke the API.
+    pub domain_name: Option<String>,
+    /// The deployment stage of the API request.
+    pub stage: Option<String>,
+}
+
+impl RequestContext {
+    /// Deployment stage with the domain name, to build stage-scoped URLs
+    pub fn deployment_stage(&self) -> Option<Stage> {
+        self.stage
+            .as_ref()
+            .map(|name| Stage::new(name.clone(), self.domain_name.clone()))
+    }
+}
+
+/// Deployment stage of the API, e.g. "prod" or "dev"
+///
+/// On the default `execute-api` domain, URLs of a named stage start with `/{stage}`.
+/// HTTP API `$default` stage and custom domains have no stage prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    name: String,
+    domain_name: Option<String>,
+}
+
+impl Stage {
+    /// Stage of `name`, served at `domain_name`
+    pub fn new<S: Into<String>>(name: S, domain_name: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            domain_name,
+        }
+    }
+
+    /// Stage name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// HTTP API `$default` stage
+    pub fn is_default(&self) -> bool {
+        self.name == "$default"
+    }
+
+    /// Path prefix of the stage, `/{stage}` on `execute-api` domain or empty
+    pub fn path_prefix(&self) -> String {
+        let execute_api = self
+            .domain_name
+            .as_deref()
+            .map(|domain| domain.contains(".execute-api."))
+            .unwrap_or(false);
+        if execute_api && !self.is_default() {
+            format!("/{}", self.name)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Stage-scoped absolute path, `path` is the path seen by the app
+    pub fn path(&self, path: &str) -> String {
+        let slash = if path.starts_with('/') { "" } else { "/" };
+        format!("{}{}{}", self.path_prefix(), slash, path)
+    }
+
+    /// Stage-scoped URL, None without the domain name
+    pub fn url(&self, path: &str) -> Option<String> {
+        self.domain_name
+            .as_ref()
+            .map(|domain| format!("https://{}{}", domain, self.path(path)))
+    }
+}
+
+/// Identity of the API caller
+///
+/// Attached to request extensions, as same as `RequestContext`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallerIdentity {
+    /// The source IP address of the TCP connection making the request to API Gateway.
+    pub source_ip: Option<IpAddr>,
+    /// The AWS access key associated with the request. (REST API, HTTP API or function URL with IAM auth)
+    pub access_key: Option<String>,
+    /// The Amazon Resource Name (ARN) of the effective user. (REST API, HTTP API or function URL with IAM auth)
+    pub user_arn: Option<String>,
+    /// The AWS account ID of the caller. (REST API, HTTP API or function URL with IAM auth)
+    pub account_id: Option<String>,
+    /// The `sub` claim of the verified OIDC token. (ALB with OIDC auth, `alb-oidc` feature)
+    pub oidc_subject: Option<String>,
+    /// All claims of the verified OIDC token. (ALB with OIDC auth, `alb-oidc` feature)
+    pub oidc_claims: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Lambda function URL of the request
+///
+/// Function URL events are the same as HTTP API payload format 2.0,
+/// except the request context. Attached to request extensions for function URL requests only.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionUrlContext {
+    /// URL ID, the first label of the domain name
+    pub url_id: String,
+    /// Function URL domain, e.g. `<url-id>.lambda-url.<region>.on.aws`
+    pub domain_name: String,
+    /// AWS region in the domain name
+    pub region: Option<String>,
+    /// IAM identity of the caller, None unless the auth type is `AWS_IAM`
+    pub iam: Option<IamIdentity>,
+}
+
+/// IAM identity of the caller signing the request with SigV4
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IamIdentity {
+    /// The AWS access key of the caller
+    pub access_key: Option<String>,
+    /// The AWS account ID of the caller
+    pub account_id: Option<String>,
+    /// The caller ID, e.g. `AROA...:session-name`
+    pub caller_id: Option<String>,
+    /// The AWS Organizations ID of the caller's account
+    pub principal_org_id: Option<String>,
+    /// The Amazon Resource Name (ARN) of the caller
+    pub user_arn: Option<String>,
+    /// The IAM user ID of the caller
+    pub user_id: Option<String>,
+}
+
+/// Lambda event JSON of the request, as received from the runtime
+///
+/// Attached to request extensions of API Gateway, ALB and function URL requests
+/// with `RunOptions::raw_event`, to read fields not exposed by `RequestContext`,
+/// e.g. stage variables or custom authorizer context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawEvent(Arc<serde_json::Value>);
+
+impl RawEvent {
+    pub(crate) fn new(event: serde_json::Value) -> Self {
+        Self(Arc::new(event))
+    }
+
+    /// Event JSON
+    pub fn json(&self) -> &serde_json::Value {
+        &self.0
+    }
+}
+
+/// CloudFront viewer information, when API Gateway or ALB sits behind CloudFront
+///
+/// Parsed from `cloudfront-viewer-*` and `cloudfront-is-*-viewer` headers,
+/// which CloudFront adds when they are in the origin request policy.
+/// Attached to hyper and Actix-web request extensions,
+/// and to Rocket request local cache as `Option<ViewerInfo>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ViewerInfo {
+    /// CloudFront request ID (`x-amz-cf-id`)
+    pub cf_id: Option<String>,
+    /// Two-letter country code (`cloudfront-viewer-country`)
+    pub country: Option<String>,
+    /// Region code in ISO 3166-2 (`cloudfront-viewer-country-region`)
+    pub country_region: Option<String>,
+    /// City name (`cloudfront-viewer-city`)
+    pub city: Option<String>,
+    /// Time zone in IANA format, e.g. "Asia/Tokyo" (`cloudfront-viewer-time-zone`)
+    pub time_zone: Option<String>,
+    /// Viewer IP address and port (`cloudfront-viewer-address`)
+    pub address: Option<String>,
+    /// `cloudfront-is-mobile-viewer`
+    pub is_mobile: Option<bool>,
+    /// `cloudfront-is-tablet-viewer`
+    pub is_tablet: Option<bool>,
+    /// `cloudfront-is-desktop-viewer`
+    pub is_desktop: Option<bool>,
+    /// `cloudfront-is-smarttv-viewer`
+    pub is_smarttv: Option<bool>,
+}
+
+impl ViewerInfo {
+    /// Is the request forwarded by CloudFront?
+    pub fn is_cloudfront(&self) -> bool {
+        self.cf_id.is_some()
+    }
+}
+
+/// User-Agent and User-Agent client hints of the request
+///
+/// Parsed from the request context and `sec-ch-ua*` headers.
+/// Browsers send hints other than `sec-ch-ua`, `sec-ch-ua-mobile` and `sec-ch-ua-platform`
+/// only when the server asked for them by `Accept-CH` response header.
+/// Attached to hyper and Actix-web request extensions,
+/// and to Rocket request local cache as `Option<ClientHints>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHints {
+    /// User-Agent of the API caller
+    pub user_agent: Option<String>,
+    /// Brands and their significant versions (`sec-ch-ua`)
+    pub brands: Vec<Brand>,
+    /// Mobile device or not (`sec-ch-ua-mobile`)
+    pub mobile: Option<bool>,
+    /// Platform, e.g. "Windows", "Android" (`sec-ch-ua-platform`)
+    pub platform: Option<String>,
+    /// Platform version (`sec-ch-ua-platform-version`)
+    pub platform_version: Option<String>,
+    /// Device model (`sec-ch-ua-model`)
+    pub model: Option<String>,
+}
+
+/// Brand in `sec-ch-ua` client hint
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Brand {
+    /// Brand name, e.g. "Google Chrome"
+    pub brand: String,
+    /// Version, e.g. "118"
+    pub version: String,
+}
+
+impl ClientHints {
+    /// Is the brand in `sec-ch-ua`?
+    pub fn has_brand(&self, brand: &str) -> bool {
+        self.brands.iter().any(|b| b.brand == brand)
+    }
+}
+
+/// Parse structured header string, e.g. `"Android"`
+pub(crate) fn parse_sf_string(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Parse structured header boolean, `?1` or `?0`
+pub(crate) fn parse_sf_boolean(value: &str) -> Option<bool> {
+    match value.trim() {
+        "?1" => Some(true),
+        "?0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse brand list, e.g. `"Chromium";v="118", "Google Chrome";v="118"`
+pub(crate) fn parse_brands(value: &str) -> Vec<Brand> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let brand = parse_sf_string(params.next()?);
+            if brand.is_empty() {
+                return None;
+            }
+            let version = params
+                .filter_map(|param| param.trim().strip_prefix("v="))
+                .map(parse_sf_string)
+                .next()
+                .unwrap_or_default();
+            Some(Brand { brand, version })
+        })
+        .collect()
+}
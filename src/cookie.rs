@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Cookies
+//! Request cookies parsed once for all adapters, and SameSite defaults of response cookies
+//!
+use crate::request::LambdaHttpEvent;
+use crate::response::LambdaResponse;
+use std::borrow::Cow;
+
+/// Cookies of the request
+///
+/// HTTP API sends cookies in `cookies` array, REST API and ALB in `Cookie` headers.
+/// Both are parsed here, so all web frameworks see the same cookies.
+/// Also attached to hyper request extensions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar {
+    /// (name, value) as sent by the client, values are percent encoded
+    cookies: Vec<(String, String)>,
+}
+
+impl CookieJar {
+    /// Cookies in the event, entries without name are dropped
+    pub(crate) fn from_event(event: &LambdaHttpEvent) -> Self {
+        let cookies = event
+            .cookies()
+            .iter()
+            .filter_map(|cookie| split_cookie(cookie))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Self { cookies }
+    }
+
+    /// Percent decoded value of the first cookie with the name
+    pub fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.get_raw(name).map(decode)
+    }
+
+    /// Value of the first cookie with the name, as sent by the client
+    pub fn get_raw(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|(cookie_name, _)| cookie_name == name)
+            .map(|(_, value)| value as &str)
+    }
+
+    /// All cookies, name and percent decoded value
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Cow<'_, str>)> {
+        self.cookies
+            .iter()
+            .map(|(name, value)| (name as &str, decode(value)))
+    }
+
+    /// Number of cookies
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    /// No cookies?
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    /// Single `Cookie` header value, None without cookies
+    pub(crate) fn header_value(&self) -> Option<String> {
+        if self.cookies.is_empty() {
+            return None;
+        }
+        Some(
+            self.cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+}
+
+/// Trimmed name and value of `name=value`, None without `=` or name
+pub(crate) fn split_cookie(cookie: &str) -> Option<(&str, &str)> {
+    let (name, value) = cookie.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some((name, value.trim()))
+    }
+}
+
+fn decode(value: &str) -> Cow<'_, str> {
+    // Quoted values are allowed by RFC 6265
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    percent_encoding::percent_decode_str(value).decode_utf8_lossy()
+}
+
+/// `SameSite` attribute added to response cookies without it, set by `RunOptions::cookie_same_site`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// `SameSite=Strict`
+    Strict,
+    /// `SameSite=Lax`
+    Lax,
+    /// `SameSite=None`, `Secure` is also added as browsers require it
+    None,
+}
+
+impl SameSite {
+    fn attribute(&self) -> &'static str {
+        match self {
+            Self::Strict => "SameSite=Strict",
+            Self::Lax => "SameSite=Lax",
+            Self::None => "SameSite=None",
+        }
+    }
+
+    /// Set-Cookie value with the default attribute, None when no change is needed
+    fn apply(&self, set_cookie: &str) -> Option<String> {
+        let attributes = set_cookie
+            .split(';')
+            .skip(1)
+            .map(|attr| attr.trim().to_ascii_lowercase())
+            .collect::<Vec<_>>();
+        if attributes.iter().any(|attr| attr.starts_with("samesite")) {
+            return None;
+        }
+        let mut cookie = format!("{}; {}", set_cookie.trim_end_matches(';'), self.attribute());
+        if *self == Self::None && !attributes.iter().any(|attr| attr == "secure") {
+            cookie.push_str("; Secure");
+        }
+        Some(cookie)
+    }
+}
+
+/// Add the default `SameSite` attribute to response cookies, in all response formats
+pub(crate) fn apply_same_site(response: &mut LambdaResponse, same_site: SameSite) {
+    let set_cookies = response
+        .cookies
+        .iter_mut()
+        .chain(response.headers.get_mut("set-cookie"))
+        .chain(
+            response
+                .multi_value_headers
+                .get_mut("set-cookie")
+                .into_iter()
+                .flatten(),
+        );
+    for set_cookie in set_cookies {
+        if let Some(cookie) = same_site.apply(set_cookie) {
+            *set_cookie = cookie;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ResponseFormat;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_cookie_jar() {
+        for event_str in [
+            API_GATEWAY_V2_GET_TWO_COOKIES,
+            API_GATEWAY_REST_GET_TWO_COOKIES,
+        ] {
+            let event: LambdaHttpEvent = serde_json::from_str(event_str).unwrap();
+            let jar = CookieJar::from_event(&event);
+            assert_eq!(jar.len(), 2);
+            assert_eq!(jar.get("cookie1").as_deref(), Some("value1"));
+            assert_eq!(jar.get("cookie3"), None);
+            assert_eq!(
+                jar.header_value().unwrap(),
+                "cookie1=value1; cookie2=value2"
+            );
+        }
+
+        let event_str = API_GATEWAY_V2_GET_TWO_COOKIES.replace(
+            r#""cookies":["#,
+            r#""cookies":["name=%E3%81%82%20b","quoted=\"v\"","=noname","#,
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let jar = CookieJar::from_event(&event);
+        assert_eq!(jar.get("name").as_deref(), Some("あ b"));
+        assert_eq!(jar.get_raw("name"), Some("%E3%81%82%20b"));
+        assert_eq!(jar.get("quoted").as_deref(), Some("v"));
+        assert_eq!(jar.len(), 4);
+
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let jar = CookieJar::from_event(&event);
+        assert!(jar.is_empty());
+        assert_eq!(jar.header_value(), None);
+    }
+
+    #[test]
+    fn test_same_site() {
+        assert_eq!(
+            SameSite::Lax.apply("key=value; Path=/;").unwrap(),
+            "key=value; Path=/; SameSite=Lax"
+        );
+        assert_eq!(SameSite::Lax.apply("key=value; samesite=strict"), None);
+        assert_eq!(
+            SameSite::None.apply("key=value").unwrap(),
+            "key=value; SameSite=None; Secure"
+        );
+        assert_eq!(
+            SameSite::None.apply("key=value; Secure").unwrap(),
+            "key=value; Secure; SameSite=None"
+        );
+
+        let mut response = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        response.add_header("set-cookie", "a=1");
+        response.add_header("set-cookie", "b=2; SameSite=None; Secure");
+        apply_same_site(&mut response, SameSite::Strict);
+        assert_eq!(
+            response.multi_value_headers["set-cookie"],
+            vec!["a=1; SameSite=Strict", "b=2; SameSite=None; Secure"]
+        );
+
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("set-cookie", "a=1");
+        apply_same_site(&mut response, SameSite::Lax);
+        assert_eq!(response.cookies, vec!["a=1; SameSite=Lax"]);
+    }
+}
@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Request correlation
+//! Propagates request ID and trace headers of the current request to outbound requests
+//!
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use core::future::Future;
+use std::sync::Arc;
+
+/// Header filled with API Gateway request ID when the request has none
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static CURRENT: Option<Arc<Correlation>>;
+}
+
+/// Headers of the current request, to be propagated to fan-out subrequests
+///
+/// Header names are configured by `RunOptions::propagate_headers`.
+/// Available while the web application handles the request, as a tokio task-local.
+/// Tasks spawned by `tokio::spawn` do not inherit it, re-scope them by `Correlation::scope`.
+///
+/// ```
+/// use lambda_web::Correlation;
+///
+/// // Add them to outbound requests, e.g. by reqwest::RequestBuilder::header
+/// let headers = Correlation::current()
+///     .map(|correlation| correlation.headers().to_vec())
+///     .unwrap_or_default();
+///
+/// # async fn spawn_fan_out() {
+/// // Spawned tasks run with the correlation of the request
+/// if let Some(correlation) = Correlation::current() {
+///     tokio::spawn(correlation.scope(async {
+///         assert!(Correlation::current().is_some());
+///     }));
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Correlation {
+    headers: Vec<(String, String)>,
+}
+
+impl Correlation {
+    /// Correlation of the request being handled, None outside of the handler
+    pub fn current() -> Option<Correlation> {
+        CURRENT
+            .try_with(|current| current.as_deref().cloned())
+            .ok()
+            .flatten()
+    }
+
+    /// Run the future with this correlation as current, e.g. a task to be spawned
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(Some(Arc::new(self)), fut).await
+    }
+
+    /// Header names (lowercase) and values to propagate
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+/// Capture configured headers from the request
+pub(crate) fn capture(event: &LambdaHttpEvent, options: &RunOptions) -> Option<Arc<Correlation>> {
+    if options.propagate_headers.is_empty() {
+        return None;
+    }
+    let headers = options
+        .propagate_headers
+        .iter()
+        .filter_map(|name| {
+            let value = event.header(name).map(|v| v.to_string()).or_else(|| {
+                if name == REQUEST_ID_HEADER {
+                    event.request_context().request_id
+                } else {
+                    None
+                }
+            });
+            value.map(|value| (name.clone(), value))
+        })
+        .collect();
+    Some(Arc::new(Correlation { headers }))
+}
+
+/// Run the future with the correlation as current
+pub(crate) async fn scope<F: Future>(correlation: Option<Arc<Correlation>>, fut: F) -> F::Output {
+    CURRENT.scope(correlation, fut).await
+}
+
+/// Service wrapper adding headers of the current request to outbound hyper requests,
+/// headers already set are not overwritten
+///
+/// ```no_run
+/// use lambda_web::Correlated;
+/// use tower::ServiceExt;
+///
+/// # async fn fan_out() -> Result<(), hyper::Error> {
+/// let client = Correlated::new(hyper::Client::new());
+/// let req = hyper::Request::get("http://backend.internal/items")
+///     .body(hyper::Body::empty())
+///     .unwrap();
+/// let res = client.oneshot(req).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "hyper")]
+#[derive(Debug, Clone)]
+pub struct Correlated<S>(S);
+
+#[cfg(feature = "hyper")]
+impl<S> Correlated<S> {
+    /// Wrap a hyper client or tower service
+    pub fn new(inner: S) -> Self {
+        Self(inner)
+    }
+
+    /// Wrapped service
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl<S, B> hyper::service::Service<hyper::Request<B>> for Correlated<S>
+where
+    S: hyper::service::Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: hyper::Request<B>) -> Self::Future {
+        use hyper::header::{HeaderName, HeaderValue};
+
+        if let Some(correlation) = Correlation::current() {
+            let headers = req.headers_mut();
+            for (name, value) in correlation.headers() {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(value),
+                ) {
+                    headers.entry(name).or_insert(value);
+                }
+            }
+        }
+        self.0.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[tokio::test]
+    async fn test_scope() {
+        let options = RunOptions::default().propagate_headers(["X-Request-Id", "traceparent"]);
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_WITH_CONTEXT).unwrap();
+        let correlation = capture(&event, &options);
+
+        assert_eq!(Correlation::current(), None);
+        let headers = scope(correlation.clone(), async {
+            tokio::task::yield_now().await;
+            Correlation::current().unwrap().headers().to_vec()
+        })
+        .await;
+        // Filled with API Gateway request ID
+        assert_eq!(
+            headers,
+            vec![(
+                REQUEST_ID_HEADER.to_string(),
+                "JKJaXmPLvHcESHA=".to_string()
+            )]
+        );
+        assert_eq!(Correlation::current(), None);
+
+        // Spawned tasks are re-scoped explicitly
+        let spawned = scope(correlation.clone(), async {
+            let correlation = Correlation::current().unwrap();
+            let unscoped = tokio::spawn(async { Correlation::current() });
+            let rescoped = tokio::spawn(correlation.scope(async { Correlation::current() }));
+            (unscoped.await.unwrap(), rescoped.await.unwrap())
+        })
+        .await;
+        assert_eq!(spawned.0, None);
+        assert_eq!(spawned.1.as_ref(), correlation.as_deref());
+
+        // Not configured
+        assert_eq!(capture(&event, &RunOptions::default()), None);
+    }
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Deserialize helpers for Lambda event JSON
+//!
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Deserialize headers with lowercase header names,
+/// ALB and local emulators may send mixed case header names.
+pub(crate) fn deserialize_lowercase_headers<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use std::collections::hash_map::Entry;
+
+    // API Gateway test console sends null
+    let headers = Option::<HashMap<String, String>>::deserialize(deserializer)?.unwrap_or_default();
+    let mut lowercase_headers = HashMap::with_capacity(headers.len());
+    for (k, v) in headers {
+        match lowercase_headers.entry(k.to_ascii_lowercase()) {
+            Entry::Occupied(mut entry) => {
+                // Same header in different case, join values
+                let joined: &mut String = entry.get_mut();
+                joined.push(',');
+                joined.push_str(&v);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(v);
+            }
+        }
+    }
+    Ok(lowercase_headers)
+}
+
+/// Deserialize multi-value headers with lowercase header names
+pub(crate) fn deserialize_lowercase_multi_value_headers<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    // API Gateway test console sends null
+    let headers =
+        Option::<HashMap<String, Vec<String>>>::deserialize(deserializer)?.unwrap_or_default();
+    let mut lowercase_headers = HashMap::<String, Vec<String>>::with_capacity(headers.len());
+    for (k, v) in headers {
+        lowercase_headers
+            .entry(k.to_ascii_lowercase())
+            .or_default()
+            .extend(v);
+    }
+    Ok(lowercase_headers)
+}
+
+/// Deserialize null as default value
+pub(crate) fn deserialize_null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
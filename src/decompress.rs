@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Response and request decompression
+//! Decodes pre-compressed response bodies for clients not accepting the content coding,
+//! and compressed request bodies for web frameworks
+//!
+use crate::request::{DecodeError, LambdaHttpEvent};
+use crate::response::LambdaResponse;
+use std::io::Read;
+
+/// Largest decompressed body, larger bodies are sent as they are
+const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Content codings accepted by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AcceptEncoding {
+    br: bool,
+    gzip: bool,
+}
+
+impl AcceptEncoding {
+    /// From `Accept-Encoding` header, codings with `q=0` are not accepted
+    pub(crate) fn from_event(event: &LambdaHttpEvent) -> Self {
+        let mut accept = Self {
+            br: false,
+            gzip: false,
+        };
+        let header = event
+            .header("accept-encoding")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        for elm in header.split(',') {
+            let mut params = elm.split(';');
+            let coding = params.next().unwrap_or_default().trim();
+            let rejected = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .map(|q| q <= 0.0)
+                    .unwrap_or(false)
+            });
+            if rejected {
+                continue;
+            }
+            match coding {
+                "br" => accept.br = true,
+                "gzip" | "x-gzip" => accept.gzip = true,
+                "*" => {
+                    accept.br = true;
+                    accept.gzip = true;
+                }
+                _ => {}
+            }
+        }
+        accept
+    }
+}
+
+/// Decompress the body when the client does not accept its `Content-Encoding`.
+/// Bodies failing to decompress are sent as they are.
+pub(crate) fn decompress_unsupported(response: &mut LambdaResponse, accept: AcceptEncoding) {
+    let coding = match response.header("content-encoding") {
+        Some(coding) => coding.trim().to_ascii_lowercase(),
+        None => return,
+    };
+    let body = if response.is_base64 {
+        match base64::decode(&response.body) {
+            Ok(body) => body,
+            Err(_) => return,
+        }
+    } else {
+        response.body.as_bytes().to_vec()
+    };
+    let decompressed = match coding.as_str() {
+        "br" if !accept.br => read_limited(brotli::Decompressor::new(&body[..], 4096)),
+        "gzip" | "x-gzip" if !accept.gzip => read_limited(flate2::read::GzDecoder::new(&body[..])),
+        _ => return,
+    };
+    let decompressed = match decompressed {
+        Some(decompressed) => decompressed,
+        None => {
+            tracing::warn!(
+                "failed to decompress {} response body for the client",
+                coding
+            );
+            return;
+        }
+    };
+
+    response.remove_header("content-encoding");
+    response.remove_header("content-length");
+    // The decompressed representation is not byte-identical
+    if let Some(etag) = response
+        .header("etag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .map(|etag| format!("W/{}", etag))
+    {
+        response.remove_header("etag");
+        response.add_header("etag", &etag);
+    }
+    if response.header("vary").is_none() {
+        response.add_header("vary", "accept-encoding");
+    }
+    response.body = base64::encode(decompressed);
+    response.is_base64 = true;
+}
+
+/// Decompress `Content-Encoding: br` or `gzip` request body,
+/// so web frameworks parse JSON or form bodies as they are.
+/// Other codings are passed to the web framework.
+pub(crate) fn decompress_request(event: &mut LambdaHttpEvent) -> Result<(), DecodeError> {
+    let coding = match event.header("content-encoding") {
+        Some(coding) => coding.trim().to_ascii_lowercase(),
+        None => return Ok(()),
+    };
+    if coding != "br" && coding != "gzip" && coding != "x-gzip" {
+        return Ok(());
+    }
+    let body = event.body_bytes()?;
+    let decompressed = match coding.as_str() {
+        "br" => read_limited(brotli::Decompressor::new(&body[..], 4096)),
+        _ => read_limited(flate2::read::GzDecoder::new(&body[..])),
+    }
+    .ok_or_else(|| DecodeError::Malformed(format!("failed to decompress {} body", coding)))?;
+
+    event.set_body(&decompressed);
+    event.remove_header("content-encoding");
+    event.remove_header("content-length");
+    Ok(())
+}
+
+/// Read all, None on errors or when larger than `MAX_DECOMPRESSED_SIZE`
+fn read_limited<R: Read>(reader: R) -> Option<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    reader
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decompressed)
+        .ok()?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return None;
+    }
+    Some(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ResponseFormat;
+    use crate::test_consts::*;
+    use std::io::Write;
+
+    fn event_accepting(accept_encoding: &str) -> AcceptEncoding {
+        let event_str = API_GATEWAY_V2_GET_ROOT_NOQUERY.replace(
+            r#""headers":{"#,
+            &format!(r#""headers":{{"accept-encoding":"{}","#, accept_encoding),
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        AcceptEncoding::from_event(&event)
+    }
+
+    #[test]
+    fn test_accept_encoding() {
+        assert_eq!(
+            event_accepting("gzip, deflate, br"),
+            AcceptEncoding {
+                br: true,
+                gzip: true
+            }
+        );
+        assert_eq!(
+            event_accepting("gzip;q=1.0, br;q=0"),
+            AcceptEncoding {
+                br: false,
+                gzip: true
+            }
+        );
+        assert_eq!(
+            event_accepting("identity"),
+            AcceptEncoding {
+                br: false,
+                gzip: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_decompress_unsupported() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"Hello, world!").unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let mut response = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        response.add_header("content-encoding", "gzip");
+        response.add_header("etag", "\"abc\"");
+        response.body = base64::encode(&gzipped);
+        response.is_base64 = true;
+
+        // Client accepting gzip
+        let mut accepted = response.clone();
+        decompress_unsupported(&mut accepted, event_accepting("gzip"));
+        assert_eq!(accepted.body, response.body);
+
+        // Client not accepting gzip
+        decompress_unsupported(&mut response, event_accepting("br"));
+        assert_eq!(response.body, base64::encode("Hello, world!"));
+        assert_eq!(response.header("content-encoding"), None);
+        assert_eq!(response.header("etag"), Some("W/\"abc\""));
+        assert_eq!(response.header("vary"), Some("accept-encoding"));
+
+        // Broken body is kept
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("content-encoding", "br");
+        response.body = base64::encode("not brotli");
+        response.is_base64 = true;
+        decompress_unsupported(&mut response, event_accepting("identity"));
+        assert_eq!(response.header("content-encoding"), Some("br"));
+    }
+
+    fn event_with_body(content_encoding: &str, body: &[u8]) -> LambdaHttpEvent<'static> {
+        let event_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(
+                r#""headers":{"#,
+                &format!(
+                    r#""headers":{{"content-encoding":"{}","content-length":"{}","#,
+                    content_encoding,
+                    body.len()
+                ),
+            )
+            .replace(r#""isBase64Encoded":false"#, r#""isBase64Encoded":true"#)
+            .replace(
+                r#""body":null"#,
+                &format!(r#""body":"{}""#, base64::encode(body)),
+            );
+        serde_json::from_str(&event_str).unwrap()
+    }
+
+    #[test]
+    fn test_decompress_request() {
+        let mut compressed = Vec::new();
+        {
+            let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 4, 22);
+            encoder.write_all(br#"{"hello":"world"}"#).unwrap();
+        }
+        let mut event = event_with_body("br", &compressed);
+        decompress_request(&mut event).unwrap();
+        assert_eq!(event.header("content-encoding"), None);
+        assert_eq!(event.header("content-length"), None);
+        assert_eq!(event.body().unwrap(), br#"{"hello":"world"}"#);
+
+        // Other codings are passed through
+        let mut event = event_with_body("deflate", b"raw");
+        decompress_request(&mut event).unwrap();
+        assert_eq!(event.header("content-encoding"), Some("deflate"));
+
+        // Broken body
+        let mut event = event_with_body("gzip", b"not gzip");
+        assert!(matches!(
+            decompress_request(&mut event),
+            Err(DecodeError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Deployment template generation
+//! Emits SAM template and CDK snippet matching the requirements of this crate,
+//! e.g. from build.rs or a small xtask binary.
+//!
+//! ```
+//! use lambda_web::deploy::{ApiKind, Architecture, DeployConfig};
+//!
+//! let config = DeployConfig::new("HelloFunction")
+//!     .architecture(Architecture::Arm64)
+//!     .api(ApiKind::HttpApi);
+//! let template = config.sam_template();
+//! assert!(template.contains("PayloadFormatVersion: \"2.0\""));
+//! ```
+//!
+
+/// CPU architecture of Lambda function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    /// x86_64
+    X86_64,
+    /// arm64 (Graviton)
+    Arm64,
+}
+
+impl Architecture {
+    /// Architecture of the build target,
+    /// from `CARGO_CFG_TARGET_ARCH` in build scripts. Defaults to x86_64.
+    pub fn from_build_target() -> Self {
+        match std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+            Ok("aarch64") => Self::Arm64,
+            _ => Self::X86_64,
+        }
+    }
+
+    fn sam_name(&self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86_64",
+            Self::Arm64 => "arm64",
+        }
+    }
+
+    fn cdk_name(&self) -> &'static str {
+        match self {
+            Self::X86_64 => "X86_64",
+            Self::Arm64 => "ARM_64",
+        }
+    }
+}
+
+/// How HTTP requests reach the function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKind {
+    /// API Gateway HTTP API with payload format 2.0
+    HttpApi,
+    /// API Gateway REST API with binary media types `*/*`
+    RestApi,
+    /// Lambda function URL
+    FunctionUrl,
+}
+
+/// Deployment configuration
+#[derive(Debug, Clone)]
+pub struct DeployConfig {
+    function_name: String,
+    architecture: Architecture,
+    memory_size: u32,
+    timeout: u32,
+    api: ApiKind,
+}
+
+impl DeployConfig {
+    /// Configuration with defaults, x86_64, 128 MB, 30 seconds, HTTP API
+    pub fn new<S: Into<String>>(function_name: S) -> Self {
+        Self {
+            function_name: function_name.into(),
+            architecture: Architecture::X86_64,
+            memory_size: 128,
+            timeout: 30,
+            api: ApiKind::HttpApi,
+        }
+    }
+
+    /// CPU architecture
+    pub fn architecture(mut self, architecture: Architecture) -> Self {
+        self.architecture = architecture;
+        self
+    }
+
+    /// Memory size in MB
+    pub fn memory_size(mut self, memory_size: u32) -> Self {
+        self.memory_size = memory_size;
+        self
+    }
+
+    /// Timeout in seconds
+    pub fn timeout(mut self, timeout: u32) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// API in front of the function
+    pub fn api(mut self, api: ApiKind) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// SAM template (YAML)
+    pub fn sam_template(&self) -> String {
+        let events = match self.api {
+            ApiKind::HttpApi => "      Events:
+        HttpApi:
+          Type: HttpApi
+          Properties:
+            PayloadFormatVersion: \"2.0\"
+"
+            .to_string(),
+            ApiKind::RestApi => "      Events:
+        RestApi:
+          Type: Api
+          Properties:
+            Path: /{proxy+}
+            Method: ANY
+            RestApiId: !Ref RestApi
+"
+            .to_string(),
+            ApiKind::FunctionUrl => "      FunctionUrlConfig:
+        AuthType: NONE
+"
+            .to_string(),
+        };
+        let rest_api = if self.api == ApiKind::RestApi {
+            "  RestApi:
+    Type: AWS::Serverless::Api
+    Properties:
+      StageName: Prod
+      BinaryMediaTypes:
+        - \"*~1*\"
+"
+        } else {
+            ""
+        };
+
+        format!(
+            "AWSTemplateFormatVersion: \"2010-09-09\"
+Transform: AWS::Serverless-2016-10-31
+Resources:
+  {name}:
+    Type: AWS::Serverless::Function
+    Metadata:
+      BuildMethod: rust-cargolambda
+    Properties:
+      CodeUri: .
+      Handler: bootstrap
+      Runtime: provided.al2
+      Architectures:
+        - {arch}
+      MemorySize: {memory}
+      Timeout: {timeout}
+{events}{rest_api}",
+            name = self.function_name,
+            arch = self.architecture.sam_name(),
+            memory = self.memory_size,
+            timeout = self.timeout,
+            events = events,
+            rest_api = rest_api,
+        )
+    }
+
+    /// CDK snippet (TypeScript), to be pasted in a Stack constructor
+    pub fn cdk_snippet(&self) -> String {
+        let function = format!(
+            "const fn = new lambda.Function(this, '{name}', {{
+  code: lambda.Code.fromAsset('target/lambda/{name}'),
+  handler: 'bootstrap',
+  runtime: lambda.Runtime.PROVIDED_AL2,
+  architecture: lambda.Architecture.{arch},
+  memorySize: {memory},
+  timeout: cdk.Duration.seconds({timeout}),
+}});
+",
+            name = self.function_name,
+            arch = self.architecture.cdk_name(),
+            memory = self.memory_size,
+            timeout = self.timeout,
+        );
+        let api = match self.api {
+            ApiKind::HttpApi => {
+                "new apigwv2.HttpApi(this, 'HttpApi', {
+  defaultIntegration: new HttpLambdaIntegration('Integration', fn, {
+    payloadFormatVersion: apigwv2.PayloadFormatVersion.VERSION_2_0,
+  }),
+});
+"
+            }
+            ApiKind::RestApi => {
+                "new apigw.LambdaRestApi(this, 'RestApi', {
+  handler: fn,
+  binaryMediaTypes: ['*/*'],
+});
+"
+            }
+            ApiKind::FunctionUrl => {
+                "fn.addFunctionUrl({
+  authType: lambda.FunctionUrlAuthType.NONE,
+});
+"
+            }
+        };
+        function + api
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sam_template() {
+        let template = DeployConfig::new("HelloFunction")
+            .architecture(Architecture::Arm64)
+            .memory_size(256)
+            .sam_template();
+        assert!(template.contains("  HelloFunction:\n"));
+        assert!(template.contains("Handler: bootstrap"));
+        assert!(template.contains("- arm64"));
+        assert!(template.contains("MemorySize: 256"));
+        assert!(template.contains("PayloadFormatVersion: \"2.0\""));
+
+        let template = DeployConfig::new("HelloFunction")
+            .api(ApiKind::RestApi)
+            .sam_template();
+        assert!(template.contains("BinaryMediaTypes"));
+        assert!(template.contains("- x86_64"));
+    }
+
+    #[test]
+    fn test_cdk_snippet() {
+        let snippet = DeployConfig::new("HelloFunction")
+            .api(ApiKind::RestApi)
+            .cdk_snippet();
+        assert!(snippet.contains("handler: 'bootstrap'"));
+        assert!(snippet.contains("binaryMediaTypes: ['*/*']"));
+    }
+}
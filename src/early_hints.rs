@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Early hints
+//! `Link` preload hints emitted by apps while they are building the response
+//!
+use std::sync::{Arc, Mutex};
+
+/// Early hints of a response, attached to hyper request extensions
+///
+/// Lambda response streaming has no interim responses, so 103 Early Hints can not be sent
+/// from the function itself. Hints are sent as `Link` headers of the streaming prelude,
+/// which goes out as soon as the app returns response headers and before the body is streamed.
+/// CDNs supporting Early Hints, e.g. Cloudflare, cache these `Link` headers
+/// and answer following requests with 103 before forwarding them to the function URL.
+/// Buffered responses get the same `Link` headers.
+/// Enable `streaming` feature and `RunOptions::response_mode` to stream responses.
+///
+/// ```no_run
+/// use axum::{response::Html, routing::get, Extension, Router};
+/// use lambda_web::{run_hyper_on_lambda, EarlyHints, LambdaError};
+///
+/// async fn page(Extension(hints): Extension<EarlyHints>) -> Html<&'static str> {
+///     hints.preload("/static/app.css", "style");
+///     hints.preconnect("https://api.example.com");
+///     Html("<html>...</html>")
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new().route("/", get(page));
+///     run_hyper_on_lambda(app).await
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EarlyHints {
+    links: Arc<Mutex<Vec<String>>>,
+}
+
+impl EarlyHints {
+    /// `Link: <href>; rel=preload; as=...`, e.g. `as_type` is `style`, `script` or `font`
+    pub fn preload(&self, href: &str, as_type: &str) {
+        let crossorigin = if as_type == "font" {
+            "; crossorigin"
+        } else {
+            ""
+        };
+        self.link(&format!(
+            "<{}>; rel=preload; as={}{}",
+            href, as_type, crossorigin
+        ));
+    }
+
+    /// `Link: <origin>; rel=preconnect`
+    pub fn preconnect(&self, origin: &str) {
+        self.link(&format!("<{}>; rel=preconnect", origin));
+    }
+
+    /// Any `Link` header value, duplicates are ignored
+    pub fn link(&self, value: &str) {
+        // Values breaking the header are dropped
+        if value.contains(|c: char| c.is_control()) {
+            return;
+        }
+        let mut links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+        if !links.iter().any(|link| link == value) {
+            links.push(value.to_string());
+        }
+    }
+
+    /// `Link` header values emitted so far
+    pub fn links(&self) -> Vec<String> {
+        self.links
+            .lock()
+            .map(|links| links.clone())
+            .unwrap_or_default()
+    }
+
+    /// Add hints to the response headers, except links already sent by the app
+    pub(crate) fn apply(&self, headers: &mut hyper::HeaderMap) {
+        for link in self.links() {
+            let exists = headers
+                .get_all(hyper::header::LINK)
+                .iter()
+                .any(|value| value.as_bytes() == link.as_bytes());
+            if exists {
+                continue;
+            }
+            if let Ok(value) = hyper::header::HeaderValue::from_str(&link) {
+                headers.append(hyper::header::LINK, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_early_hints() {
+        let hints = EarlyHints::default();
+        hints.preload("/app.css", "style");
+        hints.clone().preload("/font.woff2", "font");
+        hints.preconnect("https://api.example.com");
+        hints.preload("/app.css", "style");
+        hints.link("</x>\r\nx-injected: 1");
+        assert_eq!(
+            hints.links(),
+            vec![
+                "</app.css>; rel=preload; as=style",
+                "</font.woff2>; rel=preload; as=font; crossorigin",
+                "<https://api.example.com>; rel=preconnect",
+            ]
+        );
+
+        let mut headers = hyper::HeaderMap::new();
+        headers.append(
+            hyper::header::LINK,
+            "</app.css>; rel=preload; as=style".parse().unwrap(),
+        );
+        hints.apply(&mut headers);
+        assert_eq!(headers.get_all(hyper::header::LINK).iter().count(), 3);
+    }
+}
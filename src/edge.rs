@@ -0,0 +1,746 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Lambda@Edge
+//! Translates CloudFront Lambda@Edge events into HTTP requests,
+//! so web frameworks run at the edge, forward requests to origins and rewrite CloudFront responses.
+//!
+use crate::request::{LambdaHttpEvent, SyntheticRequest};
+use crate::response::{LambdaResponse, ResponseFormat};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Response header telling the response body is the request forwarded by request triggers
+pub const EDGE_FORWARD_HEADER: &str = "x-lambda-edge-forward";
+
+/// Lambda@Edge trigger
+///
+/// Viewer-request events are translated into the request from the viewer,
+/// and the response of the app is returned to the viewer without reaching the cache or origin.
+/// Request bodies are available when "Include body" is enabled on the trigger.
+/// Generated responses are limited to 40 KB by CloudFront.
+/// Origin-request events are translated in the same way, after the cache miss.
+///
+/// Request triggers return either a generated response, or the request forwarded
+/// to the cache or origin. hyper request extensions have `EdgeRequest`,
+/// which is modified and returned by `EdgeRequest::forward`.
+///
+/// Viewer-response and origin-response events are translated into the request CloudFront
+/// received. hyper request extensions have the trigger and the CloudFront response
+/// as `http::Response<()>`, the body of which is not available to Lambda@Edge.
+/// The app returns the response to CloudFront, with an empty body to keep the original body.
+/// Read-only headers of the trigger are kept as they are, and disallowed headers are dropped.
+///
+/// ```no_run
+/// use axum::{http::{Request, Response}, body::Body, Router};
+/// use lambda_web::{run_hyper_on_lambda, LambdaError};
+///
+/// async fn security_headers(req: Request<Body>) -> Response<Body> {
+///     let mut builder = Response::builder();
+///     if let Some(origin) = req.extensions().get::<Response<()>>() {
+///         builder = builder.status(origin.status());
+///         for (name, value) in origin.headers() {
+///             builder = builder.header(name, value);
+///         }
+///     }
+///     builder
+///         .header("strict-transport-security", "max-age=63072000")
+///         .body(Body::empty())
+///         .unwrap()
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new().fallback(security_headers);
+///     run_hyper_on_lambda(app).await
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeEventType {
+    /// When CloudFront receives a request from the viewer, before checking the cache
+    ViewerRequest,
+    /// Before CloudFront forwards the request to the origin, on cache miss
+    OriginRequest,
+    /// Before CloudFront returns the response to the viewer
+    ViewerResponse,
+    /// After CloudFront receives the response from the origin, before caching it
+    OriginResponse,
+}
+
+/// Headers Lambda@Edge functions must not add or change
+/// https://docs.aws.amazon.com/AmazonCloudFront/latest/DeveloperGuide/edge-functions-restrictions.html
+const DISALLOWED_HEADERS: &[&str] = &[
+    "connection",
+    "expect",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "trailer",
+    "upgrade",
+    "x-cache",
+    "x-forwarded-proto",
+    "x-real-ip",
+];
+const DISALLOWED_HEADER_PREFIXES: &[&str] = &["x-accel-", "x-amz-cf-", "x-edge-"];
+
+/// Trigger of the Lambda@Edge event, and the CloudFront response received by
+/// response triggers, body is not available to Lambda@Edge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EdgeResponse {
+    pub(crate) event_type: EdgeEventType,
+    /// None for request triggers
+    pub(crate) status: Option<u16>,
+    /// Lowercase name and value, in the order CloudFront sent
+    pub(crate) headers: Vec<(String, String)>,
+    /// Request JSON of request triggers, returned to forward it
+    pub(crate) request: Option<Value>,
+}
+
+impl EdgeResponse {
+    /// Read-only headers of the trigger, kept as CloudFront sent
+    fn read_only_headers(&self) -> &'static [&'static str] {
+        match self.event_type {
+            // Generated responses have no CloudFront response to keep
+            EdgeEventType::ViewerRequest | EdgeEventType::OriginRequest => &[],
+            EdgeEventType::ViewerResponse => &[
+                "content-encoding",
+                "content-length",
+                "transfer-encoding",
+                "via",
+                "warning",
+            ],
+            EdgeEventType::OriginResponse => &["transfer-encoding", "via"],
+        }
+    }
+
+    /// The CloudFront response as `http::Response`, attached to hyper request extensions
+    /// of response triggers
+    #[cfg(feature = "hyper")]
+    pub(crate) fn to_http(&self) -> Option<hyper::Response<()>> {
+        use hyper::header::{HeaderName, HeaderValue};
+        use std::str::FromStr;
+
+        let mut response = hyper::Response::new(());
+        *response.status_mut() =
+            hyper::StatusCode::from_u16(self.status?).unwrap_or(hyper::StatusCode::BAD_GATEWAY);
+        for (name, value) in self.headers.iter() {
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::from_str(name), HeaderValue::from_str(value))
+            {
+                response.headers_mut().append(name, value);
+            }
+        }
+        Some(response)
+    }
+
+    /// Request to be forwarded, only for request triggers
+    pub(crate) fn edge_request(&self) -> Option<EdgeRequest> {
+        self.request.clone().map(EdgeRequest)
+    }
+}
+
+/// Request of Lambda@Edge viewer-request or origin-request trigger
+///
+/// Attached to hyper request extensions of request triggers.
+/// Return `forward()` instead of a generated response to pass the request,
+/// optionally modified, to the cache or origin.
+/// Other web frameworks return a response with `EDGE_FORWARD_HEADER`
+/// and `to_json()` as body.
+///
+/// ```no_run
+/// use axum::{body::Body, http::{Request, Response}, Router};
+/// use lambda_web::{run_hyper_on_lambda, EdgeRequest, LambdaError};
+///
+/// async fn route_to_origin(req: Request<Body>) -> Response<Body> {
+///     let mut edge = req.extensions().get::<EdgeRequest>().cloned().unwrap();
+///     if edge.header("cloudfront-viewer-country") == Some("JP") {
+///         edge.set_origin_domain_name("origin.ap-northeast-1.example.com");
+///     }
+///     edge.forward()
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new().fallback(route_to_origin);
+///     run_hyper_on_lambda(app).await
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeRequest(Value);
+
+impl EdgeRequest {
+    /// Percent-encoded path
+    pub fn uri(&self) -> &str {
+        self.0["uri"].as_str().unwrap_or("/")
+    }
+
+    /// Rewrite the path, percent-encoded
+    pub fn set_uri<S: Into<String>>(&mut self, uri: S) {
+        self.0["uri"] = Value::from(uri.into());
+    }
+
+    /// Query string without `?`
+    pub fn querystring(&self) -> &str {
+        self.0["querystring"].as_str().unwrap_or_default()
+    }
+
+    /// Rewrite the query string, without `?`
+    pub fn set_querystring<S: Into<String>>(&mut self, querystring: S) {
+        self.0["querystring"] = Value::from(querystring.into());
+    }
+
+    /// First value of the header, case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.0["headers"][name.to_ascii_lowercase()][0]["value"].as_str()
+    }
+
+    /// Replace all values of the header
+    pub fn set_header<S: Into<String>>(&mut self, name: &str, value: S) {
+        let name = name.to_ascii_lowercase();
+        let header = serde_json::json!([{ "key": canonical_name(&name), "value": value.into() }]);
+        if !self.0["headers"].is_object() {
+            self.0["headers"] = Value::Object(Default::default());
+        }
+        self.0["headers"][name] = header;
+    }
+
+    /// Remove all values of the header
+    pub fn remove_header(&mut self, name: &str) {
+        if let Some(headers) = self.0["headers"].as_object_mut() {
+            headers.remove(&name.to_ascii_lowercase());
+        }
+    }
+
+    /// Origin of origin-request trigger, `{"custom":{...}}` or `{"s3":{...}}`
+    pub fn origin(&self) -> Option<&Value> {
+        self.0.get("origin")
+    }
+
+    /// Switch the origin domain name, the `host` header is updated too
+    pub fn set_origin_domain_name(&mut self, domain_name: &str) {
+        let origin = match self.0.get_mut("origin").and_then(Value::as_object_mut) {
+            Some(origin) => origin,
+            None => return,
+        };
+        for (_, origin) in origin.iter_mut() {
+            origin["domainName"] = Value::from(domain_name);
+        }
+        self.set_header("host", domain_name);
+    }
+
+    /// Request JSON returned to CloudFront
+    pub fn to_json(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// Response forwarding this request to the cache or origin
+    #[cfg(feature = "hyper")]
+    pub fn forward(&self) -> hyper::Response<hyper::Body> {
+        let mut response = hyper::Response::new(hyper::Body::from(self.to_json()));
+        let headers = response.headers_mut();
+        headers.insert(
+            EDGE_FORWARD_HEADER,
+            hyper::header::HeaderValue::from_static("1"),
+        );
+        headers.insert(
+            hyper::header::CONTENT_TYPE,
+            hyper::header::HeaderValue::from_static("application/json"),
+        );
+        response
+    }
+}
+
+/// Lambda@Edge event
+#[derive(Debug)]
+pub(crate) struct EdgeEvent {
+    config: CfConfig,
+    request: CfRequest,
+    /// Request JSON of request triggers
+    raw_request: Option<Value>,
+    /// None for request triggers
+    response: Option<CfResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EdgeRecords {
+    #[serde(rename = "Records")]
+    records: Vec<EdgeRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EdgeRecord {
+    cf: CfRecord,
+}
+
+#[derive(Deserialize, Debug)]
+struct CfRecord {
+    config: CfConfig,
+    request: Value,
+    response: Option<CfResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CfConfig {
+    distribution_domain_name: String,
+    event_type: String,
+    request_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CfRequest {
+    client_ip: String,
+    #[serde(default)]
+    headers: CfHeaders,
+    method: String,
+    #[serde(default)]
+    querystring: String,
+    uri: String,
+    /// With "Include body" option of request triggers
+    #[serde(default)]
+    body: Option<CfBody>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CfBody {
+    #[serde(default)]
+    data: String,
+    /// `base64` or `text`
+    encoding: String,
+    /// Bodies over 40 KB (viewer-request) or 1 MB (origin-request) are truncated
+    #[serde(default)]
+    input_truncated: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CfResponse {
+    #[serde(default)]
+    headers: CfHeaders,
+    status: String,
+}
+
+/// CloudFront headers, lowercase name to original case names and values
+type CfHeaders = BTreeMap<String, Vec<CfHeader>>;
+
+/// Header value, the original case `key` is not used
+#[derive(Deserialize, Debug)]
+struct CfHeader {
+    value: String,
+}
+
+impl<'de> Deserialize<'de> for EdgeEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let record = EdgeRecords::deserialize(deserializer)?
+            .records
+            .into_iter()
+            .next()
+            .ok_or_else(|| D::Error::custom("no Lambda@Edge record"))?
+            .cf;
+        let request = CfRequest::deserialize(&record.request).map_err(D::Error::custom)?;
+        match (record.config.event_type.as_str(), record.response) {
+            ("viewer-request", None) | ("origin-request", None) => Ok(Self {
+                config: record.config,
+                request,
+                raw_request: Some(record.request),
+                response: None,
+            }),
+            ("viewer-response", Some(response)) | ("origin-response", Some(response)) => Ok(Self {
+                config: record.config,
+                request,
+                raw_request: None,
+                response: Some(response),
+            }),
+            (event_type, _) => Err(D::Error::custom(format!(
+                "Lambda@Edge {} trigger is not supported",
+                event_type
+            ))),
+        }
+    }
+}
+
+impl EdgeEvent {
+    /// Request CloudFront received, with the CloudFront response attached
+    pub(crate) fn into_http<'a>(self) -> LambdaHttpEvent<'a> {
+        let event_type = match self.config.event_type.as_str() {
+            "viewer-request" => EdgeEventType::ViewerRequest,
+            "origin-request" => EdgeEventType::OriginRequest,
+            "viewer-response" => EdgeEventType::ViewerResponse,
+            _ => EdgeEventType::OriginResponse,
+        };
+        let (status, response_headers) = match self.response {
+            Some(response) => (
+                Some(response.status.parse().unwrap_or(502)),
+                response
+                    .headers
+                    .into_iter()
+                    .flat_map(|(name, values)| {
+                        values
+                            .into_iter()
+                            .map(move |header| (name.clone(), header.value))
+                    })
+                    .collect(),
+            ),
+            None => (None, Vec::new()),
+        };
+        let response = EdgeResponse {
+            event_type,
+            status,
+            headers: response_headers,
+            request: self.raw_request,
+        };
+
+        let mut headers = HashMap::new();
+        for (name, values) in self.request.headers {
+            // Responses are compressed by CloudFront, not by this crate
+            if name == "accept-encoding" {
+                continue;
+            }
+            let separator = if name == "cookie" { "; " } else { ", " };
+            let value = values
+                .into_iter()
+                .map(|header| header.value)
+                .collect::<Vec<_>>()
+                .join(separator);
+            headers.insert(name, value);
+        }
+        headers
+            .entry("host".to_string())
+            .or_insert(self.config.distribution_domain_name);
+
+        let (body, is_base64_encoded) = match self.request.body {
+            Some(body) => {
+                if body.input_truncated {
+                    tracing::warn!("Lambda@Edge request body is truncated by CloudFront");
+                }
+                (Some(body.data), body.encoding == "base64")
+            }
+            None => (None, false),
+        };
+
+        LambdaHttpEvent::synthetic(SyntheticRequest {
+            method: self.request.method,
+            path: percent_encoding::percent_decode_str(&self.request.uri)
+                .decode_utf8_lossy()
+                .into_owned(),
+            query: self.request.querystring,
+            headers,
+            body,
+            is_base64_encoded,
+            source_ip: self.request.client_ip,
+            request_id: self.config.request_id,
+            edge: Some(response),
+            ..Default::default()
+        })
+    }
+}
+
+/// Keep read-only and drop disallowed headers,
+/// then respond in Lambda@Edge format.
+/// Returns true when the response forwards the request, the body is returned as it is.
+pub(crate) fn finish_edge_response(response: &mut LambdaResponse, edge: &EdgeResponse) -> bool {
+    if edge.request.is_some() && response.header(EDGE_FORWARD_HEADER).is_some() {
+        response.remove_header(EDGE_FORWARD_HEADER);
+        response.format = ResponseFormat::Json;
+        return true;
+    }
+    // Headers set by this crate are in `headers`, by web frameworks in `multi_value_headers`
+    let mut headers = std::mem::take(&mut response.multi_value_headers);
+    for (name, value) in std::mem::take(&mut response.headers) {
+        headers.entry(name).or_default().push(value);
+    }
+    headers.retain(|name, _| {
+        !DISALLOWED_HEADERS.contains(&name.as_str())
+            && !DISALLOWED_HEADER_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+    });
+    for name in edge.read_only_headers() {
+        headers.remove(*name);
+        let values: Vec<String> = edge
+            .headers
+            .iter()
+            .filter(|(header, _)| header == name)
+            .map(|(_, value)| value.clone())
+            .collect();
+        if !values.is_empty() {
+            headers.insert(name.to_string(), values);
+        }
+    }
+    response.multi_value_headers = headers;
+    response.format = ResponseFormat::CloudFront;
+    false
+}
+
+/// Lambda@Edge response JSON
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EdgeWireResponse<'a> {
+    status: String,
+    headers: BTreeMap<&'a str, Vec<CfHeaderRef<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_encoding: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct CfHeaderRef<'a> {
+    key: String,
+    value: &'a str,
+}
+
+/// Serialize in Lambda@Edge format.
+/// Empty body keeps the body from the origin or the cache.
+pub(crate) fn serialize_response<S>(
+    response: &LambdaResponse,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let headers = response
+        .multi_value_headers
+        .iter()
+        .map(|(name, values)| {
+            let key = canonical_name(name);
+            let values = values
+                .iter()
+                .map(|value| CfHeaderRef {
+                    key: key.clone(),
+                    value,
+                })
+                .collect();
+            (name.as_str(), values)
+        })
+        .collect();
+    let has_body = !response.body.is_empty();
+    EdgeWireResponse {
+        status: response.status.to_string(),
+        headers,
+        body: Some(response.body.as_str()).filter(|_| has_body),
+        body_encoding: if !has_body {
+            None
+        } else if response.is_base64 {
+            Some("base64")
+        } else {
+            Some("text")
+        },
+    }
+    .serialize(serializer)
+}
+
+/// `content-type` to `Content-Type`
+fn canonical_name(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_edge_request() {
+        let event: InvocationEvent = serde_json::from_str(EDGE_ORIGIN_RESPONSE).unwrap();
+        let event = match event {
+            InvocationEvent::Edge(event) => event.into_http(),
+            _ => panic!("not a Lambda@Edge event"),
+        };
+        assert_eq!(event.method(), "GET");
+        assert_eq!(event.path_query(), "/index.html?lang=ja");
+        assert_eq!(event.hostname(), Some("d111111abcdef8.cloudfront.net"));
+        assert_eq!(event.header("cookie"), Some("a=1; b=2"));
+        assert_eq!(event.header("accept-encoding"), None);
+        assert!(event.multi_value());
+
+        let edge = event.edge_response().unwrap();
+        assert_eq!(edge.event_type, EdgeEventType::OriginResponse);
+        assert_eq!(edge.status, Some(200));
+        assert!(edge
+            .headers
+            .contains(&("via".to_string(), "1.1 example.cloudfront.net".to_string())));
+
+        // Request triggers have no response
+        let event_str = EDGE_ORIGIN_RESPONSE.replace("origin-response", "origin-request");
+        assert!(serde_json::from_str::<InvocationEvent>(&event_str).is_err());
+    }
+
+    #[test]
+    fn test_viewer_request() {
+        let event: InvocationEvent = serde_json::from_str(EDGE_VIEWER_REQUEST).unwrap();
+        let event = match event {
+            InvocationEvent::Edge(event) => event.into_http(),
+            _ => panic!("not a Lambda@Edge event"),
+        };
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/api/items");
+        assert_eq!(event.hostname(), Some("d111111abcdef8.cloudfront.net"));
+        assert!(event.multi_value());
+        let edge = event.edge_response().unwrap();
+        assert_eq!(edge.event_type, EdgeEventType::ViewerRequest);
+        assert_eq!(edge.status, None);
+        assert_eq!(event.body().unwrap(), br#"{"name":"a"}"#);
+
+        // Response triggers have the response
+        let event_str = EDGE_VIEWER_REQUEST.replace("viewer-request", "viewer-response");
+        assert!(serde_json::from_str::<InvocationEvent>(&event_str).is_err());
+    }
+
+    #[test]
+    fn test_generated_response() {
+        let edge = EdgeResponse {
+            event_type: EdgeEventType::ViewerRequest,
+            status: None,
+            headers: Vec::new(),
+            request: Some(serde_json::json!({"uri": "/"})),
+        };
+        let mut response = LambdaResponse::new(403, ResponseFormat::MultiValueHeaders);
+        response.add_header("content-type", "text/plain");
+        response.add_header("x-cache", "Miss");
+        response
+            .headers
+            .insert("x-robots-tag".into(), "noindex".into());
+        response.body = "Forbidden".to_string();
+        assert!(!finish_edge_response(&mut response, &edge));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "status": "403",
+                "headers": {
+                    "content-type": [{"key": "Content-Type", "value": "text/plain"}],
+                    "x-robots-tag": [{"key": "X-Robots-Tag", "value": "noindex"}]
+                },
+                "body": "Forbidden",
+                "bodyEncoding": "text"
+            })
+        );
+
+        // Empty body
+        response.body = String::new();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json.get("body"), None);
+        assert_eq!(json.get("bodyEncoding"), None);
+    }
+
+    #[test]
+    fn test_origin_request() {
+        let event: InvocationEvent = serde_json::from_str(EDGE_ORIGIN_REQUEST).unwrap();
+        let event = match event {
+            InvocationEvent::Edge(event) => event.into_http(),
+            _ => panic!("not a Lambda@Edge event"),
+        };
+        assert_eq!(event.path_query(), "/images/a.png?size=small");
+        let edge = event.edge_response().unwrap().clone();
+        assert_eq!(edge.event_type, EdgeEventType::OriginRequest);
+
+        let mut request = edge.edge_request().unwrap();
+        assert_eq!(request.header("Host"), Some("origin.example.com"));
+        assert_eq!(
+            request.origin().unwrap()["custom"]["domainName"],
+            "origin.example.com"
+        );
+        request.set_origin_domain_name("eu.origin.example.com");
+        request.set_uri("/images/eu/a.png");
+        request.set_header("x-region", "eu");
+        request.remove_header("user-agent");
+
+        // Forwarded request
+        let mut response = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        response.add_header(EDGE_FORWARD_HEADER, "1");
+        response.add_header("content-type", "application/json");
+        response.body = request.to_json();
+        assert!(finish_edge_response(&mut response, &edge));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["uri"], "/images/eu/a.png");
+        assert_eq!(json["querystring"], "size=small");
+        assert_eq!(
+            json["origin"]["custom"]["domainName"],
+            "eu.origin.example.com"
+        );
+        assert_eq!(
+            json["headers"]["host"],
+            serde_json::json!([{"key": "Host", "value": "eu.origin.example.com"}])
+        );
+        assert_eq!(
+            json["headers"]["x-region"],
+            serde_json::json!([{"key": "X-Region", "value": "eu"}])
+        );
+        assert_eq!(json["headers"].get("user-agent"), None);
+
+        // Response triggers can not forward
+        let edge = EdgeResponse {
+            request: None,
+            ..edge
+        };
+        let mut response = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        response.add_header(EDGE_FORWARD_HEADER, "1");
+        assert!(!finish_edge_response(&mut response, &edge));
+    }
+
+    #[test]
+    fn test_edge_response() {
+        let edge = EdgeResponse {
+            event_type: EdgeEventType::ViewerResponse,
+            status: Some(200),
+            request: None,
+            headers: vec![
+                ("content-length".to_string(), "11".to_string()),
+                ("via".to_string(), "1.1 example.cloudfront.net".to_string()),
+            ],
+        };
+        let mut response = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        response.add_header("content-type", "text/html");
+        response.add_header("set-cookie", "a=1");
+        response.add_header("set-cookie", "b=2");
+        response.add_header("content-length", "5");
+        response.add_header("connection", "close");
+        response
+            .headers
+            .insert("x-robots-tag".into(), "noindex".into());
+        finish_edge_response(&mut response, &edge);
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "status": "200",
+                "headers": {
+                    "content-length": [{"key": "Content-Length", "value": "11"}],
+                    "content-type": [{"key": "Content-Type", "value": "text/html"}],
+                    "set-cookie": [
+                        {"key": "Set-Cookie", "value": "a=1"},
+                        {"key": "Set-Cookie", "value": "b=2"}
+                    ],
+                    "via": [{"key": "Via", "value": "1.1 example.cloudfront.net"}],
+                    "x-robots-tag": [{"key": "X-Robots-Tag", "value": "noindex"}]
+                }
+            })
+        );
+
+        // Generated body
+        response.body = "Hello".to_string();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["body"], "Hello");
+        assert_eq!(json["bodyEncoding"], "text");
+    }
+}
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Runtime Interface Emulator
+//! Serves the invoke endpoint of the Lambda Runtime Interface Emulator on a local port,
+//! so container images are tested locally without installing the emulator
+//!
+use crate::bridge::WebEvent;
+use crate::hyper014::{HyperHandler, HyperRequest, HyperResponse};
+use crate::options::RunOptions;
+use lambda_runtime::{Error as LambdaError, LambdaEvent, Service as LambdaService};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Port of the emulator in container images, mapped by `docker run -p 9000:8080`
+pub const EMULATOR_PORT: u16 = 8080;
+
+/// Invocation timeout of the emulator, overridden by `AWS_LAMBDA_FUNCTION_TIMEOUT` seconds
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Run hyper based web framework behind the Runtime Interface Emulator protocol
+///
+/// Events POSTed to `/2015-03-31/functions/function/invocations` are handled
+/// as same as `run_hyper_on_lambda_with_options`, one at a time.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use lambda_web::{
+///     run_hyper_on_emulator, run_hyper_on_lambda, ExecutionEnv, LambdaError, RunOptions,
+///     EMULATOR_PORT,
+/// };
+/// use std::net::SocketAddr;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+///
+///     if ExecutionEnv::detect().has_runtime_api() {
+///         run_hyper_on_lambda(app).await?;
+///     } else {
+///         // curl -d @event.json http://localhost:9000/2015-03-31/functions/function/invocations
+///         let addr = SocketAddr::from(([0, 0, 0, 0], EMULATOR_PORT));
+///         run_hyper_on_emulator(app, RunOptions::default(), addr).await?;
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn run_hyper_on_emulator<S, B>(
+    svc: S,
+    options: RunOptions,
+    addr: SocketAddr,
+) -> Result<(), LambdaError>
+where
+    S: hyper::service::Service<HyperRequest, Response = HyperResponse<B>> + Clone + Send + 'static,
+    S::Error: Into<LambdaError>,
+    S::Future: Send,
+    B: hyper::body::HttpBody + Send + 'static,
+    B::Data: Send,
+    <B as hyper::body::HttpBody>::Error: std::error::Error + Send + Sync + 'static,
+{
+    options.snapshot_hooks.run()?;
+    crate::capture::install(options.failure_capture.clone());
+    crate::bridge::install_raw_event(options.raw_event);
+    let handler = Arc::new(Mutex::new(HyperHandler::new(svc, options)));
+    let mut incoming = hyper::server::conn::AddrIncoming::bind(&addr)?;
+    tracing::info!(
+        "Runtime Interface Emulator listening on http://{}",
+        incoming.local_addr()
+    );
+    let invocations = Arc::new(AtomicU64::new(0));
+
+    loop {
+        let stream = match futures_util::future::poll_fn(|cx| {
+            hyper::server::accept::Accept::poll_accept(std::pin::Pin::new(&mut incoming), cx)
+        })
+        .await
+        {
+            Some(stream) => stream?,
+            None => return Ok(()),
+        };
+
+        let handler = handler.clone();
+        let invocations = invocations.clone();
+        let svc = hyper::service::service_fn(move |req: hyper::Request<hyper::Body>| {
+            let handler = handler.clone();
+            let request_id = format!(
+                "emulator-{}",
+                invocations.fetch_add(1, Ordering::Relaxed) + 1
+            );
+            async move { invoke(handler, req, request_id).await }
+        });
+        // One invocation at a time, as same as the emulator
+        if let Err(err) = hyper::server::conn::Http::new()
+            .http1_only(true)
+            .http1_keep_alive(false)
+            .serve_connection(stream, svc)
+            .await
+        {
+            tracing::warn!("emulator connection error: {}", err);
+        }
+    }
+}
+
+/// Handle an invoke request, the response is the function response or error JSON
+async fn invoke<S, B>(
+    handler: Arc<Mutex<HyperHandler<S, B>>>,
+    req: hyper::Request<hyper::Body>,
+    request_id: String,
+) -> Result<hyper::Response<hyper::Body>, LambdaError>
+where
+    S: hyper::service::Service<HyperRequest, Response = HyperResponse<B>> + Clone + Send + 'static,
+    S::Error: Into<LambdaError>,
+    S::Future: Send,
+    B: hyper::body::HttpBody + Send + 'static,
+    B::Data: Send,
+    <B as hyper::body::HttpBody>::Error: std::error::Error + Send + Sync + 'static,
+{
+    if req.method() != hyper::Method::POST || !is_invoke_path(req.uri().path()) {
+        return Ok(hyper::Response::builder()
+            .status(404)
+            .body(hyper::Body::from("Not Found"))?);
+    }
+    let event_buf = hyper::body::to_bytes(req.into_body()).await?;
+
+    let result = match serde_json::from_slice::<WebEvent>(&event_buf) {
+        Ok(event) => {
+            futures_util::future::poll_fn(|cx| handler.lock().unwrap().poll_ready(cx)).await?;
+            let future = handler
+                .lock()
+                .unwrap()
+                .call(LambdaEvent::new(event, context(request_id)));
+            future.await
+        }
+        Err(err) => Err(err.into()),
+    };
+    let body = match result {
+        Ok(response) => serde_json::to_vec(&response)?,
+        Err(err) => {
+            // Same as the error response of lambda_runtime
+            serde_json::to_vec(&json!({
+                "errorType": "InvalidEvent",
+                "errorMessage": err.to_string()
+            }))?
+        }
+    };
+    Ok(hyper::Response::builder()
+        .header("content-type", "application/json")
+        .body(hyper::Body::from(body))?)
+}
+
+/// `/2015-03-31/functions/{function}/invocations`
+fn is_invoke_path(path: &str) -> bool {
+    path.strip_prefix("/2015-03-31/functions/")
+        .and_then(|rest| rest.strip_suffix("/invocations"))
+        .map(|function| !function.is_empty() && !function.contains('/'))
+        .unwrap_or(false)
+}
+
+/// Invocation context with the deadline by the function timeout
+fn context(request_id: String) -> lambda_runtime::Context {
+    let timeout = std::env::var("AWS_LAMBDA_FUNCTION_TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT);
+    let deadline = (SystemTime::now() + timeout)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let mut context = lambda_runtime::Context::default();
+    context.request_id = request_id;
+    context.deadline = deadline;
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_invoke_path() {
+        assert!(is_invoke_path("/2015-03-31/functions/function/invocations"));
+        assert!(is_invoke_path("/2015-03-31/functions/my-func/invocations"));
+        assert!(!is_invoke_path("/2015-03-31/functions//invocations"));
+        assert!(!is_invoke_path("/2018-06-01/runtime/invocation/next"));
+    }
+
+    #[test]
+    fn test_context() {
+        let context = context("emulator-1".to_string());
+        assert_eq!(context.request_id, "emulator-1");
+        let deadline = SystemTime::UNIX_EPOCH + Duration::from_millis(context.deadline);
+        assert!(deadline > SystemTime::now());
+    }
+}
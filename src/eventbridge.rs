@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: MIT
+//!
+//! EventBridge routes
+//! Translates EventBridge events, e.g. scheduled events, into HTTP requests by their
+//! rules or detail types, so cron jobs run through the same router.
+//!
+use crate::request::{DecodeError, LambdaHttpEvent, SyntheticRequest};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Request header with the EventBridge event ID
+pub const EVENTBRIDGE_EVENT_ID_HEADER: &str = "x-eventbridge-event-id";
+/// Request header with the detail type, e.g. `Scheduled Event`
+pub const EVENTBRIDGE_DETAIL_TYPE_HEADER: &str = "x-eventbridge-detail-type";
+/// Request header with the event source, e.g. `aws.events`
+pub const EVENTBRIDGE_SOURCE_HEADER: &str = "x-eventbridge-source";
+
+/// HTTP method and path of EventBridge rules and detail types
+///
+/// Routes are matched in the order of registration, by the rule (or EventBridge Scheduler
+/// schedule) in `resources` of the event, or by `detail-type`.
+/// Events without matching route are rejected.
+/// The event JSON is the request body except GET and HEAD requests,
+/// and `EventBridgeEvent` in request extensions of hyper based web frameworks.
+/// EventBridge ignores the response, return errors from the handler to be retried.
+///
+/// ```
+/// use lambda_web::{EventBridgeRoutes, RunOptions};
+///
+/// let routes = EventBridgeRoutes::new()
+///     .rule("nightly-cleanup", "GET", "/internal/cron/cleanup")
+///     .detail_type("Object Created", "POST", "/internal/s3/created");
+/// let options = RunOptions::default().eventbridge_routes(routes);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventBridgeRoutes {
+    routes: Vec<EventBridgeRoute>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EventBridgeRoute {
+    matcher: Matcher,
+    method: String,
+    path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Matcher {
+    /// Rule or schedule name, or its ARN
+    Rule(String),
+    DetailType(String),
+}
+
+impl EventBridgeRoutes {
+    /// Routes without mapping, no events are accepted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map events of the rule to HTTP method and path, the path may have a query string.
+    /// The rule is a rule or EventBridge Scheduler schedule name, or its ARN.
+    pub fn rule<R, M, P>(self, rule: R, method: M, path: P) -> Self
+    where
+        R: Into<String>,
+        M: Into<String>,
+        P: Into<String>,
+    {
+        self.push(Matcher::Rule(rule.into()), method.into(), path.into())
+    }
+
+    /// Map events of the detail type, e.g. `Scheduled Event`, to HTTP method and path
+    pub fn detail_type<D, M, P>(self, detail_type: D, method: M, path: P) -> Self
+    where
+        D: Into<String>,
+        M: Into<String>,
+        P: Into<String>,
+    {
+        self.push(
+            Matcher::DetailType(detail_type.into()),
+            method.into(),
+            path.into(),
+        )
+    }
+
+    fn push(mut self, matcher: Matcher, method: String, path: String) -> Self {
+        self.routes.push(EventBridgeRoute {
+            matcher,
+            method: method.to_ascii_uppercase(),
+            path,
+        });
+        self
+    }
+
+    fn find(&self, event: &EventBridgeEvent) -> Option<&EventBridgeRoute> {
+        self.routes.iter().find(|route| match &route.matcher {
+            Matcher::Rule(rule) => event.resources().any(|resource| {
+                resource == rule
+                    || ((resource.contains(":rule/") || resource.contains(":schedule/"))
+                        && resource.rsplit('/').next() == Some(rule.as_str()))
+            }),
+            Matcher::DetailType(detail_type) => event.detail_type() == detail_type,
+        })
+    }
+}
+
+/// EventBridge event JSON, in request extensions of hyper based web frameworks
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventBridgeEvent(Value);
+
+impl EventBridgeEvent {
+    /// `id` of the event
+    pub fn id(&self) -> &str {
+        self.0["id"].as_str().unwrap_or_default()
+    }
+
+    /// `detail-type` of the event, e.g. `Scheduled Event`
+    pub fn detail_type(&self) -> &str {
+        self.0["detail-type"].as_str().unwrap_or_default()
+    }
+
+    /// `source` of the event, e.g. `aws.events`
+    pub fn source(&self) -> &str {
+        self.0["source"].as_str().unwrap_or_default()
+    }
+
+    /// `time` of the event, e.g. `2025-10-09T03:00:00Z`
+    pub fn time(&self) -> Option<&str> {
+        self.0["time"].as_str()
+    }
+
+    /// ARNs in `resources`, e.g. the rule of scheduled events
+    pub fn resources(&self) -> impl Iterator<Item = &str> {
+        self.0["resources"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+    }
+
+    /// `detail` of the event, empty object for scheduled events
+    pub fn detail(&self) -> &Value {
+        &self.0["detail"]
+    }
+
+    /// Whole event JSON
+    pub fn to_json(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for EventBridgeEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let event = Value::deserialize(deserializer)?;
+        let is_str = |key: &str| event.get(key).map(Value::is_string).unwrap_or(false);
+        if is_str("detail-type") && is_str("source") && event.get("detail").is_some() {
+            Ok(Self(event))
+        } else {
+            Err(serde::de::Error::custom("not an EventBridge event"))
+        }
+    }
+}
+
+impl EventBridgeEvent {
+    /// HTTP request mapped by the rule or detail type
+    pub(crate) fn into_http<'a>(
+        self,
+        routes: Option<&EventBridgeRoutes>,
+    ) -> Result<LambdaHttpEvent<'a>, DecodeError> {
+        let route = routes
+            .and_then(|routes| routes.find(&self))
+            .ok_or_else(|| {
+                DecodeError::Malformed(format!(
+                    "no route for EventBridge event {}",
+                    self.detail_type()
+                ))
+            })?;
+        let (path, query) = match route.path.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (route.path.as_str(), ""),
+        };
+
+        let mut headers = HashMap::new();
+        headers.insert(
+            EVENTBRIDGE_EVENT_ID_HEADER.to_string(),
+            self.id().to_string(),
+        );
+        headers.insert(
+            EVENTBRIDGE_DETAIL_TYPE_HEADER.to_string(),
+            self.detail_type().to_string(),
+        );
+        headers.insert(
+            EVENTBRIDGE_SOURCE_HEADER.to_string(),
+            self.source().to_string(),
+        );
+        let body = if route.method == "GET" || route.method == "HEAD" {
+            None
+        } else {
+            headers.insert("content-type".to_string(), "application/json".to_string());
+            Some(self.0.to_string())
+        };
+
+        Ok(LambdaHttpEvent::synthetic(SyntheticRequest {
+            method: route.method.clone(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers,
+            body,
+            source_ip: "127.0.0.1".to_string(),
+            request_id: Some(self.id().to_string()).filter(|id| !id.is_empty()),
+            eventbridge: Some(self),
+            ..Default::default()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::test_consts::*;
+
+    fn eventbridge_event(event_str: &str) -> EventBridgeEvent {
+        match serde_json::from_str(event_str).unwrap() {
+            InvocationEvent::EventBridge(event) => event,
+            event => panic!("not an EventBridge event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_rule() {
+        let routes = EventBridgeRoutes::new()
+            .rule("other-schedule", "GET", "/internal/cron/other")
+            .rule("nightly-cleanup", "get", "/internal/cron/cleanup?dry_run=0")
+            .detail_type("Scheduled Event", "POST", "/internal/cron");
+        let event = eventbridge_event(EVENTBRIDGE_SCHEDULED)
+            .into_http(Some(&routes))
+            .unwrap();
+        assert_eq!(event.method(), "GET");
+        assert_eq!(event.path_query(), "/internal/cron/cleanup?dry_run=0");
+        assert_eq!(
+            event.header(EVENTBRIDGE_EVENT_ID_HEADER),
+            Some("53dc4d37-cffa-4f76-80c9-8b7d4a4d2eaa")
+        );
+        assert_eq!(event.header(EVENTBRIDGE_SOURCE_HEADER), Some("aws.events"));
+        let original = event.eventbridge_event().unwrap();
+        assert_eq!(original.time(), Some("2025-10-09T03:00:00Z"));
+        assert_eq!(original.detail(), &serde_json::json!({}));
+        assert!(event.body().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_detail_type() {
+        let routes = EventBridgeRoutes::new()
+            .rule("other-schedule", "GET", "/internal/cron/other")
+            .detail_type("Scheduled Event", "POST", "/internal/cron");
+        let event = eventbridge_event(EVENTBRIDGE_SCHEDULED)
+            .into_http(Some(&routes))
+            .unwrap();
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.header("content-type"), Some("application/json"));
+        let body: Value = serde_json::from_slice(&event.body().unwrap()).unwrap();
+        assert_eq!(body["detail-type"], "Scheduled Event");
+
+        // Without route
+        let routes = EventBridgeRoutes::new().detail_type("Object Created", "POST", "/s3");
+        assert!(eventbridge_event(EVENTBRIDGE_SCHEDULED)
+            .into_http(Some(&routes))
+            .is_err());
+        assert!(eventbridge_event(EVENTBRIDGE_SCHEDULED)
+            .into_http(None)
+            .is_err());
+    }
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Execution environment detection
+//!
+
+/// Environment variable to override detected execution environment,
+/// one of `lambda`, `rie`, `cargo-lambda` or `local`.
+pub const EXECUTION_ENV_OVERRIDE: &str = "LAMBDA_WEB_EXECUTION_ENV";
+
+/// Where this binary is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionEnv {
+    /// AWS Lambda
+    Lambda,
+    /// Lambda Runtime Interface Emulator, including `sam local`
+    LambdaRIE,
+    /// `cargo lambda watch`
+    CargoLambdaWatch,
+    /// Not under Lambda Runtime API, run as a local HTTP server
+    Local,
+}
+
+impl ExecutionEnv {
+    /// Detect execution environment from environment variables.
+    ///
+    /// `LAMBDA_WEB_EXECUTION_ENV` takes precedence, e.g. to force `local` in a container
+    /// whose image sets `AWS_LAMBDA_RUNTIME_API`.
+    pub fn detect() -> Self {
+        Self::detect_from(|name| std::env::var(name).ok())
+    }
+
+    /// Is Lambda Runtime API available? (not `Local`)
+    pub fn has_runtime_api(&self) -> bool {
+        *self != Self::Local
+    }
+
+    fn detect_from<F>(var: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(env) = var(EXECUTION_ENV_OVERRIDE).and_then(|v| Self::from_name(&v)) {
+            return env;
+        }
+
+        match var("AWS_LAMBDA_RUNTIME_API") {
+            None => Self::Local,
+            // cargo lambda serves Runtime API under /.rt path
+            Some(api) if api.contains("/.rt") => Self::CargoLambdaWatch,
+            // Set only by Lambda service, RIE does not set it
+            Some(_) if var("AWS_LAMBDA_INITIALIZATION_TYPE").is_some() => Self::Lambda,
+            Some(_) => Self::LambdaRIE,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "lambda" => Some(Self::Lambda),
+            "rie" => Some(Self::LambdaRIE),
+            "cargo-lambda" => Some(Self::CargoLambdaWatch),
+            "local" => Some(Self::Local),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect(vars: &[(&str, &str)]) -> ExecutionEnv {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ExecutionEnv::detect_from(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&[]), ExecutionEnv::Local);
+        assert_eq!(
+            detect(&[
+                ("AWS_LAMBDA_RUNTIME_API", "127.0.0.1:9001"),
+                ("AWS_LAMBDA_INITIALIZATION_TYPE", "on-demand")
+            ]),
+            ExecutionEnv::Lambda
+        );
+        assert_eq!(
+            detect(&[("AWS_LAMBDA_RUNTIME_API", "127.0.0.1:9001")]),
+            ExecutionEnv::LambdaRIE
+        );
+        assert_eq!(
+            detect(&[("AWS_LAMBDA_RUNTIME_API", "[::]:9000/.rt")]),
+            ExecutionEnv::CargoLambdaWatch
+        );
+
+        // Override
+        assert_eq!(
+            detect(&[
+                ("AWS_LAMBDA_RUNTIME_API", "127.0.0.1:9001"),
+                ("LAMBDA_WEB_EXECUTION_ENV", "local")
+            ]),
+            ExecutionEnv::Local
+        );
+        assert_eq!(
+            detect(&[("LAMBDA_WEB_EXECUTION_ENV", "Lambda")]),
+            ExecutionEnv::Lambda
+        );
+        assert!(!ExecutionEnv::Local.has_runtime_api());
+    }
+}
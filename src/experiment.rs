@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MIT
+//!
+//! A/B experiment assignment
+//! Assigns variants by the hash of a stable client key, same variant in every container
+//!
+use crate::request::LambdaHttpEvent;
+
+/// Response header listing assigned variants, e.g. `checkout=treatment, banner=control`
+pub const EXPERIMENT_HEADER: &str = "x-experiment";
+
+/// Stable key of the client, hashed to assign variants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientKey {
+    /// Value of the cookie, e.g. user or session ID
+    Cookie(String),
+    /// Value of the request header, e.g. `x-user-id` set by an authorizer
+    Header(String),
+    /// Source IP address of the client
+    SourceIp,
+}
+
+/// Experiment with weighted variants, set by `RunOptions::experiment`
+///
+/// Requests without the client key are not assigned.
+///
+/// ```
+/// use lambda_web::{ClientKey, Experiment, RunOptions};
+///
+/// let options = RunOptions::default().experiment(
+///     Experiment::new("checkout", ClientKey::Cookie("uid".to_string()))
+///         .variant("control", 90)
+///         .variant("one-click", 10),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Experiment {
+    name: String,
+    key: ClientKey,
+    variants: Vec<(String, u32)>,
+}
+
+impl Experiment {
+    /// Experiment without variants, add them by `variant`
+    pub fn new<S: Into<String>>(name: S, key: ClientKey) -> Self {
+        Self {
+            name: name.into(),
+            key,
+            variants: Vec::new(),
+        }
+    }
+
+    /// Add variant with relative weight, variants of zero weight are never assigned
+    pub fn variant<S: Into<String>>(mut self, name: S, weight: u32) -> Self {
+        self.variants.push((name.into(), weight));
+        self
+    }
+
+    /// Variant for the client key
+    fn assign(&self, client_key: &str) -> Option<&str> {
+        use sha2::{Digest, Sha256};
+
+        let total = self
+            .variants
+            .iter()
+            .map(|(_, weight)| u64::from(*weight))
+            .sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        // Salted by the experiment name, so experiments are independent
+        let mut hasher = Sha256::new();
+        hasher.update(self.name.as_bytes());
+        hasher.update(b":");
+        hasher.update(client_key.as_bytes());
+        let digest = hasher.finalize();
+        let mut bucket_bytes = [0u8; 8];
+        bucket_bytes.copy_from_slice(&digest[..8]);
+        let mut bucket = u64::from_be_bytes(bucket_bytes) % total;
+
+        for (variant, weight) in self.variants.iter() {
+            let weight = u64::from(*weight);
+            if bucket < weight {
+                return Some(variant);
+            }
+            bucket -= weight;
+        }
+        None
+    }
+
+    /// Client key in the request
+    fn client_key(&self, event: &LambdaHttpEvent) -> Option<String> {
+        match &self.key {
+            ClientKey::Cookie(cookie_name) => crate::CookieJar::from_event(event)
+                .get_raw(cookie_name)
+                .filter(|value| !value.is_empty())
+                .map(String::from),
+            ClientKey::Header(header_name) => event
+                .header(header_name)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(String::from),
+            ClientKey::SourceIp => event.source_ip().map(|ip| ip.to_string()),
+        }
+    }
+}
+
+/// Variants assigned to the request
+///
+/// Attached to hyper and Actix-web request extensions, and to Rocket request local cache
+/// as `Option<Assignments>`. Sent in `x-experiment` response header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Assignments(Vec<(String, String)>);
+
+impl Assignments {
+    /// Assign variants of the experiments
+    pub(crate) fn from_event(event: &LambdaHttpEvent, experiments: &[Experiment]) -> Self {
+        Self(
+            experiments
+                .iter()
+                .filter_map(|experiment| {
+                    let client_key = experiment.client_key(event)?;
+                    let variant = experiment.assign(&client_key)?;
+                    Some((experiment.name.clone(), variant.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Variant of the experiment, None when not assigned
+    pub fn variant(&self, experiment: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == experiment)
+            .map(|(_, variant)| variant.as_str())
+    }
+
+    /// Pairs of experiment and variant
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, variant)| (name.as_str(), variant.as_str()))
+    }
+
+    /// `x-experiment` header value, None without assignments
+    pub(crate) fn header_value(&self) -> Option<String> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let pairs = self
+            .iter()
+            .map(|(name, variant)| format!("{}={}", name, variant))
+            .collect::<Vec<_>>();
+        Some(pairs.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_assign() {
+        let experiment = Experiment::new("checkout", ClientKey::SourceIp)
+            .variant("control", 70)
+            .variant("treatment", 30)
+            .variant("disabled", 0);
+
+        // Stable for the key
+        assert_eq!(experiment.assign("user-1"), experiment.assign("user-1"));
+
+        let mut counts = [0usize; 2];
+        for n in 0..10_000 {
+            match experiment.assign(&format!("user-{}", n)) {
+                Some("control") => counts[0] += 1,
+                Some("treatment") => counts[1] += 1,
+                other => panic!("unexpected variant {:?}", other),
+            }
+        }
+        assert!((6_500..7_500).contains(&counts[0]));
+        assert!((2_500..3_500).contains(&counts[1]));
+
+        assert_eq!(
+            Experiment::new("empty", ClientKey::SourceIp).assign("user-1"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_event() {
+        let experiments = vec![
+            Experiment::new("banner", ClientKey::Cookie("cookie2".to_string())).variant("blue", 1),
+            Experiment::new("checkout", ClientKey::Header("x-user-id".to_string()))
+                .variant("control", 1),
+            Experiment::new("layout", ClientKey::SourceIp).variant("wide", 1),
+        ];
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_TWO_COOKIES).unwrap();
+        let assignments = Assignments::from_event(&event, &experiments);
+
+        assert_eq!(assignments.variant("banner"), Some("blue"));
+        // No x-user-id header
+        assert_eq!(assignments.variant("checkout"), None);
+        assert_eq!(
+            assignments.header_value().as_deref(),
+            Some("banner=blue, layout=wide")
+        );
+
+        assert_eq!(Assignments::default().header_value(), None);
+    }
+}
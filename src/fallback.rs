@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Fallback service
+//! Calls another hyper service when the primary one returns 404 Not Found
+//!
+use crate::hyper014::{HyperRequest, HyperResponse};
+use crate::LambdaError;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// hyper service calling `fallback` when `primary` returns 404 Not Found,
+/// e.g. an API router with a static file server or SPA `index.html` responder
+///
+/// The request body is buffered already on AWS Lambda, so both services receive
+/// the same method, URI, headers and body.
+/// Request context extensions set by this crate (`RequestContext`, `CallerIdentity`,
+/// `CookieJar`, `EarlyHints`, etc.) are copied to the fallback request,
+/// extensions added by the primary service are not.
+///
+/// Both services must return the same body type, map one of them when they differ.
+/// Actix Web (`App::default_service`) and Rocket (catchers) have their own fallbacks.
+///
+/// ```no_run
+/// use axum::{response::Html, routing::get, Router};
+/// use lambda_web::{run_hyper_on_lambda, Fallback, LambdaError};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let api = Router::new().route("/api/hello", get(|| async { "Hello, API!" }));
+///     // Client side routing, any other path renders the SPA
+///     let spa = Router::new().fallback(|| async { Html("<html>...</html>") });
+///     run_hyper_on_lambda(Fallback::new(api, spa)).await
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fallback<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P, F> Fallback<P, F> {
+    /// Call `fallback` when `primary` returns 404
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+impl<P, F, B> hyper::service::Service<HyperRequest> for Fallback<P, F>
+where
+    P: hyper::service::Service<HyperRequest, Response = HyperResponse<B>> + Clone + Send + 'static,
+    P::Error: Into<LambdaError>,
+    P::Future: Send,
+    F: hyper::service::Service<HyperRequest, Response = HyperResponse<B>> + Clone + Send + 'static,
+    F::Error: Into<LambdaError>,
+    F::Future: Send,
+    B: Send + 'static,
+{
+    type Response = HyperResponse<B>;
+    type Error = LambdaError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    /// Ready when both services are ready
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.primary.poll_ready(cx) {
+            Poll::Ready(Ok(())) => self.fallback.poll_ready(cx).map_err(Into::into),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, req: HyperRequest) -> Self::Future {
+        // Take the services made ready by poll_ready, leaving clones behind
+        let clone = self.primary.clone();
+        let mut primary = std::mem::replace(&mut self.primary, clone);
+        let clone = self.fallback.clone();
+        let mut fallback = std::mem::replace(&mut self.fallback, clone);
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+            let fallback_req = fallback_request(&parts, body.clone());
+
+            let response = primary
+                .call(HyperRequest::from_parts(parts, hyper::Body::from(body)))
+                .await
+                .map_err(Into::into)?;
+            if response.status() != hyper::StatusCode::NOT_FOUND {
+                return Ok(response);
+            }
+            fallback.call(fallback_req).await.map_err(Into::into)
+        })
+    }
+}
+
+/// Copy of the request for the fallback service
+fn fallback_request(parts: &hyper::http::request::Parts, body: hyper::body::Bytes) -> HyperRequest {
+    let mut req = HyperRequest::new(hyper::Body::from(body));
+    *req.method_mut() = parts.method.clone();
+    *req.uri_mut() = parts.uri.clone();
+    *req.version_mut() = parts.version;
+    *req.headers_mut() = parts.headers.clone();
+
+    let (from, to) = (&parts.extensions, req.extensions_mut());
+    copy_extension::<crate::RequestContext>(from, to);
+    copy_extension::<crate::CallerIdentity>(from, to);
+    copy_extension::<crate::ViewerInfo>(from, to);
+    copy_extension::<crate::ClientHints>(from, to);
+    copy_extension::<crate::ContainerAffinity>(from, to);
+    copy_extension::<crate::CookieJar>(from, to);
+    copy_extension::<crate::EarlyHints>(from, to);
+    copy_extension::<crate::FunctionUrlContext>(from, to);
+    copy_extension::<crate::Tenant>(from, to);
+    copy_extension::<crate::BuildInfo>(from, to);
+    #[cfg(feature = "sessions")]
+    copy_extension::<crate::Session>(from, to);
+    req
+}
+
+fn copy_extension<T: Clone + Send + Sync + 'static>(
+    from: &hyper::http::Extensions,
+    to: &mut hyper::http::Extensions,
+) {
+    if let Some(value) = from.get::<T>() {
+        to.insert(value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::WebEvent;
+    use crate::test_consts::*;
+    use crate::{HyperHandler, RunOptions};
+    use lambda_runtime::{Context, LambdaEvent, Service as LambdaService};
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_fallback() {
+        let api = tower::service_fn(|req: HyperRequest| async move {
+            let res = if req.uri().path().starts_with("/api/") {
+                HyperResponse::new(hyper::Body::from("api"))
+            } else {
+                let mut res = HyperResponse::new(hyper::Body::from("not found"));
+                *res.status_mut() = hyper::StatusCode::NOT_FOUND;
+                res
+            };
+            Ok::<_, Infallible>(res)
+        });
+        let spa = tower::service_fn(|req: HyperRequest| async move {
+            // Request context is kept
+            assert!(req.extensions().get::<crate::RequestContext>().is_some());
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            Ok::<_, Infallible>(HyperResponse::new(hyper::Body::from(format!(
+                "spa {}",
+                String::from_utf8_lossy(&body)
+            ))))
+        });
+        let mut handler = HyperHandler::new(Fallback::new(api, spa), RunOptions::default());
+
+        // Served by primary
+        let event_str = API_GATEWAY_V2_GET_SOMEWHERE_NOQUERY.replace("/somewhere", "/api/hello");
+        let event: WebEvent = serde_json::from_str(&event_str).unwrap();
+        let res = handler
+            .call(LambdaEvent::new(event, Context::default()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body, base64::encode("api"));
+
+        // 404 from primary, served by fallback with the same body
+        let event: WebEvent = serde_json::from_str(API_GATEWAY_V2_POST_FORM_URLENCODED).unwrap();
+        let res = handler
+            .call(LambdaEvent::new(event, Context::default()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, 200);
+        assert_eq!(
+            res.body,
+            base64::encode("spa key1=value1&key2=value2&Ok=Ok")
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Health check endpoint
+//! Answered by this crate before web frameworks, for Route 53 and ALB health checks
+//!
+use crate::idempotency::StoreFuture;
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use crate::response::{LambdaResponse, ResponseFormat};
+use core::future::Future;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Default path of the health check endpoint
+pub const DEFAULT_HEALTH_PATH: &str = "/__lambda_web/health";
+
+type CheckFn = Arc<dyn Fn() -> StoreFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Health check endpoint settings
+///
+/// `GET` or `HEAD` to the path returns 200 with JSON when all dependency checks pass,
+/// or 503 when any of them fails. The stage prefix of REST API is ignored.
+/// Off unless set by `RunOptions::health_check`, so the path reaches the web framework by default.
+///
+/// ```
+/// use lambda_web::{HealthCheck, RunOptions};
+///
+/// let options = RunOptions::default().health_check(
+///     HealthCheck::new()
+///         .path("/healthz")
+///         .check("database", || async { Ok(()) }),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct HealthCheck {
+    path: String,
+    checks: Vec<(String, CheckFn)>,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_HEALTH_PATH.to_string(),
+            checks: Vec::new(),
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Health check at `/__lambda_web/health` without dependency checks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Path of the health check endpoint
+    pub fn path<S: Into<String>>(mut self, path: S) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Add dependency check, returning Err with the reason when unhealthy
+    pub fn check<F, Fut>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let check: CheckFn = Arc::new(move || Box::pin(check()));
+        self.checks.push((name.to_string(), check));
+        self
+    }
+
+    /// Is the request to the health check endpoint?
+    fn matches(&self, event: &LambdaHttpEvent) -> bool {
+        is_request_to(event, &self.path)
+    }
+}
+
+/// Is the request GET or HEAD to the path, ignoring the stage prefix of REST API?
+pub(crate) fn is_request_to(event: &LambdaHttpEvent, endpoint: &str) -> bool {
+    matches!(event.method(), "GET" | "HEAD") && app_path(event) == endpoint
+}
+
+/// Request path without query string and the stage prefix of REST API
+pub(crate) fn app_path(event: &LambdaHttpEvent) -> String {
+    let path_query = event.path_query();
+    let path = path_query.split('?').next().unwrap_or_default();
+    event
+        .request_context()
+        .stage
+        .and_then(|stage| path.strip_prefix(&format!("/{}", stage)).map(String::from))
+        .filter(|path| path.starts_with('/'))
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Health check response, None when not enabled or the request is to other paths
+pub(crate) fn health_response(
+    event: &LambdaHttpEvent,
+    options: &RunOptions,
+) -> Option<StoreFuture<'static, LambdaResponse>> {
+    let health_check = options.health_check.as_ref()?;
+    if !health_check.matches(event) {
+        return None;
+    }
+    let checks = health_check.checks.clone();
+    let build_info = options.build_info.clone();
+    Some(Box::pin(async move {
+        let mut healthy = true;
+        let mut results = serde_json::Map::new();
+        for (name, check) in checks.iter() {
+            let status = match check().await {
+                Ok(()) => "ok".to_string(),
+                Err(reason) => {
+                    healthy = false;
+                    format!("error: {}", reason)
+                }
+            };
+            results.insert(name.clone(), status.into());
+        }
+        let body = json!({
+            "status": if healthy { "ok" } else { "error" },
+            "checks": results,
+            "lambda_web": env!("CARGO_PKG_VERSION"),
+            "function_version": std::env::var("AWS_LAMBDA_FUNCTION_VERSION").ok(),
+            "container_id": crate::affinity::container_id(),
+            "build": build_info,
+        });
+
+        let mut response =
+            LambdaResponse::new(if healthy { 200 } else { 503 }, ResponseFormat::Headers);
+        response.add_header("content-type", "application/json");
+        response.add_header("cache-control", "no-store");
+        response.body = body.to_string();
+        response
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[tokio::test]
+    async fn test_health_response() {
+        let v2_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(r#""rawPath":"/""#, r#""rawPath":"/__lambda_web/health""#);
+        let v2: LambdaHttpEvent = serde_json::from_str(&v2_str).unwrap();
+        let enabled = RunOptions::default().health_check(HealthCheck::new());
+        let res = health_response(&v2, &enabled).unwrap().await;
+        assert_eq!(res.status, 200);
+        let body: serde_json::Value = serde_json::from_str(&res.body).unwrap();
+        assert_eq!(body["status"], "ok");
+
+        // REST API path with stage prefix
+        let rest_str = API_GATEWAY_REST_GET_ROOT_NOQUERY.replace(
+            r#""path":"/stage/""#,
+            r#""path":"/stage/__lambda_web/health""#,
+        );
+        let rest: LambdaHttpEvent = serde_json::from_str(&rest_str).unwrap();
+        assert!(health_response(&rest, &enabled).is_some());
+
+        // Failed dependency check
+        let options = RunOptions::default().health_check(
+            HealthCheck::new()
+                .check("database", || async { Ok(()) })
+                .check("cache", || async { Err("timeout".to_string()) }),
+        );
+        let res = health_response(&v2, &options).unwrap().await;
+        assert_eq!(res.status, 503);
+        let body: serde_json::Value = serde_json::from_str(&res.body).unwrap();
+        assert_eq!(body["checks"]["database"], "ok");
+        assert_eq!(body["checks"]["cache"], "error: timeout");
+
+        // Other paths, and off by default
+        let root: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        assert!(health_response(&root, &enabled).is_none());
+        assert!(health_response(&v2, &RunOptions::default()).is_none());
+    }
+}
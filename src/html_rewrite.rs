@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MIT
+//!
+//! HTML rewriting
+//! Fixes asset URLs of HTML responses served under API Gateway stage prefix
+//!
+use crate::brotli::ResponseCompression;
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+
+/// How HTML responses are rewritten for the stage prefix, set by `RunOptions::html_rewrite`
+///
+/// On the default `execute-api` domain, pages of a named stage are served at `/{stage}/...`,
+/// while web frameworks render asset URLs without the stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlRewrite {
+    /// Insert `<base href="/{stage}/">` at the beginning of `<head>`,
+    /// for pages using relative URLs like `css/style.css`
+    BaseHref,
+    /// Prefix root-relative `href`, `src` and `action` attributes with `/{stage}`,
+    /// for pages using URLs like `/css/style.css`
+    RootRelativeUrls,
+}
+
+/// HTML rewriter of a request, made before the event is consumed by the web framework
+#[derive(Debug, Clone)]
+pub(crate) struct HtmlRewriter {
+    mode: HtmlRewrite,
+    prefix: String,
+}
+
+impl HtmlRewriter {
+    /// Rewriter for the request, None without stage prefix or when disabled
+    pub(crate) fn for_request(event: &LambdaHttpEvent, options: &RunOptions) -> Option<Self> {
+        let mode = options.html_rewrite?;
+        let prefix = event.request_context().deployment_stage()?.path_prefix();
+        if prefix.is_empty() {
+            return None;
+        }
+        Some(Self { mode, prefix })
+    }
+
+    /// Rewriter for this response, None unless uncompressed HTML
+    pub(crate) fn for_response<'a, R: ResponseCompression>(
+        rewriter: Option<&'a Self>,
+        response: &R,
+    ) -> Option<&'a Self> {
+        let html = response.content_encoding().is_none()
+            && response
+                .content_type()
+                .map(|ctype| ctype.trim().to_ascii_lowercase().starts_with("text/html"))
+                .unwrap_or(false);
+        rewriter.filter(|_| html)
+    }
+
+    /// Rewrite the body, or return it as is when the HTML can not be parsed
+    pub(crate) fn rewrite<T>(&self, body: T) -> T
+    where
+        T: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        match self.rewrite_html(body.as_ref()) {
+            Ok(rewritten) => T::from(rewritten),
+            Err(_) => body,
+        }
+    }
+
+    fn rewrite_html(&self, html: &[u8]) -> Result<Vec<u8>, lol_html::errors::RewritingError> {
+        use lol_html::html_content::ContentType;
+        use lol_html::{element, HtmlRewriter as LolHtmlRewriter, Settings};
+
+        let prefix = self.prefix.as_str();
+        let handler = match self.mode {
+            HtmlRewrite::BaseHref => element!("head", move |el| {
+                let base = format!(r#"<base href="{}/">"#, prefix);
+                el.prepend(&base, ContentType::Html);
+                Ok(())
+            }),
+            HtmlRewrite::RootRelativeUrls => {
+                element!("[href^='/'], [src^='/'], [action^='/']", move |el| {
+                    for name in ["href", "src", "action"] {
+                        if let Some(url) = el.get_attribute(name) {
+                            if is_unprefixed_root_relative(&url, prefix) {
+                                el.set_attribute(name, &format!("{}{}", prefix, url))?;
+                            }
+                        }
+                    }
+                    Ok(())
+                })
+            }
+        };
+
+        let mut output = Vec::with_capacity(html.len() + 64);
+        let mut rewriter = LolHtmlRewriter::new(
+            Settings {
+                element_content_handlers: vec![handler],
+                ..Settings::default()
+            },
+            |chunk: &[u8]| output.extend_from_slice(chunk),
+        );
+        rewriter.write(html)?;
+        rewriter.end()?;
+        Ok(output)
+    }
+}
+
+/// Is the URL like `/css/style.css`, not `//cdn.example.com/...` nor already prefixed?
+fn is_unprefixed_root_relative(url: &str, prefix: &str) -> bool {
+    url.starts_with('/')
+        && !url.starts_with("//")
+        && url != prefix
+        && !url
+            .strip_prefix(prefix)
+            .map(|rest| rest.starts_with('/') || rest.starts_with('?'))
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    const HTML: &str = r#"<html><head><link rel="stylesheet" href="/css/style.css"></head><body><img src="img/logo.png"><script src="//cdn.example.com/app.js"></script><a href="/stage/about">About</a><form action="/login"></form></body></html>"#;
+
+    fn rewriter(mode: HtmlRewrite, event_str: &str) -> Option<HtmlRewriter> {
+        let event: LambdaHttpEvent = serde_json::from_str(event_str).unwrap();
+        HtmlRewriter::for_request(&event, &RunOptions::default().html_rewrite(mode))
+    }
+
+    #[test]
+    fn test_base_href() {
+        let rewriter = rewriter(HtmlRewrite::BaseHref, API_GATEWAY_REST_GET_WITH_CONTEXT).unwrap();
+        let html = String::from_utf8(rewriter.rewrite(HTML.as_bytes().to_vec())).unwrap();
+        assert!(html.starts_with(r#"<html><head><base href="/stage/"><link"#));
+    }
+
+    #[test]
+    fn test_root_relative_urls() {
+        let rewriter = rewriter(
+            HtmlRewrite::RootRelativeUrls,
+            API_GATEWAY_REST_GET_WITH_CONTEXT,
+        )
+        .unwrap();
+        let html = String::from_utf8(rewriter.rewrite(HTML.as_bytes().to_vec())).unwrap();
+        assert!(html.contains(r#"href="/stage/css/style.css""#));
+        assert!(html.contains(r#"action="/stage/login""#));
+        // Relative, protocol-relative and already prefixed URLs are kept
+        assert!(html.contains(r#"src="img/logo.png""#));
+        assert!(html.contains(r#"src="//cdn.example.com/app.js""#));
+        assert!(html.contains(r#"href="/stage/about""#));
+    }
+
+    #[test]
+    fn test_no_stage_prefix() {
+        // HTTP API $default stage
+        assert!(rewriter(HtmlRewrite::BaseHref, API_GATEWAY_V2_GET_WITH_CONTEXT).is_none());
+        // Disabled
+        let event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_WITH_CONTEXT).unwrap();
+        assert!(HtmlRewriter::for_request(&event, &RunOptions::default()).is_none());
+    }
+}
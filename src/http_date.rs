@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT
+//!
+//! HTTP date
+//! `Date` response header in IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+//!
+use crate::options::RunOptions;
+use crate::response::LambdaResponse;
+use std::time::{Duration, SystemTime};
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Set `Date` header by `RunOptions::date_header`,
+/// keeping the header of the web framework when it is valid and within the tolerance
+pub(crate) fn apply_date_header(response: &mut LambdaResponse, options: &RunOptions) {
+    let tolerance = match options.date_header {
+        Some(tolerance) => tolerance,
+        None => return,
+    };
+    let now = SystemTime::now();
+    let valid = response
+        .header("date")
+        .and_then(parse_http_date)
+        .map(|date| {
+            let skew = now
+                .duration_since(date)
+                .or_else(|_| date.duration_since(now))
+                .unwrap_or(Duration::ZERO);
+            skew <= tolerance
+        })
+        .unwrap_or(false);
+    if !valid {
+        response.remove_header("date");
+        response.add_header("date", &format_http_date(now));
+    }
+}
+
+/// Format the time in IMF-fixdate
+pub(crate) fn format_http_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = secs / 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Parse IMF-fixdate, None for obsolete formats or invalid dates
+pub(crate) fn parse_http_date(date: &str) -> Option<SystemTime> {
+    // "Sun, 06 Nov 1994 08:49:37 GMT"
+    let date = date.trim();
+    let (_weekday, rest) = date.split_once(", ")?;
+    let parts = rest.split(' ').collect::<Vec<_>>();
+    let (day, month, year, time) = match parts[..] {
+        [day, month, year, time, "GMT"] => (day, month, year, time),
+        _ => return None,
+    };
+    let day: u32 = day.parse().ok().filter(|d| (1..=31).contains(d))?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u32 + 1;
+    let year: i64 = year.parse().ok().filter(|y| *y >= 1970)?;
+    let hms = time
+        .split(':')
+        .map(|n| n.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hour, minute, second) = match hms[..] {
+        [h, m, s] if h < 24 && m < 60 && s < 61 => (h, m, s),
+        _ => return None,
+    };
+    let days = days_from_civil(year, month, day);
+    let secs = days as u64 * 86400 + hour * 3600 + minute * 60 + second;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Days since 1970-01-01 of the date in proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Date of days since 1970-01-01, (year, month, day)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ResponseFormat;
+
+    #[test]
+    fn test_http_date() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(time));
+        let leap_day = SystemTime::UNIX_EPOCH + Duration::from_secs(1709164800);
+        assert_eq!(format_http_date(leap_day), "Thu, 29 Feb 2024 00:00:00 GMT");
+        assert_eq!(parse_http_date(&format_http_date(leap_day)), Some(leap_day));
+
+        // Obsolete RFC 850 and asctime formats
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+    }
+
+    #[test]
+    fn test_apply_date_header() {
+        let options = RunOptions::default().date_header(Duration::from_secs(60));
+
+        // Missing
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        apply_date_header(&mut response, &options);
+        assert!(parse_http_date(response.header("date").unwrap()).is_some());
+
+        // Within the tolerance
+        let date = format_http_date(SystemTime::now() - Duration::from_secs(10));
+        let mut response = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        response.add_header("date", &date);
+        apply_date_header(&mut response, &options);
+        assert_eq!(response.multi_value_headers["date"], vec![date]);
+
+        // Skewed
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("date", "Sun, 06 Nov 1994 08:49:37 GMT");
+        apply_date_header(&mut response, &options);
+        assert_ne!(
+            response.header("date"),
+            Some("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+
+        // Disabled
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        apply_date_header(&mut response, &RunOptions::default());
+        assert_eq!(response.header("date"), None);
+    }
+}
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MIT
+//!
+//! HTTPS enforcement
+//! Redirects plain HTTP requests behind ALB to HTTPS, and sends HSTS
+//!
+use crate::request::LambdaHttpEvent;
+use crate::response::{LambdaResponse, ResponseFormat};
+use std::time::Duration;
+
+/// HTTPS enforcement, set by `RunOptions::https_redirect`
+///
+/// Requests with `x-forwarded-proto: http`, e.g. to ALB HTTP listeners,
+/// are redirected to the same URL with `https` before the web framework,
+/// by 301 for GET and HEAD, or 308 keeping the method and body for others.
+/// Responses of the web framework have `strict-transport-security`,
+/// unless the app sets it. The health check endpoint is not redirected.
+///
+/// ```
+/// use lambda_web::{HttpsRedirect, RunOptions};
+/// use std::time::Duration;
+///
+/// let options = RunOptions::default().https_redirect(
+///     HttpsRedirect::new()
+///         .hsts_max_age(Duration::from_secs(63072000))
+///         .include_subdomains(true),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpsRedirect {
+    hsts_max_age: Option<Duration>,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Default for HttpsRedirect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpsRedirect {
+    /// Redirect to HTTPS, with HSTS of one year max-age
+    pub fn new() -> Self {
+        Self {
+            hsts_max_age: Some(Duration::from_secs(31536000)),
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// `max-age` of HSTS
+    pub fn hsts_max_age(mut self, max_age: Duration) -> Self {
+        self.hsts_max_age = Some(max_age);
+        self
+    }
+
+    /// Redirect only, without HSTS
+    pub fn without_hsts(mut self) -> Self {
+        self.hsts_max_age = None;
+        self
+    }
+
+    /// `includeSubDomains` directive of HSTS
+    pub fn include_subdomains(mut self, enabled: bool) -> Self {
+        self.include_subdomains = enabled;
+        self
+    }
+
+    /// `preload` directive of HSTS, for the browsers' preload lists
+    pub fn preload(mut self, enabled: bool) -> Self {
+        self.preload = enabled;
+        self
+    }
+
+    /// `strict-transport-security` header value, None without HSTS
+    fn hsts_value(&self) -> Option<String> {
+        let mut value = format!("max-age={}", self.hsts_max_age?.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        Some(value)
+    }
+}
+
+/// Redirect response for plain HTTP requests, None for HTTPS or without Host
+pub(crate) fn redirect_response(event: &LambdaHttpEvent) -> Option<LambdaResponse> {
+    let proto = event.header("x-forwarded-proto")?;
+    let proto = proto.split(',').next().unwrap_or_default().trim();
+    if !proto.eq_ignore_ascii_case("http") {
+        return None;
+    }
+    let hostname = event.hostname()?;
+    // Default port of HTTP is not HTTPS one
+    let hostname = hostname.strip_suffix(":80").unwrap_or(hostname);
+    let location = format!("https://{}{}", hostname, event.path_query());
+
+    let status = if matches!(event.method(), "GET" | "HEAD") {
+        301
+    } else {
+        308
+    };
+    let mut response = LambdaResponse::new(status, ResponseFormat::Headers);
+    response.add_header("location", &location);
+    Some(response)
+}
+
+/// Add HSTS to the response of the web framework, unless the app sets it
+pub(crate) fn apply_hsts(response: &mut LambdaResponse, redirect: &HttpsRedirect) {
+    if response.header("strict-transport-security").is_some() {
+        return;
+    }
+    if let Some(value) = redirect.hsts_value() {
+        response.add_header("strict-transport-security", &value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_redirect_response() {
+        let event_str = ALB_GET_MIXEDCASE_HEADERS.replace("https", "http");
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let response = redirect_response(&event).unwrap();
+        assert_eq!(response.status, 301);
+        assert_eq!(
+            response.header("location").unwrap(),
+            format!(
+                "https://{}{}",
+                event.hostname().unwrap(),
+                event.path_query()
+            )
+        );
+
+        // Already HTTPS
+        let event: LambdaHttpEvent = serde_json::from_str(ALB_GET_MIXEDCASE_HEADERS).unwrap();
+        assert!(redirect_response(&event).is_none());
+    }
+
+    #[test]
+    fn test_apply_hsts() {
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        apply_hsts(
+            &mut response,
+            &HttpsRedirect::new().include_subdomains(true).preload(true),
+        );
+        assert_eq!(
+            response.header("strict-transport-security"),
+            Some("max-age=31536000; includeSubDomains; preload")
+        );
+
+        // Set by the app
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("strict-transport-security", "max-age=60");
+        apply_hsts(&mut response, &HttpsRedirect::new());
+        assert_eq!(
+            response.header("strict-transport-security"),
+            Some("max-age=60")
+        );
+
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        apply_hsts(&mut response, &HttpsRedirect::new().without_hsts());
+        assert_eq!(response.header("strict-transport-security"), None);
+    }
+}
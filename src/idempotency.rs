@@ -0,0 +1,782 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Idempotency cache
+//! Replays stored response for the request with same Idempotency-Key header
+//!
+use crate::request::LambdaHttpEvent;
+use crate::response::LambdaResponse;
+use core::future::Future;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Boxed future returned by `IdempotencyStore`
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Record of an `Idempotency-Key`, stored by `IdempotencyStore`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// SHA-256 of the request body in lowercase hex, to reject the key reused for other bodies
+    pub body_hash: String,
+    /// Response of the first request, None while it is in progress
+    pub response: Option<LambdaResponse>,
+}
+
+/// Storage of responses for idempotency cache
+///
+/// Implement this trait to store responses in DynamoDB, ElastiCache, etc.
+/// `LambdaResponse` is serializable, and contains the already encoded body.
+pub trait IdempotencyStore: Send + Sync {
+    /// Store the in-progress record for `ttl` unless the key has an unexpired record.
+    /// Returns the existing record, or None when stored.
+    ///
+    /// Must be atomic, e.g. DynamoDB conditional write, so concurrent retries
+    /// run the request handler only once.
+    fn put_if_absent<'a>(
+        &'a self,
+        key: &'a str,
+        record: &'a IdempotencyRecord,
+        ttl: Duration,
+    ) -> StoreFuture<'a, Option<IdempotencyRecord>>;
+
+    /// Store the completed record for `ttl`, replacing the in-progress one
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        record: &'a IdempotencyRecord,
+        ttl: Duration,
+    ) -> StoreFuture<'a, ()>;
+
+    /// Remove the record, so retries of a failed request run the handler again
+    fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()>;
+}
+
+/// In-memory idempotency store
+///
+/// Stored responses live only in a warm Lambda container,
+/// retries routed to other containers are not replayed.
+#[derive(Default)]
+pub struct MemoryIdempotencyStore {
+    entries: Mutex<HashMap<String, (Instant, IdempotencyRecord)>>,
+}
+
+impl MemoryIdempotencyStore {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    fn put_if_absent<'a>(
+        &'a self,
+        key: &'a str,
+        record: &'a IdempotencyRecord,
+        ttl: Duration,
+    ) -> StoreFuture<'a, Option<IdempotencyRecord>> {
+        let existing = self.entries.lock().ok().and_then(|mut entries| {
+            // Remove expired records
+            let now = Instant::now();
+            entries.retain(|_, (expires, _)| now < *expires);
+            match entries.get(key) {
+                Some((_, existing)) => Some(existing.clone()),
+                None => {
+                    entries.insert(key.to_string(), (now + ttl, record.clone()));
+                    None
+                }
+            }
+        });
+        Box::pin(async move { existing })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        record: &'a IdempotencyRecord,
+        ttl: Duration,
+    ) -> StoreFuture<'a, ()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), (Instant::now() + ttl, record.clone()));
+        }
+        Box::pin(async {})
+    }
+
+    fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+        Box::pin(async {})
+    }
+}
+
+/// Lambda functions time out in 15 minutes at most,
+/// in-progress records of crashed invocations expire by then
+const IN_PROGRESS_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// `Idempotency-Key` of the request with its body hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IdempotencyKey {
+    key: String,
+    body_hash: String,
+}
+
+/// Cache key of the request, made from `Idempotency-Key` header,
+/// or the gateway request ID when `by_request_id` and the header is absent.
+///
+/// Header keys are scoped by the caller, the principal authenticated by API Gateway
+/// or the hash of `Authorization` or `Cookie` header, so callers can not replay others' responses.
+/// Requests without caller identity are not cached.
+/// Keys also contain `Accept-Encoding`, as stored responses are already compressed.
+pub(crate) fn idempotency_key(
+    event: &LambdaHttpEvent,
+    by_request_id: bool,
+) -> Option<IdempotencyKey> {
+    let sha256 = |value: &str| crate::checksum::BodyChecksum::Sha256.hex_digest(value.as_bytes());
+    let (caller, key) = match event.header("idempotency-key") {
+        Some(key) => {
+            let cookies = event.cookies();
+            let caller = event
+                .caller_principal()
+                .or_else(|| event.header("authorization").map(sha256))
+                .or_else(|| {
+                    if cookies.is_empty() {
+                        None
+                    } else {
+                        Some(sha256(&cookies.join("; ")))
+                    }
+                })?;
+            (caller, key.to_string())
+        }
+        // Request IDs are issued by the gateway, not by callers
+        None if by_request_id => (
+            "-".to_string(),
+            format!("request-id:{}", event.request_context().request_id?),
+        ),
+        None => return None,
+    };
+    let encoding = event
+        .header("accept-encoding")
+        .map(|encoding| {
+            encoding
+                .to_ascii_lowercase()
+                .split_whitespace()
+                .collect::<String>()
+        })
+        .filter(|encoding| !encoding.is_empty())
+        .unwrap_or_else(|| "identity".to_string());
+    Some(IdempotencyKey {
+        key: format!(
+            "{} {} {} {} {}",
+            caller,
+            encoding,
+            event.method(),
+            event.path_query(),
+            key
+        ),
+        body_hash: event.body_sha256(),
+    })
+}
+
+/// Idempotency cache settings in RunOptions
+#[derive(Clone)]
+pub(crate) struct Idempotency {
+    pub(crate) store: Arc<dyn IdempotencyStore>,
+    pub(crate) ttl: Duration,
+}
+
+/// Replay stored response if exists,
+/// otherwise run the handler future and store its response.
+///
+/// The handler future is dropped without being polled on replay,
+/// so request handlers are not executed again.
+/// Responds 409 Conflict while the first request is in progress,
+/// and 422 Unprocessable Entity when the key is reused with another body.
+pub(crate) async fn idempotent_response<F, E>(
+    idempotency: Option<Idempotency>,
+    key: Option<IdempotencyKey>,
+    handler: F,
+) -> Result<LambdaResponse, E>
+where
+    F: Future<Output = Result<LambdaResponse, E>>,
+{
+    let (idempotency, key) = match (idempotency, key) {
+        (Some(idempotency), Some(key)) => (idempotency, key),
+        _ => return handler.await,
+    };
+
+    let in_progress = IdempotencyRecord {
+        body_hash: key.body_hash.clone(),
+        response: None,
+    };
+    let ttl = idempotency.ttl.min(IN_PROGRESS_TTL);
+    match idempotency
+        .store
+        .put_if_absent(&key.key, &in_progress, ttl)
+        .await
+    {
+        Some(record) if record.body_hash != key.body_hash => {
+            return Ok(LambdaResponse::text(422, "Unprocessable Entity"));
+        }
+        // Replay stored response
+        Some(IdempotencyRecord {
+            response: Some(response),
+            ..
+        }) => return Ok(response),
+        Some(_) => return Ok(LambdaResponse::text(409, "Conflict")),
+        None => {}
+    }
+
+    let response = match handler.await {
+        Ok(response) => response,
+        Err(err) => {
+            idempotency.store.remove(&key.key).await;
+            return Err(err);
+        }
+    };
+    let set_cookie = response.header("set-cookie").is_some() || !response.cookies.is_empty();
+    if response.status < 500 && !set_cookie {
+        let record = IdempotencyRecord {
+            body_hash: key.body_hash,
+            response: Some(response.clone()),
+        };
+        idempotency
+            .store
+            .put(&key.key, &record, idempotency.ttl)
+            .await;
+    } else {
+        // Server errors are not stored, so retries can succeed.
+        // Responses with cookies are not stored, to keep sessions out of the store.
+        idempotency.store.remove(&key.key).await;
+    }
+    Ok(response)
+}
+
+#[cfg(feature = "dynamodb")]
+pub use dynamodb::DynamoDbIdempotencyStore;
+
+#[cfg(feature = "dynamodb")]
+mod dynamodb {
+    use super::*;
+    use crate::sigv4::{AwsTransport, Credentials, SignedRequest, TransportResponse};
+    use lambda_runtime::Error as LambdaError;
+    use serde_json::{json, Value};
+    use std::time::SystemTime;
+
+    /// Idempotency store of a DynamoDB table, signed by SigV4 and sent by `AwsTransport`
+    ///
+    /// The table has `idempotencyKey` string partition key, the SHA-256 of the cache key.
+    /// Items have `record` (JSON of `IdempotencyRecord`) and `expiresAt`
+    /// (Unix time in seconds, enable DynamoDB TTL on it) attributes.
+    /// Expired items are overwritten before DynamoDB TTL deletes them.
+    /// The execution role needs `dynamodb:PutItem` and `DeleteItem` permissions.
+    ///
+    /// Retries get 409 Conflict while DynamoDB is unavailable,
+    /// so request handlers are not executed twice.
+    ///
+    /// ```no_run
+    /// use lambda_web::{AwsTransport, DynamoDbIdempotencyStore, RunOptions};
+    /// use std::time::Duration;
+    /// # fn example(transport: impl AwsTransport + 'static) {
+    /// let store = DynamoDbIdempotencyStore::new("idempotency").transport(transport);
+    /// let options = RunOptions::default().idempotency(store, Duration::from_secs(3600));
+    /// # }
+    /// ```
+    #[derive(Clone)]
+    pub struct DynamoDbIdempotencyStore {
+        table: String,
+        region: String,
+        credentials: Option<Credentials>,
+        transport: Option<Arc<dyn AwsTransport>>,
+    }
+
+    impl DynamoDbIdempotencyStore {
+        /// Store in the table, in `AWS_REGION` with the execution role credentials
+        pub fn new<S: Into<String>>(table: S) -> Self {
+            Self {
+                table: table.into(),
+                region: std::env::var("AWS_REGION").unwrap_or_default(),
+                credentials: Credentials::from_env(),
+                transport: None,
+            }
+        }
+
+        /// Override the region
+        pub fn region<S: Into<String>>(mut self, region: S) -> Self {
+            self.region = region.into();
+            self
+        }
+
+        /// Override the credentials
+        pub fn credentials(mut self, credentials: Credentials) -> Self {
+            self.credentials = Some(credentials);
+            self
+        }
+
+        /// HTTPS client to send requests
+        pub fn transport<T: AwsTransport + 'static>(mut self, transport: T) -> Self {
+            self.transport = Some(Arc::new(transport));
+            self
+        }
+
+        fn item(
+            key: &str,
+            record: &IdempotencyRecord,
+            ttl: Duration,
+            now: SystemTime,
+        ) -> Result<Value, LambdaError> {
+            let expires_at = unix_time(now) + ttl.as_secs();
+            Ok(json!({
+                "idempotencyKey": {"S": hashed_key(key)},
+                "record": {"S": serde_json::to_string(record)?},
+                "expiresAt": {"N": expires_at.to_string()}
+            }))
+        }
+
+        fn sign(
+            &self,
+            target: &str,
+            body: &Value,
+            now: SystemTime,
+        ) -> Result<SignedRequest, LambdaError> {
+            let credentials = self
+                .credentials
+                .as_ref()
+                .ok_or("no AWS credentials to sign DynamoDB request")?;
+            let host = format!("dynamodb.{}.amazonaws.com", self.region);
+            let mut request = crate::sigv4::sign(
+                "POST",
+                &host,
+                "/",
+                body.to_string().into_bytes(),
+                &self.region,
+                "dynamodb",
+                credentials,
+                now,
+            );
+            request.headers.push((
+                "content-type".to_string(),
+                "application/x-amz-json-1.0".to_string(),
+            ));
+            request.headers.push((
+                "x-amz-target".to_string(),
+                format!("DynamoDB_20120810.{}", target),
+            ));
+            Ok(request)
+        }
+
+        async fn send(&self, target: &str, body: Value) -> Result<TransportResponse, LambdaError> {
+            let transport = self
+                .transport
+                .as_ref()
+                .ok_or("no AwsTransport is configured")?;
+            transport
+                .send(self.sign(target, &body, SystemTime::now())?)
+                .await
+        }
+
+        async fn call(&self, target: &str, body: Value) -> Result<(), LambdaError> {
+            let response = self.send(target, body).await?;
+            if !(200..300).contains(&response.status) {
+                return Err(format!(
+                    "DynamoDB {} failed with {}: {}",
+                    target,
+                    response.status,
+                    String::from_utf8_lossy(&response.body)
+                )
+                .into());
+            }
+            Ok(())
+        }
+
+        async fn try_put_if_absent(
+            &self,
+            key: &str,
+            record: &IdempotencyRecord,
+            ttl: Duration,
+        ) -> Result<Option<IdempotencyRecord>, LambdaError> {
+            let now = SystemTime::now();
+            let body = json!({
+                "TableName": self.table,
+                "Item": Self::item(key, record, ttl, now)?,
+                "ConditionExpression": "attribute_not_exists(idempotencyKey) OR expiresAt < :now",
+                "ExpressionAttributeValues": {":now": {"N": unix_time(now).to_string()}},
+                "ReturnValuesOnConditionCheckFailure": "ALL_OLD"
+            });
+            let response = self.send("PutItem", body).await?;
+            if (200..300).contains(&response.status) {
+                return Ok(None);
+            }
+            let error: Value = serde_json::from_slice(&response.body).unwrap_or_default();
+            match record_from_error(&error) {
+                Some(existing) => Ok(Some(existing)),
+                None => Err(format!(
+                    "DynamoDB PutItem failed with {}: {}",
+                    response.status,
+                    String::from_utf8_lossy(&response.body)
+                )
+                .into()),
+            }
+        }
+    }
+
+    fn unix_time(now: SystemTime) -> u64 {
+        now.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Partition key, fixed length regardless of paths and keys sent by callers
+    fn hashed_key(key: &str) -> String {
+        crate::checksum::BodyChecksum::Sha256.hex_digest(key.as_bytes())
+    }
+
+    /// Existing record returned by ConditionalCheckFailedException,
+    /// None for other errors
+    fn record_from_error(error: &Value) -> Option<IdempotencyRecord> {
+        let error_type = error["__type"].as_str()?;
+        if !error_type.ends_with("#ConditionalCheckFailedException") {
+            return None;
+        }
+        Some(
+            error["Item"]["record"]["S"]
+                .as_str()
+                .and_then(|record| serde_json::from_str(record).ok())
+                // Unreadable record, conflicts with the request
+                .unwrap_or(IdempotencyRecord {
+                    body_hash: String::new(),
+                    response: None,
+                }),
+        )
+    }
+
+    impl IdempotencyStore for DynamoDbIdempotencyStore {
+        fn put_if_absent<'a>(
+            &'a self,
+            key: &'a str,
+            record: &'a IdempotencyRecord,
+            ttl: Duration,
+        ) -> StoreFuture<'a, Option<IdempotencyRecord>> {
+            Box::pin(async move {
+                match self.try_put_if_absent(key, record, ttl).await {
+                    Ok(existing) => existing,
+                    Err(err) => {
+                        // Not to run the handler twice, treated as in progress
+                        tracing::warn!("failed to store idempotency record, {}", err);
+                        Some(record.clone())
+                    }
+                }
+            })
+        }
+
+        fn put<'a>(
+            &'a self,
+            key: &'a str,
+            record: &'a IdempotencyRecord,
+            ttl: Duration,
+        ) -> StoreFuture<'a, ()> {
+            Box::pin(async move {
+                let result = match Self::item(key, record, ttl, SystemTime::now()) {
+                    Ok(item) => {
+                        let body = json!({ "TableName": self.table, "Item": item });
+                        self.call("PutItem", body).await
+                    }
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = result {
+                    tracing::warn!("failed to store idempotent response, {}", err);
+                }
+            })
+        }
+
+        fn remove<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+            Box::pin(async move {
+                let body = json!({
+                    "TableName": self.table,
+                    "Key": {"idempotencyKey": {"S": hashed_key(key)}}
+                });
+                if let Err(err) = self.call("DeleteItem", body).await {
+                    tracing::warn!("failed to remove idempotency record, {}", err);
+                }
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_item() {
+            let record = IdempotencyRecord {
+                body_hash: "hash".to_string(),
+                response: Some(LambdaResponse::text(201, "created")),
+            };
+            let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1760000000);
+            let item = DynamoDbIdempotencyStore::item("key", &record, Duration::from_secs(60), now)
+                .unwrap();
+            assert_eq!(item["idempotencyKey"]["S"], hashed_key("key"));
+            assert_eq!(item["expiresAt"]["N"], "1760000060");
+
+            let error = json!({
+                "__type": "com.amazonaws.dynamodb.v20120810#ConditionalCheckFailedException",
+                "message": "The conditional request failed",
+                "Item": item
+            });
+            assert_eq!(record_from_error(&error), Some(record));
+            let error = json!({
+                "__type": "com.amazonaws.dynamodb.v20120810#ProvisionedThroughputExceededException"
+            });
+            assert_eq!(record_from_error(&error), None);
+        }
+
+        #[test]
+        fn test_sign() {
+            let store = DynamoDbIdempotencyStore::new("idempotency")
+                .region("ap-northeast-1")
+                .credentials(Credentials {
+                    access_key_id: "AKIDEXAMPLE".to_string(),
+                    secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                    session_token: None,
+                });
+            let request = store
+                .sign(
+                    "PutItem",
+                    &json!({"TableName": "idempotency"}),
+                    SystemTime::now(),
+                )
+                .unwrap();
+            assert_eq!(
+                request.url,
+                "https://dynamodb.ap-northeast-1.amazonaws.com/"
+            );
+            assert!(request.headers.contains(&(
+                "x-amz-target".to_string(),
+                "DynamoDB_20120810.PutItem".to_string()
+            )));
+        }
+
+        #[tokio::test]
+        async fn test_fail_closed() {
+            // No transport, retries conflict instead of running the handler
+            let store = DynamoDbIdempotencyStore::new("idempotency");
+            let record = IdempotencyRecord {
+                body_hash: "hash".to_string(),
+                response: None,
+            };
+            assert_eq!(
+                store
+                    .put_if_absent("key", &record, Duration::from_secs(60))
+                    .await,
+                Some(record)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    fn with_idempotency_key(event: &str, headers: &str) -> String {
+        event.replace(
+            "\"headers\":{",
+            &format!("\"headers\":{{\"Idempotency-Key\":\"abc\",{}", headers),
+        )
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        assert_eq!(idempotency_key(&event, false), None);
+
+        // No caller identity, not cached
+        let event_str = with_idempotency_key(API_GATEWAY_V2_POST_FORM_URLENCODED, "");
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assert_eq!(idempotency_key(&event, false), None);
+
+        // Scoped by Authorization header
+        let event_str = with_idempotency_key(
+            API_GATEWAY_V2_POST_FORM_URLENCODED,
+            "\"Authorization\":\"Bearer alice\",",
+        );
+        let alice: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let event_str = with_idempotency_key(
+            API_GATEWAY_V2_POST_FORM_URLENCODED,
+            "\"Authorization\":\"Bearer bob\",",
+        );
+        let bob: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let alice_key = idempotency_key(&alice, false).unwrap();
+        let bob_key = idempotency_key(&bob, false).unwrap();
+        assert!(alice_key.key.ends_with(" identity POST /somewhere abc"));
+        assert_eq!(alice_key.body_hash, alice.body_sha256());
+        assert_ne!(alice_key.key, bob_key.key);
+
+        // Scoped by Cookie header
+        let cookie = |session: &str| {
+            let event_str = with_idempotency_key(API_GATEWAY_V2_POST_FORM_URLENCODED, "").replace(
+                "\"isBase64Encoded\"",
+                &format!("\"cookies\":[\"session={}\"],\"isBase64Encoded\"", session),
+            );
+            let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+            idempotency_key(&event, false).unwrap().key
+        };
+        assert!(cookie("alice").ends_with(" identity POST /somewhere abc"));
+        assert_ne!(cookie("alice"), cookie("bob"));
+
+        // Scoped by Accept-Encoding header
+        let event_str = with_idempotency_key(
+            API_GATEWAY_V2_POST_FORM_URLENCODED,
+            "\"Authorization\":\"Bearer alice\",\"Accept-Encoding\":\"GZIP, br\",",
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let key = idempotency_key(&event, false).unwrap();
+        assert!(key.key.ends_with(" gzip,br POST /somewhere abc"));
+        assert_ne!(key.key, alice_key.key);
+
+        // Scoped by JWT subject
+        let event_str = with_idempotency_key(API_GATEWAY_V2_POST_FORM_URLENCODED, "").replace(
+            "\"requestContext\":{",
+            "\"requestContext\":{\"authorizer\":{\"jwt\":{\"claims\":{\"sub\":\"user-1\"}}},",
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assert_eq!(
+            idempotency_key(&event, false).unwrap().key,
+            "user-1 identity POST /somewhere abc"
+        );
+
+        // Gateway request ID without Idempotency-Key header
+        let event_str = API_GATEWAY_V2_POST_FORM_URLENCODED.replace(
+            "\"requestContext\":{",
+            "\"requestContext\":{\"requestId\":\"req-1\",",
+        );
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assert_eq!(idempotency_key(&event, false), None);
+        assert_eq!(
+            idempotency_key(&event, true).unwrap().key,
+            "- identity POST /somewhere request-id:req-1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryIdempotencyStore::new();
+        let ttl = Duration::from_secs(60);
+        let in_progress = IdempotencyRecord {
+            body_hash: "hash".to_string(),
+            response: None,
+        };
+        let completed = IdempotencyRecord {
+            body_hash: "hash".to_string(),
+            response: Some(LambdaResponse::text(200, "Hello")),
+        };
+
+        assert_eq!(store.put_if_absent("key", &in_progress, ttl).await, None);
+        assert_eq!(
+            store.put_if_absent("key", &in_progress, ttl).await,
+            Some(in_progress.clone())
+        );
+        store.put("key", &completed, ttl).await;
+        assert_eq!(
+            store.put_if_absent("key", &in_progress, ttl).await,
+            Some(completed.clone())
+        );
+        assert_eq!(store.put_if_absent("other", &in_progress, ttl).await, None);
+
+        // Removed
+        store.remove("other").await;
+        assert_eq!(store.put_if_absent("other", &in_progress, ttl).await, None);
+
+        // Expired
+        store.put("key", &completed, Duration::from_secs(0)).await;
+        assert_eq!(store.put_if_absent("key", &in_progress, ttl).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_response() {
+        let idempotency = Idempotency {
+            store: Arc::new(MemoryIdempotencyStore::new()),
+            ttl: Duration::from_secs(60),
+        };
+        let key = |key: &str, body_hash: &str| {
+            Some(IdempotencyKey {
+                key: key.to_string(),
+                body_hash: body_hash.to_string(),
+            })
+        };
+
+        let first = LambdaResponse::text(201, "first");
+        let second = LambdaResponse::text(201, "second");
+        let res = idempotent_response(Some(idempotency.clone()), key("pay", "body"), async {
+            Ok::<_, ()>(first.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(first.clone()));
+
+        // Replayed
+        let res = idempotent_response(Some(idempotency.clone()), key("pay", "body"), async {
+            Ok::<_, ()>(second.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(first));
+
+        // Same key with another body
+        let res = idempotent_response(Some(idempotency.clone()), key("pay", "other"), async {
+            Ok::<_, ()>(second.clone())
+        })
+        .await;
+        assert_eq!(res.unwrap().status, 422);
+
+        // Without key, not replayed
+        let res = idempotent_response(Some(idempotency.clone()), None, async {
+            Ok::<_, ()>(second.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(second.clone()));
+
+        // In progress
+        let in_progress = IdempotencyRecord {
+            body_hash: "body".to_string(),
+            response: None,
+        };
+        idempotency
+            .store
+            .put("running", &in_progress, idempotency.ttl)
+            .await;
+        let res = idempotent_response(Some(idempotency.clone()), key("running", "body"), async {
+            Ok::<_, ()>(second.clone())
+        })
+        .await;
+        assert_eq!(res.unwrap().status, 409);
+
+        // Server errors are not stored
+        let error = LambdaResponse::text(500, "error");
+        let ok = LambdaResponse::text(200, "ok");
+        let res = idempotent_response(Some(idempotency.clone()), key("fail", "body"), async {
+            Ok::<_, ()>(error.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(error));
+        let res = idempotent_response(Some(idempotency.clone()), key("fail", "body"), async {
+            Ok::<_, ()>(ok.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(ok.clone()));
+
+        // Responses with Set-Cookie are not stored
+        let mut login = LambdaResponse::text(200, "login");
+        login.add_header("set-cookie", "session=secret");
+        let res = idempotent_response(Some(idempotency.clone()), key("login", "body"), async {
+            Ok::<_, ()>(login.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(login));
+        let res = idempotent_response(Some(idempotency), key("login", "body"), async {
+            Ok::<_, ()>(ok.clone())
+        })
+        .await;
+        assert_eq!(res, Ok(ok));
+    }
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Immutable assets
+//! Long-lived `Cache-Control` for fingerprinted static assets matched by path patterns
+//!
+use crate::request::LambdaHttpEvent;
+use crate::response::LambdaResponse;
+
+/// `Cache-Control` of immutable assets, cached for a year
+pub(crate) const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Path patterns of immutable assets, with `{a,b}` alternatives expanded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ImmutableAssets {
+    patterns: Vec<String>,
+}
+
+impl ImmutableAssets {
+    pub(crate) fn add(&mut self, pattern: &str) {
+        self.patterns.extend(expand_alternatives(pattern));
+    }
+
+    /// Does the request path match any pattern?
+    pub(crate) fn matches(&self, event: &LambdaHttpEvent) -> bool {
+        let path_query = event.path_query();
+        let path = path_query.split('?').next().unwrap_or_default();
+        self.patterns
+            .iter()
+            .any(|pattern| glob_matches(pattern.as_bytes(), path.as_bytes()))
+    }
+}
+
+/// Replace `Cache-Control` of successful responses
+pub(crate) fn apply_immutable(response: &mut LambdaResponse) {
+    if (200..300).contains(&response.status) || response.status == 304 {
+        response.remove_header("cache-control");
+        response.add_header("cache-control", IMMUTABLE_CACHE_CONTROL);
+    }
+}
+
+/// `/assets/*.{js,css}` to `/assets/*.js` and `/assets/*.css`
+fn expand_alternatives(pattern: &str) -> Vec<String> {
+    let (open, close) = match (pattern.find('{'), pattern.find('}')) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return vec![pattern.to_string()],
+    };
+    let (prefix, suffix) = (&pattern[..open], &pattern[close + 1..]);
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| {
+            expand_alternatives(&format!("{}{}{}", prefix, alternative, suffix))
+        })
+        .collect()
+}
+
+/// `*` matches within a path segment, `**` across segments, `?` a character
+fn glob_matches(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| glob_matches(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            let segment_len = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=segment_len).any(|i| glob_matches(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => match path {
+            [c, path_rest @ ..] if *c != b'/' => glob_matches(rest, path_rest),
+            _ => false,
+        },
+        [c, rest @ ..] => match path {
+            [p, path_rest @ ..] if p == c => glob_matches(rest, path_rest),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ResponseFormat;
+    use crate::test_consts::*;
+
+    fn matches(assets: &ImmutableAssets, path: &str) -> bool {
+        let event_str = API_GATEWAY_V2_GET_SOMEWHERE_ONEQUERY.replace("/somewhere", path);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assets.matches(&event)
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut assets = ImmutableAssets::default();
+        assets.add("/assets/*.{js,css}");
+        assets.add("/static/**");
+        assert!(matches(&assets, "/assets/app.0a1b2c.js"));
+        assert!(matches(&assets, "/assets/app.css"));
+        assert!(matches(&assets, "/static/fonts/a.woff2"));
+        assert!(!matches(&assets, "/assets/app.html"));
+        assert!(!matches(&assets, "/assets/js/app.js"));
+        assert!(!matches(&assets, "/somewhere"));
+    }
+
+    #[test]
+    fn test_apply_immutable() {
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("cache-control", "no-cache");
+        apply_immutable(&mut response);
+        assert_eq!(
+            response.header("cache-control"),
+            Some(IMMUTABLE_CACHE_CONTROL)
+        );
+
+        // Errors are not cached
+        let mut response = LambdaResponse::new(404, ResponseFormat::HttpApiV2);
+        apply_immutable(&mut response);
+        assert_eq!(response.header("cache-control"), None);
+    }
+}
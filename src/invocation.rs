@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Invocation ID
+//! Per-invocation ID seeded from the request ID, and unique IDs derived from it
+//!
+use crate::request::LambdaHttpEvent;
+use core::future::Future;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Invocations handled by this container
+static INVOCATION_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Unique IDs made outside of invocations
+static UNSCOPED_SEQ: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Invocation>>> = RefCell::new(None);
+}
+
+/// Invocation being handled
+#[derive(Debug)]
+pub(crate) struct Invocation {
+    id: String,
+    seq: AtomicU64,
+}
+
+impl Invocation {
+    /// Count the invocation, its ID is the request ID of API Gateway or function URL,
+    /// or `{container_id}-{count}` for ALB and bridged events without request ID
+    pub(crate) fn start(event: &LambdaHttpEvent) -> Arc<Self> {
+        let count = INVOCATION_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+        let id = event
+            .request_context()
+            .request_id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("{}-{}", crate::container_id(), count));
+        Arc::new(Self {
+            id,
+            seq: AtomicU64::new(0),
+        })
+    }
+}
+
+/// ID of the invocation being handled, None outside of the handler
+///
+/// Available while the web application handles the request,
+/// but not in tasks spawned by `tokio::spawn`.
+pub fn invocation_id() -> Option<String> {
+    CURRENT.with(|current| current.borrow().as_ref().map(|inv| inv.id.clone()))
+}
+
+/// Number of invocations handled by this container, monotonic while the container is warm
+pub fn invocation_count() -> u64 {
+    INVOCATION_COUNT.load(Ordering::Relaxed)
+}
+
+/// ID unique across containers, e.g. for log lines or records created by the request
+///
+/// `{invocation_id}-{n}` with a counter per invocation,
+/// or `{container_id}-0-{n}` outside of the handler.
+///
+/// ```
+/// let first = lambda_web::unique_id();
+/// assert_ne!(first, lambda_web::unique_id());
+/// ```
+pub fn unique_id() -> String {
+    CURRENT.with(|current| match current.borrow().as_ref() {
+        Some(inv) => format!("{}-{}", inv.id, inv.seq.fetch_add(1, Ordering::Relaxed) + 1),
+        None => format!(
+            "{}-0-{}",
+            crate::container_id(),
+            UNSCOPED_SEQ.fetch_add(1, Ordering::Relaxed) + 1
+        ),
+    })
+}
+
+/// Run the future with the invocation as current
+pub(crate) async fn scope<F: Future>(invocation: Arc<Invocation>, fut: F) -> F::Output {
+    futures_util::pin_mut!(fut);
+    futures_util::future::poll_fn(|cx| {
+        let _guard = Enter::new(Some(invocation.clone()));
+        fut.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// Set current invocation, restore previous one on drop
+struct Enter(Option<Arc<Invocation>>);
+
+impl Enter {
+    fn new(invocation: Option<Arc<Invocation>>) -> Self {
+        Self(CURRENT.with(|current| current.replace(invocation)))
+    }
+}
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[tokio::test]
+    async fn test_scope() {
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_WITH_CONTEXT).unwrap();
+        let count = invocation_count();
+        let invocation = Invocation::start(&event);
+        assert!(invocation_count() > count);
+
+        assert_eq!(invocation_id(), None);
+        let ids = scope(invocation, async {
+            tokio::task::yield_now().await;
+            (invocation_id(), unique_id(), unique_id())
+        })
+        .await;
+        assert_eq!(
+            ids,
+            (
+                Some("JKJaXmPLvHcESHA=".to_string()),
+                "JKJaXmPLvHcESHA=-1".to_string(),
+                "JKJaXmPLvHcESHA=-2".to_string()
+            )
+        );
+        assert_eq!(invocation_id(), None);
+        assert!(unique_id().starts_with(crate::container_id()));
+    }
+
+    #[test]
+    fn test_without_request_id() {
+        let event: LambdaHttpEvent = serde_json::from_str(ALB_GET_MIXEDCASE_HEADERS).unwrap();
+        let invocation = Invocation::start(&event);
+        assert!(invocation.id.starts_with(crate::container_id()));
+    }
+}
@@ -0,0 +1,351 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Direct invocation
+//! Calls web applications on other Lambda functions with hyper requests via the Invoke API,
+//! without API Gateway between functions
+//!
+use crate::sigv4::{AwsTransport, Credentials, SignedRequest};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Error of direct invocation
+#[derive(Debug)]
+pub enum InvokeError {
+    /// Error status code of the Invoke API, e.g. 403 without `lambda:InvokeFunction` permission
+    Status(u16),
+    /// The function failed, with the error message
+    FunctionError(String),
+    /// The function returned a payload other than HTTP response
+    InvalidResponse(String),
+    /// No credentials in the environment
+    NoCredentials,
+    /// Error of `AwsTransport`
+    Transport(lambda_runtime::Error),
+}
+
+impl std::fmt::Display for InvokeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Status(status) => write!(f, "Invoke API failed with {}", status),
+            Self::FunctionError(message) => write!(f, "function error: {}", message),
+            Self::InvalidResponse(reason) => write!(f, "invalid function response: {}", reason),
+            Self::NoCredentials => write!(f, "no AWS credentials to sign Invoke request"),
+            Self::Transport(err) => write!(f, "Invoke request failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InvokeError {}
+
+/// Client calling a web application on another Lambda function
+///
+/// Requests are sent as HTTP API payload format 2.0 events, so the function may run
+/// any web framework with this crate or other adapters.
+///
+/// ```no_run
+/// use lambda_web::{AwsTransport, LambdaInvoker};
+/// # fn example(transport: impl AwsTransport + 'static) {
+/// let invoker = LambdaInvoker::new("users-api:live").transport(transport);
+/// let request = hyper::Request::get("/users/42")
+///     .header("host", "users.internal")
+///     .body(Vec::new())
+///     .unwrap();
+/// # async {
+/// let response = invoker.call(&request).await.unwrap();
+/// # };
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LambdaInvoker {
+    function_name: String,
+    region: String,
+    credentials: Option<Credentials>,
+    transport: Option<Arc<dyn AwsTransport>>,
+}
+
+impl LambdaInvoker {
+    /// Invoker of the function name, `name:alias` or ARN,
+    /// signed by the execution role credentials in the region of the ARN or `AWS_REGION`
+    pub fn new<S: Into<String>>(function_name: S) -> Self {
+        let function_name = function_name.into();
+        let region = function_name
+            .strip_prefix("arn:")
+            .and_then(|arn| arn.split(':').nth(2))
+            .map(|region| region.to_string())
+            .or_else(|| std::env::var("AWS_REGION").ok())
+            .unwrap_or_default();
+        Self {
+            function_name,
+            region,
+            credentials: Credentials::from_env(),
+            transport: None,
+        }
+    }
+
+    /// Override the region
+    pub fn region<S: Into<String>>(mut self, region: S) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Override the credentials
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// HTTPS client to send requests
+    pub fn transport<T: AwsTransport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Signed Invoke request with the request event as payload
+    pub fn sign<B: AsRef<[u8]>>(
+        &self,
+        request: &hyper::Request<B>,
+    ) -> Result<SignedRequest, InvokeError> {
+        self.sign_at(request, SystemTime::now())
+    }
+
+    /// Invoke the function with the request, then return its response
+    pub async fn call<B: AsRef<[u8]>>(
+        &self,
+        request: &hyper::Request<B>,
+    ) -> Result<hyper::Response<Vec<u8>>, InvokeError> {
+        let transport = self
+            .transport
+            .as_ref()
+            .ok_or_else(|| InvokeError::Transport("no AwsTransport is configured".into()))?;
+        let response = transport
+            .send(self.sign(request)?)
+            .await
+            .map_err(InvokeError::Transport)?;
+        if !(200..300).contains(&response.status) {
+            return Err(InvokeError::Status(response.status));
+        }
+        response_from_payload(&response.body)
+    }
+
+    fn sign_at<B: AsRef<[u8]>>(
+        &self,
+        request: &hyper::Request<B>,
+        now: SystemTime,
+    ) -> Result<SignedRequest, InvokeError> {
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or(InvokeError::NoCredentials)?;
+        let host = format!("lambda.{}.amazonaws.com", self.region);
+        let path = format!(
+            "/2015-03-31/functions/{}/invocations",
+            crate::sigv4::uri_encode(&self.function_name)
+        );
+        let payload = request_to_event(request).to_string().into_bytes();
+        let mut signed = crate::sigv4::sign(
+            "POST",
+            &host,
+            &path,
+            payload,
+            &self.region,
+            "lambda",
+            credentials,
+            now,
+        );
+        signed
+            .headers
+            .push(("content-type".to_string(), "application/json".to_string()));
+        Ok(signed)
+    }
+}
+
+/// HTTP API payload format 2.0 event of the request, as API Gateway sends
+pub fn request_to_event<B: AsRef<[u8]>>(request: &hyper::Request<B>) -> Value {
+    let mut headers = HashMap::<String, String>::new();
+    let mut cookies = Vec::new();
+    for (name, value) in request.headers() {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        if *name == hyper::header::COOKIE {
+            cookies.extend(value.split(';').map(|c| c.trim().to_string()));
+            continue;
+        }
+        headers
+            .entry(name.as_str().to_string())
+            .and_modify(|joined| {
+                joined.push(',');
+                joined.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    let domain_name = headers
+        .get("host")
+        .cloned()
+        .or_else(|| request.uri().host().map(|host| host.to_string()))
+        .unwrap_or_else(|| "localhost".to_string());
+    headers
+        .entry("host".to_string())
+        .or_insert_with(|| domain_name.clone());
+
+    let raw_path = percent_encoding::percent_decode_str(request.uri().path())
+        .decode_utf8_lossy()
+        .into_owned();
+    let body = request.body().as_ref();
+    let mut event = json!({
+        "version": "2.0",
+        "routeKey": "$default",
+        "rawPath": raw_path,
+        "rawQueryString": request.uri().query().unwrap_or_default(),
+        "headers": headers,
+        "requestContext": {
+            "domainName": domain_name,
+            "http": {
+                "method": request.method().as_str(),
+                "path": raw_path,
+                "protocol": "HTTP/1.1",
+                "sourceIp": "127.0.0.1"
+            },
+            "routeKey": "$default",
+            "stage": "$default"
+        },
+        "isBase64Encoded": !body.is_empty()
+    });
+    if !cookies.is_empty() {
+        event["cookies"] = json!(cookies);
+    }
+    if !body.is_empty() {
+        event["body"] = json!(base64::encode(body));
+    }
+    event
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResponsePayload {
+    status_code: Option<u16>,
+    #[serde(default)]
+    headers: Option<HashMap<String, String>>,
+    #[serde(default)]
+    multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    #[serde(default)]
+    cookies: Option<Vec<String>>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    is_base64_encoded: bool,
+    #[serde(default)]
+    error_message: Option<String>,
+}
+
+/// Response of the function payload in HTTP API, REST API or ALB response format
+pub fn response_from_payload(payload: &[u8]) -> Result<hyper::Response<Vec<u8>>, InvokeError> {
+    let payload: ResponsePayload = serde_json::from_slice(payload)
+        .map_err(|err| InvokeError::InvalidResponse(err.to_string()))?;
+    let status = match (payload.status_code, payload.error_message) {
+        (Some(status), _) => status,
+        (None, Some(message)) => return Err(InvokeError::FunctionError(message)),
+        (None, None) => {
+            return Err(InvokeError::InvalidResponse(
+                "no statusCode in the payload".to_string(),
+            ))
+        }
+    };
+
+    let mut builder = hyper::Response::builder().status(status);
+    for (name, value) in payload.headers.into_iter().flatten() {
+        builder = builder.header(name, value);
+    }
+    for (name, values) in payload.multi_value_headers.into_iter().flatten() {
+        for value in values {
+            builder = builder.header(name.as_str(), value);
+        }
+    }
+    for cookie in payload.cookies.into_iter().flatten() {
+        builder = builder.header(hyper::header::SET_COOKIE, cookie);
+    }
+    let body = match payload.body {
+        Some(body) if payload.is_base64_encoded => {
+            base64::decode(&body).map_err(|err| InvokeError::InvalidResponse(err.to_string()))?
+        }
+        Some(body) => body.into_bytes(),
+        None => Vec::new(),
+    };
+    builder
+        .body(body)
+        .map_err(|err| InvokeError::InvalidResponse(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::LambdaHttpEvent;
+
+    #[test]
+    fn test_request_to_event() {
+        let request = hyper::Request::post("https://users.internal/users/a%20b?x=1&y=2")
+            .header("content-type", "application/json")
+            .header("cookie", "a=1; b=2")
+            .header("accept", "text/html")
+            .header("accept", "application/json")
+            .body(br#"{"name":"alice"}"#.to_vec())
+            .unwrap();
+        let event_str = request_to_event(&request).to_string();
+
+        // Parsed as same as API Gateway events
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/users/a%20b?x=1&y=2");
+        assert_eq!(event.hostname(), Some("users.internal"));
+        assert_eq!(event.header("accept"), Some("text/html,application/json"));
+        assert_eq!(event.cookies(), vec!["a=1", "b=2"]);
+        assert_eq!(event.body().unwrap(), br#"{"name":"alice"}"#);
+    }
+
+    #[test]
+    fn test_response_from_payload() {
+        let response = response_from_payload(
+            br#"{"statusCode":201,"headers":{"content-type":"text/plain"},"cookies":["s=1"],"body":"aGVsbG8=","isBase64Encoded":true}"#,
+        )
+        .unwrap();
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.headers()["content-type"], "text/plain");
+        assert_eq!(response.headers()["set-cookie"], "s=1");
+        assert_eq!(response.body(), b"hello");
+
+        // REST API format
+        let response = response_from_payload(
+            br#"{"statusCode":404,"multiValueHeaders":{"vary":["a","b"]},"body":"Not Found","isBase64Encoded":false}"#,
+        )
+        .unwrap();
+        assert_eq!(response.headers().get_all("vary").iter().count(), 2);
+        assert_eq!(response.body(), b"Not Found");
+
+        // Unhandled error of the function
+        assert!(matches!(
+            response_from_payload(br#"{"errorMessage":"boom","errorType":"Panic"}"#),
+            Err(InvokeError::FunctionError(message)) if message == "boom"
+        ));
+    }
+
+    #[test]
+    fn test_sign() {
+        let invoker = LambdaInvoker::new("arn:aws:lambda:eu-west-1:123456789012:function:users")
+            .credentials(Credentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                session_token: None,
+            });
+        let request = hyper::Request::get("/").body(Vec::new()).unwrap();
+        let signed = invoker.sign(&request).unwrap();
+        assert_eq!(
+            signed.url,
+            "https://lambda.eu-west-1.amazonaws.com/2015-03-31/functions/arn%3Aaws%3Alambda%3Aeu-west-1%3A123456789012%3Afunction%3Ausers/invocations"
+        );
+        assert!(signed
+            .headers
+            .iter()
+            .any(|(name, value)| name == "authorization" && value.contains("/eu-west-1/lambda/")));
+    }
+}
@@ -0,0 +1,322 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Large response
+//! Returns responses exceeding the Lambda payload limit via S3 presigned redirect
+//!
+use crate::idempotency::StoreFuture;
+use crate::response::{LambdaResponse, MAX_RESPONSE_PAYLOAD_SIZE};
+use lambda_runtime::Error as LambdaError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How to return responses larger than `MAX_RESPONSE_PAYLOAD_SIZE`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LargeResponseStrategy {
+    /// Return as it is, Lambda fails the invocation with payload size error (default)
+    Passthrough,
+    /// Upload the body to `bucket` with key starting with `prefix`,
+    /// and return 303 See Other redirect to the presigned URL valid for `ttl`
+    S3Redirect {
+        bucket: String,
+        prefix: String,
+        ttl: Duration,
+    },
+    /// Return plain text error response with this status code, e.g. 502 or 413
+    ErrorResponse(u16),
+}
+
+impl Default for LargeResponseStrategy {
+    fn default() -> Self {
+        Self::Passthrough
+    }
+}
+
+/// Response body uploaded by `LargeResponseStore`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeObject {
+    /// Content-Type header of the response
+    pub content_type: Option<String>,
+    /// Content-Encoding header of the response, `br` when compressed by this crate
+    pub content_encoding: Option<String>,
+    /// Response body, decoded from base64
+    pub body: Vec<u8>,
+}
+
+/// Storage of oversized response bodies
+///
+/// Implement this trait with S3 PutObject and presigned GetObject, e.g. by AWS SDK for Rust.
+/// The object should be stored with `content_type` and `content_encoding` as metadata,
+/// so clients following the redirect receive the same headers.
+pub trait LargeResponseStore: Send + Sync {
+    /// Upload the object, then return presigned GET URL valid for `ttl`
+    fn put_presigned<'a>(
+        &'a self,
+        bucket: &'a str,
+        key: &'a str,
+        object: LargeObject,
+        ttl: Duration,
+    ) -> StoreFuture<'a, Result<String, LambdaError>>;
+}
+
+/// Large response settings in RunOptions
+#[derive(Clone)]
+pub(crate) struct LargeResponse {
+    pub(crate) strategy: LargeResponseStrategy,
+    pub(crate) store: Option<Arc<dyn LargeResponseStore>>,
+}
+
+/// Replace the response exceeding the payload limit with a redirect, by the strategy
+pub(crate) async fn redirect_large_response(
+    large_response: Option<&LargeResponse>,
+    response: LambdaResponse,
+) -> LambdaResponse {
+    let large_response = match large_response {
+        Some(large_response) if large_response.strategy != LargeResponseStrategy::Passthrough => {
+            large_response
+        }
+        _ => return response,
+    };
+    if !exceeds_payload_limit(&response) {
+        return response;
+    }
+
+    let body = if response.is_base64 {
+        match base64::decode(&response.body) {
+            Ok(body) => body,
+            Err(_) => return response,
+        }
+    } else {
+        response.body.clone().into_bytes()
+    };
+    replace_large_response(large_response, &response, body)
+        .await
+        .unwrap_or(response)
+}
+
+/// Apply the strategy to the raw body certainly exceeding the payload limit after base64 encoding,
+/// before the encoding work. None when the body may fit or without strategy.
+pub(crate) async fn early_large_response(
+    large_response: Option<&LargeResponse>,
+    response: &LambdaResponse,
+    body: &[u8],
+) -> Option<LambdaResponse> {
+    let large_response = large_response?;
+    if base64_len(body.len()) <= MAX_RESPONSE_PAYLOAD_SIZE {
+        return None;
+    }
+    replace_large_response(large_response, response, body.to_vec()).await
+}
+
+/// Length of base64 encoded body
+fn base64_len(body_len: usize) -> usize {
+    (body_len + 2) / 3 * 4
+}
+
+/// Redirect or error response by the strategy, None for `Passthrough`
+async fn replace_large_response(
+    large_response: &LargeResponse,
+    response: &LambdaResponse,
+    body: Vec<u8>,
+) -> Option<LambdaResponse> {
+    let (bucket, prefix, ttl, store) = match (&large_response.strategy, &large_response.store) {
+        (
+            LargeResponseStrategy::S3Redirect {
+                bucket,
+                prefix,
+                ttl,
+            },
+            Some(store),
+        ) => (bucket, prefix, *ttl, store),
+        (LargeResponseStrategy::ErrorResponse(status), _) => {
+            let mut error = LambdaResponse::new(*status, response.format);
+            error.add_header("content-type", "text/plain; charset=utf-8");
+            error.add_header("cache-control", "no-store");
+            error.body = "Response Too Large".to_string();
+            return Some(error);
+        }
+        _ => return None,
+    };
+
+    let object = LargeObject {
+        content_type: response.header("content-type").map(|v| v.to_string()),
+        content_encoding: response.header("content-encoding").map(|v| v.to_string()),
+        body,
+    };
+    let key = object_key(prefix);
+    match store.put_presigned(bucket, &key, object, ttl).await {
+        Ok(url) => Some(redirect_response(response, &url)),
+        Err(err) => {
+            tracing::error!("failed to upload large response: {}", err);
+            Some(LambdaResponse::text(500, "Internal Server Error"))
+        }
+    }
+}
+
+/// Is the serialized response larger than Lambda payload limit?
+fn exceeds_payload_limit(response: &LambdaResponse) -> bool {
+    // Serialize only large responses
+    response.body.len() > MAX_RESPONSE_PAYLOAD_SIZE / 2
+        && serde_json::to_vec(response)
+            .map(|json| MAX_RESPONSE_PAYLOAD_SIZE < json.len())
+            .unwrap_or(false)
+}
+
+/// Unique object key in this container
+fn object_key(prefix: &str) -> String {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!(
+        "{}{}-{}-{}",
+        prefix,
+        millis,
+        crate::affinity::container_id(),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// 303 See Other to the presigned URL, keeping cookies of the original response
+fn redirect_response(response: &LambdaResponse, url: &str) -> LambdaResponse {
+    let mut redirect = LambdaResponse::new(303, response.format);
+    redirect.add_header("location", url);
+    redirect.add_header("cache-control", "no-store");
+    redirect.cookies = response.cookies.clone();
+    for cookie in response
+        .multi_value_headers
+        .get("set-cookie")
+        .into_iter()
+        .flatten()
+    {
+        redirect.add_header("set-cookie", cookie);
+    }
+    if let Some(cookie) = response.headers.get("set-cookie") {
+        redirect.add_header("set-cookie", cookie);
+    }
+    redirect
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::{ResponseFormat, MAX_ENCODABLE_BODY_SIZE};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockStore {
+        uploaded: Mutex<Vec<(String, LargeObject)>>,
+    }
+
+    impl LargeResponseStore for MockStore {
+        fn put_presigned<'a>(
+            &'a self,
+            bucket: &'a str,
+            key: &'a str,
+            object: LargeObject,
+            _ttl: Duration,
+        ) -> StoreFuture<'a, Result<String, LambdaError>> {
+            self.uploaded
+                .lock()
+                .unwrap()
+                .push((key.to_string(), object));
+            let url = format!(
+                "https://{}.s3.amazonaws.com/{}?X-Amz-Signature=0",
+                bucket, key
+            );
+            Box::pin(async move { Ok(url) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_large_response() {
+        let store = Arc::new(MockStore::default());
+        let large_response = LargeResponse {
+            strategy: LargeResponseStrategy::S3Redirect {
+                bucket: "bucket".to_string(),
+                prefix: "responses/".to_string(),
+                ttl: Duration::from_secs(300),
+            },
+            store: Some(store.clone()),
+        };
+
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("content-type", "application/octet-stream");
+        response.add_header("set-cookie", "key=value");
+        response.body = base64::encode(vec![0u8; MAX_RESPONSE_PAYLOAD_SIZE]);
+        response.is_base64 = true;
+
+        // Small response is not changed
+        let small = LambdaResponse::text(200, "Hello");
+        let res = redirect_large_response(Some(&large_response), small.clone()).await;
+        assert_eq!(res, small);
+
+        // Without strategy
+        let res = redirect_large_response(None, response.clone()).await;
+        assert_eq!(res, response);
+
+        let res = redirect_large_response(Some(&large_response), response).await;
+        assert_eq!(res.status, 303);
+        assert!(res
+            .header("location")
+            .unwrap()
+            .starts_with("https://bucket.s3.amazonaws.com/responses/"));
+        assert_eq!(res.cookies, vec!["key=value".to_string()]);
+
+        let uploaded = store.uploaded.lock().unwrap();
+        assert_eq!(uploaded.len(), 1);
+        assert!(uploaded[0].0.starts_with("responses/"));
+        assert_eq!(uploaded[0].1.body.len(), MAX_RESPONSE_PAYLOAD_SIZE);
+        assert_eq!(
+            uploaded[0].1.content_type.as_deref(),
+            Some("application/octet-stream")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_early_large_response() {
+        let store = Arc::new(MockStore::default());
+        let large_response = LargeResponse {
+            strategy: LargeResponseStrategy::S3Redirect {
+                bucket: "bucket".to_string(),
+                prefix: "responses/".to_string(),
+                ttl: Duration::from_secs(300),
+            },
+            store: Some(store.clone()),
+        };
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.add_header("content-type", "application/octet-stream");
+
+        // 4.8 MB body fits before encoding, but not after
+        let body = vec![0u8; 4_800_000];
+        assert!(body.len() < MAX_RESPONSE_PAYLOAD_SIZE);
+        let res = early_large_response(Some(&large_response), &response, &body)
+            .await
+            .unwrap();
+        assert_eq!(res.status, 303);
+        assert_eq!(store.uploaded.lock().unwrap()[0].1.body.len(), 4_800_000);
+
+        // Fits after encoding
+        let body = vec![0u8; MAX_ENCODABLE_BODY_SIZE - 3];
+        assert!(
+            early_large_response(Some(&large_response), &response, &body)
+                .await
+                .is_none()
+        );
+        assert!(early_large_response(None, &response, &[0u8; 4_800_000])
+            .await
+            .is_none());
+
+        // Error response
+        let large_response = LargeResponse {
+            strategy: LargeResponseStrategy::ErrorResponse(502),
+            store: None,
+        };
+        let res = early_large_response(Some(&large_response), &response, &[0u8; 4_800_000])
+            .await
+            .unwrap();
+        assert_eq!(res.status, 502);
+        assert_eq!(res.body, "Response Too Large");
+    }
+}
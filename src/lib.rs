@@ -1,19 +1,249 @@
 // SPDX-License-Identifier: MIT
 pub use lambda_runtime::Error as LambdaError;
 
+#[cfg(feature = "alloc-stats")]
+mod alloc_stats;
+#[cfg(feature = "alloc-stats")]
+pub use alloc_stats::{allocation_stats, AllocationStats, CountingAllocator};
+pub mod authorizer;
+mod de;
+pub mod deploy;
+mod execution_env;
+pub use execution_env::{ExecutionEnv, EXECUTION_ENV_OVERRIDE};
+
+#[cfg(test)]
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod parity;
 #[cfg(test)]
 #[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
 mod test_consts;
 
+#[cfg(feature = "alb-oidc")]
+mod alb_oidc;
+#[cfg(feature = "alb-oidc")]
+pub use alb_oidc::{AlbOidc, OIDC_DATA_HEADER};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod affinity;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use affinity::{container_id, ContainerAffinity, CONTAINER_ID_COOKIE, CONTAINER_ID_HEADER};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod api_docs;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use api_docs::{ApiDocs, DEFAULT_API_DOCS_PATH};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod bridge;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use bridge::{ConversationalBridge, WebEvent, BRIDGE_HEADER};
 #[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
 pub(crate) mod brotli;
 #[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use brotli::{compression_stats, CompressionStats, ContentTypeStats};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod build_info;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use build_info::{BuildInfo, VERSION_PATH};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod builtin;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod capture;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use capture::{fixture, CaptureReason, CapturedEvent, FailureCapture};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod checksum;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use checksum::BodyChecksum;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod connection_store;
+#[cfg(all(
+    feature = "dynamodb",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+pub use connection_store::DynamoDbConnectionStore;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use connection_store::{ConnectionStore, MemoryConnectionStore, WebSocketConnection};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod connections;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use connections::{ConnectionsClient, ConnectionsError};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod context;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use context::{
+    Brand, CallerIdentity, ClientHints, FunctionUrlContext, IamIdentity, RawEvent, RequestContext,
+    Stage, ViewerInfo,
+};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod cookie;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use cookie::{CookieJar, SameSite};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod correlation;
+#[cfg(feature = "hyper")]
+pub use correlation::Correlated;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use correlation::{Correlation, REQUEST_ID_HEADER};
+#[cfg(all(
+    feature = "decompress",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+mod decompress;
+#[cfg(all(
+    feature = "cloudfront",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+mod edge;
+#[cfg(all(
+    feature = "cloudfront",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+pub use edge::{EdgeEventType, EdgeRequest, EDGE_FORWARD_HEADER};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod eventbridge;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use eventbridge::{
+    EventBridgeEvent, EventBridgeRoutes, EVENTBRIDGE_DETAIL_TYPE_HEADER,
+    EVENTBRIDGE_EVENT_ID_HEADER, EVENTBRIDGE_SOURCE_HEADER,
+};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod experiment;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use experiment::{Assignments, ClientKey, Experiment, EXPERIMENT_HEADER};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod health;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use health::{HealthCheck, DEFAULT_HEALTH_PATH};
+#[cfg(all(
+    feature = "html-rewrite",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+mod html_rewrite;
+#[cfg(all(
+    feature = "html-rewrite",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+pub use html_rewrite::HtmlRewrite;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod http_date;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod https;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use https::HttpsRedirect;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod idempotency;
+#[cfg(all(
+    feature = "dynamodb",
+    any(feature = "actix4", feature = "rocket05", feature = "hyper")
+))]
+pub use idempotency::DynamoDbIdempotencyStore;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use idempotency::{IdempotencyRecord, IdempotencyStore, MemoryIdempotencyStore, StoreFuture};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod immutable;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod invocation;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use invocation::{invocation_count, invocation_id, unique_id};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod large_response;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use large_response::{LargeObject, LargeResponseStore, LargeResponseStrategy};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod options;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use options::{PeerPort, RunOptions, UnsafeHeaderValues};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
 mod request;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use percent_encoding::AsciiSet;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use request::{DecodeError, RFC3986_PATH_ESCAPE_SET};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub mod respond;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod response;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use response::{
+    HandlerFuture, LambdaResponse, ResponseFormat, ResponseFuture, MAX_ENCODABLE_BODY_SIZE,
+    MAX_RESPONSE_PAYLOAD_SIZE,
+};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod router;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use router::EventRouter;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod routes;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use routes::{RouteManifest, ROUTES_PATH};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod runtime_info;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use runtime_info::{runtime_info, PayloadFormat, RuntimeInfo};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod sampling;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use sampling::{LogSampling, Sampled};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod scratch;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod self_check;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use self_check::{self_check, SelfCheckError, SELF_CHECK_ENV};
+#[cfg(feature = "sessions")]
+mod session;
+#[cfg(all(feature = "sessions", feature = "dynamodb"))]
+pub use session::DynamoDbSessionStore;
+#[cfg(feature = "sessions")]
+pub use session::{
+    MemorySessionStore, Session, SessionData, SessionStore, Sessions, DEFAULT_SESSION_COOKIE,
+};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod snapshot;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use snapshot::is_snap_start;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod sigv4;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use sigv4::{AwsTransport, Credentials, SignedRequest, TransportResponse};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod sniff;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod sns;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use sns::{SnsBridge, SNS_MESSAGE_ID_HEADER, SNS_SUBJECT_HEADER, SNS_TOPIC_ARN_HEADER};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod spa;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use spa::SpaMode;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod sqs;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use sqs::{SqsBridge, SQS_MESSAGE_ID_HEADER, SQS_QUEUE_ARN_HEADER, SQS_RECEIVE_COUNT_HEADER};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod streams;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use streams::{
+    StreamBridge, STREAM_EVENT_NAME_HEADER, STREAM_PARTITION_KEY_HEADER,
+    STREAM_SEQUENCE_NUMBER_HEADER, STREAM_SOURCE_ARN_HEADER,
+};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod tenant;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use tenant::{Tenant, TenantRouting};
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+mod websocket;
+#[cfg(any(feature = "actix4", feature = "rocket05", feature = "hyper"))]
+pub use websocket::{
+    WebSocketRoutes, WEBSOCKET_CONNECTION_ID_HEADER, WEBSOCKET_EVENT_TYPE_HEADER,
+    WEBSOCKET_ROUTE_KEY_HEADER,
+};
 
 #[cfg(feature = "actix4")]
 mod actix4;
 #[cfg(feature = "actix4")]
-pub use actix4::run_actix_on_lambda;
+pub use actix4::{
+    run_actix_on_lambda, run_actix_on_lambda_with_options, run_actix_service_on_lambda,
+    run_actix_service_on_lambda_with_options, ActixHandler,
+};
 #[cfg(feature = "actix4")]
 pub use actix_web;
 
@@ -22,14 +252,42 @@ mod rocket05;
 #[cfg(feature = "rocket05")]
 pub use rocket;
 #[cfg(feature = "rocket05")]
-pub use rocket05::launch_rocket_on_lambda;
+pub use rocket05::{
+    launch_rocket_on_lambda, launch_rocket_on_lambda_with_options, rocket_local_client,
+    rocket_with_secret_key, RocketHandler,
+};
 
+#[cfg(feature = "hyper")]
+mod early_hints;
+#[cfg(feature = "hyper")]
+pub use early_hints::EarlyHints;
+#[cfg(feature = "emulator")]
+mod emulator;
+#[cfg(feature = "emulator")]
+pub use emulator::{run_hyper_on_emulator, EMULATOR_PORT};
+#[cfg(feature = "hyper")]
+mod fallback;
+#[cfg(feature = "hyper")]
+pub use fallback::Fallback;
 #[cfg(feature = "hyper")]
 mod hyper014;
 #[cfg(feature = "hyper")]
-pub use hyper014::run_hyper_on_lambda;
+pub use hyper014::{
+    run_hyper_on_lambda, run_hyper_on_lambda_with_options, run_multi_on_lambda, stage, HyperHandler,
+};
+#[cfg(feature = "hyper")]
+mod invoke;
+#[cfg(feature = "hyper")]
+pub use invoke::{request_to_event, response_from_payload, InvokeError, LambdaInvoker};
+#[cfg(feature = "streaming")]
+mod streaming;
+#[cfg(feature = "streaming")]
+pub use streaming::{ResponseMode, INVOKE_MODE_ENV};
 
-/// Returns true if it is running on AWS Lambda
+/// Returns true if it is running under Lambda Runtime API,
+/// that is AWS Lambda, Runtime Interface Emulator or `cargo lambda watch`.
+///
+/// Set `LAMBDA_WEB_EXECUTION_ENV=local` to force false, see `ExecutionEnv`.
 pub fn is_running_on_lambda() -> bool {
-    std::env::var("AWS_LAMBDA_RUNTIME_API").is_ok()
+    ExecutionEnv::detect().has_runtime_api()
 }
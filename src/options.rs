@@ -0,0 +1,793 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Options to run web frameworks on AWS Lambda
+//!
+use crate::checksum::BodyChecksum;
+use crate::idempotency::{Idempotency, IdempotencyStore};
+use crate::large_response::{LargeResponse, LargeResponseStore, LargeResponseStrategy};
+use crate::request::LambdaHttpEvent;
+use crate::LambdaError;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Options to run web application on AWS Lambda
+///
+/// ```
+/// use lambda_web::{PeerPort, RunOptions};
+///
+/// let options = RunOptions::default().peer_port(PeerPort::Forwarded);
+/// ```
+#[derive(Clone, Default)]
+pub struct RunOptions {
+    pub(crate) peer_port: PeerPort,
+    pub(crate) response_header_blocklist: Vec<String>,
+    pub(crate) idempotency: Option<Idempotency>,
+    pub(crate) idempotency_request_id: bool,
+    pub(crate) container_affinity: bool,
+    pub(crate) cookie_same_site: Option<crate::SameSite>,
+    pub(crate) experiments: Vec<crate::Experiment>,
+    pub(crate) production_stages: Option<Vec<String>>,
+    pub(crate) log_compression_stats: bool,
+    pub(crate) max_compression_size: Option<usize>,
+    pub(crate) sniff_content_type: bool,
+    pub(crate) default_charset: bool,
+    #[cfg(feature = "decompress")]
+    pub(crate) decompress_unsupported: bool,
+    #[cfg(feature = "decompress")]
+    pub(crate) decompress_requests: bool,
+    pub(crate) date_header: Option<Duration>,
+    pub(crate) deadline_compression: Option<crate::brotli::DeadlineCompression>,
+    pub(crate) log_runtime_info: bool,
+    pub(crate) snapshot_hooks: crate::snapshot::SnapshotHooks,
+    pub(crate) access_log: Option<crate::LogSampling>,
+    pub(crate) conversational_bridge: Option<crate::ConversationalBridge>,
+    pub(crate) websocket_routes: Option<crate::WebSocketRoutes>,
+    pub(crate) connection_store: Option<Arc<dyn crate::ConnectionStore>>,
+    pub(crate) sqs_bridge: Option<crate::SqsBridge>,
+    pub(crate) stream_bridge: Option<crate::StreamBridge>,
+    pub(crate) sns_bridge: Option<crate::SnsBridge>,
+    pub(crate) eventbridge_routes: Option<crate::EventBridgeRoutes>,
+    pub(crate) failure_capture: Option<crate::FailureCapture>,
+    pub(crate) raw_event: bool,
+    pub(crate) large_response: Option<LargeResponse>,
+    pub(crate) body_checksum: Option<BodyChecksum>,
+    pub(crate) decode_double_encoded_path: bool,
+    pub(crate) semicolon_query_separator: bool,
+    pub(crate) query_plus_as_space: bool,
+    pub(crate) path_escape_set: Option<&'static percent_encoding::AsciiSet>,
+    pub(crate) tenant_routing: Option<crate::TenantRouting>,
+    pub(crate) spa_mode: Option<crate::SpaMode>,
+    pub(crate) https_redirect: Option<crate::HttpsRedirect>,
+    pub(crate) strict_header_names: bool,
+    pub(crate) unsafe_header_values: UnsafeHeaderValues,
+    pub(crate) method_override: Vec<String>,
+    pub(crate) max_header_count: Option<usize>,
+    pub(crate) max_header_bytes: Option<usize>,
+    pub(crate) propagate_headers: Vec<String>,
+    pub(crate) immutable_assets: Option<crate::immutable::ImmutableAssets>,
+    pub(crate) health_check: Option<crate::HealthCheck>,
+    pub(crate) route_manifest: Option<crate::RouteManifest>,
+    pub(crate) api_docs: Option<crate::ApiDocs>,
+    pub(crate) build_info: Option<crate::BuildInfo>,
+    pub(crate) version_endpoint: bool,
+    #[cfg(feature = "alb-oidc")]
+    pub(crate) alb_oidc: Option<crate::AlbOidc>,
+    #[cfg(feature = "sessions")]
+    pub(crate) sessions: Option<crate::Sessions>,
+    #[cfg(feature = "html-rewrite")]
+    pub(crate) html_rewrite: Option<crate::HtmlRewrite>,
+    #[cfg(feature = "streaming")]
+    pub(crate) response_mode: crate::ResponseMode,
+    #[cfg(feature = "streaming")]
+    pub(crate) streaming_high_water_mark: Option<usize>,
+    #[cfg(feature = "actix4")]
+    pub(crate) actix_payload_chunk_size: Option<usize>,
+    #[cfg(feature = "rocket05")]
+    pub(crate) rocket_tracked_client: bool,
+}
+
+impl RunOptions {
+    /// Port number of peer (client) address passed to web frameworks
+    pub fn peer_port(mut self, peer_port: PeerPort) -> Self {
+        self.peer_port = peer_port;
+        self
+    }
+
+    /// Decode one more layer of percent-encoding in request paths, e.g. `%2520` to `%20`.
+    /// Copes with proxies (e.g. CloudFront origin path) encoding already encoded paths.
+    /// Decoded paths are logged by `tracing`.
+    ///
+    /// Paths containing a literal `%XX` sequence are decoded too, so it is off by default.
+    pub fn decode_double_encoded_path(mut self, enabled: bool) -> Self {
+        self.decode_double_encoded_path = enabled;
+        self
+    }
+
+    /// Treat `;` in query strings as separator as same as `&`, e.g. `?a=1;b=2`.
+    /// Off by default, `;` is passed to web frameworks as it is.
+    ///
+    /// API Gateway REST API and ALB split query strings only at `&`,
+    /// so `a=1;b=2` reaches this crate as `a` = `1;b=2` and is split again here.
+    /// Matrix parameters in the path (`/path;key=value`) are not changed.
+    pub fn semicolon_query_separator(mut self, enabled: bool) -> Self {
+        self.semicolon_query_separator = enabled;
+        self
+    }
+
+    /// Pass `+` in REST API and ALB query strings as it is, instead of `%2B`,
+    /// so form parsers (Rocket, `serde_urlencoded`) read it as space, as same as HTTP API.
+    /// Recommended for Rocket, whose query guards treat `+` and `%2B` differently.
+    ///
+    /// REST API decodes `%2B` to `+` too, so a literal `+` sent as `%2B` is also read as space.
+    /// Off by default, `+` is encoded to `%2B` and read as a literal `+`.
+    pub fn query_plus_as_space(mut self, enabled: bool) -> Self {
+        self.query_plus_as_space = enabled;
+        self
+    }
+
+    /// Characters percent-encoded again in HTTP API and function URL paths,
+    /// which are percent-decoded by API Gateway. Default is `RFC3986_PATH_ESCAPE_SET`.
+    ///
+    /// ```
+    /// use lambda_web::{AsciiSet, RunOptions, RFC3986_PATH_ESCAPE_SET};
+    ///
+    /// // Backend expecting `:` and `@` as they are
+    /// const PATH_ESCAPE_SET: &AsciiSet = &RFC3986_PATH_ESCAPE_SET.remove(b':').remove(b'@');
+    /// let options = RunOptions::default().path_escape_set(PATH_ESCAPE_SET);
+    /// ```
+    pub fn path_escape_set(mut self, escape_set: &'static percent_encoding::AsciiSet) -> Self {
+        self.path_escape_set = Some(escape_set);
+        self
+    }
+
+    /// Parse tenant from the subdomain of the Host name, and optionally rewrite the path.
+    /// The tenant is attached to hyper and Actix-web request extensions as `Tenant`,
+    /// and to Rocket request local cache as `Option<Tenant>`.
+    pub fn tenant_routing(mut self, routing: crate::TenantRouting) -> Self {
+        self.tenant_routing = Some(routing);
+        self
+    }
+
+    /// Rewrite client side routes of single-page applications to `index.html`,
+    /// before tenant routing and the web framework. See `SpaMode`.
+    pub fn spa_mode(mut self, spa: crate::SpaMode) -> Self {
+        self.spa_mode = Some(spa);
+        self
+    }
+
+    /// Redirect plain HTTP requests to HTTPS before the web framework,
+    /// and send HSTS on responses. See `HttpsRedirect`.
+    pub fn https_redirect(mut self, redirect: crate::HttpsRedirect) -> Self {
+        self.https_redirect = Some(redirect);
+        self
+    }
+
+    /// Verify `x-amzn-oidc-data` tokens of ALB with OIDC authentication,
+    /// and set their claims to `CallerIdentity` in hyper request extensions.
+    /// Invalid tokens are logged and ignored, the app should reject requests without claims.
+    #[cfg(feature = "alb-oidc")]
+    pub fn alb_oidc(mut self, oidc: crate::AlbOidc) -> Self {
+        self.alb_oidc = Some(oidc);
+        self
+    }
+
+    /// Server-side sessions for hyper based frameworks, `Session` in hyper request extensions.
+    /// Session data is kept in the store, the cookie carries only the session ID.
+    /// No effect on Actix Web and Rocket.
+    #[cfg(feature = "sessions")]
+    pub fn sessions(mut self, sessions: crate::Sessions) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Health check endpoint answered before the web framework, off by default
+    pub fn health_check(mut self, health_check: crate::HealthCheck) -> Self {
+        self.health_check = Some(health_check);
+        self
+    }
+
+    /// Answer `OPTIONS` requests to declared routes with `Allow` header,
+    /// and optionally list the routes at `/__lambda_web/routes`. See `RouteManifest`.
+    pub fn route_manifest(mut self, manifest: crate::RouteManifest) -> Self {
+        self.route_manifest = Some(manifest);
+        self
+    }
+
+    /// Serve OpenAPI document at `/openapi.json` before the web framework. See `ApiDocs`.
+    pub fn api_docs(mut self, docs: crate::ApiDocs) -> Self {
+        self.api_docs = Some(docs);
+        self
+    }
+
+    /// Build metadata of the application, made by `build_info!()`.
+    /// Attached to hyper request extensions and reported by the health check endpoint.
+    pub fn build_info(mut self, build_info: crate::BuildInfo) -> Self {
+        self.build_info = Some(build_info);
+        self
+    }
+
+    /// Answer `GET /__lambda_web/version` with build metadata, off by default
+    pub fn version_endpoint(mut self, enabled: bool) -> Self {
+        self.version_endpoint = enabled;
+        self
+    }
+
+    /// Propagate these request headers to outbound requests via `Correlation`,
+    /// e.g. `x-request-id`, `traceparent`, `x-amzn-trace-id`.
+    /// Header names are case-insensitive.
+    ///
+    /// `x-request-id` is filled with API Gateway request ID when the request has none.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    ///
+    /// let options = RunOptions::default().propagate_headers(["x-request-id", "traceparent"]);
+    /// ```
+    pub fn propagate_headers<I, T>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.propagate_headers
+            .extend(names.into_iter().map(|n| n.as_ref().to_ascii_lowercase()));
+        self
+    }
+
+    /// Reject requests with more than `max_count` headers (cookies are counted one by one)
+    /// or more than `max_bytes` of header names and values with 431 Request Header Fields Too Large,
+    /// before web frameworks allocate them. 4096 headers and 1 MiB by default.
+    pub fn max_request_headers(mut self, max_count: usize, max_bytes: usize) -> Self {
+        self.max_header_count = Some(max_count);
+        self.max_header_bytes = Some(max_bytes);
+        self
+    }
+
+    /// `Cache-Control: public, max-age=31536000, immutable` for successful responses
+    /// of fingerprinted assets matching the path patterns, replacing one set by the app.
+    /// `*` matches within a path segment, `**` across segments, and `{js,css}` either of them.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    ///
+    /// let options = RunOptions::default().immutable_assets(["/assets/*.{js,css}", "/fonts/**"]);
+    /// ```
+    pub fn immutable_assets<I, T>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let assets = self.immutable_assets.get_or_insert_with(Default::default);
+        for pattern in patterns {
+            assets.add(pattern.as_ref());
+        }
+        self
+    }
+
+    /// Reject requests with header names containing characters not allowed by RFC 7230
+    /// with 400 Bad Request. Off by default, such headers are silently dropped by web frameworks.
+    pub fn strict_header_names(mut self, enabled: bool) -> Self {
+        self.strict_header_names = enabled;
+        self
+    }
+
+    /// How to treat response header values with CR, LF or other control characters,
+    /// which web frameworks may pass through from user input. Default is `Strip`.
+    pub fn unsafe_header_values(mut self, policy: UnsafeHeaderValues) -> Self {
+        self.unsafe_header_values = policy;
+        self
+    }
+
+    /// Honor `X-HTTP-Method-Override` header of POST requests, for clients behind proxies
+    /// passing only GET and POST. Overrides to methods other than these, or of requests
+    /// other than POST, are rejected with 400 Bad Request. The header is removed
+    /// before the web framework. Off by default.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    ///
+    /// let options = RunOptions::default().method_override(["PUT", "PATCH", "DELETE"]);
+    /// ```
+    pub fn method_override<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.method_override = methods
+            .into_iter()
+            .map(|method| method.as_ref().to_ascii_uppercase())
+            .collect();
+        self
+    }
+
+    /// Add `X-Robots-Tag: noindex` to responses of stages other than these,
+    /// not to be indexed by search engines on dev or staging API domains.
+    /// Responses with `X-Robots-Tag` set by the application are not changed.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    ///
+    /// let options = RunOptions::default().noindex_non_production(["prod", "$default"]);
+    /// ```
+    pub fn noindex_non_production<I, T>(mut self, production_stages: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.production_stages = Some(production_stages.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Rewrite HTML responses served under the stage prefix of the default `execute-api` domain,
+    /// so that asset URLs rendered without the stage are not broken.
+    /// Responses compressed by the application and streaming responses are not rewritten.
+    ///
+    /// ```
+    /// use lambda_web::{HtmlRewrite, RunOptions};
+    ///
+    /// let options = RunOptions::default().html_rewrite(HtmlRewrite::RootRelativeUrls);
+    /// ```
+    #[cfg(feature = "html-rewrite")]
+    pub fn html_rewrite(mut self, mode: crate::HtmlRewrite) -> Self {
+        self.html_rewrite = Some(mode);
+        self
+    }
+
+    /// Strip these headers from responses, e.g. `x-powered-by`, `server`.
+    /// Header names are case-insensitive.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    ///
+    /// let options = RunOptions::default().strip_response_headers(["server", "x-powered-by"]);
+    /// ```
+    pub fn strip_response_headers<I, T>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.response_header_blocklist
+            .extend(names.into_iter().map(|n| n.as_ref().to_ascii_lowercase()));
+        self
+    }
+
+    /// Replay stored response for retried requests with same `Idempotency-Key` header
+    ///
+    /// Responses except 5xx and those with `Set-Cookie` are stored for `ttl`.
+    /// Keys are scoped by the caller, the principal authenticated by API Gateway
+    /// or the hash of `Authorization` or `Cookie` header, and requests without
+    /// caller identity are not cached. Stored responses are replayed only to requests
+    /// with the same `Accept-Encoding` header.
+    /// Retries while the first request is in progress get 409 Conflict,
+    /// and keys reused with another body get 422 Unprocessable Entity.
+    /// Requests without `Idempotency-Key` header are not affected,
+    /// unless `idempotency_request_id` is enabled.
+    ///
+    /// `MemoryIdempotencyStore` keeps responses in the Lambda container,
+    /// `DynamoDbIdempotencyStore` (`dynamodb` feature) shares them between containers.
+    ///
+    /// ```
+    /// use lambda_web::{MemoryIdempotencyStore, RunOptions};
+    /// use std::time::Duration;
+    ///
+    /// let options = RunOptions::default()
+    ///     .idempotency(MemoryIdempotencyStore::new(), Duration::from_secs(3600));
+    /// ```
+    pub fn idempotency<S>(mut self, store: S, ttl: Duration) -> Self
+    where
+        S: IdempotencyStore + 'static,
+    {
+        self.idempotency = Some(Idempotency {
+            store: Arc::new(store),
+            ttl,
+        });
+        self
+    }
+
+    /// Key requests without `Idempotency-Key` header by the gateway request ID,
+    /// so redelivered invocations of the same event are replayed. Off by default.
+    /// Needs `idempotency` to be set.
+    pub fn idempotency_request_id(mut self, enabled: bool) -> Self {
+        self.idempotency_request_id = enabled;
+        self
+    }
+
+    /// Return responses larger than `MAX_RESPONSE_PAYLOAD_SIZE` by the strategy,
+    /// instead of failing the invocation. The store uploads bodies for `S3Redirect`.
+    ///
+    /// ```no_run
+    /// use lambda_web::{LargeResponseStore, LargeResponseStrategy, RunOptions};
+    /// use std::time::Duration;
+    ///
+    /// fn options<S: LargeResponseStore + 'static>(store: S) -> RunOptions {
+    ///     RunOptions::default().large_response(
+    ///         LargeResponseStrategy::S3Redirect {
+    ///             bucket: "my-bucket".to_string(),
+    ///             prefix: "responses/".to_string(),
+    ///             ttl: Duration::from_secs(300),
+    ///         },
+    ///         store,
+    ///     )
+    /// }
+    /// ```
+    pub fn large_response<S>(mut self, strategy: LargeResponseStrategy, store: S) -> Self
+    where
+        S: LargeResponseStore + 'static,
+    {
+        self.large_response = Some(LargeResponse {
+            strategy,
+            store: Some(Arc::new(store)),
+        });
+        self
+    }
+
+    /// Return plain text error response with the status code, e.g. 502,
+    /// for responses larger than `MAX_RESPONSE_PAYLOAD_SIZE` after base64 encoding,
+    /// instead of failing the invocation.
+    pub fn large_response_error(mut self, status: u16) -> Self {
+        self.large_response = Some(LargeResponse {
+            strategy: LargeResponseStrategy::ErrorResponse(status),
+            store: None,
+        });
+        self
+    }
+
+    /// Attach checksum of the response body in `x-content-sha256` or `x-content-crc32c` header.
+    /// The checksum is of the body as sent, after Brotli compression,
+    /// useful to tell base64 or compression corruption from application bugs.
+    ///
+    /// ```
+    /// use lambda_web::{BodyChecksum, RunOptions};
+    ///
+    /// let options = RunOptions::default().body_checksum(BodyChecksum::Crc32c);
+    /// ```
+    pub fn body_checksum(mut self, checksum: BodyChecksum) -> Self {
+        self.body_checksum = Some(checksum);
+        self
+    }
+
+    /// Send the Lambda container ID in `x-lambda-container-id` header and
+    /// `lambda-container-id` cookie, to tell when a warm container changed.
+    /// See `ContainerAffinity`.
+    pub fn container_affinity(mut self, enabled: bool) -> Self {
+        self.container_affinity = enabled;
+        self
+    }
+
+    /// Add `SameSite` attribute to response cookies set without it,
+    /// in the same way for all web frameworks. Cookies with `SameSite` are kept as they are.
+    pub fn cookie_same_site(mut self, same_site: crate::SameSite) -> Self {
+        self.cookie_same_site = Some(same_site);
+        self
+    }
+
+    /// Assign a variant of the A/B experiment to each request by its client key.
+    /// Assignments are attached to request extensions as `Assignments` (Rocket local cache),
+    /// and sent in `x-experiment` response header. Call repeatedly to run several experiments.
+    pub fn experiment(mut self, experiment: crate::Experiment) -> Self {
+        self.experiments.push(experiment);
+        self
+    }
+
+    /// Log compression ratio and time of each Brotli compressed response by `tracing`.
+    /// Statistics are always collected, see `compression_stats()`.
+    pub fn log_compression_stats(mut self, enabled: bool) -> Self {
+        self.log_compression_stats = enabled;
+        self
+    }
+
+    /// Do not compress response bodies larger than `size` bytes.
+    /// Compressing a large body delays the response,
+    /// and bodies up to `MAX_ENCODABLE_BODY_SIZE` can be returned without compression.
+    /// No limit by default.
+    ///
+    /// ```
+    /// use lambda_web::{RunOptions, MAX_ENCODABLE_BODY_SIZE};
+    ///
+    /// let options = RunOptions::default().max_compression_size(MAX_ENCODABLE_BODY_SIZE);
+    /// ```
+    pub fn max_compression_size(mut self, size: usize) -> Self {
+        self.max_compression_size = Some(size);
+        self
+    }
+
+    /// Infer `Content-Type` from magic bytes when the web framework returns none,
+    /// so that text responses are compressed and clients see the right type.
+    pub fn sniff_content_type(mut self, enabled: bool) -> Self {
+        self.sniff_content_type = enabled;
+        self
+    }
+
+    /// Append `; charset=utf-8` to `text/*` and `application/json` content types without charset,
+    /// so that clients defaulting to latin-1 do not garble UTF-8 responses.
+    pub fn default_charset(mut self, enabled: bool) -> Self {
+        self.default_charset = enabled;
+        self
+    }
+
+    /// Decompress pre-compressed `br` or `gzip` response bodies
+    /// for clients not accepting the `Content-Encoding`, e.g. cached `.br` files.
+    #[cfg(feature = "decompress")]
+    pub fn decompress_unsupported(mut self, enabled: bool) -> Self {
+        self.decompress_unsupported = enabled;
+        self
+    }
+
+    /// Decompress `Content-Encoding: br` or `gzip` request bodies before the web framework,
+    /// which would fail to parse compressed JSON or form bodies.
+    /// Bodies failing to decompress are rejected with 400 Bad Request.
+    #[cfg(feature = "decompress")]
+    pub fn decompress_requests(mut self, enabled: bool) -> Self {
+        self.decompress_requests = enabled;
+        self
+    }
+
+    /// Set `Date` response header when the web framework omits it,
+    /// or replace it when it is invalid or off by more than `tolerance` from the Lambda clock.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    /// use std::time::Duration;
+    ///
+    /// let options = RunOptions::default().date_header(Duration::from_secs(60));
+    /// ```
+    pub fn date_header(mut self, tolerance: Duration) -> Self {
+        self.date_header = Some(tolerance);
+        self
+    }
+
+    /// Trade bytes for latency near the invocation deadline.
+    /// Brotli quality is lowered when the remaining time is less than `fast_within`,
+    /// and compression is skipped when it is less than `skip_within`.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    /// use std::time::Duration;
+    ///
+    /// let options = RunOptions::default()
+    ///     .deadline_aware_compression(Duration::from_secs(3), Duration::from_millis(500));
+    /// ```
+    pub fn deadline_aware_compression(
+        mut self,
+        fast_within: Duration,
+        skip_within: Duration,
+    ) -> Self {
+        self.deadline_compression = Some(crate::brotli::DeadlineCompression {
+            fast_within,
+            skip_within,
+        });
+        self
+    }
+
+    /// Log `runtime_info()` by `tracing` on the first request,
+    /// architecture, memory size, region and payload format.
+    pub fn log_runtime_info(mut self, enabled: bool) -> Self {
+        self.log_runtime_info = enabled;
+        self
+    }
+
+    /// Run the hook before the SnapStart snapshot is taken, e.g. to close connections.
+    /// Hooks run in the order added, only when initialized for SnapStart (`is_snap_start()`).
+    /// An error fails the initialization.
+    pub fn before_snapshot<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Result<(), LambdaError> + Send + Sync + 'static,
+    {
+        self.snapshot_hooks.before_snapshot.push(Arc::new(hook));
+        self
+    }
+
+    /// Run the hook after the function is restored from the SnapStart snapshot,
+    /// e.g. to re-seed random generators and re-establish connections.
+    /// An error is reported to Runtime API and fails the restore.
+    ///
+    /// ```
+    /// use lambda_web::RunOptions;
+    ///
+    /// let options = RunOptions::default()
+    ///     .before_snapshot(|| Ok(()))
+    ///     .after_restore(|| {
+    ///         // Reconnect database pools here
+    ///         Ok(())
+    ///     });
+    /// ```
+    pub fn after_restore<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Result<(), LambdaError> + Send + Sync + 'static,
+    {
+        self.snapshot_hooks.after_restore.push(Arc::new(hook));
+        self
+    }
+
+    /// Log method, path, status and elapsed time of sampled requests by `tracing`.
+    /// The decision is attached to request extensions as `Sampled` (Rocket local cache),
+    /// so the app can log details of the same requests.
+    pub fn access_log(mut self, sampling: crate::LogSampling) -> Self {
+        self.access_log = Some(sampling);
+        self
+    }
+
+    /// Accept Alexa Skills Kit and Lex V2 events as POST requests to the routes.
+    /// See `ConversationalBridge`.
+    pub fn conversational_bridge(mut self, bridge: crate::ConversationalBridge) -> Self {
+        self.conversational_bridge = Some(bridge);
+        self
+    }
+
+    /// Translate API Gateway WebSocket events into HTTP requests by their route keys
+    pub fn websocket_routes(mut self, routes: crate::WebSocketRoutes) -> Self {
+        self.websocket_routes = Some(routes);
+        self
+    }
+
+    /// Store connections accepted by `$connect` with 2xx response, and delete them on `$disconnect`.
+    /// Connections failed to be stored are rejected with 500.
+    pub fn websocket_connection_store<S: crate::ConnectionStore + 'static>(
+        mut self,
+        store: S,
+    ) -> Self {
+        self.connection_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Accept SQS events as POST requests to the path, only for hyper based web frameworks.
+    /// See `SqsBridge`.
+    pub fn sqs_bridge(mut self, bridge: crate::SqsBridge) -> Self {
+        self.sqs_bridge = Some(bridge);
+        self
+    }
+
+    /// Accept Kinesis and DynamoDB Streams events as POST requests to the path,
+    /// only for hyper based web frameworks. See `StreamBridge`.
+    pub fn stream_bridge(mut self, bridge: crate::StreamBridge) -> Self {
+        self.stream_bridge = Some(bridge);
+        self
+    }
+
+    /// Accept SNS events as POST requests to the path, only for hyper based web frameworks.
+    /// See `SnsBridge`.
+    pub fn sns_bridge(mut self, bridge: crate::SnsBridge) -> Self {
+        self.sns_bridge = Some(bridge);
+        self
+    }
+
+    /// Translate EventBridge events, e.g. scheduled events, into HTTP requests
+    /// by their rules or detail types. See `EventBridgeRoutes`.
+    pub fn eventbridge_routes(mut self, routes: crate::EventBridgeRoutes) -> Self {
+        self.eventbridge_routes = Some(routes);
+        self
+    }
+
+    /// Capture events failing to parse or answered with 4xx/5xx status, see `FailureCapture`.
+    /// Installed by the `run_*_on_lambda_with_options` launchers.
+    pub fn capture_failures(mut self, capture: crate::FailureCapture) -> Self {
+        self.failure_capture = Some(capture);
+        self
+    }
+
+    /// Attach the Lambda event JSON to API Gateway, ALB and function URL requests
+    /// as `RawEvent` request extension, read by tower layers and `warp::ext::get`.
+    /// Installed by the `run_*_on_lambda_with_options` launchers.
+    ///
+    /// Off by default, the event JSON is parsed twice when enabled.
+    pub fn raw_event(mut self, enabled: bool) -> Self {
+        self.raw_event = enabled;
+        self
+    }
+
+    /// Stream responses to Lambda function URL with `InvokeMode: RESPONSE_STREAM`,
+    /// only for hyper based web frameworks with `streaming` feature. See `ResponseMode`.
+    ///
+    /// With `ResponseMode::Auto`, one binary serves both invoke modes.
+    /// Set `LAMBDA_WEB_INVOKE_MODE=RESPONSE_STREAM` environment variable
+    /// on the functions whose URL is configured for streaming.
+    ///
+    /// Options needing the whole response body, `idempotency`, `body_checksum`
+    /// and `decompress_unsupported`, can not be used with streaming responses.
+    ///
+    /// ```
+    /// use lambda_web::{ResponseMode, RunOptions};
+    ///
+    /// let options = RunOptions::default().response_mode(ResponseMode::Auto);
+    /// ```
+    #[cfg(feature = "streaming")]
+    pub fn response_mode(mut self, response_mode: crate::ResponseMode) -> Self {
+        self.response_mode = response_mode;
+        self
+    }
+
+    /// Largest chunk of streaming response body handed to Runtime API at once,
+    /// 64 KiB by default. Only for hyper based web frameworks with `ResponseMode::Streaming`.
+    ///
+    /// Larger chunks from the web framework are split, and the next chunk is read from
+    /// the body after the previous one is written. So a slow client backpressures the body
+    /// stream, instead of response data buffering up in this crate.
+    #[cfg(feature = "streaming")]
+    pub fn streaming_high_water_mark(mut self, bytes: usize) -> Self {
+        self.streaming_high_water_mark = Some(bytes.max(1));
+        self
+    }
+
+    /// Size of chunks the request body is delivered in as `web::Payload` stream.
+    /// Only for Actix Web, by default the whole body is a single chunk.
+    ///
+    /// Real HTTP servers deliver the body in chunks of socket reads (typically 8-64 KiB),
+    /// so middlewares counting bytes per chunk or reporting upload progress behave the same.
+    #[cfg(feature = "actix4")]
+    pub fn actix_payload_chunk_size(mut self, bytes: usize) -> Self {
+        self.actix_payload_chunk_size = Some(bytes.max(1));
+        self
+    }
+
+    /// Use tracked Rocket local client, which keeps cookies set by responses
+    /// and sends them with following requests. Only for Rocket.
+    ///
+    /// The cookie jar is shared by all requests to the warm container, whoever sends them.
+    /// Enable only for single user tools and tests, never for public applications.
+    #[cfg(feature = "rocket05")]
+    pub fn rocket_tracked_client(mut self, tracked: bool) -> Self {
+        self.rocket_tracked_client = tracked;
+        self
+    }
+
+    /// Is this response body small enough to be compressed?
+    pub(crate) fn is_compressible_size(&self, size: usize) -> bool {
+        self.max_compression_size
+            .map(|max_size| size <= max_size)
+            .unwrap_or(true)
+    }
+
+    /// Is this response header to be stripped?
+    pub(crate) fn is_blocked_response_header(&self, name: &str) -> bool {
+        self.response_header_blocklist
+            .iter()
+            .any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Port number of peer (client) address
+///
+/// API Gateway and ALB do not tell the TCP port of the client,
+/// so web frameworks receive a substitute port number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerPort {
+    /// Fixed port number, default is `Fixed(0)`
+    Fixed(u16),
+    /// Port number in X-Forwarded-Port header,
+    /// that is the listener port of API Gateway or ALB (443 in most cases).
+    /// Falls back to 0 when the header is missing.
+    Forwarded,
+}
+
+impl Default for PeerPort {
+    fn default() -> Self {
+        Self::Fixed(0)
+    }
+}
+
+/// Response header values with control characters other than tab
+///
+/// Such values can split headers (CR, LF) or truncate them (NUL)
+/// in API Gateway, ALB or CloudFront.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsafeHeaderValues {
+    /// Remove the control characters from the value
+    Strip,
+    /// Remove the header entirely
+    Reject,
+}
+
+impl Default for UnsafeHeaderValues {
+    fn default() -> Self {
+        Self::Strip
+    }
+}
+
+impl PeerPort {
+    /// Port number for this request
+    pub(crate) fn port(&self, event: &LambdaHttpEvent) -> u16 {
+        match self {
+            Self::Fixed(port) => *port,
+            Self::Forwarded => event.forwarded_port().unwrap_or(0),
+        }
+    }
+}
@@ -0,0 +1,463 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Support matrix test
+//! Runs the same scenarios on every (web framework adapter x event type) pair,
+//! so that all adapters behave the same on all gateways.
+//!
+//! Add a new adapter to `Adapter`, with the same app as the others,
+//! and add new scenarios to `parity_tests!`.
+//!
+use crate::bridge::WebEvent;
+use crate::options::RunOptions;
+use crate::response::LambdaResponse;
+use lambda_runtime::{Context, LambdaEvent, Service as LambdaService};
+use serde_json::{json, Value};
+
+const TEXT: &str = "text/plain; charset=utf-8";
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Request sent to the apps
+struct Scenario {
+    method: &'static str,
+    path: &'static str,
+    query: &'static str,
+    cookies: &'static [&'static str],
+    body: Option<&'static [u8]>,
+    expected: Outcome,
+}
+
+impl Scenario {
+    fn get(path: &'static str) -> Self {
+        Self {
+            method: "GET",
+            path,
+            query: "",
+            cookies: &[],
+            body: None,
+            expected: Outcome::default(),
+        }
+    }
+
+    fn post(path: &'static str, body: &'static [u8]) -> Self {
+        Self {
+            method: "POST",
+            body: Some(body),
+            ..Self::get(path)
+        }
+    }
+
+    fn query(mut self, query: &'static str) -> Self {
+        self.query = query;
+        self
+    }
+
+    fn cookies(mut self, cookies: &'static [&'static str]) -> Self {
+        self.cookies = cookies;
+        self
+    }
+
+    fn expect(mut self, status: u16, content_type: Option<&str>, body: &[u8]) -> Self {
+        self.expected = Outcome {
+            status,
+            content_type: content_type.map(String::from),
+            location: None,
+            body: body.to_vec(),
+        };
+        self
+    }
+
+    fn expect_redirect(mut self, status: u16, location: &str) -> Self {
+        self.expected = Outcome {
+            status,
+            location: Some(location.to_string()),
+            ..Outcome::default()
+        };
+        self
+    }
+
+    /// Query string parameters of REST API and ALB events
+    fn query_params(&self) -> Value {
+        if self.query.is_empty() {
+            return Value::Null;
+        }
+        let mut params = serde_json::Map::new();
+        for pair in self.query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            params
+                .entry(key)
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+                .unwrap()
+                .push(json!(value));
+        }
+        Value::Object(params)
+    }
+
+    fn body_json(&self) -> Value {
+        match self.body {
+            Some(body) => json!(base64::encode(body)),
+            None => Value::Null,
+        }
+    }
+}
+
+/// Event types sent by AWS
+#[derive(Debug, Clone, Copy)]
+enum Gateway {
+    HttpApiV2,
+    RestApi,
+    Alb,
+}
+
+const GATEWAYS: [Gateway; 3] = [Gateway::HttpApiV2, Gateway::RestApi, Gateway::Alb];
+
+impl Gateway {
+    /// Lambda event JSON of the scenario
+    fn event(&self, scenario: &Scenario) -> String {
+        let mut headers = json!({
+            "host": "example.com",
+            "x-forwarded-for": "1.2.3.4",
+            "x-forwarded-port": "443",
+            "x-forwarded-proto": "https"
+        });
+        if scenario.body.is_some() {
+            headers["content-type"] = json!(OCTET_STREAM);
+        }
+        let multi_value_headers = |headers: &Value| {
+            let mut multi = headers
+                .as_object()
+                .unwrap()
+                .iter()
+                .map(|(k, v)| (k.clone(), json!([v])))
+                .collect::<serde_json::Map<_, _>>();
+            if !scenario.cookies.is_empty() {
+                multi.insert("cookie".to_string(), json!([scenario.cookies.join("; ")]));
+            }
+            Value::Object(multi)
+        };
+
+        let event = match self {
+            Self::HttpApiV2 => json!({
+                "version": "2.0",
+                "rawPath": scenario.path,
+                "rawQueryString": scenario.query,
+                "headers": headers,
+                "cookies": scenario.cookies,
+                "requestContext": {
+                    "domainName": "example.com",
+                    "http": { "method": scenario.method, "sourceIp": "1.2.3.4" }
+                },
+                "body": scenario.body_json(),
+                "isBase64Encoded": scenario.body.is_some()
+            }),
+            Self::RestApi => json!({
+                "httpMethod": scenario.method,
+                "path": scenario.path,
+                "headers": headers,
+                "multiValueHeaders": multi_value_headers(&headers),
+                "queryStringParameters": null,
+                "multiValueQueryStringParameters": scenario.query_params(),
+                "requestContext": {
+                    "domainName": "example.com",
+                    // Custom domain without stage prefix
+                    "path": scenario.path,
+                    "identity": { "sourceIp": "1.2.3.4" }
+                },
+                "body": scenario.body_json(),
+                "isBase64Encoded": scenario.body.is_some()
+            }),
+            Self::Alb => json!({
+                "requestContext": {
+                    "elb": {
+                        "targetGroupArn": "arn:aws:elasticloadbalancing:ap-northeast-1:123456789012:targetgroup/lambda/abcdef0123456789"
+                    }
+                },
+                "httpMethod": scenario.method,
+                "path": scenario.path,
+                "multiValueQueryStringParameters": scenario.query_params(),
+                "multiValueHeaders": multi_value_headers(&headers),
+                "body": scenario.body_json(),
+                "isBase64Encoded": scenario.body.is_some()
+            }),
+        };
+        event.to_string()
+    }
+}
+
+/// Response compared across adapters
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Outcome {
+    status: u16,
+    content_type: Option<String>,
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+impl From<LambdaResponse> for Outcome {
+    fn from(response: LambdaResponse) -> Self {
+        let body = if response.is_base64 {
+            base64::decode(&response.body).unwrap()
+        } else {
+            response.body.as_bytes().to_vec()
+        };
+        Self {
+            status: response.status,
+            content_type: response.header("content-type").map(String::from),
+            location: response.header("location").map(String::from),
+            body,
+        }
+    }
+}
+
+/// Body of `GET /echo`, the same for all apps
+fn echo_body(path: &str, query: Option<&str>, session: Option<&str>) -> String {
+    format!(
+        "path={}\nquery={}\nsession={}",
+        path,
+        query.unwrap_or_default(),
+        session.unwrap_or_default()
+    )
+}
+
+/// Web framework adapters, each runs the same app
+#[derive(Debug, Clone, Copy)]
+enum Adapter {
+    #[cfg(feature = "hyper")]
+    Hyper,
+    #[cfg(feature = "actix4")]
+    Actix,
+    #[cfg(feature = "rocket05")]
+    Rocket,
+}
+
+const ADAPTERS: &[Adapter] = &[
+    #[cfg(feature = "hyper")]
+    Adapter::Hyper,
+    #[cfg(feature = "actix4")]
+    Adapter::Actix,
+    #[cfg(feature = "rocket05")]
+    Adapter::Rocket,
+];
+
+impl Adapter {
+    async fn serve(&self, event: &str) -> LambdaResponse {
+        let event: WebEvent = serde_json::from_str(event).unwrap();
+        let event = LambdaEvent::new(event, Context::default());
+        let options = RunOptions::default();
+        match self {
+            #[cfg(feature = "hyper")]
+            Self::Hyper => crate::HyperHandler::new(tower::service_fn(hyper_app), options)
+                .call(event)
+                .await
+                .unwrap(),
+            #[cfg(feature = "actix4")]
+            Self::Actix => {
+                let svc = actix_web::test::init_service(actix_app()).await;
+                crate::ActixHandler::new(svc, options)
+                    .call(event)
+                    .await
+                    .unwrap()
+            }
+            #[cfg(feature = "rocket05")]
+            Self::Rocket => {
+                let client = rocket::local::asynchronous::Client::untracked(rocket_app())
+                    .await
+                    .unwrap();
+                crate::RocketHandler::new(client, options)
+                    .call(event)
+                    .await
+                    .unwrap()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hyper")]
+async fn hyper_app(
+    req: crate::hyper014::HyperRequest,
+) -> Result<hyper::Response<hyper::Body>, std::convert::Infallible> {
+    use hyper::{Body, Method, Response};
+
+    let text = |status: u16, body: String| {
+        Response::builder()
+            .status(status)
+            .header("content-type", TEXT)
+            .body(Body::from(body))
+            .unwrap()
+    };
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/echo") => {
+            let session = req
+                .headers()
+                .get_all("cookie")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(';'))
+                .filter_map(|cookie| cookie.trim().split_once('='))
+                .find(|(name, _)| *name == "session")
+                .map(|(_, value)| value.to_string());
+            text(
+                200,
+                echo_body(req.uri().path(), req.uri().query(), session.as_deref()),
+            )
+        }
+        (&Method::POST, "/binary") => {
+            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            Response::builder()
+                .header("content-type", OCTET_STREAM)
+                .body(Body::from(body))
+                .unwrap()
+        }
+        (&Method::GET, "/redirect") => Response::builder()
+            .status(302)
+            .header("location", "/target")
+            .body(Body::empty())
+            .unwrap(),
+        (&Method::GET, "/error") => text(500, "error".to_string()),
+        _ => text(404, "not found".to_string()),
+    };
+    Ok(response)
+}
+
+#[cfg(feature = "actix4")]
+fn actix_app() -> actix_web::App<
+    impl actix_service::ServiceFactory<
+        actix_web::dev::ServiceRequest,
+        Config = (),
+        Response = actix_web::dev::ServiceResponse,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+
+    App::new()
+        .route(
+            "/echo",
+            web::get().to(|req: HttpRequest| async move {
+                let session = req.cookie("session").map(|c| c.value().to_string());
+                HttpResponse::Ok().content_type(TEXT).body(echo_body(
+                    req.path(),
+                    Some(req.query_string()),
+                    session.as_deref(),
+                ))
+            }),
+        )
+        .route(
+            "/binary",
+            web::post().to(|body: web::Bytes| async move {
+                HttpResponse::Ok().content_type(OCTET_STREAM).body(body)
+            }),
+        )
+        .route(
+            "/redirect",
+            web::get().to(|| async {
+                HttpResponse::Found()
+                    .append_header(("location", "/target"))
+                    .finish()
+            }),
+        )
+        .route(
+            "/error",
+            web::get().to(|| async {
+                HttpResponse::InternalServerError()
+                    .content_type(TEXT)
+                    .body("error")
+            }),
+        )
+        .default_service(web::to(|| async {
+            HttpResponse::NotFound()
+                .content_type(TEXT)
+                .body("not found")
+        }))
+}
+
+#[cfg(feature = "rocket05")]
+fn rocket_app() -> rocket::Rocket<rocket::Build> {
+    use rocket::http::{ContentType, CookieJar, Status};
+    use rocket::response::Redirect;
+    use rocket::{catch, catchers, get, post, routes};
+
+    #[get("/echo")]
+    fn echo(origin: &rocket::http::uri::Origin<'_>, jar: &CookieJar<'_>) -> (ContentType, String) {
+        let session = jar.get("session").map(|c| c.value().to_string());
+        (
+            ContentType::parse_flexible(TEXT).unwrap(),
+            echo_body(
+                origin.path().as_str(),
+                origin.query().map(|q| q.as_str()),
+                session.as_deref(),
+            ),
+        )
+    }
+
+    #[post("/binary", data = "<body>")]
+    fn binary(body: Vec<u8>) -> (ContentType, Vec<u8>) {
+        (ContentType::Binary, body)
+    }
+
+    #[get("/redirect")]
+    fn redirect() -> Redirect {
+        Redirect::found("/target")
+    }
+
+    #[get("/error")]
+    fn error() -> (Status, (ContentType, &'static str)) {
+        (
+            Status::InternalServerError,
+            (ContentType::parse_flexible(TEXT).unwrap(), "error"),
+        )
+    }
+
+    #[catch(404)]
+    fn not_found() -> (ContentType, &'static str) {
+        (ContentType::parse_flexible(TEXT).unwrap(), "not found")
+    }
+
+    rocket::build()
+        .mount("/", routes![echo, binary, redirect, error])
+        .register("/", catchers![not_found])
+}
+
+/// Run the scenario on all pairs, and compare outcomes with the expected one
+async fn check_parity(scenario: Scenario) {
+    for gateway in GATEWAYS {
+        let event = gateway.event(&scenario);
+        for adapter in ADAPTERS {
+            let outcome = Outcome::from(adapter.serve(&event).await);
+            assert_eq!(
+                outcome, scenario.expected,
+                "{:?} adapter on {:?} event: {}",
+                adapter, gateway, event
+            );
+        }
+    }
+}
+
+/// Generate a test for each scenario
+macro_rules! parity_tests {
+    ($($name:ident: $scenario:expr;)*) => {
+        $(
+            #[tokio::test]
+            async fn $name() {
+                check_parity($scenario).await;
+            }
+        )*
+    };
+}
+
+parity_tests! {
+    parity_path: Scenario::get("/echo")
+        .expect(200, Some(TEXT), b"path=/echo\nquery=\nsession=");
+    parity_query: Scenario::get("/echo")
+        .query("q=rust")
+        .expect(200, Some(TEXT), b"path=/echo\nquery=q=rust\nsession=");
+    parity_cookies: Scenario::get("/echo")
+        .cookies(&["theme=dark", "session=abc123"])
+        .expect(200, Some(TEXT), b"path=/echo\nquery=\nsession=abc123");
+    parity_binary_body: Scenario::post("/binary", b"\x00\x01\xfe\xff binary")
+        .expect(200, Some(OCTET_STREAM), b"\x00\x01\xfe\xff binary");
+    parity_redirect: Scenario::get("/redirect").expect_redirect(302, "/target");
+    parity_error: Scenario::get("/error").expect(500, Some(TEXT), b"error");
+    parity_not_found: Scenario::get("/nowhere").expect(404, Some(TEXT), b"not found");
+}
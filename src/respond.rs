@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Response helpers
+//! Common responses convertible to responses of each web framework,
+//! which serialize correctly in both HTTP API v2 and multi-value header formats.
+//!
+//! ```
+//! use lambda_web::respond;
+//!
+//! let created = respond::json(201, &serde_json::json!({ "id": 1 }))
+//!     .cookie("session=abc; Path=/; HttpOnly")
+//!     .cookie("theme=dark; Path=/");
+//! let moved = respond::redirect("/login");
+//! let deleted = respond::no_content();
+//! # assert_eq!(created.status(), 201);
+//! ```
+//!
+//! `Reply` is converted to `hyper::Response<hyper::Body>` (axum, warp, etc.) by `From`,
+//! and is `Responder` of Actix Web and Rocket.
+//!
+use percent_encoding::{utf8_percent_encode, CONTROLS};
+use serde::Serialize;
+
+/// Response built by helpers in this module
+///
+/// Each cookie is a separate `set-cookie` header, never joined by commas,
+/// so it becomes `cookies` in HTTP API v2 and `multiValueHeaders` in REST API and ALB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// Redirect to the URL with `302 Found`
+///
+/// Control characters in the URL are percent-encoded, so they can not split headers.
+pub fn redirect(url: &str) -> Reply {
+    let location = utf8_percent_encode(url, CONTROLS).to_string();
+    Reply::new(302).header("location", &location)
+}
+
+/// JSON response, or `500 Internal Server Error` when the value can not be serialized
+pub fn json<T: Serialize + ?Sized>(status: u16, value: &T) -> Reply {
+    match serde_json::to_vec(value) {
+        Ok(body) => Reply::new(status)
+            .header("content-type", "application/json")
+            .body(body),
+        Err(_) => Reply::new(500),
+    }
+}
+
+/// `204 No Content`
+pub fn no_content() -> Reply {
+    Reply::new(204)
+}
+
+/// Plain text response
+pub fn text(status: u16, text: &str) -> Reply {
+    Reply::new(status)
+        .header("content-type", "text/plain; charset=utf-8")
+        .body(text.as_bytes().to_vec())
+}
+
+impl Reply {
+    /// Empty response, invalid status codes are sent as 500
+    pub fn new(status: u16) -> Self {
+        Self {
+            status: if (100..1000).contains(&status) {
+                status
+            } else {
+                500
+            },
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Append a header, lowercased. Headers with invalid names or values are dropped on conversion.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .push((name.to_ascii_lowercase(), value.to_string()));
+        self
+    }
+
+    /// Append a `set-cookie` header, e.g. `"key=value; Path=/; HttpOnly"`
+    pub fn cookie(self, set_cookie: &str) -> Self {
+        self.header("set-cookie", set_cookie)
+    }
+
+    /// Replace the body
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// HTTP status code
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Values of the header, in the order appended
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Response body
+    pub fn body_bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Headers with valid names and values
+    fn valid_headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .filter(|(name, value)| {
+                !name.is_empty()
+                    && name
+                        .bytes()
+                        .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+                    && value
+                        .bytes()
+                        .all(|b| b == b'\t' || (b >= 0x20 && b != 0x7f))
+            })
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl From<Reply> for hyper::Response<hyper::Body> {
+    fn from(reply: Reply) -> Self {
+        let mut builder = hyper::Response::builder().status(reply.status);
+        for (name, value) in reply.valid_headers() {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(hyper::Body::from(reply.body))
+            .unwrap_or_else(|_| {
+                let mut res = hyper::Response::new(hyper::Body::empty());
+                *res.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+                res
+            })
+    }
+}
+
+#[cfg(feature = "actix4")]
+impl actix_web::Responder for Reply {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &actix_web::HttpRequest) -> actix_web::HttpResponse {
+        use actix_web::http::StatusCode;
+
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut builder = actix_web::HttpResponse::build(status);
+        for (name, value) in self.valid_headers() {
+            builder.append_header((name, value));
+        }
+        builder.body(self.body)
+    }
+}
+
+#[cfg(feature = "rocket05")]
+impl<'r> rocket::response::Responder<'r, 'static> for Reply {
+    fn respond_to(self, _req: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
+        let mut builder = rocket::Response::build();
+        builder.status(rocket::http::Status::new(self.status));
+        let headers = self
+            .valid_headers()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        for (name, value) in headers {
+            builder.raw_header_adjoin(name, value);
+        }
+        builder
+            .sized_body(self.body.len(), std::io::Cursor::new(self.body))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect() {
+        let reply = redirect("/login?next=/\r\nx-injected: 1");
+        assert_eq!(reply.status(), 302);
+        assert_eq!(
+            reply.header_values("Location").collect::<Vec<_>>(),
+            vec!["/login?next=/%0D%0Ax-injected: 1"]
+        );
+        assert_eq!(reply.valid_headers().count(), 1);
+    }
+
+    #[test]
+    fn test_json() {
+        let reply = json(201, &serde_json::json!({ "id": 1 }))
+            .cookie("a=1; Path=/")
+            .cookie("b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT");
+        assert_eq!(reply.status(), 201);
+        assert_eq!(reply.body_bytes(), br#"{"id":1}"#);
+        assert_eq!(
+            reply.header_values("set-cookie").collect::<Vec<_>>(),
+            vec!["a=1; Path=/", "b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT"]
+        );
+
+        assert_eq!(no_content().status(), 204);
+        assert_eq!(Reply::new(42).status(), 500);
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        let reply = no_content()
+            .header("x-ok", "1")
+            .header("bad name", "1")
+            .header("x-bad-value", "a\nb");
+        let headers = reply.valid_headers().collect::<Vec<_>>();
+        assert_eq!(headers, vec![("x-ok", "1")]);
+    }
+}
@@ -0,0 +1,865 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Lambda response
+//! Typed Lambda response JSON, and RunOptions applied to responses of every web framework
+//!
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use core::future::Future;
+use futures_util::future::{Either, Ready};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Lambda response payload limit (6 MB) for buffered (not streaming) invocations
+pub const MAX_RESPONSE_PAYLOAD_SIZE: usize = 6 * 1024 * 1024;
+
+/// Maximum response body size which can be encoded in Lambda response.
+/// Response bodies are base64 encoded, 4/3 times larger than original.
+/// Actual limit is slightly smaller, since the status code and headers are included in the payload.
+pub const MAX_ENCODABLE_BODY_SIZE: usize = MAX_RESPONSE_PAYLOAD_SIZE / 4 * 3;
+
+/// Boxed future of Lambda response, `Send` to be run on multi-threaded runtimes
+pub type ResponseFuture<E> = Pin<Box<dyn Future<Output = Result<LambdaResponse, E>> + Send>>;
+
+/// Future of web handler services
+///
+/// Responses made by this crate without calling the web application,
+/// e.g. 400 Bad Request or 404 Not Found, are ready without allocation.
+pub type HandlerFuture<F> = Either<Ready<<F as Future>::Output>, F>;
+
+/// Response made by this crate, ready without allocation
+pub(crate) fn ready_response<F, E>(response: LambdaResponse) -> HandlerFuture<F>
+where
+    F: Future<Output = Result<LambdaResponse, E>>,
+{
+    Either::Left(futures_util::future::ready(Ok(response)))
+}
+
+/// Request information used in response post-processing.
+/// Made before the event is consumed by the web framework.
+pub(crate) struct RequestInfo {
+    idempotency_key: Option<crate::idempotency::IdempotencyKey>,
+    first_event: bool,
+    bridged: bool,
+    #[cfg(feature = "cloudfront")]
+    edge: Option<crate::edge::EdgeResponse>,
+    websocket: bool,
+    connection_update: Option<crate::connection_store::ConnectionUpdate>,
+    correlation: Option<Arc<crate::Correlation>>,
+    invocation: Arc<crate::invocation::Invocation>,
+    noindex: bool,
+    immutable: bool,
+    experiments: Option<String>,
+    access_log: Option<crate::sampling::AccessLog>,
+    captured_event: Option<Arc<serde_json::Value>>,
+    #[cfg(feature = "decompress")]
+    accept_encoding: Option<crate::decompress::AcceptEncoding>,
+}
+
+impl RequestInfo {
+    pub(crate) fn new(event: &LambdaHttpEvent, options: &RunOptions) -> Self {
+        Self {
+            idempotency_key: options.idempotency.as_ref().and_then(|_| {
+                crate::idempotency::idempotency_key(event, options.idempotency_request_id)
+            }),
+            first_event: crate::runtime_info::observe_event(event),
+            bridged: event.is_bridged(),
+            #[cfg(feature = "cloudfront")]
+            edge: event.edge_response().cloned(),
+            websocket: event.is_websocket(),
+            connection_update: options
+                .connection_store
+                .as_ref()
+                .and_then(|_| crate::connection_store::ConnectionUpdate::from_event(event)),
+            correlation: crate::correlation::capture(event, options),
+            invocation: crate::invocation::Invocation::start(event),
+            noindex: is_non_production(event, options),
+            immutable: options
+                .immutable_assets
+                .as_ref()
+                .map(|assets| assets.matches(event))
+                .unwrap_or(false),
+            experiments: crate::experiment::Assignments::from_event(event, &options.experiments)
+                .header_value(),
+            access_log: crate::sampling::AccessLog::start(event, options.access_log.as_ref()),
+            captured_event: event.captured_event().cloned(),
+            #[cfg(feature = "decompress")]
+            accept_encoding: if options.decompress_unsupported {
+                Some(crate::decompress::AcceptEncoding::from_event(event))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Run the handler with the invocation and the correlation of the request as current
+    pub(crate) fn scope<F: Future>(&self, handler: F) -> impl Future<Output = F::Output> {
+        let handler = crate::correlation::scope(self.correlation.clone(), handler);
+        crate::invocation::scope(self.invocation.clone(), handler)
+    }
+}
+
+/// Is the request to a stage not listed in `RunOptions::noindex_non_production`?
+/// Requests without stage (ALB) are production.
+fn is_non_production(event: &LambdaHttpEvent, options: &RunOptions) -> bool {
+    match (&options.production_stages, event.request_context().stage) {
+        (Some(production_stages), Some(stage)) => !production_stages.contains(&stage),
+        _ => false,
+    }
+}
+
+/// Wait for the handler response, then apply RunOptions to it
+pub(crate) async fn finish_response<F, E>(
+    options: Arc<RunOptions>,
+    info: RequestInfo,
+    handler: F,
+) -> Result<LambdaResponse, E>
+where
+    F: Future<Output = Result<LambdaResponse, E>>,
+{
+    if info.first_event && options.log_runtime_info {
+        tracing::info!("{}", crate::runtime_info());
+    }
+    let handler = info.scope(handler);
+    let mut response = crate::idempotency::idempotent_response(
+        options.idempotency.clone(),
+        info.idempotency_key.clone(),
+        handler,
+    )
+    .await?;
+    crate::capture::capture_status(info.captured_event.clone(), response.status);
+    apply_options(&mut response, &options);
+    apply_request_headers(&mut response, &info);
+    if info.bridged {
+        // Alexa or Lex receives the response body only
+        response.format = ResponseFormat::Json;
+        if let Some(access_log) = &info.access_log {
+            access_log.finish(response.status);
+        }
+        return Ok(response);
+    }
+    #[cfg(feature = "cloudfront")]
+    if let Some(edge) = &info.edge {
+        if crate::edge::finish_edge_response(&mut response, edge) {
+            // CloudFront receives the forwarded request only
+            if let Some(access_log) = &info.access_log {
+                access_log.finish(response.status);
+            }
+            return Ok(response);
+        }
+    }
+    if let (Some(update), Some(store)) = (info.connection_update, &options.connection_store) {
+        update.apply(store, &mut response).await;
+    }
+    if info.websocket {
+        crate::websocket::finish_websocket_response(&mut response);
+    }
+    #[cfg(feature = "decompress")]
+    if let Some(accept_encoding) = info.accept_encoding {
+        crate::decompress::decompress_unsupported(&mut response, accept_encoding);
+    }
+    let mut response =
+        crate::large_response::redirect_large_response(options.large_response.as_ref(), response)
+            .await;
+    if let Some(checksum) = options.body_checksum {
+        crate::checksum::attach_checksum(&mut response, checksum);
+    }
+    // Lambda@Edge responses are not in the API Gateway format checked
+    #[cfg(feature = "cloudfront")]
+    let api_gateway_format = response.format != ResponseFormat::CloudFront;
+    #[cfg(not(feature = "cloudfront"))]
+    let api_gateway_format = true;
+    if crate::self_check::self_check_enabled() && api_gateway_format {
+        if let Ok(response_json) = serde_json::to_value(&response) {
+            crate::self_check::log_self_check(&response_json);
+        }
+    }
+    if let Some(access_log) = &info.access_log {
+        access_log.finish(response.status);
+    }
+    Ok(response)
+}
+
+/// Apply RunOptions to the status and headers of a streaming response.
+/// Options needing the whole body are rejected before streaming.
+#[cfg(feature = "streaming")]
+pub(crate) fn finish_streaming_response(
+    response: &mut LambdaResponse,
+    info: &RequestInfo,
+    options: &RunOptions,
+) {
+    if info.first_event && options.log_runtime_info {
+        tracing::info!("{}", crate::runtime_info());
+    }
+    crate::capture::capture_status(info.captured_event.clone(), response.status);
+    apply_options(response, options);
+    apply_request_headers(response, info);
+    if let Some(access_log) = &info.access_log {
+        access_log.finish(response.status);
+    }
+}
+
+/// Response headers by the request, e.g. `x-robots-tag`
+fn apply_request_headers(response: &mut LambdaResponse, info: &RequestInfo) {
+    if info.noindex && response.header("x-robots-tag").is_none() {
+        response.add_header("x-robots-tag", "noindex");
+    }
+    if info.immutable {
+        crate::immutable::apply_immutable(response);
+    }
+    if let Some(experiments) = &info.experiments {
+        response.add_header(crate::experiment::EXPERIMENT_HEADER, experiments);
+    }
+}
+
+/// Set the response body, Brotli compressed when `compress` and base64 encoded.
+/// Bodies certainly exceeding the payload limit after base64 encoding
+/// are replaced by the large response strategy, before the encoding work.
+pub(crate) async fn encode_response_body<T>(
+    mut response: LambdaResponse,
+    body: T,
+    client_support_br: bool,
+    compress: bool,
+    content_type: Option<String>,
+    deadline: Option<std::time::SystemTime>,
+    options: &RunOptions,
+) -> LambdaResponse
+where
+    T: AsRef<[u8]> + Send + Sync + 'static,
+{
+    // Content-Type sniffing, before compression decision
+    let sniffed = if content_type.is_none()
+        && options.sniff_content_type
+        && response.header("content-encoding").is_none()
+    {
+        crate::sniff::sniff_content_type(body.as_ref())
+    } else {
+        None
+    };
+    let (compress, content_type) = match sniffed {
+        Some(sniffed) => {
+            response.add_header("content-type", sniffed);
+            (
+                client_support_br && crate::brotli::is_compressible_type(sniffed),
+                Some(sniffed.to_string()),
+            )
+        }
+        None => (compress, content_type),
+    };
+
+    let quality = if compress && options.is_compressible_size(body.as_ref().len()) {
+        crate::brotli::compression_quality(options, deadline)
+    } else {
+        None
+    };
+    if let Some(quality) = quality {
+        match crate::brotli::compress_response_body(body, content_type, quality, options).await {
+            Ok(compressed) => {
+                response.add_header("content-encoding", "br");
+                response.body = compressed;
+            }
+            // Compression task failed
+            Err(uncompressed) => response.body = uncompressed,
+        }
+    } else {
+        if let Some(replaced) = crate::large_response::early_large_response(
+            options.large_response.as_ref(),
+            &response,
+            body.as_ref(),
+        )
+        .await
+        {
+            return replaced;
+        }
+        response.body = base64::encode(body);
+    }
+    response.is_base64 = true;
+    response
+}
+
+/// Apply RunOptions to Lambda response
+pub(crate) fn apply_options(response: &mut LambdaResponse, options: &RunOptions) {
+    sanitize_header_values(response, options.unsafe_header_values);
+    if let Some(same_site) = options.cookie_same_site {
+        crate::cookie::apply_same_site(response, same_site);
+    }
+    if options.container_affinity {
+        let container_id = crate::affinity::container_id();
+        response.add_header(crate::affinity::CONTAINER_ID_HEADER, container_id);
+        response.add_header(
+            "set-cookie",
+            &crate::affinity::container_cookie(container_id),
+        );
+    }
+    crate::http_date::apply_date_header(response, options);
+    if let Some(redirect) = options.https_redirect.as_ref() {
+        crate::https::apply_hsts(response, redirect);
+    }
+    if options.default_charset {
+        apply_default_charset(response);
+    }
+}
+
+/// Is the character not allowed in header values? Tab is allowed by RFC 7230.
+fn is_unsafe_header_char(c: char) -> bool {
+    c.is_ascii_control() && c != '\t'
+}
+
+/// Strip control characters from header values, or remove headers with them
+fn sanitize_header_values(response: &mut LambdaResponse, policy: crate::UnsafeHeaderValues) {
+    let is_safe = |value: &String| !value.chars().any(is_unsafe_header_char);
+    let sanitize = |name: &str, values: &mut Vec<String>| {
+        if values.iter().all(is_safe) {
+            return;
+        }
+        tracing::warn!(
+            "response header {} has control characters in the value",
+            name
+        );
+        match policy {
+            crate::UnsafeHeaderValues::Strip => values
+                .iter_mut()
+                .for_each(|value| value.retain(|c| !is_unsafe_header_char(c))),
+            crate::UnsafeHeaderValues::Reject => values.retain(is_safe),
+        }
+    };
+    response.headers.retain(|name, value| {
+        let mut values = vec![std::mem::take(value)];
+        sanitize(name, &mut values);
+        match values.pop() {
+            Some(sanitized) => {
+                *value = sanitized;
+                true
+            }
+            None => false,
+        }
+    });
+    response.multi_value_headers.retain(|name, values| {
+        sanitize(name, values);
+        !values.is_empty()
+    });
+    sanitize("set-cookie", &mut response.cookies);
+}
+
+/// Append `; charset=utf-8` to text content types lacking charset
+fn apply_default_charset(response: &mut LambdaResponse) {
+    let content_type = match response.header("content-type") {
+        Some(content_type) => content_type,
+        None => return,
+    };
+    let mut params = content_type.split(';');
+    let mime = params
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let has_charset = params.any(|param| {
+        param
+            .trim()
+            .get(..8)
+            .map(|name| name.eq_ignore_ascii_case("charset="))
+            .unwrap_or(false)
+    });
+    if has_charset || !(mime.starts_with("text/") || mime == "application/json") {
+        return;
+    }
+    let content_type = format!("{}; charset=utf-8", content_type.trim_end());
+    response.remove_header("content-type");
+    response.add_header("content-type", &content_type);
+}
+
+/// Wire format of Lambda response JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// API Gateway HTTP API payload format 2.0 and function URL,
+    /// `headers` and `cookies`
+    HttpApiV2,
+    /// API Gateway REST API and ALB, `multiValueHeaders`
+    MultiValueHeaders,
+    /// `headers` only, accepted by all of API Gateway, ALB and function URL
+    Headers,
+    /// JSON body only, for events translated by `ConversationalBridge`
+    Json,
+    /// Lambda@Edge response, multi-value headers with original case names
+    #[cfg(feature = "cloudfront")]
+    CloudFront,
+}
+
+impl ResponseFormat {
+    /// Format of framework responses, REST API and ALB requests use multi-value headers
+    pub(crate) fn from_multi_value(multi_value: bool) -> Self {
+        if multi_value {
+            Self::MultiValueHeaders
+        } else {
+            Self::HttpApiV2
+        }
+    }
+}
+
+/// Lambda response returned to API Gateway, ALB or function URL
+///
+/// Serialized to the wire format selected by `format`.
+/// Headers are kept in sorted order, so serialized JSON is deterministic.
+///
+/// ```
+/// use lambda_web::{LambdaResponse, ResponseFormat};
+///
+/// let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+/// response.add_header("content-type", "text/plain");
+/// response.add_header("set-cookie", "key=value");
+/// response.body = "Hello".to_string();
+/// assert_eq!(response.cookies, vec!["key=value".to_string()]);
+///
+/// let json = serde_json::to_value(&response).unwrap();
+/// assert_eq!(json["statusCode"], 200);
+/// assert_eq!(json["headers"]["content-type"], "text/plain");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "WireResponse")]
+pub struct LambdaResponse {
+    /// Wire format
+    pub format: ResponseFormat,
+    /// HTTP status code
+    pub status: u16,
+    /// Headers, in `HttpApiV2` and `Headers` format
+    pub headers: BTreeMap<String, String>,
+    /// Headers, in `MultiValueHeaders` and `CloudFront` format
+    pub multi_value_headers: BTreeMap<String, Vec<String>>,
+    /// Set-Cookie header values, in `HttpApiV2` format
+    pub cookies: Vec<String>,
+    /// Body, base64 encoded when `is_base64` is true
+    pub body: String,
+    /// Is body base64 encoded?
+    pub is_base64: bool,
+}
+
+impl LambdaResponse {
+    /// Response without headers and body
+    pub fn new(status: u16, format: ResponseFormat) -> Self {
+        Self {
+            format,
+            status,
+            headers: BTreeMap::new(),
+            multi_value_headers: BTreeMap::new(),
+            cookies: Vec::new(),
+            body: String::new(),
+            is_base64: false,
+        }
+    }
+
+    /// Plain text response made by this crate, e.g. 400 Bad Request
+    pub fn text(status: u16, text: &str) -> Self {
+        let mut response = Self::new(status, ResponseFormat::Headers);
+        response.add_header("content-type", "text/plain");
+        response.body = text.to_string();
+        response
+    }
+
+    /// Add header in the wire format, header name is normalized to lowercase.
+    /// `set-cookie` goes to `cookies` in `HttpApiV2` format,
+    /// other headers replace the same name except `MultiValueHeaders` format.
+    /// Values are kept as they are, so cookie attributes are not reordered nor dropped.
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        // Rocket sends "Set-Cookie", others send "set-cookie"
+        let name = name.to_ascii_lowercase();
+        match self.format {
+            ResponseFormat::HttpApiV2 if name == "set-cookie" => {
+                self.cookies.push(value.to_string())
+            }
+            ResponseFormat::HttpApiV2 | ResponseFormat::Headers | ResponseFormat::Json => {
+                self.headers.insert(name, value.to_string());
+            }
+            // MultiValueHeaders, and CloudFront with `cloudfront` feature
+            _ => self
+                .multi_value_headers
+                .entry(name)
+                .or_default()
+                .push(value.to_string()),
+        }
+    }
+
+    /// First value of the header ( name must be lowercase )
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(name)
+            .or_else(|| {
+                self.multi_value_headers
+                    .get(name)
+                    .and_then(|values| values.first())
+            })
+            .map(|value| value as &str)
+    }
+
+    /// Remove all values of the header ( name must be lowercase )
+    pub(crate) fn remove_header(&mut self, name: &str) {
+        self.headers.remove(name);
+        self.multi_value_headers.remove(name);
+    }
+}
+
+impl LambdaResponse {
+    /// Serialize the body as JSON value, in `Json` format.
+    /// Bodies not in JSON, e.g. text responses of built-in errors,
+    /// become `{"statusCode":..,"message":..}` objects.
+    fn serialize_json_body<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::Error;
+
+        let body = if self.is_base64 {
+            base64::decode(&self.body).map_err(S::Error::custom)?
+        } else {
+            self.body.as_bytes().to_vec()
+        };
+        match serde_json::from_slice::<serde_json::Value>(&body) {
+            Ok(value) => value.serialize(serializer),
+            Err(err) => {
+                tracing::warn!("{} response body is not JSON, {}", self.status, err);
+                serde_json::json!({
+                    "statusCode": self.status,
+                    "message": String::from_utf8_lossy(&body),
+                })
+                .serialize(serializer)
+            }
+        }
+    }
+}
+
+impl Serialize for LambdaResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let (headers, multi_value_headers, cookies) = match self.format {
+            ResponseFormat::HttpApiV2 => (Some(&self.headers), None, Some(&self.cookies)),
+            ResponseFormat::MultiValueHeaders => (None, Some(&self.multi_value_headers), None),
+            ResponseFormat::Headers => (Some(&self.headers), None, None),
+            ResponseFormat::Json => return self.serialize_json_body(serializer),
+            #[cfg(feature = "cloudfront")]
+            ResponseFormat::CloudFront => return crate::edge::serialize_response(self, serializer),
+        };
+        WireResponseRef {
+            is_base64_encoded: self.is_base64,
+            status_code: self.status,
+            headers,
+            multi_value_headers,
+            cookies,
+            body: &self.body,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Lambda response JSON, serialized without copying the body
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WireResponseRef<'a> {
+    is_base64_encoded: bool,
+    status_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<&'a BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    multi_value_headers: Option<&'a BTreeMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cookies: Option<&'a Vec<String>>,
+    body: &'a str,
+}
+
+/// Lambda response JSON, deserialized from stored responses
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireResponse {
+    #[serde(default)]
+    is_base64_encoded: bool,
+    status_code: u16,
+    #[serde(default)]
+    headers: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    multi_value_headers: Option<BTreeMap<String, Vec<String>>>,
+    #[serde(default)]
+    cookies: Option<Vec<String>>,
+    #[serde(default)]
+    body: String,
+}
+
+impl From<WireResponse> for LambdaResponse {
+    fn from(wire: WireResponse) -> Self {
+        let format = if wire.multi_value_headers.is_some() {
+            ResponseFormat::MultiValueHeaders
+        } else if wire.cookies.is_some() {
+            ResponseFormat::HttpApiV2
+        } else {
+            ResponseFormat::Headers
+        };
+        Self {
+            format,
+            status: wire.status_code,
+            headers: wire.headers.unwrap_or_default(),
+            multi_value_headers: wire.multi_value_headers.unwrap_or_default(),
+            cookies: wire.cookies.unwrap_or_default(),
+            body: wire.body,
+            is_base64: wire.is_base64_encoded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_add_header() {
+        use crate::test_consts::SET_COOKIES_WITH_ATTRIBUTES;
+
+        let mut res = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        res.add_header("x-header", "value");
+        res.add_header("set-cookie", "key=value");
+        assert_eq!(res.header("x-header"), Some("value"));
+        assert_eq!(res.cookies, vec!["key=value".to_string()]);
+
+        let mut res = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        res.add_header("x-header", "value1");
+        res.add_header("x-header", "value2");
+        res.add_header("set-cookie", "key=value");
+        assert_eq!(
+            res.multi_value_headers["x-header"],
+            vec!["value1".to_string(), "value2".to_string()]
+        );
+        assert_eq!(
+            res.multi_value_headers["set-cookie"],
+            vec!["key=value".to_string()]
+        );
+
+        // Mixed case names are merged
+        let mut res = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        res.add_header("Set-Cookie", SET_COOKIES_WITH_ATTRIBUTES[0]);
+        res.add_header("set-cookie", SET_COOKIES_WITH_ATTRIBUTES[1]);
+        res.add_header("Content-Type", "text/plain");
+        assert_eq!(res.cookies, SET_COOKIES_WITH_ATTRIBUTES);
+        assert_eq!(res.header("content-type"), Some("text/plain"));
+        let mut res = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        res.add_header("Set-Cookie", SET_COOKIES_WITH_ATTRIBUTES[0]);
+        res.add_header("set-cookie", SET_COOKIES_WITH_ATTRIBUTES[1]);
+        assert_eq!(
+            res.multi_value_headers["set-cookie"],
+            SET_COOKIES_WITH_ATTRIBUTES
+        );
+
+        // Error response without cookies
+        let mut res = LambdaResponse::text(400, "Bad Request");
+        res.add_header("set-cookie", "key=value");
+        assert_eq!(res.header("set-cookie"), Some("key=value"));
+        assert!(res.cookies.is_empty());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut res = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        res.add_header("x-b", "2");
+        res.add_header("content-type", "text/plain");
+        res.add_header("x-a", "1");
+        res.body = "SGVsbG8=".to_string();
+        res.is_base64 = true;
+        assert_eq!(
+            serde_json::to_string(&res).unwrap(),
+            r#"{"isBase64Encoded":true,"statusCode":200,"headers":{"content-type":"text/plain","x-a":"1","x-b":"2"},"cookies":[],"body":"SGVsbG8="}"#
+        );
+        let decoded: LambdaResponse =
+            serde_json::from_str(&serde_json::to_string(&res).unwrap()).unwrap();
+        assert_eq!(decoded, res);
+
+        let mut res = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+        res.add_header("x-header", "value");
+        assert_eq!(
+            serde_json::to_value(&res).unwrap(),
+            json!({
+                "isBase64Encoded": false,
+                "statusCode": 200,
+                "multiValueHeaders": {"x-header": ["value"]},
+                "body": ""
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(&LambdaResponse::text(400, "Bad Request")).unwrap(),
+            json!({
+                "isBase64Encoded": false,
+                "statusCode": 400,
+                "headers": {"content-type": "text/plain"},
+                "body": "Bad Request"
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let mut res = LambdaResponse::new(200, ResponseFormat::Json);
+        res.add_header("content-type", "application/json");
+        res.body = base64::encode(r#"{"version":"1.0","response":{}}"#);
+        res.is_base64 = true;
+        assert_eq!(
+            serde_json::to_value(&res).unwrap(),
+            json!({"version": "1.0", "response": {}})
+        );
+
+        res.body = "not JSON".to_string();
+        res.is_base64 = false;
+        assert_eq!(
+            serde_json::to_value(&res).unwrap(),
+            json!({"statusCode": 200, "message": "not JSON"})
+        );
+
+        // Built-in error responses to bridged events
+        let mut res = LambdaResponse::text(500, "Internal Server Error");
+        res.format = ResponseFormat::Json;
+        assert_eq!(
+            serde_json::to_value(&res).unwrap(),
+            json!({"statusCode": 500, "message": "Internal Server Error"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_container_affinity() {
+        use crate::test_consts::*;
+
+        let options = Arc::new(RunOptions::default().container_affinity(true));
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let res = finish_response(options, info, async {
+            Ok::<_, ()>(LambdaResponse::new(200, ResponseFormat::HttpApiV2))
+        })
+        .await
+        .unwrap();
+
+        let container_id = crate::affinity::container_id();
+        assert_eq!(
+            res.header(crate::affinity::CONTAINER_ID_HEADER),
+            Some(container_id)
+        );
+        assert_eq!(
+            res.cookies,
+            vec![crate::affinity::container_cookie(container_id)]
+        );
+    }
+
+    #[test]
+    fn test_default_charset() {
+        let with_type = |content_type: &str| {
+            let mut res = LambdaResponse::new(200, ResponseFormat::MultiValueHeaders);
+            res.add_header("content-type", content_type);
+            apply_default_charset(&mut res);
+            res.header("content-type").unwrap().to_string()
+        };
+        assert_eq!(with_type("text/html"), "text/html; charset=utf-8");
+        assert_eq!(
+            with_type("application/json"),
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            with_type("text/plain; format=flowed"),
+            "text/plain; format=flowed; charset=utf-8"
+        );
+        assert_eq!(
+            with_type("text/html; Charset=Shift_JIS"),
+            "text/html; Charset=Shift_JIS"
+        );
+        assert_eq!(with_type("image/png"), "image/png");
+
+        // No content type
+        let mut res = LambdaResponse::new(204, ResponseFormat::HttpApiV2);
+        apply_default_charset(&mut res);
+        assert_eq!(res.header("content-type"), None);
+    }
+
+    #[test]
+    fn test_sanitize_header_values() {
+        let response = |format| {
+            let mut res = LambdaResponse::new(200, format);
+            res.add_header("location", "/next\r\nset-cookie: admin=1");
+            res.add_header("x-tab", "a\tb");
+            res.add_header("set-cookie", "key=value\0");
+            res.add_header("set-cookie", "other=value");
+            res
+        };
+
+        let mut res = response(ResponseFormat::HttpApiV2);
+        sanitize_header_values(&mut res, crate::UnsafeHeaderValues::Strip);
+        assert_eq!(res.header("location"), Some("/nextset-cookie: admin=1"));
+        assert_eq!(res.header("x-tab"), Some("a\tb"));
+        assert_eq!(res.cookies, vec!["key=value", "other=value"]);
+
+        let mut res = response(ResponseFormat::HttpApiV2);
+        sanitize_header_values(&mut res, crate::UnsafeHeaderValues::Reject);
+        assert_eq!(res.header("location"), None);
+        assert_eq!(res.header("x-tab"), Some("a\tb"));
+        assert_eq!(res.cookies, vec!["other=value"]);
+
+        let mut res = response(ResponseFormat::MultiValueHeaders);
+        sanitize_header_values(&mut res, crate::UnsafeHeaderValues::Reject);
+        assert!(!res.multi_value_headers.contains_key("location"));
+        assert_eq!(
+            res.multi_value_headers["set-cookie"],
+            vec!["other=value".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_experiment_header() {
+        use crate::test_consts::*;
+        use crate::{ClientKey, Experiment};
+
+        let options = Arc::new(RunOptions::default().experiment(
+            Experiment::new("banner", ClientKey::Cookie("cookie1".to_string())).variant("blue", 1),
+        ));
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_TWO_COOKIES).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let res = finish_response(options, info, async {
+            Ok::<_, ()>(LambdaResponse::new(200, ResponseFormat::HttpApiV2))
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            res.header(crate::experiment::EXPERIMENT_HEADER),
+            Some("banner=blue")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_noindex_non_production() {
+        use crate::test_consts::*;
+
+        let options = Arc::new(RunOptions::default().noindex_non_production(["prod"]));
+        let handler = || async { Ok::<_, ()>(LambdaResponse::new(200, ResponseFormat::Headers)) };
+
+        // "stage" is not production
+        let event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_WITH_CONTEXT).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let res = finish_response(options.clone(), info, handler())
+            .await
+            .unwrap();
+        assert_eq!(res.header("x-robots-tag"), Some("noindex"));
+
+        // Production stage
+        let event_str =
+            API_GATEWAY_REST_GET_WITH_CONTEXT.replace(r#""stage":"stage""#, r#""stage":"prod""#);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let res = finish_response(options.clone(), info, handler())
+            .await
+            .unwrap();
+        assert_eq!(res.header("x-robots-tag"), None);
+
+        // Not configured
+        let options = Arc::new(RunOptions::default());
+        let event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_WITH_CONTEXT).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let res = finish_response(options, info, handler()).await.unwrap();
+        assert_eq!(res.header("x-robots-tag"), None);
+    }
+}
@@ -3,12 +3,20 @@
 //! Run Rocket on AWS Lambda
 //!
 //!
-use crate::request::LambdaHttpEvent;
+use crate::bridge::WebEvent;
+#[cfg(feature = "html-rewrite")]
+use crate::html_rewrite::HtmlRewriter;
+use crate::options::RunOptions;
+use crate::request::{DecodeError, LambdaHttpEvent};
+use crate::response::{
+    finish_response, ready_response, HandlerFuture, LambdaResponse, RequestInfo, ResponseFormat,
+    ResponseFuture,
+};
 use core::convert::TryFrom;
-use core::future::Future;
+use futures_util::future::Either;
 use lambda_runtime::{Error as LambdaError, LambdaEvent, Service as LambdaService};
-use std::pin::Pin;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Launch Rocket application on AWS Lambda
 ///
@@ -38,21 +46,146 @@ use std::sync::Arc;
 pub async fn launch_rocket_on_lambda<P: rocket::Phase>(
     r: rocket::Rocket<P>,
 ) -> Result<(), LambdaError> {
-    lambda_runtime::run(RocketHandler(Arc::new(
-        rocket::local::asynchronous::Client::untracked(r).await?,
-    )))
-    .await?;
+    launch_rocket_on_lambda_with_options(r, RunOptions::default()).await
+}
+
+/// Launch Rocket application on AWS Lambda with options
+///
+/// Shutdown fairings (`Fairing::on_shutdown`) run when the Lambda container receives SIGTERM
+/// or the runtime loop exits. Lambda sends SIGTERM only when an extension is registered.
+///
+/// ```no_run
+/// use rocket::{self, get, routes};
+/// use lambda_web::{launch_rocket_on_lambda_with_options, LambdaError, PeerPort, RunOptions};
+///
+/// #[get("/hello/<name>/<age>")]
+/// fn hello(name: &str, age: u8) -> String {
+///     format!("Hello, {} year old named {}!", age, name)
+/// }
+///
+/// #[rocket::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let rocket = rocket::build().mount("/", routes![hello]);
+///     let options = RunOptions::default().peer_port(PeerPort::Forwarded);
+///     launch_rocket_on_lambda_with_options(rocket, options).await?;
+///     Ok(())
+/// }
+/// ```
+///
+pub async fn launch_rocket_on_lambda_with_options<P: rocket::Phase>(
+    r: rocket::Rocket<P>,
+    options: RunOptions,
+) -> Result<(), LambdaError> {
+    use futures_util::future::{select, Either};
+
+    let client = Arc::new(rocket_local_client(r, &options).await?);
+    options.snapshot_hooks.run()?;
+    crate::capture::install(options.failure_capture.clone());
+    crate::bridge::install_raw_event(options.raw_event);
+    let runtime = Box::pin(lambda_runtime::run(RocketHandler(
+        client.clone(),
+        Arc::new(options),
+    )));
+
+    // Run until the runtime loop exits or SIGTERM is received
+    let result = match select(runtime, Box::pin(sigterm())).await {
+        Either::Left((result, _)) => result,
+        Either::Right((_, runtime)) => {
+            // Drop the runtime loop and its handler holding the client
+            drop(runtime);
+            Ok(())
+        }
+    };
+
+    // Run shutdown fairings, as same as Rocket::launch() returns
+    if let Ok(client) = Arc::try_unwrap(client) {
+        let _ = client.terminate().await;
+    }
+
+    result
+}
+
+/// Rocket with a stable secret key for private cookies
+///
+/// Without `secret_key`, Rocket generates a random key in each Lambda container,
+/// so private cookies encrypted in one container can not be decrypted in others.
+/// `secret_key` is 256 bit or larger key in base64 or hex, same as `ROCKET_SECRET_KEY`,
+/// e.g. loaded from AWS Secrets Manager.
+///
+/// ```no_run
+/// use lambda_web::{launch_rocket_on_lambda, rocket_with_secret_key, LambdaError};
+///
+/// #[rocket::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let secret_key = std::env::var("SECRET_KEY")?;
+///     let rocket = rocket_with_secret_key(rocket::build(), &secret_key);
+///     launch_rocket_on_lambda(rocket).await?;
+///     Ok(())
+/// }
+/// ```
+pub fn rocket_with_secret_key(
+    r: rocket::Rocket<rocket::Build>,
+    secret_key: &str,
+) -> rocket::Rocket<rocket::Build> {
+    let figment = r.figment().clone().merge(("secret_key", secret_key));
+    r.configure(figment)
+}
+
+/// Rocket local client as used by `launch_rocket_on_lambda_with_options`
+///
+/// Untracked by default, tracked with `RunOptions::rocket_tracked_client(true)`.
+/// Tests can dispatch requests with the same client configuration as on Lambda.
+pub async fn rocket_local_client<P: rocket::Phase>(
+    r: rocket::Rocket<P>,
+    options: &RunOptions,
+) -> Result<rocket::local::asynchronous::Client, rocket::Error> {
+    use rocket::local::asynchronous::Client;
+
+    if options.rocket_tracked_client {
+        Client::tracked(r).await
+    } else {
+        Client::untracked(r).await
+    }
+}
+
+/// Wait for SIGTERM,
+/// sent by Lambda before shutting down the container when an extension is registered.
+#[cfg(unix)]
+async fn sigterm() {
+    use tokio::signal::unix::{signal, SignalKind};
 
-    Ok(())
+    if let Ok(mut sigterm) = signal(SignalKind::terminate()) {
+        sigterm.recv().await;
+    } else {
+        // Signal handler unavailable, wait forever
+        core::future::pending::<()>().await;
+    }
+}
+
+// No SIGTERM except Unix
+#[cfg(not(unix))]
+async fn sigterm() {
+    core::future::pending::<()>().await;
 }
 
 /// Lambda_runtime handler for Rocket
-struct RocketHandler(Arc<rocket::local::asynchronous::Client>);
+///
+/// Service of `LambdaEvent<WebEvent>` used by `launch_rocket_on_lambda`,
+/// to be run by your own `lambda_runtime` setup.
+/// Shutdown fairings do not run unless the client is terminated.
+pub struct RocketHandler(Arc<rocket::local::asynchronous::Client>, Arc<RunOptions>);
+
+impl RocketHandler {
+    /// Handler of Rocket local client with options, see `rocket_local_client`
+    pub fn new(client: rocket::local::asynchronous::Client, options: RunOptions) -> Self {
+        Self(Arc::new(client), Arc::new(options))
+    }
+}
 
-impl LambdaService<LambdaEvent<LambdaHttpEvent<'_>>> for RocketHandler {
-    type Response = serde_json::Value;
+impl LambdaService<LambdaEvent<WebEvent<'_>>> for RocketHandler {
+    type Response = LambdaResponse;
     type Error = rocket::Error;
-    type Future = Pin<Box<dyn Future<Output = Result<serde_json::Value, Self::Error>> + Send>>;
+    type Future = HandlerFuture<ResponseFuture<Self::Error>>;
 
     /// Always ready in case of Rocket local client
     fn poll_ready(
@@ -65,20 +198,40 @@ impl LambdaService<LambdaEvent<LambdaHttpEvent<'_>>> for RocketHandler {
     /// Lambda handler function
     /// Parse Lambda event as Rocket LocalRequest,
     /// serialize Rocket LocalResponse to Lambda JSON response
-    fn call(&mut self, req: LambdaEvent<LambdaHttpEvent<'_>>) -> Self::Future {
-        use serde_json::json;
-
-        let event = req.payload;
-        let _context = req.context;
+    fn call(&mut self, req: LambdaEvent<WebEvent<'_>>) -> Self::Future {
+        let event = match req.payload.into_http(&self.1) {
+            Ok(event) => event,
+            // Conversational event without route, or invalid header names
+            Err(request_err) => return ready_response(request_err.response()),
+        };
+        if let Some(builtin) = crate::builtin::builtin_response(&event, &self.1) {
+            let request_info = RequestInfo::new(&event, &self.1);
+            let fut = async move { Ok(builtin.await) };
+            return Either::Right(Box::pin(finish_response(self.1.clone(), request_info, fut)));
+        }
+        let deadline = Some(crate::brotli::deadline_from_millis(req.context.deadline));
 
         // check if web client supports content-encoding: br
         let client_br = event.client_supports_brotli();
         // multi-value-headers response format
         let multi_value = event.multi_value();
+        // HTML rewriting for the stage prefix
+        #[cfg(feature = "html-rewrite")]
+        let html_rewriter = HtmlRewriter::for_request(&event, &self.1);
+
+        // Rocket supports only standard methods,
+        // extension methods (PROPFIND, PURGE, etc.) are not implemented
+        if !is_rocket_method(event.method()) {
+            return ready_response(LambdaResponse::text(501, "Not Implemented"));
+        }
+
+        // Request information for response post-processing
+        let request_info = RequestInfo::new(&event, &self.1);
 
         // Parse request
-        let decode_result = RequestDecode::try_from(event);
+        let decode_result = RequestDecode::from_event(event, &self.1);
         let client = self.0.clone();
+        let options = self.1.clone();
         let fut = async move {
             match decode_result {
                 Ok(req_decode) => {
@@ -89,23 +242,33 @@ impl LambdaService<LambdaEvent<LambdaHttpEvent<'_>>> for RocketHandler {
                     let response = local_request.dispatch().await;
 
                     // Return response as API Gateway JSON
-                    api_gateway_response_from_rocket(response, client_br, multi_value).await
+                    api_gateway_response_from_rocket(
+                        response,
+                        client_br,
+                        multi_value,
+                        deadline,
+                        #[cfg(feature = "html-rewrite")]
+                        html_rewriter.as_ref(),
+                        &options,
+                    )
+                    .await
                 }
-                Err(_request_err) => {
-                    // Request parsing error
-                    Ok(json!({
-                        "isBase64Encoded": false,
-                        "statusCode": 400u16,
-                        "headers": { "content-type": "text/plain"},
-                        "body": "Bad Request" // No details for security
-                    }))
+                Err(request_err) => {
+                    // Request parsing error, 4xx by cause without details for security
+                    Ok(request_err.response())
                 }
             }
         };
-        Box::pin(fut)
+        Either::Right(Box::pin(finish_response(self.1.clone(), request_info, fut)))
     }
 }
 
+/// Can Rocket handle this HTTP method?
+fn is_rocket_method(method: &str) -> bool {
+    use std::str::FromStr;
+    rocket::http::Method::from_str(method).is_ok()
+}
+
 // Request decoded from API gateway JSON.
 // To move async boundary in call() function,
 // all elements must be owned
@@ -113,31 +276,69 @@ struct RequestDecode {
     path_and_query: String,
     method: rocket::http::Method,
     source_ip: std::net::IpAddr,
-    cookies: Vec<String>,
+    source_port: u16,
+    cookies: crate::CookieJar,
     headers: Vec<rocket::http::Header<'static>>,
     body: Vec<u8>,
+    tenant: Option<crate::Tenant>,
+    assignments: Option<crate::Assignments>,
+    sampled: Option<crate::Sampled>,
+    client_hints: crate::ClientHints,
+    viewer_info: crate::ViewerInfo,
+    container_affinity: crate::ContainerAffinity,
 }
 
 impl TryFrom<LambdaHttpEvent<'_>> for RequestDecode {
-    type Error = LambdaError;
+    type Error = DecodeError;
 
     /// Request from API Gateway event
     fn try_from(event: LambdaHttpEvent) -> Result<Self, Self::Error> {
+        Self::from_event(event, &RunOptions::default())
+    }
+}
+
+impl RequestDecode {
+    /// Request from API Gateway event with options
+    fn from_event(event: LambdaHttpEvent, options: &RunOptions) -> Result<Self, DecodeError> {
         use rocket::http::{Header, Method};
         use std::net::IpAddr;
         use std::str::FromStr;
 
         // path ? query_string
         let path_and_query = event.path_query();
+        crate::request::check_uri_length(&path_and_query)?;
 
         // Method, Source IP
-        let method = Method::from_str(&event.method()).map_err(|_| "InvalidMethod")?;
+        let method = Method::from_str(&event.method()).map_err(|_| DecodeError::InvalidMethod)?;
         let source_ip = event
             .source_ip()
             .unwrap_or(IpAddr::from([0u8, 0u8, 0u8, 0u8]));
+        let source_port = options.peer_port.port(&event);
 
         // Parse cookies
-        let cookies = event.cookies().iter().map(|c| c.to_string()).collect();
+        let cookies = crate::CookieJar::from_event(&event);
+
+        // Request local cache, as same as hyper request extensions
+        let tenant = options
+            .tenant_routing
+            .as_ref()
+            .zip(event.hostname())
+            .and_then(|(routing, hostname)| routing.tenant(hostname));
+        let assignments = if options.experiments.is_empty() {
+            None
+        } else {
+            Some(crate::experiment::Assignments::from_event(
+                &event,
+                &options.experiments,
+            ))
+        };
+        let sampled = options
+            .access_log
+            .as_ref()
+            .map(|sampling| sampling.sample(&event));
+        let client_hints = event.client_hints();
+        let viewer_info = event.viewer_info();
+        let container_affinity = crate::ContainerAffinity::from_event(&event);
 
         // Headers
         let headers = event
@@ -153,14 +354,19 @@ impl TryFrom<LambdaHttpEvent<'_>> for RequestDecode {
             path_and_query,
             method,
             source_ip,
+            source_port,
             cookies,
             headers,
             body,
+            tenant,
+            assignments,
+            sampled,
+            client_hints,
+            viewer_info,
+            container_affinity,
         })
     }
-}
 
-impl RequestDecode {
     /// Make Rocket LocalRequest
     fn make_request<'c, 's: 'c>(
         &'s self,
@@ -171,16 +377,15 @@ impl RequestDecode {
         // path, method, remote address, body
         let req = client
             .req(self.method, &self.path_and_query)
-            .remote(std::net::SocketAddr::from((self.source_ip, 0u16)))
+            .remote(std::net::SocketAddr::from((
+                self.source_ip,
+                self.source_port,
+            )))
             .body(&self.body);
 
         // Copy cookies
-        let req = self.cookies.iter().fold(req, |req, cookie_name_val| {
-            if let Ok(cookie) = Cookie::parse_encoded(cookie_name_val) {
-                req.cookie(cookie)
-            } else {
-                req
-            }
+        let req = self.cookies.iter().fold(req, |req, (name, value)| {
+            req.cookie(Cookie::new(name.to_string(), value.into_owned()))
         });
 
         // Copy headers
@@ -189,6 +394,15 @@ impl RequestDecode {
             .iter()
             .fold(req, |req, header| req.header(header.clone()));
 
+        // Request local cache as Option, e.g. `request.local_cache(|| None::<Tenant>)`
+        let inner = req.inner();
+        inner.local_cache(|| self.tenant.clone());
+        inner.local_cache(|| self.assignments.clone());
+        inner.local_cache(|| self.sampled);
+        inner.local_cache(|| Some(self.client_hints.clone()));
+        inner.local_cache(|| Some(self.viewer_info.clone()));
+        inner.local_cache(|| Some(self.container_affinity.clone()));
+
         req
     }
 }
@@ -210,68 +424,51 @@ async fn api_gateway_response_from_rocket(
     response: rocket::local::asynchronous::LocalResponse<'_>,
     client_support_br: bool,
     multi_value: bool,
-) -> Result<serde_json::Value, rocket::Error> {
+    deadline: Option<SystemTime>,
+    #[cfg(feature = "html-rewrite")] html_rewriter: Option<&HtmlRewriter>,
+    options: &RunOptions,
+) -> Result<LambdaResponse, rocket::Error> {
     use crate::brotli::ResponseCompression;
-    use serde_json::json;
 
     // HTTP status
-    let status_code = response.status().code;
+    let mut lambda_response = LambdaResponse::new(
+        response.status().code,
+        ResponseFormat::from_multi_value(multi_value),
+    );
 
-    // Convert header to JSON map
-    let mut cookies = Vec::<String>::new();
-    let mut headers = serde_json::Map::new();
+    // Convert headers,
+    // REST API returns multiValueHeaders, HTTP API v2 returns headers and cookies
     for header in response.headers().iter() {
-        let header_name = header.name.into_string();
-        let header_value = header.value.into_owned();
-        if multi_value {
-            // REST API format, returns multiValueHeaders
-            if let Some(values) = headers.get_mut(&header_name) {
-                if let Some(value_ary) = values.as_array_mut() {
-                    value_ary.push(json!(header_value));
-                }
-            } else {
-                headers.insert(header_name, json!([header_value]));
-            }
-        } else {
-            // HTTP API v2 format, returns headers
-            if &header_name == "set-cookie" {
-                cookies.push(header_value);
-            } else {
-                headers.insert(header_name, json!(header_value));
-            }
+        if options.is_blocked_response_header(header.name.as_str()) {
+            // Stripped by options
+            continue;
         }
+        lambda_response.add_header(header.name.as_str(), &header.value);
     }
 
     // check if response should be compressed
     let compress = client_support_br && response.can_brotli_compress();
+    #[cfg(feature = "html-rewrite")]
+    let html_rewriter = HtmlRewriter::for_response(html_rewriter, &response);
+    let content_type = ResponseCompression::content_type(&response).map(|ctype| ctype.to_string());
     let body_bytes = response.into_bytes().await.unwrap_or_default();
-    let body_base64 = if compress {
-        if multi_value {
-            headers.insert("content-encoding".to_string(), json!(["br"]));
-        } else {
-            headers.insert("content-encoding".to_string(), json!("br"));
-        }
-        crate::brotli::compress_response_body(&body_bytes)
-    } else {
-        base64::encode(body_bytes)
+    #[cfg(feature = "html-rewrite")]
+    let body_bytes = match html_rewriter {
+        Some(rewriter) => rewriter.rewrite(body_bytes),
+        None => body_bytes,
     };
-
-    if multi_value {
-        Ok(json!({
-            "isBase64Encoded": true,
-            "statusCode": status_code,
-            "multiValueHeaders": headers,
-            "body": body_base64
-        }))
-    } else {
-        Ok(json!({
-            "isBase64Encoded": true,
-            "statusCode": status_code,
-            "cookies": cookies,
-            "headers": headers,
-            "body": body_base64
-        }))
-    }
+    let lambda_response = crate::response::encode_response_body(
+        lambda_response,
+        body_bytes,
+        client_support_br,
+        compress,
+        content_type,
+        deadline,
+        options,
+    )
+    .await;
+
+    Ok(lambda_response)
 }
 
 #[cfg(test)]
@@ -489,6 +686,101 @@ mod tests {
         );
     }
 
+    #[async_test]
+    async fn test_remote_port_decode() {
+        use crate::options::PeerPort;
+
+        let rocket = rocket::build();
+        let client = Client::untracked(rocket).await.unwrap();
+
+        let decode = prepare_request(API_GATEWAY_V2_GET_ROOT_ONEQUERY);
+        let req = decode.make_request(&client);
+        assert_eq!(req.inner().remote().unwrap().port(), 0);
+
+        let options = RunOptions::default().peer_port(PeerPort::Fixed(12345));
+        let reqjson: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_GET_ROOT_ONEQUERY).unwrap();
+        let decode = RequestDecode::from_event(reqjson, &options).unwrap();
+        let req = decode.make_request(&client);
+        assert_eq!(req.inner().remote().unwrap().port(), 12345);
+
+        let options = RunOptions::default().peer_port(PeerPort::Forwarded);
+        let reqjson: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_ROOT_ONEQUERY).unwrap();
+        let decode = RequestDecode::from_event(reqjson, &options).unwrap();
+        let req = decode.make_request(&client);
+        assert_eq!(req.inner().remote().unwrap().port(), 443);
+    }
+
+    #[async_test]
+    async fn test_tenant_local_cache() {
+        use crate::{Tenant, TenantRouting};
+
+        let rocket = rocket::build();
+        let client = Client::untracked(rocket).await.unwrap();
+
+        let options = RunOptions::default().tenant_routing(TenantRouting::subdomain_of(
+            "execute-api.ap-northeast-1.amazonaws.com",
+        ));
+        let reqjson: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let decode = RequestDecode::from_event(reqjson, &options).unwrap();
+        let req = decode.make_request(&client);
+        let tenant = req.inner().local_cache(|| None::<Tenant>);
+        assert_eq!(tenant.as_ref().map(Tenant::name), Some("yyyyyyyyyy"));
+
+        let decode = prepare_request(API_GATEWAY_V2_GET_ROOT_NOQUERY);
+        let req = decode.make_request(&client);
+        assert!(req.inner().local_cache(|| None::<Tenant>).is_none());
+    }
+
+    #[async_test]
+    async fn test_local_cache() {
+        use crate::{ClientHints, ContainerAffinity, Sampled, ViewerInfo};
+
+        let rocket = rocket::build();
+        let client = Client::untracked(rocket).await.unwrap();
+
+        let options = RunOptions::default().access_log(crate::LogSampling::all());
+        let reqjson: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let decode = RequestDecode::from_event(reqjson, &options).unwrap();
+        let req = decode.make_request(&client);
+        let inner = req.inner();
+        assert!(inner.local_cache(|| None::<ClientHints>).is_some());
+        assert!(inner.local_cache(|| None::<ViewerInfo>).is_some());
+        assert!(inner.local_cache(|| None::<ContainerAffinity>).is_some());
+        assert_eq!(
+            inner
+                .local_cache(|| None::<Sampled>)
+                .map(|s| s.is_sampled()),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_method_decode() {
+        use rocket::http::Method;
+
+        for (method_str, method) in [
+            ("PUT", Method::Put),
+            ("DELETE", Method::Delete),
+            ("OPTIONS", Method::Options),
+            ("PATCH", Method::Patch),
+        ] {
+            let event_str =
+                API_GATEWAY_V2_GET_ROOT_NOQUERY.replace("\"GET\"", &format!("\"{}\"", method_str));
+            assert_eq!(prepare_request(&event_str).method, method);
+        }
+
+        // Extension methods are not supported by Rocket
+        assert!(!is_rocket_method("PROPFIND"));
+        assert!(!is_rocket_method("PURGE"));
+        let event_str = API_GATEWAY_REST_GET_ROOT_NOQUERY.replace("\"GET\"", "\"PROPFIND\"");
+        let reqjson: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assert!(RequestDecode::try_from(reqjson).is_err());
+    }
+
     #[async_test]
     async fn test_form_post() {
         use rocket::http::ContentType;
@@ -547,6 +839,107 @@ mod tests {
         );
     }
 
+    #[async_test]
+    async fn test_rocket_local_client() {
+        use rocket::http::{Cookie, CookieJar};
+        use rocket::{get, routes};
+
+        #[get("/set")]
+        fn set(jar: &CookieJar<'_>) {
+            jar.add(Cookie::new("key", "value"));
+        }
+
+        #[get("/get")]
+        fn get(jar: &CookieJar<'_>) -> String {
+            jar.get("key")
+                .map(|c| c.value().to_string())
+                .unwrap_or_default()
+        }
+
+        let secret_key = base64::encode([1u8; 64]);
+        let rocket =
+            rocket_with_secret_key(rocket::build(), &secret_key).mount("/", routes![set, get]);
+        let options = RunOptions::default().rocket_tracked_client(true);
+        let client = rocket_local_client(rocket, &options).await.unwrap();
+        assert_eq!(
+            client
+                .rocket()
+                .figment()
+                .extract_inner::<String>("secret_key")
+                .unwrap(),
+            secret_key
+        );
+
+        // Cookie set by the previous response is sent by the tracked client
+        client.get("/set").dispatch().await;
+        let body = client.get("/get").dispatch().await.into_string().await;
+        assert_eq!(body.as_deref(), Some("value"));
+
+        // Untracked by default
+        let rocket = rocket::build().mount("/", routes![set, get]);
+        let client = rocket_local_client(rocket, &RunOptions::default())
+            .await
+            .unwrap();
+        client.get("/set").dispatch().await;
+        let body = client.get("/get").dispatch().await.into_string().await;
+        assert_eq!(body.as_deref(), Some(""));
+    }
+
+    #[async_test]
+    async fn test_set_cookie_attributes() {
+        use rocket::response::{self, Responder, Response};
+        use rocket::{get, routes, Request};
+
+        struct Cookies;
+        impl<'r> Responder<'r, 'static> for Cookies {
+            fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+                Response::build()
+                    .raw_header_adjoin("Set-Cookie", SET_COOKIES_WITH_ATTRIBUTES[0])
+                    .raw_header_adjoin("Set-Cookie", SET_COOKIES_WITH_ATTRIBUTES[1])
+                    .ok()
+            }
+        }
+
+        #[get("/")]
+        fn cookies() -> Cookies {
+            Cookies
+        }
+
+        let options = RunOptions::default();
+        let rocket = rocket::build().mount("/", routes![cookies]);
+        let client = Client::untracked(rocket).await.unwrap();
+
+        let response = client.get("/").dispatch().await;
+        let res = api_gateway_response_from_rocket(
+            response,
+            false,
+            false,
+            None,
+            #[cfg(feature = "html-rewrite")]
+            None,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.cookies, SET_COOKIES_WITH_ATTRIBUTES);
+        let response = client.get("/").dispatch().await;
+        let res = api_gateway_response_from_rocket(
+            response,
+            false,
+            true,
+            None,
+            #[cfg(feature = "html-rewrite")]
+            None,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            res.multi_value_headers["set-cookie"],
+            SET_COOKIES_WITH_ATTRIBUTES
+        );
+    }
+
     #[async_test]
     async fn test_parse_cookies() {
         let rocket = rocket::build();
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Event router
+//! Runs the web handler and handlers of other events (SQS, S3, etc.) in one function
+//!
+use crate::bridge::WebEvent;
+use crate::response::LambdaResponse;
+use core::future::Future;
+use lambda_runtime::{Context, Error as LambdaError, LambdaEvent, Service as LambdaService};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::pin::Pin;
+
+type RouteFuture = Pin<Box<dyn Future<Output = Result<Value, LambdaError>>>>;
+type Route = Box<dyn FnMut(&Value, &Context) -> Option<RouteFuture>>;
+
+/// Dispatch Lambda events to the web handler or to handlers of other event types
+///
+/// Web events (API Gateway, ALB, function URL) go to the web handler.
+/// Other events are deserialized to the event type of each handler in registration order,
+/// and go to the first handler whose event type matches.
+/// Register stricter event types first, a type with all optional fields matches any event.
+/// Events matching nothing fail the invocation.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use lambda_web::{EventRouter, HyperHandler, LambdaError, RunOptions};
+/// use lambda_runtime::LambdaEvent;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct SqsEvent {
+///     #[serde(rename = "Records")]
+///     records: Vec<SqsMessage>,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct SqsMessage {
+///     body: String,
+/// }
+///
+/// async fn on_sqs(event: LambdaEvent<SqsEvent>) -> Result<(), LambdaError> {
+///     for message in event.payload.records {
+///         println!("{}", message.body);
+///     }
+///     Ok(())
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new().route("/", get(|| async { "Hello, World!" }));
+///     let router = EventRouter::new(HyperHandler::new(app, RunOptions::default()))
+///         .on(on_sqs);
+///     lambda_runtime::run(router).await?;
+///     Ok(())
+/// }
+/// ```
+pub struct EventRouter<H> {
+    web: H,
+    routes: Vec<Route>,
+}
+
+impl<H> EventRouter<H>
+where
+    H: LambdaService<LambdaEvent<WebEvent<'static>>, Response = LambdaResponse>,
+    H::Error: std::fmt::Display,
+    H::Future: 'static,
+{
+    /// Router with the web handler, `HyperHandler`, `ActixHandler` or `RocketHandler`
+    pub fn new(web: H) -> Self {
+        Self {
+            web,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Add handler of events deserialized as `E`, returning JSON response `R`
+    pub fn on<E, R, F, Fut>(mut self, mut handler: F) -> Self
+    where
+        E: DeserializeOwned,
+        R: Serialize,
+        F: FnMut(LambdaEvent<E>) -> Fut + 'static,
+        Fut: Future<Output = Result<R, LambdaError>> + 'static,
+    {
+        let route: Route = Box::new(move |event: &Value, context: &Context| {
+            let event = E::deserialize(event).ok()?;
+            let fut = handler(LambdaEvent::new(event, context.clone()));
+            Some(Box::pin(async move { Ok(serde_json::to_value(fut.await?)?) }) as RouteFuture)
+        });
+        self.routes.push(route);
+        self
+    }
+}
+
+impl<H> LambdaService<LambdaEvent<Value>> for EventRouter<H>
+where
+    H: LambdaService<LambdaEvent<WebEvent<'static>>, Response = LambdaResponse>,
+    H::Error: std::fmt::Display,
+    H::Future: 'static,
+{
+    type Response = Value;
+    type Error = LambdaError;
+    type Future = RouteFuture;
+
+    /// Returns Poll::Ready when the web handler can process more requests.
+    fn poll_ready(
+        &mut self,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.web
+            .poll_ready(cx)
+            .map_err(|err| LambdaError::from(err.to_string()))
+    }
+
+    /// Dispatch the event by its shape
+    fn call(&mut self, req: LambdaEvent<Value>) -> Self::Future {
+        let (event, context) = (req.payload, req.context);
+
+        if let Ok(web_event) = WebEvent::deserialize(&event) {
+            let fut = self.web.call(LambdaEvent::new(web_event, context));
+            return Box::pin(async move {
+                let response = fut
+                    .await
+                    .map_err(|err| LambdaError::from(err.to_string()))?;
+                Ok(serde_json::to_value(&response)?)
+            });
+        }
+        for route in self.routes.iter_mut() {
+            if let Some(fut) = route(&event, &context) {
+                return fut;
+            }
+        }
+        Box::pin(async { Err(LambdaError::from("no handler for the event")) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[derive(Deserialize)]
+    struct SqsEvent {
+        #[serde(rename = "Records")]
+        records: Vec<Value>,
+    }
+
+    #[tokio::test]
+    async fn test_event_router() {
+        let web = tower::service_fn(|_req: LambdaEvent<WebEvent<'static>>| async {
+            Ok::<_, LambdaError>(LambdaResponse::text(200, "web"))
+        });
+        let mut router = EventRouter::new(web).on(|event: LambdaEvent<SqsEvent>| async move {
+            Ok(serde_json::json!({ "processed": event.payload.records.len() }))
+        });
+        let call = |event: &str| {
+            LambdaEvent::new(
+                serde_json::from_str::<Value>(event).unwrap(),
+                Context::default(),
+            )
+        };
+
+        // Web event
+        let res = router
+            .call(call(API_GATEWAY_V2_GET_ROOT_NOQUERY))
+            .await
+            .unwrap();
+        assert_eq!(res["statusCode"], 200);
+        assert_eq!(res["body"], "web");
+
+        // SQS event
+        let res = router
+            .call(call(r#"{"Records":[{"body":"a"},{"body":"b"}]}"#))
+            .await
+            .unwrap();
+        assert_eq!(res["processed"], 2);
+
+        // Unknown event
+        assert!(router.call(call(r#"{"detail":{}}"#)).await.is_err());
+    }
+}
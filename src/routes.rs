@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Route manifest
+//! Answers OPTIONS with `Allow` header, and lists routes at a debug endpoint
+//!
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use crate::response::{LambdaResponse, ResponseFormat};
+use serde_json::json;
+
+/// Path of the route listing endpoint
+pub const ROUTES_PATH: &str = "/__lambda_web/routes";
+
+/// Routes of the web application, set by `RunOptions::route_manifest`
+///
+/// axum 0.6 `Router` does not expose its routes, so declare them along with the router.
+/// Paths are matched by segments, `:name` or `{name}` matches one segment
+/// and `*name` or `{*name}` matches the rest.
+///
+/// `OPTIONS` requests to declared paths are answered with `Allow` header,
+/// except CORS preflight requests which go to the web framework.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use lambda_web::{run_hyper_on_lambda_with_options, LambdaError, RouteManifest, RunOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new()
+///         .route("/items", get(|| async { "[]" }).post(|| async { "{}" }))
+///         .route("/items/:id", get(|| async { "{}" }).delete(|| async { "" }));
+///     let manifest = RouteManifest::new()
+///         .route("/items", &["GET", "POST"])
+///         .route("/items/:id", &["GET", "DELETE"])
+///         .debug_endpoint(true);
+///     let options = RunOptions::default().route_manifest(manifest);
+///     run_hyper_on_lambda_with_options(app, options).await
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteManifest {
+    routes: Vec<(String, Vec<String>)>,
+    debug_endpoint: bool,
+}
+
+impl RouteManifest {
+    /// Empty manifest
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare methods of the path, merged with methods already declared for the path
+    pub fn route(mut self, path: &str, methods: &[&str]) -> Self {
+        let methods = methods.iter().map(|method| method.to_ascii_uppercase());
+        match self.routes.iter_mut().find(|(pattern, _)| pattern == path) {
+            Some((_, declared)) => {
+                for method in methods {
+                    if !declared.contains(&method) {
+                        declared.push(method);
+                    }
+                }
+            }
+            None => self.routes.push((path.to_string(), methods.collect())),
+        }
+        self
+    }
+
+    /// List routes in JSON at `/__lambda_web/routes`, disabled by default
+    pub fn debug_endpoint(mut self, enabled: bool) -> Self {
+        self.debug_endpoint = enabled;
+        self
+    }
+
+    /// Methods allowed for the path, None when no route matches.
+    /// `HEAD` is allowed with `GET`, and `OPTIONS` is always allowed.
+    pub fn allowed_methods(&self, path: &str) -> Option<Vec<String>> {
+        let mut allowed: Vec<String> = Vec::new();
+        let mut matched = false;
+        for (pattern, methods) in self.routes.iter() {
+            if !path_matches(pattern, path) {
+                continue;
+            }
+            matched = true;
+            for method in methods {
+                if !allowed.contains(method) {
+                    allowed.push(method.clone());
+                }
+            }
+        }
+        if !matched {
+            return None;
+        }
+        if allowed.iter().any(|method| method == "GET") && !allowed.iter().any(|m| m == "HEAD") {
+            allowed.push("HEAD".to_string());
+        }
+        if !allowed.iter().any(|method| method == "OPTIONS") {
+            allowed.push("OPTIONS".to_string());
+        }
+        Some(allowed)
+    }
+}
+
+/// Does the path match the route pattern?
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut segments = path.trim_matches('/').split('/');
+    for pattern_segment in pattern.trim_matches('/').split('/') {
+        if pattern_segment.starts_with('*') || pattern_segment.starts_with("{*") {
+            // Wildcard matches the rest, at least one segment
+            return segments.next().map(|s| !s.is_empty()).unwrap_or(false);
+        }
+        let segment = match segments.next() {
+            Some(segment) => segment,
+            None => return false,
+        };
+        let is_param = pattern_segment.starts_with(':')
+            || (pattern_segment.starts_with('{') && pattern_segment.ends_with('}'));
+        if is_param {
+            if segment.is_empty() {
+                return false;
+            }
+        } else if pattern_segment != segment {
+            return false;
+        }
+    }
+    segments.next().is_none()
+}
+
+/// OPTIONS or route listing response answered by this crate,
+/// None when the request goes to the web framework
+pub(crate) fn routes_response(
+    event: &LambdaHttpEvent,
+    options: &RunOptions,
+) -> Option<LambdaResponse> {
+    let manifest = options.route_manifest.as_ref()?;
+
+    if manifest.debug_endpoint && crate::health::is_request_to(event, ROUTES_PATH) {
+        let routes = manifest
+            .routes
+            .iter()
+            .map(|(path, methods)| json!({ "path": path, "methods": methods }))
+            .collect::<Vec<_>>();
+        let mut response = LambdaResponse::new(200, ResponseFormat::Headers);
+        response.add_header("content-type", "application/json");
+        response.add_header("cache-control", "no-store");
+        response.body = json!({ "routes": routes }).to_string();
+        return Some(response);
+    }
+
+    // CORS preflight is answered by the web framework
+    if event.method() != "OPTIONS" || event.header("access-control-request-method").is_some() {
+        return None;
+    }
+    let allowed = manifest.allowed_methods(&crate::health::app_path(event))?;
+    let mut response = LambdaResponse::new(204, ResponseFormat::Headers);
+    response.add_header("allow", &allowed.join(", "));
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    fn manifest() -> RouteManifest {
+        RouteManifest::new()
+            .route("/items", &["GET", "POST"])
+            .route("/items/:id", &["get", "DELETE"])
+            .route("/items/{id}", &["PUT"])
+            .route("/files/*path", &["GET"])
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let manifest = manifest();
+        assert_eq!(
+            manifest.allowed_methods("/items").unwrap(),
+            vec!["GET", "POST", "HEAD", "OPTIONS"]
+        );
+        assert_eq!(
+            manifest.allowed_methods("/items/42/").unwrap(),
+            vec!["GET", "DELETE", "PUT", "HEAD", "OPTIONS"]
+        );
+        assert_eq!(
+            manifest.allowed_methods("/files/a/b.txt").unwrap(),
+            vec!["GET", "HEAD", "OPTIONS"]
+        );
+        assert_eq!(manifest.allowed_methods("/files"), None);
+        assert_eq!(manifest.allowed_methods("/items/42/parts"), None);
+        assert_eq!(manifest.allowed_methods("/"), None);
+    }
+
+    #[test]
+    fn test_routes_response() {
+        let options = RunOptions::default().route_manifest(manifest().debug_endpoint(true));
+        let options_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(r#""rawPath":"/""#, r#""rawPath":"/items/42""#)
+            .replace(r#""method":"GET""#, r#""method":"OPTIONS""#);
+        let event: LambdaHttpEvent = serde_json::from_str(&options_str).unwrap();
+        let res = routes_response(&event, &options).unwrap();
+        assert_eq!(res.status, 204);
+        assert_eq!(res.header("allow"), Some("GET, DELETE, PUT, HEAD, OPTIONS"));
+
+        // Other methods and undeclared paths go to the web framework
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        assert!(routes_response(&event, &options).is_none());
+        let event: LambdaHttpEvent =
+            serde_json::from_str(&options_str.replace("/items/42", "/other")).unwrap();
+        assert!(routes_response(&event, &options).is_none());
+
+        // Route listing
+        let list_str = API_GATEWAY_V2_GET_ROOT_NOQUERY
+            .replace(r#""rawPath":"/""#, r#""rawPath":"/__lambda_web/routes""#);
+        let event: LambdaHttpEvent = serde_json::from_str(&list_str).unwrap();
+        let res = routes_response(&event, &options).unwrap();
+        let body: serde_json::Value = serde_json::from_str(&res.body).unwrap();
+        assert_eq!(body["routes"][0]["path"], "/items");
+        assert_eq!(body["routes"][0]["methods"], json!(["GET", "POST"]));
+        let options = RunOptions::default().route_manifest(manifest());
+        assert!(routes_response(&event, &options).is_none());
+    }
+}
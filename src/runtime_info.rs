@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Runtime environment report
+//! Details needed to diagnose 404/400 problems, e.g. wrong payload format
+//!
+use crate::execution_env::ExecutionEnv;
+use crate::request::LambdaHttpEvent;
+use std::sync::OnceLock;
+
+/// Payload format of Lambda events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadFormat {
+    /// API Gateway HTTP API, payload format version 2.0
+    HttpApiV2,
+    /// Lambda function URL (same as payload format version 2.0)
+    FunctionUrl,
+    /// API Gateway REST API, or HTTP API with payload format version 1.0
+    RestApi,
+    /// Application Load Balancer with multi-value headers
+    Alb,
+}
+
+/// Runtime environment report, returned by `runtime_info()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeInfo {
+    /// CPU architecture of this binary, e.g. `x86_64`, `aarch64`
+    pub arch: &'static str,
+    /// Detected execution environment
+    pub execution_env: ExecutionEnv,
+    /// AWS region
+    pub region: Option<String>,
+    /// Lambda function name
+    pub function_name: Option<String>,
+    /// Configured memory size in MB
+    pub memory_size_mb: Option<u32>,
+    /// Payload format of the first event, None before the first event
+    pub payload_format: Option<PayloadFormat>,
+}
+
+impl std::fmt::Display for RuntimeInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "arch={} env={:?} region={} function={} memory={} payload={}",
+            self.arch,
+            self.execution_env,
+            self.region.as_deref().unwrap_or("-"),
+            self.function_name.as_deref().unwrap_or("-"),
+            self.memory_size_mb
+                .map(|m| format!("{}MB", m))
+                .unwrap_or_else(|| "-".to_string()),
+            self.payload_format
+                .map(|p| format!("{:?}", p))
+                .unwrap_or_else(|| "-".to_string()),
+        )
+    }
+}
+
+/// Payload format of the first event
+static PAYLOAD_FORMAT: OnceLock<PayloadFormat> = OnceLock::new();
+
+/// Report of the runtime environment, to be included in support issues
+///
+/// ```
+/// println!("{}", lambda_web::runtime_info());
+/// ```
+pub fn runtime_info() -> RuntimeInfo {
+    RuntimeInfo {
+        arch: std::env::consts::ARCH,
+        execution_env: ExecutionEnv::detect(),
+        region: std::env::var("AWS_REGION").ok(),
+        function_name: std::env::var("AWS_LAMBDA_FUNCTION_NAME").ok(),
+        memory_size_mb: std::env::var("AWS_LAMBDA_FUNCTION_MEMORY_SIZE")
+            .ok()
+            .and_then(|m| m.parse().ok()),
+        payload_format: PAYLOAD_FORMAT.get().copied(),
+    }
+}
+
+/// Record payload format of the event, returns true for the first event
+pub(crate) fn observe_event(event: &LambdaHttpEvent) -> bool {
+    PAYLOAD_FORMAT.set(event.payload_format()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_info() {
+        let info = runtime_info();
+        assert_eq!(info.arch, std::env::consts::ARCH);
+        assert!(info.to_string().starts_with("arch="));
+    }
+}
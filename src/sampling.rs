@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Request log sampling
+//! Logs a sampled portion of requests, with the decision shared with app code
+//!
+use crate::request::LambdaHttpEvent;
+
+/// Sampling of request logs, set by `RunOptions::access_log`
+///
+/// The decision is made from the hash of the request ID,
+/// so it is the same for the access log of this crate and app logs reading `Sampled`.
+///
+/// ```
+/// use lambda_web::{LogSampling, RunOptions};
+///
+/// // 1% of requests, but all requests to /api/checkout
+/// let options = RunOptions::default()
+///     .access_log(LogSampling::rate(0.01).path("/api/checkout", 1.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogSampling {
+    rate: f64,
+    paths: Vec<(String, f64)>,
+}
+
+impl LogSampling {
+    /// Sample this ratio of requests, 0.0 to 1.0
+    pub fn rate(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Log every request
+    pub fn all() -> Self {
+        Self::rate(1.0)
+    }
+
+    /// Sample requests under the path prefix at another ratio, the longest prefix wins
+    pub fn path<S: Into<String>>(mut self, prefix: S, rate: f64) -> Self {
+        self.paths.push((prefix.into(), rate.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Sampling ratio of the path
+    fn rate_of(&self, path: &str) -> f64 {
+        self.paths
+            .iter()
+            .filter(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .map(|rest| prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'))
+                    .unwrap_or(false)
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, rate)| *rate)
+            .unwrap_or(self.rate)
+    }
+
+    /// Is the request sampled?
+    pub(crate) fn sample(&self, event: &LambdaHttpEvent) -> Sampled {
+        let path_query = event.path_query();
+        let path = path_query.split('?').next().unwrap_or_default();
+        let rate = self.rate_of(path);
+        if rate >= 1.0 {
+            return Sampled(true);
+        }
+        if rate <= 0.0 {
+            return Sampled(false);
+        }
+        // Request ID, or trace ID of ALB
+        let key = event
+            .request_context()
+            .request_id
+            .or_else(|| event.header("x-amzn-trace-id").map(String::from))
+            .unwrap_or(path_query);
+        Sampled(unit_hash(&key) < rate)
+    }
+}
+
+/// Hash of the key, uniformly distributed in [0.0, 1.0)
+fn unit_hash(key: &str) -> f64 {
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    // 53 bits of mantissa
+    (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Sampling decision of the request, attached to hyper and Actix-web request extensions,
+/// and to Rocket request local cache as `Option<Sampled>`
+///
+/// Apps can log details only for sampled requests, consistently with the access log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampled(bool);
+
+impl Sampled {
+    /// Is this request sampled for logging?
+    pub fn is_sampled(&self) -> bool {
+        self.0
+    }
+}
+
+/// Access log of a sampled request
+#[derive(Debug)]
+pub(crate) struct AccessLog {
+    method: String,
+    path: String,
+    start: std::time::Instant,
+}
+
+impl AccessLog {
+    /// Start access log when sampled
+    pub(crate) fn start(event: &LambdaHttpEvent, sampling: Option<&LogSampling>) -> Option<Self> {
+        if !sampling?.sample(event).is_sampled() {
+            return None;
+        }
+        let path_query = event.path_query();
+        Some(Self {
+            method: event.method().to_string(),
+            // Query strings may contain secrets, not logged
+            path: path_query.split('?').next().unwrap_or_default().to_string(),
+            start: std::time::Instant::now(),
+        })
+    }
+
+    /// Log the request with response status and elapsed time
+    pub(crate) fn finish(&self, status: u16) {
+        tracing::info!(
+            "{} {} {} {}ms",
+            self.method,
+            self.path,
+            status,
+            self.start.elapsed().as_millis()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_rate_of() {
+        let sampling = LogSampling::rate(0.1)
+            .path("/api", 0.5)
+            .path("/api/checkout", 1.0);
+        assert_eq!(sampling.rate_of("/"), 0.1);
+        assert_eq!(sampling.rate_of("/api"), 0.5);
+        assert_eq!(sampling.rate_of("/api/items"), 0.5);
+        assert_eq!(sampling.rate_of("/api/checkout/confirm"), 1.0);
+        assert_eq!(sampling.rate_of("/apis"), 0.1);
+    }
+
+    #[test]
+    fn test_sample() {
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_WITH_CONTEXT).unwrap();
+        assert!(LogSampling::all().sample(&event).is_sampled());
+        assert!(!LogSampling::rate(0.0).sample(&event).is_sampled());
+
+        // Same decision for the same request
+        let sampling = LogSampling::rate(0.5);
+        assert_eq!(sampling.sample(&event), sampling.sample(&event));
+
+        let sampled = (0..10_000)
+            .filter(|n| unit_hash(&format!("request-{}", n)) < 0.2)
+            .count();
+        assert!((1_700..2_300).contains(&sampled));
+    }
+
+    #[test]
+    fn test_access_log() {
+        let event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_GET_SOMEWHERE_ONEQUERY).unwrap();
+        assert!(AccessLog::start(&event, None).is_none());
+        assert!(AccessLog::start(&event, Some(&LogSampling::rate(0.0))).is_none());
+        let log = AccessLog::start(&event, Some(&LogSampling::all())).unwrap();
+        assert_eq!(log.method, "GET");
+        assert_eq!(log.path, "/somewhere");
+    }
+}
@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Scratch buffer
+//! Reused string buffer for request URIs and cookie headers, to avoid per-invocation allocations
+//!
+use std::cell::RefCell;
+
+/// Larger buffers are released after use, not to hold memory of an unusual request
+const MAX_RETAINED_CAPACITY: usize = 64 * 1024;
+
+thread_local! {
+    static SCRATCH: RefCell<String> = RefCell::new(String::new());
+}
+
+/// Run `f` with the cleared scratch buffer of this thread
+///
+/// Lambda runtime processes invocations one by one,
+/// so the buffer is effectively scoped to the invocation being converted.
+/// Nested calls fall back to a fresh buffer.
+pub(crate) fn with_scratch<R>(f: impl FnOnce(&mut String) -> R) -> R {
+    SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+        Ok(mut buf) => {
+            buf.clear();
+            let result = f(&mut buf);
+            if MAX_RETAINED_CAPACITY < buf.capacity() {
+                *buf = String::new();
+            }
+            result
+        }
+        Err(_) => f(&mut String::new()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_scratch() {
+        let ptr = with_scratch(|buf| {
+            buf.push_str("/path?query");
+            buf.as_ptr()
+        });
+        // Reused and cleared
+        with_scratch(|buf| {
+            assert!(buf.is_empty());
+            assert!("/path?query".len() <= buf.capacity());
+            buf.push('/');
+            assert_eq!(buf.as_ptr(), ptr);
+        });
+
+        // Nested
+        with_scratch(|outer| {
+            outer.push_str("outer");
+            with_scratch(|inner| {
+                assert!(inner.is_empty());
+                inner.push_str("inner");
+            });
+            assert_eq!(outer, "outer");
+        });
+
+        // Large buffer is released
+        with_scratch(|buf| buf.extend(std::iter::repeat('a').take(MAX_RETAINED_CAPACITY + 1)));
+        with_scratch(|buf| assert!(buf.capacity() <= MAX_RETAINED_CAPACITY));
+    }
+}
@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Self-check of Lambda response JSON
+//! Finds malformed proxy responses, which API Gateway answers with
+//! 502 "Internal server error" without telling the reason.
+//!
+use crate::response::MAX_RESPONSE_PAYLOAD_SIZE;
+use serde_json::Value;
+
+/// Environment variable to enable self-check of every response,
+/// set `LAMBDA_WEB_SELF_CHECK=1` to log problems as `tracing` warnings.
+pub const SELF_CHECK_ENV: &str = "LAMBDA_WEB_SELF_CHECK";
+
+/// Problems found by `self_check()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfCheckError {
+    /// Description of each problem
+    pub problems: Vec<String>,
+}
+
+impl std::fmt::Display for SelfCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Malformed Lambda proxy response: {}",
+            self.problems.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for SelfCheckError {}
+
+/// Validate Lambda response JSON against API Gateway proxy integration contract.
+///
+/// Checks field types, status code range, base64 body,
+/// header name and value characters, and payload size.
+///
+/// ```
+/// use serde_json::json;
+///
+/// let response = json!({
+///     "isBase64Encoded": false,
+///     "statusCode": 200,
+///     "headers": { "content-type": "text/plain" },
+///     "body": "Hello"
+/// });
+/// assert!(lambda_web::self_check(&response).is_ok());
+///
+/// let response = json!({ "statusCode": "200", "body": 123 });
+/// assert!(lambda_web::self_check(&response).is_err());
+/// ```
+pub fn self_check(response: &Value) -> Result<(), SelfCheckError> {
+    let mut problems = Vec::new();
+
+    let obj = match response.as_object() {
+        Some(obj) => obj,
+        None => {
+            problems.push("response is not a JSON object".to_string());
+            return Err(SelfCheckError { problems });
+        }
+    };
+
+    // statusCode
+    match obj.get("statusCode").map(|v| v.as_u64()) {
+        Some(Some(code)) if (100..=599).contains(&code) => {}
+        Some(Some(code)) => problems.push(format!("statusCode {} is out of range", code)),
+        Some(None) => problems.push("statusCode is not an integer".to_string()),
+        None => problems.push("statusCode is missing".to_string()),
+    }
+
+    // isBase64Encoded & body
+    let is_base64 = match obj.get("isBase64Encoded") {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(_) => {
+            problems.push("isBase64Encoded is not a boolean".to_string());
+            false
+        }
+    };
+    match obj.get("body") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(body)) => {
+            if is_base64 && base64::decode(body).is_err() {
+                problems.push("body is not valid base64".to_string());
+            }
+        }
+        Some(_) => problems.push("body is not a string".to_string()),
+    }
+
+    // headers
+    match obj.get("headers") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(headers)) => {
+            for (name, value) in headers {
+                check_header_name(name, &mut problems);
+                match value {
+                    Value::String(value) => check_header_value(name, value, &mut problems),
+                    _ => problems.push(format!("headers.{} is not a string", name)),
+                }
+            }
+        }
+        Some(_) => problems.push("headers is not an object".to_string()),
+    }
+
+    // multiValueHeaders
+    match obj.get("multiValueHeaders") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(headers)) => {
+            for (name, values) in headers {
+                check_header_name(name, &mut problems);
+                match values.as_array() {
+                    Some(values) => {
+                        for value in values {
+                            match value {
+                                Value::String(value) => {
+                                    check_header_value(name, value, &mut problems)
+                                }
+                                _ => problems.push(format!(
+                                    "multiValueHeaders.{} contains non-string value",
+                                    name
+                                )),
+                            }
+                        }
+                    }
+                    None => problems.push(format!("multiValueHeaders.{} is not an array", name)),
+                }
+            }
+        }
+        Some(_) => problems.push("multiValueHeaders is not an object".to_string()),
+    }
+
+    // cookies (HTTP API v2)
+    match obj.get("cookies") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(cookies)) => {
+            for cookie in cookies {
+                match cookie {
+                    Value::String(cookie) => check_header_value("cookies", cookie, &mut problems),
+                    _ => problems.push("cookies contains non-string value".to_string()),
+                }
+            }
+        }
+        Some(_) => problems.push("cookies is not an array".to_string()),
+    }
+
+    // Payload size
+    let size = serde_json::to_vec(response).map(|v| v.len()).unwrap_or(0);
+    if MAX_RESPONSE_PAYLOAD_SIZE < size {
+        problems.push(format!(
+            "response size {} bytes exceeds Lambda limit {} bytes",
+            size, MAX_RESPONSE_PAYLOAD_SIZE
+        ));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(SelfCheckError { problems })
+    }
+}
+
+/// Header name must be RFC 7230 token
+fn check_header_name(name: &str, problems: &mut Vec<String>) {
+    let is_tchar = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(is_tchar) {
+        problems.push(format!(
+            "header name {:?} contains invalid characters",
+            name
+        ));
+    }
+}
+
+/// Header value must be visible ASCII, space or tab
+fn check_header_value(name: &str, value: &str, problems: &mut Vec<String>) {
+    let is_valid = |c: char| c == ' ' || c == '\t' || c.is_ascii_graphic();
+    if !value.chars().all(is_valid) {
+        problems.push(format!("{} header value contains invalid characters", name));
+    }
+}
+
+/// Is self-check of every response enabled by environment variable?
+pub(crate) fn self_check_enabled() -> bool {
+    std::env::var(SELF_CHECK_ENV)
+        .map(|v| !v.is_empty() && v != "0")
+        .unwrap_or(false)
+}
+
+/// Log problems of the response
+pub(crate) fn log_self_check(response: &Value) {
+    if let Err(err) = self_check(response) {
+        tracing::warn!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_self_check() {
+        let res = json!({
+            "isBase64Encoded": true,
+            "statusCode": 200,
+            "headers": { "content-type": "text/plain" },
+            "cookies": ["key=value"],
+            "body": base64::encode("Hello")
+        });
+        assert_eq!(self_check(&res), Ok(()));
+
+        let res = json!({
+            "isBase64Encoded": true,
+            "statusCode": 200,
+            "multiValueHeaders": { "x-header": ["value1", "value2"] },
+            "body": ""
+        });
+        assert_eq!(self_check(&res), Ok(()));
+
+        let res = json!({
+            "isBase64Encoded": "true",
+            "statusCode": 1000,
+            "headers": { "bad header": "value\r\n", "x-number": 1 },
+            "body": 123
+        });
+        let err = self_check(&res).unwrap_err();
+        assert_eq!(err.problems.len(), 6);
+
+        let res = json!({"statusCode": 200, "isBase64Encoded": true, "body": "not base64!"});
+        assert_eq!(
+            self_check(&res).unwrap_err().problems,
+            vec!["body is not valid base64".to_string()]
+        );
+
+        assert!(self_check(&json!("string")).is_err());
+        assert!(self_check(&json!({})).is_err());
+    }
+}
@@ -0,0 +1,716 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Server-side sessions
+//! Session cookie handled by this crate, session data kept in a store outside Lambda containers
+//!
+use crate::idempotency::StoreFuture;
+use crate::request::LambdaHttpEvent;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default name of the session cookie
+pub const DEFAULT_SESSION_COOKIE: &str = "lambda-web-session";
+
+/// Data of a session, JSON object
+pub type SessionData = serde_json::Map<String, serde_json::Value>;
+
+/// Storage of session data
+///
+/// Implement this trait to keep sessions in ElastiCache, etc.,
+/// or use `DynamoDbSessionStore` with `dynamodb` feature.
+/// Lambda containers come and go, so sessions must outlive them.
+pub trait SessionStore: Send + Sync {
+    /// Load session data, None if not stored or expired
+    fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<SessionData>>;
+
+    /// Store session data for `ttl`
+    fn save<'a>(&'a self, id: &'a str, data: &'a SessionData, ttl: Duration)
+        -> StoreFuture<'a, ()>;
+
+    /// Delete session data
+    fn delete<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()>;
+}
+
+/// In-memory session store
+///
+/// Sessions live only in a warm Lambda container, for local development and tests.
+#[derive(Default)]
+pub struct MemorySessionStore {
+    entries: Mutex<HashMap<String, (Instant, SessionData)>>,
+}
+
+impl MemorySessionStore {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<SessionData>> {
+        let data = self.entries.lock().ok().and_then(|entries| {
+            entries
+                .get(id)
+                .filter(|(expires, _)| Instant::now() < *expires)
+                .map(|(_, data)| data.clone())
+        });
+        Box::pin(async move { data })
+    }
+
+    fn save<'a>(
+        &'a self,
+        id: &'a str,
+        data: &'a SessionData,
+        ttl: Duration,
+    ) -> StoreFuture<'a, ()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            // Remove expired sessions
+            let now = Instant::now();
+            entries.retain(|_, (expires, _)| now < *expires);
+            entries.insert(id.to_string(), (now + ttl, data.clone()));
+        }
+        Box::pin(async {})
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(id);
+        }
+        Box::pin(async {})
+    }
+}
+
+/// Server-side sessions, set by `RunOptions::sessions`
+///
+/// Each hyper request gets a `Session` in its extensions.
+/// The session is loaded from the store on first access,
+/// and saved with `Set-Cookie` after the response only when it was modified.
+///
+/// `tower-sessions` requires http 1.0 and does not work with hyper 0.14 based frameworks,
+/// this is the counterpart for axum 0.6 and warp 0.3.
+/// Sessions are not attached to Actix Web and Rocket requests,
+/// use `actix-session` or Rocket private cookies there.
+///
+/// ```no_run
+/// use axum::{routing::get, Extension, Router};
+/// use lambda_web::{
+///     run_hyper_on_lambda_with_options, LambdaError, MemorySessionStore, RunOptions, Session,
+///     Sessions,
+/// };
+///
+/// async fn count(Extension(session): Extension<Session>) -> String {
+///     let visits = session.get::<u32>("visits").await.unwrap_or(0) + 1;
+///     session.insert("visits", visits).await.ok();
+///     format!("{} visits", visits)
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), LambdaError> {
+///     let app = Router::new().route("/", get(count));
+///     let options = RunOptions::default().sessions(Sessions::new(MemorySessionStore::new()));
+///     run_hyper_on_lambda_with_options(app, options).await
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Sessions {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl Sessions {
+    /// Sessions kept in the store
+    pub fn new<S: SessionStore + 'static>(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            cookie_name: DEFAULT_SESSION_COOKIE.to_string(),
+            ttl: Duration::from_secs(86400),
+            secure: true,
+        }
+    }
+
+    /// Name of the session cookie, `lambda-web-session` by default
+    pub fn cookie_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// Lifetime of sessions and the cookie, renewed on every save, 1 day by default
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// `Secure` attribute of the cookie, true by default.
+    /// Disable it only for local HTTP tests.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Session of the request, identified by the cookie
+    pub(crate) fn session(&self, event: &LambdaHttpEvent) -> Session {
+        let id = crate::CookieJar::from_event(event)
+            .get_raw(&self.cookie_name)
+            .filter(|id| is_valid_id(id))
+            .map(String::from);
+        Session {
+            sessions: self.clone(),
+            state: Arc::new(Mutex::new(SessionState {
+                id,
+                stale_id: None,
+                data: None,
+                modified: false,
+                destroyed: false,
+            })),
+        }
+    }
+
+    /// `Set-Cookie` header value
+    fn cookie(&self, id: &str, max_age: u64) -> String {
+        format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly;{} SameSite=Lax",
+            self.cookie_name,
+            id,
+            max_age,
+            if self.secure { " Secure;" } else { "" }
+        )
+    }
+}
+
+/// Session of a request, attached to hyper request extensions
+///
+/// Clones share the same session.
+#[derive(Clone)]
+pub struct Session {
+    sessions: Sessions,
+    state: Arc<Mutex<SessionState>>,
+}
+
+struct SessionState {
+    id: Option<String>,
+    /// Previous ID deleted from the store on save, set by `cycle_id` or `destroy`
+    stale_id: Option<String>,
+    /// None until loaded from the store
+    data: Option<SessionData>,
+    modified: bool,
+    destroyed: bool,
+}
+
+impl SessionState {
+    /// Forget the current ID, to be deleted from the store on save
+    fn retire_id(&mut self) {
+        let id = self.id.take();
+        self.stale_id = self.stale_id.take().or(id);
+    }
+}
+
+impl Session {
+    /// Session ID, None for new sessions not saved yet
+    pub fn id(&self) -> Option<String> {
+        self.lock().id.clone()
+    }
+
+    /// Value of the key, None when missing or not deserializable to `T`
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.load().await;
+        let value = self.lock().data.as_ref()?.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    /// Set the value of the key
+    pub async fn insert<T: Serialize>(&self, key: &str, value: T) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_value(value)?;
+        self.load().await;
+        let mut state = self.lock();
+        state
+            .data
+            .get_or_insert_with(SessionData::new)
+            .insert(key.to_string(), value);
+        state.modified = true;
+        // Writes after destroy() start a new session
+        state.destroyed = false;
+        Ok(())
+    }
+
+    /// Remove the key, returns the removed value
+    pub async fn remove(&self, key: &str) -> Option<serde_json::Value> {
+        self.load().await;
+        let mut state = self.lock();
+        let removed = state.data.as_mut()?.remove(key);
+        state.modified |= removed.is_some();
+        removed
+    }
+
+    /// Delete the session from the store and expire the cookie, e.g. on logout.
+    /// Values inserted after this are saved in a new session.
+    pub fn destroy(&self) {
+        let mut state = self.lock();
+        state.retire_id();
+        state.data = Some(SessionData::new());
+        state.modified = false;
+        state.destroyed = true;
+    }
+
+    /// Move the session data to a new ID and delete the old one on save,
+    /// e.g. on login, so that IDs planted by others are not kept.
+    pub async fn cycle_id(&self) {
+        self.load().await;
+        let mut state = self.lock();
+        state.retire_id();
+        state.modified = true;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SessionState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Load session data from the store on first access
+    async fn load(&self) {
+        let id = {
+            let state = self.lock();
+            if state.data.is_some() {
+                return;
+            }
+            state.id.clone()
+        };
+        // The lock is not held across await
+        let loaded = match &id {
+            Some(id) => self.sessions.store.load(id).await,
+            None => None,
+        };
+        let mut state = self.lock();
+        if state.data.is_none() {
+            if loaded.is_none() {
+                // Expired or unknown ID, a new ID is issued on save
+                state.id = None;
+            }
+            state.data = Some(loaded.unwrap_or_default());
+        }
+    }
+
+    /// Save or delete the session after the response,
+    /// returns `Set-Cookie` header value when the cookie changes
+    pub(crate) async fn commit(&self) -> Option<String> {
+        let (stale_id, id, data) = {
+            let mut state = self.lock();
+            let stale_id = state.stale_id.take();
+            if state.destroyed {
+                (stale_id, None, None)
+            } else if state.modified {
+                let id = match &state.id {
+                    Some(id) => id.clone(),
+                    None => match new_session_id() {
+                        Ok(id) => state.id.insert(id).clone(),
+                        Err(err) => {
+                            tracing::error!("session is not saved without random ID, {}", err);
+                            return None;
+                        }
+                    },
+                };
+                (stale_id, Some(id), state.data.clone())
+            } else {
+                return None;
+            }
+        };
+        let sessions = &self.sessions;
+        if let Some(stale_id) = &stale_id {
+            sessions.store.delete(stale_id).await;
+        }
+        let id = match id {
+            Some(id) => id,
+            // Destroyed
+            None => return Some(sessions.cookie("", 0)),
+        };
+        sessions
+            .store
+            .save(&id, &data.unwrap_or_default(), sessions.ttl)
+            .await;
+        Some(sessions.cookie(&id, sessions.ttl.as_secs()))
+    }
+}
+
+/// Save the session of the request, and add `Set-Cookie` to the hyper response headers
+pub(crate) async fn commit_session(session: Option<&Session>, headers: &mut hyper::HeaderMap) {
+    let cookie = match session {
+        Some(session) => session.commit().await,
+        None => None,
+    };
+    if let Some(cookie) = cookie.and_then(|c| hyper::header::HeaderValue::from_str(&c).ok()) {
+        headers.append(hyper::header::SET_COOKIE, cookie);
+    }
+}
+
+#[cfg(feature = "dynamodb")]
+pub use dynamodb::DynamoDbSessionStore;
+
+#[cfg(feature = "dynamodb")]
+mod dynamodb {
+    use super::*;
+    use crate::sigv4::{AwsTransport, Credentials, SignedRequest};
+    use lambda_runtime::Error as LambdaError;
+    use serde_json::{json, Value};
+    use std::time::SystemTime;
+
+    /// Session store of a DynamoDB table, signed by SigV4 and sent by `AwsTransport`
+    ///
+    /// The table has `sessionId` string partition key, the SHA-256 of the session ID,
+    /// so that readers of the table cannot take over sessions.
+    /// Items have `data` (JSON of `SessionData`) and `expiresAt`
+    /// (Unix time in seconds, enable DynamoDB TTL on it) attributes.
+    /// Expired items are not loaded before DynamoDB TTL deletes them.
+    /// The execution role needs `dynamodb:GetItem`, `PutItem` and `DeleteItem` permissions.
+    ///
+    /// ```no_run
+    /// use lambda_web::{AwsTransport, DynamoDbSessionStore, RunOptions, Sessions};
+    /// # fn example(transport: impl AwsTransport + 'static) {
+    /// let store = DynamoDbSessionStore::new("sessions").transport(transport);
+    /// let options = RunOptions::default().sessions(Sessions::new(store));
+    /// # }
+    /// ```
+    #[derive(Clone)]
+    pub struct DynamoDbSessionStore {
+        table: String,
+        region: String,
+        credentials: Option<Credentials>,
+        transport: Option<Arc<dyn AwsTransport>>,
+    }
+
+    impl DynamoDbSessionStore {
+        /// Store in the table, in `AWS_REGION` with the execution role credentials
+        pub fn new<S: Into<String>>(table: S) -> Self {
+            Self {
+                table: table.into(),
+                region: std::env::var("AWS_REGION").unwrap_or_default(),
+                credentials: Credentials::from_env(),
+                transport: None,
+            }
+        }
+
+        /// Override the region
+        pub fn region<S: Into<String>>(mut self, region: S) -> Self {
+            self.region = region.into();
+            self
+        }
+
+        /// Override the credentials
+        pub fn credentials(mut self, credentials: Credentials) -> Self {
+            self.credentials = Some(credentials);
+            self
+        }
+
+        /// HTTPS client to send requests
+        pub fn transport<T: AwsTransport + 'static>(mut self, transport: T) -> Self {
+            self.transport = Some(Arc::new(transport));
+            self
+        }
+
+        fn item(
+            id: &str,
+            data: &SessionData,
+            ttl: Duration,
+            now: SystemTime,
+        ) -> Result<Value, LambdaError> {
+            let expires_at = unix_time(now) + ttl.as_secs();
+            Ok(json!({
+                "sessionId": {"S": hashed_id(id)},
+                "data": {"S": serde_json::to_string(data)?},
+                "expiresAt": {"N": expires_at.to_string()}
+            }))
+        }
+
+        fn sign(
+            &self,
+            target: &str,
+            body: &Value,
+            now: SystemTime,
+        ) -> Result<SignedRequest, LambdaError> {
+            let credentials = self
+                .credentials
+                .as_ref()
+                .ok_or("no AWS credentials to sign DynamoDB request")?;
+            let host = format!("dynamodb.{}.amazonaws.com", self.region);
+            let mut request = crate::sigv4::sign(
+                "POST",
+                &host,
+                "/",
+                body.to_string().into_bytes(),
+                &self.region,
+                "dynamodb",
+                credentials,
+                now,
+            );
+            request.headers.push((
+                "content-type".to_string(),
+                "application/x-amz-json-1.0".to_string(),
+            ));
+            request.headers.push((
+                "x-amz-target".to_string(),
+                format!("DynamoDB_20120810.{}", target),
+            ));
+            Ok(request)
+        }
+
+        async fn call(&self, target: &str, body: Value) -> Result<Value, LambdaError> {
+            let transport = self
+                .transport
+                .as_ref()
+                .ok_or("no AwsTransport is configured")?;
+            let response = transport
+                .send(self.sign(target, &body, SystemTime::now())?)
+                .await?;
+            if !(200..300).contains(&response.status) {
+                return Err(format!(
+                    "DynamoDB {} failed with {}: {}",
+                    target,
+                    response.status,
+                    String::from_utf8_lossy(&response.body)
+                )
+                .into());
+            }
+            Ok(serde_json::from_slice(&response.body)?)
+        }
+
+        async fn try_load(&self, id: &str) -> Result<Option<SessionData>, LambdaError> {
+            let body = json!({
+                "TableName": self.table,
+                "Key": {"sessionId": {"S": hashed_id(id)}},
+                "ConsistentRead": true
+            });
+            let response = self.call("GetItem", body).await?;
+            Ok(data_from_item(&response["Item"], SystemTime::now()))
+        }
+    }
+
+    fn unix_time(now: SystemTime) -> u64 {
+        now.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Partition key, session IDs are not stored as is
+    fn hashed_id(id: &str) -> String {
+        crate::checksum::BodyChecksum::Sha256.hex_digest(id.as_bytes())
+    }
+
+    /// Session data of the item, None when missing, expired or unreadable
+    fn data_from_item(item: &Value, now: SystemTime) -> Option<SessionData> {
+        let expires_at: u64 = item["expiresAt"]["N"].as_str()?.parse().ok()?;
+        if expires_at <= unix_time(now) {
+            // Not deleted by DynamoDB TTL yet
+            return None;
+        }
+        serde_json::from_str(item["data"]["S"].as_str()?).ok()
+    }
+
+    impl SessionStore for DynamoDbSessionStore {
+        fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<SessionData>> {
+            Box::pin(async move {
+                match self.try_load(id).await {
+                    Ok(data) => data,
+                    Err(err) => {
+                        // Handled as a new session
+                        tracing::warn!("failed to load session, {}", err);
+                        None
+                    }
+                }
+            })
+        }
+
+        fn save<'a>(
+            &'a self,
+            id: &'a str,
+            data: &'a SessionData,
+            ttl: Duration,
+        ) -> StoreFuture<'a, ()> {
+            Box::pin(async move {
+                let result = match Self::item(id, data, ttl, SystemTime::now()) {
+                    Ok(item) => {
+                        let body = json!({ "TableName": self.table, "Item": item });
+                        self.call("PutItem", body).await.map(|_| ())
+                    }
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = result {
+                    tracing::error!("failed to save session, {}", err);
+                }
+            })
+        }
+
+        fn delete<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()> {
+            Box::pin(async move {
+                let body = json!({
+                    "TableName": self.table,
+                    "Key": {"sessionId": {"S": hashed_id(id)}}
+                });
+                if let Err(err) = self.call("DeleteItem", body).await {
+                    tracing::warn!("failed to delete session, {}", err);
+                }
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_item() {
+            let mut data = SessionData::new();
+            data.insert("visits".to_string(), json!(3));
+            let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1760000000);
+            let id = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+            let item =
+                DynamoDbSessionStore::item(id, &data, Duration::from_secs(86400), now).unwrap();
+            assert_eq!(item["sessionId"]["S"], hashed_id(id));
+            assert_ne!(item["sessionId"]["S"], id);
+            assert_eq!(item["expiresAt"]["N"], "1760086400");
+            assert_eq!(data_from_item(&item, now), Some(data));
+
+            // Expired, but not deleted by TTL yet
+            let later = now + Duration::from_secs(86400);
+            assert_eq!(data_from_item(&item, later), None);
+            assert_eq!(data_from_item(&Value::Null, now), None);
+        }
+
+        #[test]
+        fn test_sign() {
+            let store = DynamoDbSessionStore::new("sessions")
+                .region("ap-northeast-1")
+                .credentials(Credentials {
+                    access_key_id: "AKIDEXAMPLE".to_string(),
+                    secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                    session_token: None,
+                });
+            let request = store
+                .sign(
+                    "GetItem",
+                    &json!({"TableName": "sessions"}),
+                    SystemTime::now(),
+                )
+                .unwrap();
+            assert_eq!(
+                request.url,
+                "https://dynamodb.ap-northeast-1.amazonaws.com/"
+            );
+            assert!(request.headers.contains(&(
+                "x-amz-target".to_string(),
+                "DynamoDB_20120810.GetItem".to_string()
+            )));
+        }
+    }
+}
+
+/// Session IDs are 256 bit random hex strings
+fn is_valid_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// New random session ID, from the OS random number generator
+fn new_session_id() -> Result<String, getrandom::Error> {
+    let mut random = [0u8; 32];
+    getrandom::getrandom(&mut random)?;
+    Ok(random.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    fn event_with_cookie(cookie: &str) -> String {
+        API_GATEWAY_V2_GET_TWO_COOKIES.replace(
+            r#""cookies":["#,
+            &format!(r#""cookies":["lambda-web-session={}","#, cookie),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_session() {
+        let sessions = Sessions::new(MemorySessionStore::new());
+
+        // New session, saved when modified
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let session = sessions.session(&event);
+        assert_eq!(session.get::<u32>("visits").await, None);
+        assert_eq!(session.commit().await, None);
+        session.insert("visits", 1).await.unwrap();
+        let cookie = session.commit().await.unwrap();
+        let id = session.id().unwrap();
+        assert!(is_valid_id(&id));
+        assert!(cookie.starts_with(&format!("lambda-web-session={};", id)));
+        assert!(cookie.contains("HttpOnly; Secure;"));
+
+        // Loaded by the cookie
+        let event_str = event_with_cookie(&id);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let session = sessions.session(&event);
+        assert_eq!(session.get::<u32>("visits").await, Some(1));
+        assert_eq!(session.remove("visits").await, Some(serde_json::json!(1)));
+        assert!(session.commit().await.is_some());
+
+        // Destroyed
+        let session = sessions.session(&event);
+        session.destroy();
+        let cookie = session.commit().await.unwrap();
+        assert!(cookie.starts_with("lambda-web-session=; Path=/; Max-Age=0;"));
+        assert!(sessions.store.load(&id).await.is_none());
+
+        // Unknown ID is not reused
+        let session = sessions.session(&event);
+        session.insert("key", "value").await.unwrap();
+        session.commit().await.unwrap();
+        assert_ne!(session.id().unwrap(), id);
+    }
+
+    #[tokio::test]
+    async fn test_cycle_id() {
+        let sessions = Sessions::new(MemorySessionStore::new());
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        let session = sessions.session(&event);
+        session.insert("cart", 3).await.unwrap();
+        session.commit().await.unwrap();
+        let id = session.id().unwrap();
+
+        // Data moves to a new ID, the old one is deleted
+        let event_str = event_with_cookie(&id);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let session = sessions.session(&event);
+        session.cycle_id().await;
+        let cookie = session.commit().await.unwrap();
+        let new_id = session.id().unwrap();
+        assert_ne!(new_id, id);
+        assert!(cookie.starts_with(&format!("lambda-web-session={};", new_id)));
+        assert!(sessions.store.load(&id).await.is_none());
+        assert_eq!(
+            sessions.store.load(&new_id).await.unwrap()["cart"],
+            serde_json::json!(3)
+        );
+
+        // Insert after destroy is saved in a new session
+        let event_str = event_with_cookie(&new_id);
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        let session = sessions.session(&event);
+        session.destroy();
+        session.insert("user", "guest").await.unwrap();
+        session.commit().await.unwrap();
+        let guest_id = session.id().unwrap();
+        assert_ne!(guest_id, new_id);
+        assert!(sessions.store.load(&new_id).await.is_none());
+        assert_eq!(session.get::<String>("user").await.unwrap(), "guest");
+        assert!(sessions.store.load(&guest_id).await.is_some());
+    }
+
+    #[test]
+    fn test_invalid_cookie() {
+        let sessions = Sessions::new(MemorySessionStore::new());
+        let event_str = event_with_cookie("../../etc");
+        let event: LambdaHttpEvent = serde_json::from_str(&event_str).unwrap();
+        assert_eq!(sessions.session(&event).id(), None);
+    }
+}
@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: MIT
+//!
+//! SnapStart hooks
+//! Runs app hooks before the snapshot is taken and after the function is restored
+//!
+use lambda_runtime::Error as LambdaError;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+/// Environment variable telling how the execution environment is initialized
+const INITIALIZATION_TYPE_ENV: &str = "AWS_LAMBDA_INITIALIZATION_TYPE";
+
+type Hook = Arc<dyn Fn() -> Result<(), LambdaError> + Send + Sync>;
+
+/// Hooks set by `RunOptions::before_snapshot` and `RunOptions::after_restore`
+#[derive(Clone, Default)]
+pub(crate) struct SnapshotHooks {
+    pub(crate) before_snapshot: Vec<Hook>,
+    pub(crate) after_restore: Vec<Hook>,
+}
+
+/// Is this execution environment initialized for a SnapStart snapshot?
+pub fn is_snap_start() -> bool {
+    std::env::var(INITIALIZATION_TYPE_ENV)
+        .map(|init_type| init_type == "snap-start")
+        .unwrap_or(false)
+}
+
+impl SnapshotHooks {
+    /// Run hooks around the snapshot, before the first invocation.
+    /// Does nothing unless initialized for SnapStart.
+    ///
+    /// The snapshot is taken while waiting for `/runtime/restore/next` of the Runtime API,
+    /// this blocks the thread as nothing else should run at that time.
+    pub(crate) fn run(&self) -> Result<(), LambdaError> {
+        if !is_snap_start() {
+            return Ok(());
+        }
+        let api = std::env::var("AWS_LAMBDA_RUNTIME_API")?;
+        self.run_with(&api)
+    }
+
+    fn run_with(&self, api: &str) -> Result<(), LambdaError> {
+        for hook in self.before_snapshot.iter() {
+            hook()?;
+        }
+        runtime_api_request(api, "GET", "/2018-06-01/runtime/restore/next", None)?;
+        for hook in self.after_restore.iter() {
+            if let Err(err) = hook() {
+                let error = serde_json::json!({
+                    "errorMessage": err.to_string(),
+                    "errorType": "Runtime.AfterRestoreError"
+                })
+                .to_string();
+                runtime_api_request(
+                    api,
+                    "POST",
+                    "/2018-06-01/runtime/restore/error",
+                    Some(&error),
+                )?;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Blocking HTTP/1.1 request to Runtime API, works without any HTTP client
+fn runtime_api_request(
+    api: &str,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+) -> Result<(), LambdaError> {
+    let host = api.split('/').next().unwrap_or(api);
+    let mut stream = TcpStream::connect(host)?;
+    let body = body.unwrap_or_default();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        host,
+        body.len(),
+        body
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status_line = response.lines().next().unwrap_or_default();
+    let success = status_line
+        .split(' ')
+        .nth(1)
+        .map(|status| status.starts_with('2'))
+        .unwrap_or(false);
+    if success {
+        Ok(())
+    } else {
+        Err(format!("Runtime API {} {} failed: {}", method, path, status_line).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    /// Runtime API answering each request with the status, returns received request lines
+    fn runtime_api(statuses: &'static [u16]) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 1024];
+                let len = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                requests.push(request.lines().next().unwrap().to_string());
+                write!(stream, "HTTP/1.1 {} X\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            }
+            requests
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_snapshot_hooks() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (before, after) = (calls.clone(), calls.clone());
+        let hooks = SnapshotHooks {
+            before_snapshot: vec![Arc::new(move || -> Result<(), LambdaError> {
+                before.lock().unwrap().push("before");
+                Ok(())
+            })],
+            after_restore: vec![Arc::new(move || -> Result<(), LambdaError> {
+                after.lock().unwrap().push("after");
+                Ok(())
+            })],
+        };
+        let (api, handle) = runtime_api(&[200]);
+        hooks.run_with(&api).unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec!["before", "after"]);
+        assert_eq!(
+            handle.join().unwrap(),
+            vec!["GET /2018-06-01/runtime/restore/next HTTP/1.1"]
+        );
+
+        // Failed after restore hook is reported
+        let hooks = SnapshotHooks {
+            before_snapshot: Vec::new(),
+            after_restore: vec![Arc::new(|| -> Result<(), LambdaError> {
+                Err("reconnect failed".into())
+            })],
+        };
+        let (api, handle) = runtime_api(&[200, 202]);
+        assert!(hooks.run_with(&api).is_err());
+        assert_eq!(
+            handle.join().unwrap()[1],
+            "POST /2018-06-01/runtime/restore/error HTTP/1.1"
+        );
+    }
+}
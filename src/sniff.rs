@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Content-Type sniffing
+//! Infers Content-Type from magic bytes of response bodies without the header
+//!
+
+/// Magic bytes of binary formats
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"\x00asm", "application/wasm"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+];
+
+/// Content-Type inferred from the body, None for empty or unknown bodies
+pub(crate) fn sniff_content_type(body: &[u8]) -> Option<&'static str> {
+    if body.is_empty() {
+        return None;
+    }
+    if let Some((_, ctype)) = SIGNATURES.iter().find(|(magic, _)| body.starts_with(magic)) {
+        return Some(ctype);
+    }
+    if body.len() >= 12 && &body[..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+
+    // Text formats, only when the whole body is UTF-8 without control characters
+    let text = std::str::from_utf8(body).ok()?;
+    if text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c'))
+    {
+        return None;
+    }
+    let head = text.trim_start_matches('\u{feff}').trim_start();
+    let head_lower = head
+        .get(..head.len().min(64))
+        .unwrap_or(head)
+        .to_ascii_lowercase();
+    if head_lower.starts_with("<!doctype html") || head_lower.starts_with("<html") {
+        Some("text/html; charset=utf-8")
+    } else if head_lower.starts_with("<svg") {
+        Some("image/svg+xml")
+    } else if head_lower.starts_with("<?xml") {
+        Some("application/xml")
+    } else if (head.starts_with('{') || head.starts_with('['))
+        && serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok()
+    {
+        Some("application/json")
+    } else {
+        Some("text/plain; charset=utf-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_content_type() {
+        assert_eq!(
+            sniff_content_type(b"\x89PNG\r\n\x1a\n\x00\x00"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_content_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(
+            sniff_content_type(b"  <!DOCTYPE html><html></html>"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            sniff_content_type(br#"{"key":"value"}"#),
+            Some("application/json")
+        );
+        assert_eq!(
+            sniff_content_type(b"{not json"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(
+            sniff_content_type("Hello, 世界\n".as_bytes()),
+            Some("text/plain; charset=utf-8")
+        );
+
+        // Unknown binary and empty
+        assert_eq!(sniff_content_type(b"\x00\x01\x02\x03"), None);
+        assert_eq!(sniff_content_type(b"\xff\xfe\xfd"), None);
+        assert_eq!(sniff_content_type(b""), None);
+    }
+}
@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MIT
+//!
+//! SNS bridge
+//! Translates SNS notifications into HTTP POST requests,
+//! so notification handling shares the same web application and middleware.
+//!
+use crate::request::{LambdaHttpEvent, SyntheticRequest};
+#[cfg(feature = "hyper")]
+use crate::response::ResponseFuture;
+#[cfg(feature = "hyper")]
+use lambda_runtime::Error as LambdaError;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Request header with the SNS message ID
+pub const SNS_MESSAGE_ID_HEADER: &str = "x-sns-message-id";
+/// Request header with the ARN of the topic
+pub const SNS_TOPIC_ARN_HEADER: &str = "x-sns-topic-arn";
+/// Request header with the subject, only when the message has one
+pub const SNS_SUBJECT_HEADER: &str = "x-sns-subject";
+
+/// Route of bridged SNS notifications, only for hyper based web frameworks
+///
+/// The message is POSTed to the path with the message as request body,
+/// and String or Number message attributes as request headers of lowercase names.
+/// SNS invokes the function asynchronously and ignores the response,
+/// so non-2xx responses fail the invocation to be retried by Lambda,
+/// then sent to the dead-letter queue or on-failure destination if configured.
+///
+/// ```
+/// use lambda_web::{RunOptions, SnsBridge};
+///
+/// let options = RunOptions::default().sns_bridge(SnsBridge::new("/notifications"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnsBridge {
+    path: String,
+    content_type: String,
+}
+
+impl SnsBridge {
+    /// POST messages to the path, as `application/json` by default
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self {
+            path: path.into(),
+            content_type: "application/json".to_string(),
+        }
+    }
+
+    /// Content-Type of messages, overridden by `content-type` message attribute
+    pub fn content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+}
+
+/// SNS event, SNS invokes functions with one record
+#[derive(Debug)]
+pub(crate) struct SnsEvent {
+    record: SnsRecord,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SnsRecord {
+    event_source: String,
+    sns: SnsNotification,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SnsNotification {
+    message_id: String,
+    topic_arn: String,
+    #[serde(default)]
+    subject: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    message_attributes: HashMap<String, SnsMessageAttribute>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SnsMessageAttribute {
+    #[serde(rename = "Type")]
+    data_type: String,
+    value: String,
+}
+
+impl<'de> Deserialize<'de> for SnsEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Records {
+            #[serde(rename = "Records")]
+            records: Vec<SnsRecord>,
+        }
+
+        let Records { mut records } = Records::deserialize(deserializer)?;
+        if records.len() != 1 || records[0].event_source != "aws:sns" {
+            return Err(serde::de::Error::custom("not an SNS event"));
+        }
+        Ok(Self {
+            record: records.remove(0),
+        })
+    }
+}
+
+impl SnsEvent {
+    /// Message ID and POST request to the bridge path
+    pub(crate) fn into_http<'a>(self, bridge: &SnsBridge) -> (String, LambdaHttpEvent<'a>) {
+        let notification = self.record.sns;
+        let mut headers: HashMap<String, String> = notification
+            .message_attributes
+            .into_iter()
+            .filter(|(_, attr)| {
+                attr.data_type.starts_with("String") || attr.data_type.starts_with("Number")
+            })
+            .filter_map(|(name, attr)| {
+                let name = name.to_ascii_lowercase();
+                let valid = !name.is_empty()
+                    && name != "host"
+                    && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-');
+                Some((name, attr.value)).filter(|_| valid)
+            })
+            .collect();
+        headers
+            .entry("content-type".to_string())
+            .or_insert_with(|| bridge.content_type.clone());
+        headers.insert(
+            SNS_MESSAGE_ID_HEADER.to_string(),
+            notification.message_id.clone(),
+        );
+        headers.insert(SNS_TOPIC_ARN_HEADER.to_string(), notification.topic_arn);
+        if let Some(subject) = notification.subject {
+            headers.insert(SNS_SUBJECT_HEADER.to_string(), subject);
+        }
+
+        let event = LambdaHttpEvent::synthetic(SyntheticRequest {
+            method: "POST".to_string(),
+            path: bridge.path.clone(),
+            headers,
+            body: notification.message,
+            source_ip: "127.0.0.1".to_string(),
+            request_id: Some(notification.message_id.clone()),
+            ..Default::default()
+        });
+        (notification.message_id, event)
+    }
+}
+
+/// SNS message translated into HTTP request
+#[cfg(feature = "hyper")]
+pub(crate) struct SnsMessage<'a> {
+    message_id: String,
+    event: Result<LambdaHttpEvent<'a>, crate::DecodeError>,
+}
+
+#[cfg(feature = "hyper")]
+impl<'a> SnsMessage<'a> {
+    pub(crate) fn new(
+        message_id: String,
+        event: Result<LambdaHttpEvent<'a>, crate::DecodeError>,
+    ) -> Self {
+        Self { message_id, event }
+    }
+
+    /// Keep the event JSON captured by `RunOptions::capture_failures` in the request
+    pub(crate) fn attach_capture(&mut self, captured: &std::sync::Arc<serde_json::Value>) {
+        crate::capture::attach(&mut self.event, captured.clone());
+    }
+
+    /// Call the web application with the request, non-2xx responses are errors
+    pub(crate) fn call<F>(self, call: F) -> ResponseFuture<LambdaError>
+    where
+        F: FnOnce(LambdaHttpEvent<'a>) -> ResponseFuture<LambdaError>,
+    {
+        let message_id = self.message_id;
+        let response = match self.event {
+            Ok(event) => call(event),
+            Err(err) => {
+                return Box::pin(futures_util::future::ready(Err(format!(
+                    "SNS message {} is not a valid request: {}",
+                    message_id, err
+                )
+                .into())))
+            }
+        };
+        Box::pin(async move {
+            let response = response.await?;
+            if (200..300).contains(&response.status) {
+                Ok(response)
+            } else {
+                Err(format!("SNS message {} failed with {}", message_id, response.status).into())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::test_consts::*;
+
+    fn sns_event(event_str: &str) -> SnsEvent {
+        match serde_json::from_str(event_str).unwrap() {
+            InvocationEvent::Sns(event) => event,
+            event => panic!("not an SNS event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_into_http() {
+        let bridge = SnsBridge::new("/notifications");
+        let (message_id, event) = sns_event(SNS_NOTIFICATION).into_http(&bridge);
+        assert_eq!(message_id, "95df01b4-ee98-5cb9-9903-4c221d41eb5e");
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/notifications");
+        assert_eq!(event.header("content-type"), Some("application/json"));
+        assert_eq!(event.header("x-tenant"), Some("acme"));
+        assert_eq!(event.header("testbinary"), None);
+        assert_eq!(
+            event.header(SNS_TOPIC_ARN_HEADER),
+            Some("arn:aws:sns:us-east-1:123456789012:sns-lambda")
+        );
+        assert_eq!(event.header(SNS_SUBJECT_HEADER), Some("order created"));
+        assert_eq!(event.body().unwrap(), br#"{"order":42}"#);
+    }
+
+    #[test]
+    fn test_detect() {
+        // SQS events are not SNS events
+        assert!(!matches!(
+            serde_json::from_str::<InvocationEvent>(SQS_TWO_MESSAGES),
+            Ok(InvocationEvent::Sns(_))
+        ));
+        let event_str = SNS_NOTIFICATION.replace("aws:sns", "aws:kinesis");
+        assert!(!matches!(
+            serde_json::from_str::<InvocationEvent>(&event_str),
+            Ok(InvocationEvent::Sns(_))
+        ));
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn test_call() {
+        use crate::response::{LambdaResponse, ResponseFormat};
+
+        let call = |status: u16| {
+            let message = SnsMessage::new(
+                "95df01b4".to_string(),
+                Ok(LambdaHttpEvent::synthetic(Default::default())),
+            );
+            let response = message.call(|_| {
+                Box::pin(futures_util::future::ready(Ok(LambdaResponse::new(
+                    status,
+                    ResponseFormat::HttpApiV2,
+                ))))
+            });
+            futures_util::FutureExt::now_or_never(response).unwrap()
+        };
+        assert!(call(202).is_ok());
+        assert_eq!(
+            call(500).unwrap_err().to_string(),
+            "SNS message 95df01b4 failed with 500"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MIT
+//!
+//! SPA mode
+//! Rewrites client side routes of single-page applications to `index.html`
+//!
+
+/// Single-page application routing, set by `RunOptions::spa_mode`
+///
+/// GET requests to paths without file extension, e.g. `/users/42`,
+/// are passed to the web framework as `/index.html`, with the query string kept.
+/// Paths under `/api` and the other excluded prefixes are not rewritten,
+/// neither are paths with extension such as `/assets/app.js`.
+///
+/// ```
+/// use lambda_web::{RunOptions, SpaMode};
+///
+/// let options = RunOptions::default().spa_mode(SpaMode::new().exclude("/auth"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaMode {
+    index: String,
+    excluded: Vec<String>,
+}
+
+impl Default for SpaMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpaMode {
+    /// Rewrite to `/index.html`, except under `/api`
+    pub fn new() -> Self {
+        Self {
+            index: "/index.html".to_string(),
+            excluded: vec!["/api".to_string()],
+        }
+    }
+
+    /// Path of the page served for client side routes, `/index.html` by default
+    pub fn index<S: Into<String>>(mut self, path: S) -> Self {
+        self.index = format!("/{}", path.into().trim_start_matches('/'));
+        self
+    }
+
+    /// Do not rewrite paths under the prefix, in addition to `/api`
+    pub fn exclude<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.excluded
+            .push(format!("/{}", prefix.into().trim_matches('/')));
+        self
+    }
+
+    pub(crate) fn index_path(&self) -> &str {
+        &self.index
+    }
+
+    /// Is the request to a client side route?
+    pub(crate) fn rewrites(&self, method: &str, path: &str) -> bool {
+        if method != "GET" || path == self.index {
+            return false;
+        }
+        let excluded = self.excluded.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .map(|rest| rest.is_empty() || rest.starts_with('/'))
+                .unwrap_or(false)
+        });
+        let last_segment = path.rsplit('/').next().unwrap_or_default();
+        !excluded && !last_segment.contains('.')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::RunOptions;
+    use crate::request::LambdaHttpEvent;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_rewrites() {
+        let spa = SpaMode::new().exclude("/auth/");
+        assert!(spa.rewrites("GET", "/"));
+        assert!(spa.rewrites("GET", "/users/42"));
+        assert!(spa.rewrites("GET", "/apis"));
+        assert!(!spa.rewrites("POST", "/users"));
+        assert!(!spa.rewrites("GET", "/api"));
+        assert!(!spa.rewrites("GET", "/api/users"));
+        assert!(!spa.rewrites("GET", "/auth/callback"));
+        assert!(!spa.rewrites("GET", "/assets/app.js"));
+        assert!(!spa.rewrites("GET", "/index.html"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let options = RunOptions::default().spa_mode(SpaMode::new().index("app.html"));
+        let mut event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_V2_GET_SOMEWHERE_NOQUERY).unwrap();
+        event.normalize(&options);
+        assert_eq!(event.path_query(), "/app.html");
+
+        let mut event: LambdaHttpEvent =
+            serde_json::from_str(API_GATEWAY_REST_GET_SOMEWHERE_NOQUERY).unwrap();
+        event.normalize(&options);
+        assert_eq!(event.path_query(), "/stage/app.html");
+    }
+}
@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: MIT
+//!
+//! SQS bridge
+//! Translates SQS messages into HTTP POST requests,
+//! so queue-driven work shares the same web application and middleware.
+//!
+use crate::request::{LambdaHttpEvent, SyntheticRequest};
+#[cfg(feature = "hyper")]
+use crate::response::{LambdaResponse, ResponseFormat};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Request header with the SQS message ID
+pub const SQS_MESSAGE_ID_HEADER: &str = "x-sqs-message-id";
+/// Request header with the ARN of the queue
+pub const SQS_QUEUE_ARN_HEADER: &str = "x-sqs-queue-arn";
+/// Request header with the approximate receive count, `1` on the first delivery
+pub const SQS_RECEIVE_COUNT_HEADER: &str = "x-sqs-receive-count";
+
+/// Route of bridged SQS messages, only for hyper based web frameworks
+///
+/// Each message is POSTed to the path with the message body as request body,
+/// and String or Number message attributes as request headers of lowercase names.
+/// Messages with non-2xx responses are reported in `batchItemFailures`,
+/// so enable `ReportBatchItemFailures` of the event source mapping to retry only them.
+/// Messages are processed one by one in order, each after the previous response.
+/// On FIFO queues, messages after a failure are reported as failures without being
+/// processed, to keep the order in message groups.
+///
+/// ```
+/// use lambda_web::{RunOptions, SqsBridge};
+///
+/// let options = RunOptions::default().sqs_bridge(SqsBridge::new("/jobs"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqsBridge {
+    path: String,
+    content_type: String,
+}
+
+impl SqsBridge {
+    /// POST messages to the path, as `application/json` by default
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self {
+            path: path.into(),
+            content_type: "application/json".to_string(),
+        }
+    }
+
+    /// Content-Type of message bodies, overridden by `content-type` message attribute
+    pub fn content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+}
+
+/// SQS event with one or more messages
+#[derive(Debug)]
+pub(crate) struct SqsEvent {
+    records: Vec<SqsRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SqsRecord {
+    message_id: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    attributes: HashMap<String, String>,
+    #[serde(default)]
+    message_attributes: HashMap<String, SqsMessageAttribute>,
+    event_source: String,
+    #[serde(rename = "eventSourceARN")]
+    event_source_arn: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SqsMessageAttribute {
+    #[serde(default)]
+    string_value: Option<String>,
+    data_type: String,
+}
+
+impl<'de> Deserialize<'de> for SqsEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Records {
+            #[serde(rename = "Records")]
+            records: Vec<SqsRecord>,
+        }
+
+        let Records { records } = Records::deserialize(deserializer)?;
+        if records.is_empty() || records.iter().any(|r| r.event_source != "aws:sqs") {
+            return Err(serde::de::Error::custom("not an SQS event"));
+        }
+        Ok(Self { records })
+    }
+}
+
+/// SQS message or stream record translated into HTTP request
+#[cfg(feature = "hyper")]
+pub(crate) struct SqsMessage<'a> {
+    message_id: String,
+    fifo: bool,
+    event: Result<LambdaHttpEvent<'a>, crate::DecodeError>,
+}
+
+impl SqsEvent {
+    /// POST requests to the bridge path, in the order of records
+    pub(crate) fn into_http<'a>(
+        self,
+        bridge: &SqsBridge,
+    ) -> Vec<(String, bool, LambdaHttpEvent<'a>)> {
+        self.records
+            .into_iter()
+            .map(|record| {
+                let mut headers: HashMap<String, String> = record
+                    .message_attributes
+                    .into_iter()
+                    .filter(|(_, attr)| {
+                        attr.data_type.starts_with("String") || attr.data_type.starts_with("Number")
+                    })
+                    .filter_map(|(name, attr)| {
+                        let name = name.to_ascii_lowercase();
+                        let valid = !name.is_empty()
+                            && name != "host"
+                            && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-');
+                        attr.string_value
+                            .filter(|_| valid)
+                            .map(|value| (name, value))
+                    })
+                    .collect();
+                headers
+                    .entry("content-type".to_string())
+                    .or_insert_with(|| bridge.content_type.clone());
+                headers.insert(SQS_MESSAGE_ID_HEADER.to_string(), record.message_id.clone());
+                headers.insert(
+                    SQS_QUEUE_ARN_HEADER.to_string(),
+                    record.event_source_arn.clone(),
+                );
+                if let Some(count) = record.attributes.get("ApproximateReceiveCount") {
+                    headers.insert(SQS_RECEIVE_COUNT_HEADER.to_string(), count.clone());
+                }
+
+                let fifo = record.event_source_arn.ends_with(".fifo");
+                let event = LambdaHttpEvent::synthetic(SyntheticRequest {
+                    method: "POST".to_string(),
+                    path: bridge.path.clone(),
+                    headers,
+                    body: record.body,
+                    source_ip: "127.0.0.1".to_string(),
+                    request_id: Some(record.message_id.clone()),
+                    ..Default::default()
+                });
+                (record.message_id, fifo, event)
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl<'a> SqsMessage<'a> {
+    pub(crate) fn new(
+        message_id: String,
+        fifo: bool,
+        event: Result<LambdaHttpEvent<'a>, crate::DecodeError>,
+    ) -> Self {
+        Self {
+            message_id,
+            fifo,
+            event,
+        }
+    }
+
+    /// Keep the event JSON captured by `RunOptions::capture_failures` in the request
+    pub(crate) fn attach_capture(&mut self, captured: &std::sync::Arc<serde_json::Value>) {
+        crate::capture::attach(&mut self.event, captured.clone());
+    }
+
+    /// Message ID, FIFO queue or not, and the request
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        String,
+        bool,
+        Result<LambdaHttpEvent<'a>, crate::DecodeError>,
+    ) {
+        (self.message_id, self.fifo, self.event)
+    }
+}
+
+/// `batchItemFailures` of bridged SQS messages or stream records
+#[cfg(feature = "hyper")]
+#[derive(Default)]
+pub(crate) struct BatchFailures {
+    failures: Vec<serde_json::Value>,
+    fifo_failed: bool,
+}
+
+#[cfg(feature = "hyper")]
+impl BatchFailures {
+    /// Report the message as failed
+    pub(crate) fn push(&mut self, message_id: String, fifo: bool) {
+        self.fifo_failed |= fifo;
+        self.failures
+            .push(serde_json::json!({ "itemIdentifier": message_id }));
+    }
+
+    /// Has a message on FIFO queue or stream failed? Messages after it are not processed.
+    pub(crate) fn fifo_failed(&self) -> bool {
+        self.fifo_failed
+    }
+
+    /// `{"batchItemFailures":[{"itemIdentifier":"..."}]}` response of failed messages
+    pub(crate) fn response(self) -> LambdaResponse {
+        let mut response = LambdaResponse::new(200, ResponseFormat::Json);
+        response.body = serde_json::json!({ "batchItemFailures": self.failures }).to_string();
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::test_consts::*;
+
+    fn sqs_event(event_str: &str) -> SqsEvent {
+        match serde_json::from_str(event_str).unwrap() {
+            InvocationEvent::Sqs(event) => event,
+            event => panic!("not an SQS event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_into_http() {
+        let bridge = SqsBridge::new("/jobs");
+        let mut events = sqs_event(SQS_TWO_MESSAGES).into_http(&bridge).into_iter();
+
+        let (message_id, fifo, event) = events.next().unwrap();
+        assert_eq!(message_id, "059f36b4-87a3-44ab-83d2-661975830a7d");
+        assert!(!fifo);
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/jobs");
+        assert_eq!(event.header("content-type"), Some("application/json"));
+        assert_eq!(event.header("x-tenant"), Some("acme"));
+        assert_eq!(event.header("priority"), Some("5"));
+        assert_eq!(event.header("blob"), None);
+        assert_eq!(event.header(SQS_RECEIVE_COUNT_HEADER), Some("1"));
+        assert_eq!(
+            event.header(SQS_QUEUE_ARN_HEADER),
+            Some("arn:aws:sqs:us-east-1:123456789012:my-queue")
+        );
+        assert_eq!(event.body().unwrap(), br#"{"job":"resize","id":1}"#);
+
+        // Content-Type by message attribute
+        let (_, _, event) = events.next().unwrap();
+        assert_eq!(event.header("content-type"), Some("text/plain"));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_detect() {
+        // Other event sources are not SQS events
+        let event_str = SQS_TWO_MESSAGES.replace("aws:sqs", "aws:kinesis");
+        assert!(!matches!(
+            serde_json::from_str::<InvocationEvent>(&event_str),
+            Ok(InvocationEvent::Sqs(_))
+        ));
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn test_batch_failures() {
+        let mut failures = BatchFailures::default();
+        failures.push("a".to_string(), false);
+        assert!(!failures.fifo_failed());
+        failures.push("b".to_string(), true);
+        assert!(failures.fifo_failed());
+
+        let body: serde_json::Value = serde_json::from_str(&failures.response().body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "batchItemFailures": [{ "itemIdentifier": "a" }, { "itemIdentifier": "b" }]
+            })
+        );
+    }
+}
@@ -0,0 +1,704 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Lambda response streaming
+//! Runtime API client supporting `Lambda-Runtime-Function-Response-Mode: streaming`,
+//! since lambda_runtime 0.7 supports only buffered responses.
+//!
+use crate::bridge::{InvocationEvent, WebEvent};
+use crate::hyper014::{hyper_request_with_options, HyperHandler, HyperRequest, HyperResponse};
+use crate::options::RunOptions;
+use crate::request::LambdaHttpEvent;
+use crate::response::{
+    finish_response, finish_streaming_response, LambdaResponse, RequestInfo, ResponseFormat,
+};
+use hyper::body::{Buf, Bytes, HttpBody};
+use lambda_runtime::{Error as LambdaError, LambdaEvent, Service as LambdaService};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Content-Type of streaming HTTP response
+const HTTP_INTEGRATION_RESPONSE: &str = "application/vnd.awslambda.http-integration-response";
+
+/// Separator between JSON prelude and response body
+const PRELUDE_SEPARATOR: [u8; 8] = [0u8; 8];
+
+/// Default largest chunk handed to Runtime API at once
+const DEFAULT_HIGH_WATER_MARK: usize = 64 * 1024;
+
+/// Environment variable telling the function URL invoke mode,
+/// set `RESPONSE_STREAM` as same as the function URL configuration.
+/// Lambda itself does not tell the invoke mode to the function.
+pub const INVOKE_MODE_ENV: &str = "LAMBDA_WEB_INVOKE_MODE";
+
+/// Response mode of hyper based web frameworks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseMode {
+    /// Buffered JSON response, default
+    Buffered,
+    /// Stream responses for Lambda function URL requests,
+    /// API Gateway and ALB requests are buffered.
+    Streaming,
+    /// Same as `Streaming` when `LAMBDA_WEB_INVOKE_MODE=RESPONSE_STREAM` environment variable is set,
+    /// otherwise same as `Buffered`.
+    Auto,
+}
+
+impl Default for ResponseMode {
+    fn default() -> Self {
+        Self::Buffered
+    }
+}
+
+impl ResponseMode {
+    /// Should the response to this request be streamed?
+    pub(crate) fn is_streaming(&self, event: &LambdaHttpEvent) -> bool {
+        event.is_function_url() && self.streams_function_urls()
+    }
+
+    /// Are function URL responses streamed in this function?
+    fn streams_function_urls(&self) -> bool {
+        match self {
+            Self::Buffered => false,
+            Self::Streaming => true,
+            Self::Auto => std::env::var(INVOKE_MODE_ENV)
+                .map(|mode| mode.eq_ignore_ascii_case("RESPONSE_STREAM"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Reject options needing the whole response body, which can not be streamed
+fn check_streaming_options(options: &RunOptions) -> Result<(), LambdaError> {
+    if !options.response_mode.streams_function_urls() {
+        return Ok(());
+    }
+    let mut unsupported = Vec::new();
+    if options.idempotency.is_some() {
+        unsupported.push("idempotency");
+    }
+    if options.body_checksum.is_some() {
+        unsupported.push("body_checksum");
+    }
+    #[cfg(feature = "decompress")]
+    if options.decompress_unsupported {
+        unsupported.push("decompress_unsupported");
+    }
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "RunOptions {} can not be used with streaming responses",
+            unsupported.join(", ")
+        )
+        .into())
+    }
+}
+
+/// Run hyper service with Lambda Runtime API client supporting response streaming
+///
+/// Events except streamed function URL requests are dispatched by `HyperHandler`,
+/// as same as `lambda_runtime`.
+pub(crate) async fn run_hyper_streaming<S, B>(
+    svc: S,
+    options: Arc<RunOptions>,
+) -> Result<(), LambdaError>
+where
+    S: hyper::service::Service<HyperRequest, Response = HyperResponse<B>> + Clone + Send + 'static,
+    S::Error: Into<LambdaError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    <B as HttpBody>::Error: std::error::Error + Send + Sync + 'static,
+{
+    check_streaming_options(&options)?;
+    let client = RuntimeClient::from_env()?;
+    let mut handler = HyperHandler::with_shared_options(svc, options.clone());
+    loop {
+        let invocation = client.next_invocation().await?;
+        let event = match serde_json::from_slice::<WebEvent>(&invocation.event) {
+            Ok(event) => event,
+            Err(err) => {
+                // Not a supported event
+                client
+                    .post_error(&invocation.request_id, "InvalidEvent", &err.to_string())
+                    .await?;
+                continue;
+            }
+        };
+
+        futures_util::future::poll_fn(|cx| handler.poll_ready(cx)).await?;
+
+        let event = match event {
+            WebEvent(InvocationEvent::Http(event), captured)
+                if options.response_mode.is_streaming(&event) =>
+            {
+                WebEvent(InvocationEvent::Http(event), captured)
+            }
+            event => {
+                // Buffered response, as same as lambda_runtime
+                let result = handler
+                    .call(LambdaEvent::new(event, invocation.context()))
+                    .await;
+                let posted = match result {
+                    Ok(response) => {
+                        client
+                            .post_response(&invocation.request_id, &response)
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = posted {
+                    // Handler error, or the response rejected by Runtime API, e.g. too large
+                    client
+                        .post_error(&invocation.request_id, "HandlerError", &err.to_string())
+                        .await?;
+                }
+                continue;
+            }
+        };
+
+        // Streaming response
+        let event = match event.into_http(&options) {
+            Ok(event) => event,
+            Err(request_err) => {
+                // Invalid header names, etc.
+                client
+                    .post_response(&invocation.request_id, &request_err.response())
+                    .await?;
+                continue;
+            }
+        };
+        if let Some(builtin) = crate::builtin::builtin_response(&event, &options) {
+            let request_info = RequestInfo::new(&event, &options);
+            let fut = async move { Ok::<_, LambdaError>(builtin.await) };
+            let response = finish_response(options.clone(), request_info, fut).await?;
+            client
+                .post_response(&invocation.request_id, &response)
+                .await?;
+            continue;
+        }
+        // The handler and the streamed body run in the invocation scope
+        let request_info = RequestInfo::new(&event, &options);
+        let service = handler.service_mut();
+        let (prelude, body) = request_info
+            .scope(call_streaming(service, event, &request_info, &options))
+            .await;
+        let high_water_mark = options
+            .streaming_high_water_mark
+            .unwrap_or(DEFAULT_HIGH_WATER_MARK);
+        let posted =
+            client.post_streaming_response(&invocation.request_id, prelude, body, high_water_mark);
+        if let Err(err) = request_info.scope(posted).await {
+            // The prelude may be already sent, the invocation can not be failed any more
+            tracing::error!("streaming response error, {}", err);
+        }
+    }
+}
+
+/// Response body to be streamed
+enum StreamingBody<B> {
+    /// Body from web framework
+    Body(B),
+    /// Newline delimited JSON body from web framework, sent line by line
+    NdJson(B),
+    /// Error message made by this crate
+    Static(&'static str),
+}
+
+/// Call hyper service, returns JSON prelude and body
+async fn call_streaming<S, B>(
+    svc: &mut S,
+    event: LambdaHttpEvent<'_>,
+    request_info: &RequestInfo,
+    options: &RunOptions,
+) -> (serde_json::Value, StreamingBody<B>)
+where
+    S: hyper::service::Service<HyperRequest, Response = HyperResponse<B>>,
+    B: HttpBody,
+{
+    let error_response = |status_code: u16| {
+        let mut response = LambdaResponse::new(status_code, ResponseFormat::HttpApiV2);
+        response.add_header("content-type", "text/plain");
+        response
+    };
+
+    let (mut response, body) = match hyper_request_with_options(event, options) {
+        Ok(req) => {
+            let early_hints = req.extensions().get::<crate::EarlyHints>().cloned();
+            #[cfg(feature = "sessions")]
+            let session = req.extensions().get::<crate::Session>().cloned();
+            if let Ok(response) = svc.call(req).await {
+                let (mut parts, body) = response.into_parts();
+                // Hints emitted until the response headers, sent with the prelude
+                if let Some(early_hints) = &early_hints {
+                    early_hints.apply(&mut parts.headers);
+                }
+                #[cfg(feature = "sessions")]
+                crate::session::commit_session(session.as_ref(), &mut parts.headers).await;
+                let ndjson = parts
+                    .headers
+                    .get(hyper::header::CONTENT_TYPE)
+                    .and_then(|ctype| ctype.to_str().ok())
+                    .map(crate::brotli::is_ndjson)
+                    .unwrap_or(false);
+                let body = if ndjson {
+                    StreamingBody::NdJson(body)
+                } else {
+                    StreamingBody::Body(body)
+                };
+                (streaming_response(&parts, options), body)
+            } else {
+                // Some hyper error -> 500 Internal Server Error
+                (
+                    error_response(500),
+                    StreamingBody::Static("Internal Server Error"),
+                )
+            }
+        }
+        Err(request_err) => {
+            // Request parsing error, 4xx by cause
+            (
+                error_response(request_err.status_code()),
+                StreamingBody::Static(request_err.reason()),
+            )
+        }
+    };
+    finish_streaming_response(&mut response, request_info, options);
+    (streaming_prelude(&response), body)
+}
+
+/// Status code and headers of streaming response, without body
+fn streaming_response(
+    parts: &hyper::http::response::Parts,
+    options: &RunOptions,
+) -> LambdaResponse {
+    let mut response = LambdaResponse::new(parts.status.as_u16(), ResponseFormat::HttpApiV2);
+    for (k, v) in parts.headers.iter() {
+        if options.is_blocked_response_header(k.as_str()) {
+            // Stripped by options
+            continue;
+        }
+        if let Ok(value_str) = v.to_str() {
+            response.add_header(k.as_str(), value_str);
+        }
+    }
+    response
+}
+
+/// JSON prelude of streaming response, contains status code and headers
+fn streaming_prelude(response: &LambdaResponse) -> serde_json::Value {
+    json!({
+        "statusCode": response.status,
+        "headers": response.headers,
+        "cookies": response.cookies
+    })
+}
+
+/// Lambda invocation received from Runtime API
+struct Invocation {
+    request_id: String,
+    /// Unix time in milliseconds
+    deadline: u64,
+    event: Bytes,
+}
+
+impl Invocation {
+    /// Invocation context passed to `HyperHandler`
+    fn context(&self) -> lambda_runtime::Context {
+        let mut context = lambda_runtime::Context::default();
+        context.request_id = self.request_id.clone();
+        context.deadline = self.deadline;
+        context
+    }
+}
+
+/// Lambda Runtime API client
+struct RuntimeClient {
+    client: hyper::Client<hyper::client::HttpConnector>,
+    endpoint: String,
+}
+
+impl RuntimeClient {
+    /// Runtime API endpoint from AWS_LAMBDA_RUNTIME_API environment variable
+    fn from_env() -> Result<Self, LambdaError> {
+        let api = std::env::var("AWS_LAMBDA_RUNTIME_API")?;
+        Ok(Self {
+            client: hyper::Client::new(),
+            endpoint: format!("http://{}/2018-06-01/runtime", api),
+        })
+    }
+
+    /// Wait for next invocation
+    async fn next_invocation(&self) -> Result<Invocation, LambdaError> {
+        let uri = format!("{}/invocation/next", self.endpoint);
+        let res = self.client.get(uri.parse()?).await?;
+
+        let request_id = res
+            .headers()
+            .get("lambda-runtime-aws-request-id")
+            .and_then(|v| v.to_str().ok())
+            .ok_or("No request ID in Runtime API response")?
+            .to_string();
+        let deadline = res
+            .headers()
+            .get("lambda-runtime-deadline-ms")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or("No deadline in Runtime API response")?;
+        if let Some(trace_id) = res
+            .headers()
+            .get("lambda-runtime-trace-id")
+            .and_then(|v| v.to_str().ok())
+        {
+            // X-Ray trace ID, as same as lambda_runtime
+            std::env::set_var("_X_AMZN_TRACE_ID", trace_id);
+        }
+        let event = hyper::body::to_bytes(res.into_body()).await?;
+
+        Ok(Invocation {
+            request_id,
+            deadline,
+            event,
+        })
+    }
+
+    /// Send buffered response
+    async fn post_response(
+        &self,
+        request_id: &str,
+        response: &LambdaResponse,
+    ) -> Result<(), LambdaError> {
+        let req = hyper::Request::post(format!(
+            "{}/invocation/{}/response",
+            self.endpoint, request_id
+        ))
+        .header("content-type", "application/json")
+        .body(hyper::Body::from(serde_json::to_vec(response)?))?;
+        accepted(self.client.request(req).await?).await
+    }
+
+    /// Send invocation error
+    async fn post_error(
+        &self,
+        request_id: &str,
+        error_type: &str,
+        message: &str,
+    ) -> Result<(), LambdaError> {
+        let error = json!({
+            "errorType": error_type,
+            "errorMessage": message
+        });
+        let req =
+            hyper::Request::post(format!("{}/invocation/{}/error", self.endpoint, request_id))
+                .header("content-type", "application/json")
+                .body(hyper::Body::from(serde_json::to_vec(&error)?))?;
+        accepted(self.client.request(req).await?).await
+    }
+
+    /// Send streaming response
+    async fn post_streaming_response<B>(
+        &self,
+        request_id: &str,
+        prelude: serde_json::Value,
+        body: StreamingBody<B>,
+        high_water_mark: usize,
+    ) -> Result<(), LambdaError>
+    where
+        B: HttpBody,
+        <B as HttpBody>::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let (mut sender, req_body) = hyper::Body::channel();
+        let req = hyper::Request::post(format!(
+            "{}/invocation/{}/response",
+            self.endpoint, request_id
+        ))
+        .header("content-type", HTTP_INTEGRATION_RESPONSE)
+        .header("lambda-runtime-function-response-mode", "streaming")
+        .header("transfer-encoding", "chunked")
+        .body(req_body)?;
+
+        // Send prelude and body while the request is in flight.
+        // send_data() waits until the runtime API accepts more data,
+        // and large chunks are split by the high-water mark,
+        // so the web framework body is not buffered in this crate.
+        let pump = async move {
+            sender
+                .send_data(Bytes::from(serde_json::to_vec(&prelude)?))
+                .await?;
+            sender
+                .send_data(Bytes::from_static(&PRELUDE_SEPARATOR))
+                .await?;
+            let (body, mut lines) = match body {
+                StreamingBody::Body(body) => (body, None),
+                StreamingBody::NdJson(body) => (body, Some(LineSplitter::default())),
+                StreamingBody::Static(text) => {
+                    sender
+                        .send_data(Bytes::from_static(text.as_bytes()))
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let mut body = Box::pin(body);
+            while let Some(chunk) = body.data().await {
+                match chunk {
+                    Ok(mut chunk) => {
+                        let bytes = chunk.copy_to_bytes(chunk.remaining());
+                        let pieces = match lines.as_mut() {
+                            Some(lines) => lines.push(bytes, high_water_mark),
+                            None => vec![bytes],
+                        };
+                        for piece in pieces {
+                            for piece in split_chunk(piece, high_water_mark) {
+                                sender.send_data(piece).await?;
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        // Abort response stream
+                        sender.abort();
+                        return Err(LambdaError::from(err));
+                    }
+                }
+            }
+            // Last line without newline
+            if let Some(rest) = lines.and_then(LineSplitter::finish) {
+                for piece in split_chunk(rest, high_water_mark) {
+                    sender.send_data(piece).await?;
+                }
+            }
+            Ok::<(), LambdaError>(())
+        };
+
+        let (res, pumped) = futures_util::future::join(self.client.request(req), pump).await;
+        accepted(res?).await?;
+        pumped
+    }
+}
+
+/// Check Runtime API accepted the response or error, with 202 Accepted
+async fn accepted(res: hyper::Response<hyper::Body>) -> Result<(), LambdaError> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = hyper::body::to_bytes(res.into_body())
+        .await
+        .unwrap_or_default();
+    Err(format!(
+        "Runtime API responded {}, {}",
+        status,
+        String::from_utf8_lossy(&body)
+    )
+    .into())
+}
+
+/// Splits newline delimited JSON chunks at line ends,
+/// so each record is sent as soon as the web framework yields it complete
+#[derive(Debug, Default)]
+struct LineSplitter {
+    partial: Vec<u8>,
+}
+
+impl LineSplitter {
+    /// Complete lines in the chunk, the incomplete last line is held until its newline.
+    /// Held lines are flushed when longer than `max_len`, not to buffer without limit.
+    fn push(&mut self, mut bytes: Bytes, max_len: usize) -> Vec<Bytes> {
+        let mut lines = Vec::new();
+        while let Some(pos) = bytes.iter().position(|b| *b == b'\n') {
+            let line = bytes.split_to(pos + 1);
+            if self.partial.is_empty() {
+                // Without copying
+                lines.push(line);
+            } else {
+                self.partial.extend_from_slice(&line);
+                lines.push(Bytes::from(std::mem::take(&mut self.partial)));
+            }
+        }
+        self.partial.extend_from_slice(&bytes);
+        if self.partial.len() >= max_len {
+            lines.push(Bytes::from(std::mem::take(&mut self.partial)));
+        }
+        lines
+    }
+
+    /// Remaining incomplete line
+    fn finish(self) -> Option<Bytes> {
+        if self.partial.is_empty() {
+            None
+        } else {
+            Some(Bytes::from(self.partial))
+        }
+    }
+}
+
+/// Split the chunk into pieces not larger than `max_len`, without copying
+fn split_chunk(mut bytes: Bytes, max_len: usize) -> impl Iterator<Item = Bytes> {
+    std::iter::from_fn(move || {
+        if bytes.is_empty() {
+            None
+        } else {
+            let len = bytes.len().min(max_len.max(1));
+            Some(bytes.split_to(len))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_consts::*;
+
+    #[test]
+    fn test_response_mode() {
+        let event: LambdaHttpEvent = serde_json::from_str(FUNCTION_URL_GET_ROOT).unwrap();
+        assert!(!ResponseMode::Buffered.is_streaming(&event));
+        assert!(ResponseMode::Streaming.is_streaming(&event));
+
+        // API Gateway is always buffered
+        let event: LambdaHttpEvent = serde_json::from_str(API_GATEWAY_V2_GET_ROOT_NOQUERY).unwrap();
+        assert!(!ResponseMode::Streaming.is_streaming(&event));
+        assert!(!ResponseMode::Auto.is_streaming(&event));
+    }
+
+    #[test]
+    fn test_streaming_prelude() {
+        let response = hyper::Response::builder()
+            .status(201)
+            .header("content-type", "text/plain")
+            .header("set-cookie", "key=value")
+            .body(())
+            .unwrap();
+        let (parts, _) = response.into_parts();
+        let prelude = streaming_prelude(&streaming_response(&parts, &RunOptions::default()));
+        assert_eq!(
+            prelude,
+            json!({
+                "statusCode": 201,
+                "headers": { "content-type": "text/plain" },
+                "cookies": ["key=value"]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streaming_post_processing() {
+        use crate::Correlation;
+
+        let mut svc = tower::service_fn(|_req: HyperRequest| async {
+            let correlated = Correlation::current().is_some();
+            Ok::<_, std::convert::Infallible>(
+                HyperResponse::builder()
+                    .header("x-correlated", correlated.to_string())
+                    .body(hyper::Body::empty())
+                    .unwrap(),
+            )
+        });
+        let options = RunOptions::default()
+            .propagate_headers(["x-request-id"])
+            .noindex_non_production(["prod"]);
+
+        // Handler runs in the request scope, headers are post-processed as buffered responses
+        let event: LambdaHttpEvent = serde_json::from_str(FUNCTION_URL_GET_ROOT).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let (prelude, _) = info
+            .scope(call_streaming(&mut svc, event, &info, &options))
+            .await;
+        assert_eq!(prelude["headers"]["x-correlated"], "true");
+        assert_eq!(prelude["headers"]["x-robots-tag"], "noindex");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_invocation_id() {
+        let mut svc = tower::service_fn(|_req: HyperRequest| async {
+            let invocation_id = crate::invocation_id().unwrap_or_default();
+            Ok::<_, std::convert::Infallible>(
+                HyperResponse::builder()
+                    .header("x-invocation-id", invocation_id)
+                    .body(hyper::Body::empty())
+                    .unwrap(),
+            )
+        });
+
+        let options = RunOptions::default();
+        let event: LambdaHttpEvent = serde_json::from_str(FUNCTION_URL_GET_ROOT).unwrap();
+        let info = RequestInfo::new(&event, &options);
+        let (prelude, _) = info
+            .scope(call_streaming(&mut svc, event, &info, &options))
+            .await;
+        assert_eq!(
+            prelude["headers"]["x-invocation-id"],
+            "01234567-89ab-cdef-0123-456789abcdef"
+        );
+        assert_eq!(crate::invocation_id(), None);
+    }
+
+    #[test]
+    fn test_check_streaming_options() {
+        let options = RunOptions::default()
+            .response_mode(ResponseMode::Streaming)
+            .body_checksum(crate::BodyChecksum::Crc32c);
+        assert!(check_streaming_options(&options).is_err());
+        assert!(check_streaming_options(&options.response_mode(ResponseMode::Buffered)).is_ok());
+    }
+
+    #[test]
+    fn test_invocation_context() {
+        let invocation = Invocation {
+            request_id: "request-1".to_string(),
+            deadline: 1_700_000_000_000,
+            event: Bytes::new(),
+        };
+        let context = invocation.context();
+        assert_eq!(context.request_id, "request-1");
+        assert_eq!(context.deadline, 1_700_000_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_accepted() {
+        let res = hyper::Response::builder()
+            .status(202)
+            .body(hyper::Body::empty())
+            .unwrap();
+        assert!(accepted(res).await.is_ok());
+
+        // e.g. response payload too large
+        let res = hyper::Response::builder()
+            .status(413)
+            .body(hyper::Body::from("RequestEntityTooLarge"))
+            .unwrap();
+        let err = accepted(res).await.unwrap_err();
+        assert!(err.to_string().contains("413"));
+        assert!(err.to_string().contains("RequestEntityTooLarge"));
+    }
+
+    #[test]
+    fn test_split_chunk() {
+        let bytes = Bytes::from(vec![7u8; 10]);
+        let pieces = split_chunk(bytes.clone(), 4).collect::<Vec<_>>();
+        assert_eq!(
+            pieces.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            vec![4, 4, 2]
+        );
+        assert_eq!(pieces.concat(), bytes.to_vec());
+
+        assert_eq!(split_chunk(bytes.clone(), 64).count(), 1);
+        assert_eq!(split_chunk(Bytes::new(), 4).count(), 0);
+    }
+
+    #[test]
+    fn test_line_splitter() {
+        let mut lines = LineSplitter::default();
+        let pieces = lines.push(Bytes::from_static(b"{\"a\":1}\n{\"b\":2}\n{\"c\""), 64);
+        assert_eq!(pieces, vec![&b"{\"a\":1}\n"[..], &b"{\"b\":2}\n"[..]]);
+
+        // Incomplete line is held until its newline
+        let pieces = lines.push(Bytes::from_static(b":3}\n{\"d\""), 64);
+        assert_eq!(pieces, vec![&b"{\"c\":3}\n"[..]]);
+        assert_eq!(lines.finish().as_deref(), Some(&b"{\"d\""[..]));
+
+        // Long incomplete line is flushed
+        let mut lines = LineSplitter::default();
+        assert_eq!(lines.push(Bytes::from(vec![b'x'; 10]), 8).len(), 1);
+        assert_eq!(lines.finish(), None);
+    }
+}
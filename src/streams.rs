@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Stream bridge
+//! Translates Kinesis and DynamoDB Streams records into HTTP POST requests,
+//! so stream processing shares the same web application and middleware.
+//!
+use crate::request::{LambdaHttpEvent, SyntheticRequest};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Request header with the partition key, Kinesis partition key or DynamoDB `Keys` JSON
+pub const STREAM_PARTITION_KEY_HEADER: &str = "x-stream-partition-key";
+/// Request header with the sequence number of the record
+pub const STREAM_SEQUENCE_NUMBER_HEADER: &str = "x-stream-sequence-number";
+/// Request header with the ARN of the stream
+pub const STREAM_SOURCE_ARN_HEADER: &str = "x-stream-source-arn";
+/// Request header with the DynamoDB event name, `INSERT`, `MODIFY` or `REMOVE`
+pub const STREAM_EVENT_NAME_HEADER: &str = "x-stream-event-name";
+
+/// Route of bridged Kinesis and DynamoDB Streams records, only for hyper based web frameworks
+///
+/// Each record is POSTed to the path. The body is the Kinesis data,
+/// or the `dynamodb` object (`Keys`, `NewImage` and `OldImage`) of DynamoDB Streams as JSON.
+/// Records are processed one by one in order, each after the previous response.
+/// The first record with non-2xx response is reported in `batchItemFailures` by its sequence
+/// number, together with the records after it, which are not processed.
+/// Enable `ReportBatchItemFailures` of the event source mapping to resume from the failure.
+///
+/// ```
+/// use lambda_web::{RunOptions, StreamBridge};
+///
+/// let options = RunOptions::default().stream_bridge(StreamBridge::new("/streams/orders"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamBridge {
+    path: String,
+    content_type: String,
+}
+
+impl StreamBridge {
+    /// POST records to the path, Kinesis data as `application/json` by default
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self {
+            path: path.into(),
+            content_type: "application/json".to_string(),
+        }
+    }
+
+    /// Content-Type of Kinesis data, DynamoDB records are always JSON
+    pub fn content_type<S: Into<String>>(mut self, content_type: S) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+}
+
+/// Kinesis or DynamoDB Streams event with one or more records
+#[derive(Debug)]
+pub(crate) struct StreamEvent {
+    records: Vec<StreamRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamRecord {
+    #[serde(rename = "eventSource")]
+    event_source: String,
+    #[serde(rename = "eventSourceARN", default)]
+    event_source_arn: Option<String>,
+    #[serde(rename = "eventName", default)]
+    event_name: Option<String>,
+    #[serde(default)]
+    kinesis: Option<KinesisData>,
+    #[serde(default)]
+    dynamodb: Option<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct KinesisData {
+    partition_key: String,
+    sequence_number: String,
+    /// Base64 encoded
+    data: String,
+}
+
+impl StreamRecord {
+    fn is_valid(&self) -> bool {
+        match self.event_source.as_str() {
+            "aws:kinesis" => self.kinesis.is_some(),
+            "aws:dynamodb" => self
+                .dynamodb
+                .as_ref()
+                .map(|dynamodb| dynamodb["SequenceNumber"].is_string())
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Records {
+            #[serde(rename = "Records")]
+            records: Vec<StreamRecord>,
+        }
+
+        let Records { records } = Records::deserialize(deserializer)?;
+        if records.is_empty() || !records.iter().all(StreamRecord::is_valid) {
+            return Err(serde::de::Error::custom("not a Kinesis or DynamoDB event"));
+        }
+        Ok(Self { records })
+    }
+}
+
+impl StreamEvent {
+    /// Sequence numbers and POST requests to the bridge path, in the order of records
+    pub(crate) fn into_http<'a>(self, bridge: &StreamBridge) -> Vec<(String, LambdaHttpEvent<'a>)> {
+        self.records
+            .into_iter()
+            .map(|record| {
+                let mut headers = HashMap::new();
+                let (partition_key, sequence_number, body, is_base64_encoded) =
+                    match (record.kinesis, record.dynamodb) {
+                        (Some(kinesis), _) => {
+                            headers.insert("content-type".to_string(), bridge.content_type.clone());
+                            (
+                                kinesis.partition_key,
+                                kinesis.sequence_number,
+                                kinesis.data,
+                                true,
+                            )
+                        }
+                        (None, dynamodb) => {
+                            let dynamodb = dynamodb.unwrap_or_default();
+                            headers
+                                .insert("content-type".to_string(), "application/json".to_string());
+                            (
+                                dynamodb["Keys"].to_string(),
+                                dynamodb["SequenceNumber"]
+                                    .as_str()
+                                    .unwrap_or_default()
+                                    .to_string(),
+                                dynamodb.to_string(),
+                                false,
+                            )
+                        }
+                    };
+                headers.insert(STREAM_PARTITION_KEY_HEADER.to_string(), partition_key);
+                headers.insert(
+                    STREAM_SEQUENCE_NUMBER_HEADER.to_string(),
+                    sequence_number.clone(),
+                );
+                if let Some(arn) = record.event_source_arn {
+                    headers.insert(STREAM_SOURCE_ARN_HEADER.to_string(), arn);
+                }
+                if record.event_source == "aws:dynamodb" {
+                    if let Some(event_name) = record.event_name {
+                        headers.insert(STREAM_EVENT_NAME_HEADER.to_string(), event_name);
+                    }
+                }
+
+                let event = LambdaHttpEvent::synthetic(SyntheticRequest {
+                    method: "POST".to_string(),
+                    path: bridge.path.clone(),
+                    headers,
+                    body: Some(body),
+                    is_base64_encoded,
+                    source_ip: "127.0.0.1".to_string(),
+                    ..Default::default()
+                });
+                (sequence_number, event)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::test_consts::*;
+
+    fn stream_event(event_str: &str) -> StreamEvent {
+        match serde_json::from_str(event_str).unwrap() {
+            InvocationEvent::Stream(event) => event,
+            event => panic!("not a stream event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_kinesis() {
+        let bridge = StreamBridge::new("/streams/orders").content_type("text/plain");
+        let mut events = stream_event(KINESIS_TWO_RECORDS)
+            .into_http(&bridge)
+            .into_iter();
+
+        let (sequence_number, event) = events.next().unwrap();
+        assert_eq!(
+            sequence_number,
+            "49590338271490256608559692538361571095921575989136588898"
+        );
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/streams/orders");
+        assert_eq!(event.header("content-type"), Some("text/plain"));
+        assert_eq!(event.header(STREAM_PARTITION_KEY_HEADER), Some("1"));
+        assert_eq!(
+            event.header(STREAM_SOURCE_ARN_HEADER),
+            Some("arn:aws:kinesis:us-east-2:123456789012:stream/lambda-stream")
+        );
+        assert_eq!(event.header(STREAM_EVENT_NAME_HEADER), None);
+        assert_eq!(event.body().unwrap(), b"Hello, this is a test.");
+        assert_eq!(events.count(), 1);
+    }
+
+    #[test]
+    fn test_dynamodb() {
+        let bridge = StreamBridge::new("/streams/table");
+        let mut events = stream_event(DYNAMODB_INSERT).into_http(&bridge).into_iter();
+
+        let (sequence_number, event) = events.next().unwrap();
+        assert_eq!(sequence_number, "111");
+        assert_eq!(event.header("content-type"), Some("application/json"));
+        assert_eq!(
+            event.header(STREAM_PARTITION_KEY_HEADER),
+            Some(r#"{"Id":{"N":"101"}}"#)
+        );
+        assert_eq!(event.header(STREAM_EVENT_NAME_HEADER), Some("INSERT"));
+        let body: Value = serde_json::from_slice(&event.body().unwrap()).unwrap();
+        assert_eq!(body["NewImage"]["Message"]["S"], "New item!");
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_detect() {
+        // SQS events are not stream events
+        assert!(!matches!(
+            serde_json::from_str::<InvocationEvent>(SQS_TWO_MESSAGES),
+            Ok(InvocationEvent::Stream(_))
+        ));
+        // Kinesis records without data
+        let event_str = KINESIS_TWO_RECORDS.replace(r#""kinesis":"#, r#""other":"#);
+        assert!(!matches!(
+            serde_json::from_str::<InvocationEvent>(&event_str),
+            Ok(InvocationEvent::Stream(_))
+        ));
+    }
+}
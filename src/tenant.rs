@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Multi-tenant routing
+//! Tenant parsed from the subdomain of the Host name, e.g. `{tenant}.api.example.com`
+//!
+
+/// Tenant of the request, parsed from the subdomain by `TenantRouting`
+///
+/// Attached to hyper and Actix-web request extensions,
+/// and to Rocket request local cache as `Option<Tenant>`.
+/// Tenant names are lowercase, only with `a-z`, `0-9` and `-`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tenant(String);
+
+impl Tenant {
+    /// Tenant name, the subdomain label
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Tenant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Subdomain based tenant routing, set by `RunOptions::tenant_routing`
+///
+/// Requests to other domains, or to the domain itself, have no tenant.
+///
+/// ```
+/// use lambda_web::{RunOptions, TenantRouting};
+///
+/// // "acme.api.example.com/items" is passed to the app as "/t/acme/items"
+/// let options = RunOptions::default()
+///     .tenant_routing(TenantRouting::subdomain_of("api.example.com").rewrite_path("/t"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantRouting {
+    domain: String,
+    path_prefix: Option<String>,
+}
+
+impl TenantRouting {
+    /// Tenants are subdomains of this domain, e.g. "api.example.com"
+    pub fn subdomain_of<S: Into<String>>(domain: S) -> Self {
+        Self {
+            domain: domain.into().trim_matches('.').to_ascii_lowercase(),
+            path_prefix: None,
+        }
+    }
+
+    /// Rewrite request paths to `{prefix}/{tenant}/...`, e.g. "/t"
+    pub fn rewrite_path<S: Into<String>>(mut self, prefix: S) -> Self {
+        let prefix = prefix.into();
+        self.path_prefix = Some(format!("/{}", prefix.trim_matches('/')));
+        self
+    }
+
+    /// Tenant of the Host name, None unless a subdomain of the domain
+    pub(crate) fn tenant(&self, hostname: &str) -> Option<Tenant> {
+        let hostname = hostname.split(':').next().unwrap_or_default();
+        let hostname = hostname.trim_end_matches('.').to_ascii_lowercase();
+        let label = hostname
+            .strip_suffix(self.domain.as_str())?
+            .strip_suffix('.')?;
+        let valid = !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+        if valid {
+            Some(Tenant(label.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Path prefix to be inserted for the Host name, None without path rewriting or tenant
+    pub(crate) fn path_prefix(&self, hostname: &str) -> Option<String> {
+        let prefix = self.path_prefix.as_ref()?;
+        let tenant = self.tenant(hostname)?;
+        Some(format!("{}/{}", prefix.trim_end_matches('/'), tenant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant() {
+        let routing = TenantRouting::subdomain_of("api.example.com");
+        let name = |hostname| routing.tenant(hostname).map(|t| t.name().to_string());
+
+        assert_eq!(name("acme.api.example.com").as_deref(), Some("acme"));
+        assert_eq!(name("ACME.Api.Example.com:443").as_deref(), Some("acme"));
+        assert_eq!(
+            name("tenant-1.api.example.com.").as_deref(),
+            Some("tenant-1")
+        );
+
+        // Not a tenant
+        assert_eq!(name("api.example.com"), None);
+        assert_eq!(name("acme.example.com"), None);
+        assert_eq!(name("a.b.api.example.com"), None);
+        assert_eq!(name("-acme.api.example.com"), None);
+        assert_eq!(name("acmeapi.example.com"), None);
+    }
+
+    #[test]
+    fn test_path_prefix() {
+        let routing = TenantRouting::subdomain_of("api.example.com");
+        assert_eq!(routing.path_prefix("acme.api.example.com"), None);
+
+        let routing = routing.rewrite_path("/t/");
+        assert_eq!(
+            routing.path_prefix("acme.api.example.com").as_deref(),
+            Some("/t/acme")
+        );
+        assert_eq!(routing.path_prefix("api.example.com"), None);
+
+        // Tenant directly under the root
+        let routing = TenantRouting::subdomain_of("api.example.com").rewrite_path("/");
+        assert_eq!(
+            routing.path_prefix("acme.api.example.com").as_deref(),
+            Some("/acme")
+        );
+    }
+}
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: MIT
+//!
+//! WebSocket API
+//! Translates API Gateway WebSocket events into HTTP requests by their route keys,
+//! so REST-ish routers handle `$connect`, `$disconnect` and custom routes.
+//!
+use crate::request::{DecodeError, LambdaHttpEvent, SyntheticRequest};
+use crate::response::LambdaResponse;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Request header with the WebSocket connection ID, to post messages back to the client
+pub const WEBSOCKET_CONNECTION_ID_HEADER: &str = "x-websocket-connection-id";
+/// Request header with the route key, e.g. `$connect` or `sendmessage`
+pub const WEBSOCKET_ROUTE_KEY_HEADER: &str = "x-websocket-route-key";
+/// Request header with the event type, `CONNECT`, `MESSAGE` or `DISCONNECT`
+pub const WEBSOCKET_EVENT_TYPE_HEADER: &str = "x-websocket-event-type";
+
+/// HTTP method and path of each WebSocket route key
+///
+/// Route keys are selected by the route selection expression of the API,
+/// e.g. `$request.body.action`, so custom action-based routes are mapped as well.
+/// Events of route keys without mapping are rejected.
+/// The message body is the request body, and the response body goes back to the client
+/// when the route has a route response.
+///
+/// ```
+/// use lambda_web::{RunOptions, WebSocketRoutes};
+///
+/// let routes = WebSocketRoutes::new()
+///     .route("$connect", "POST", "/ws/connections")
+///     .route("$disconnect", "DELETE", "/ws/connections")
+///     .route("sendmessage", "POST", "/ws/messages")
+///     .route("$default", "POST", "/ws/unknown");
+/// let options = RunOptions::default().websocket_routes(routes);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebSocketRoutes {
+    routes: Vec<WebSocketRoute>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WebSocketRoute {
+    route_key: String,
+    method: String,
+    path: String,
+}
+
+impl WebSocketRoutes {
+    /// Routes without mapping, no events are accepted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map the route key to HTTP method and path, the path may have a query string
+    pub fn route<K, M, P>(mut self, route_key: K, method: M, path: P) -> Self
+    where
+        K: Into<String>,
+        M: Into<String>,
+        P: Into<String>,
+    {
+        self.routes.push(WebSocketRoute {
+            route_key: route_key.into(),
+            method: method.into().to_ascii_uppercase(),
+            path: path.into(),
+        });
+        self
+    }
+
+    fn find(&self, route_key: &str) -> Option<&WebSocketRoute> {
+        self.routes
+            .iter()
+            .find(|route| route.route_key == route_key)
+    }
+}
+
+/// API Gateway WebSocket event
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebSocketEvent {
+    request_context: WebSocketRequestContext,
+    /// Only in `$connect` events
+    #[serde(default)]
+    headers: Option<HashMap<String, String>>,
+    /// Only in `$connect` events
+    #[serde(default)]
+    query_string_parameters: Option<HashMap<String, String>>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    is_base64_encoded: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct WebSocketRequestContext {
+    route_key: String,
+    event_type: String,
+    connection_id: String,
+    domain_name: String,
+    stage: Option<String>,
+    request_id: Option<String>,
+    #[serde(default)]
+    identity: Option<WebSocketIdentity>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct WebSocketIdentity {
+    source_ip: Option<String>,
+}
+
+impl WebSocketEvent {
+    /// HTTP request mapped by the route key
+    pub(crate) fn into_http<'a>(
+        self,
+        routes: Option<&WebSocketRoutes>,
+    ) -> Result<LambdaHttpEvent<'a>, DecodeError> {
+        let context = self.request_context;
+        let route = routes
+            .and_then(|routes| routes.find(&context.route_key))
+            .ok_or_else(|| {
+                DecodeError::Malformed(format!(
+                    "no route for WebSocket route key {}",
+                    context.route_key
+                ))
+            })?;
+
+        let mut headers: HashMap<String, String> = self
+            .headers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            // Responses are WebSocket messages, not compressed by this crate
+            .filter(|(name, _)| name != "accept-encoding")
+            .collect();
+        headers.insert("host".to_string(), context.domain_name);
+        headers.insert(
+            WEBSOCKET_CONNECTION_ID_HEADER.to_string(),
+            context.connection_id,
+        );
+        headers.insert(WEBSOCKET_ROUTE_KEY_HEADER.to_string(), context.route_key);
+        headers.insert(WEBSOCKET_EVENT_TYPE_HEADER.to_string(), context.event_type);
+
+        let (path, route_query) = match route.path.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (route.path.as_str(), ""),
+        };
+        let mut query = route_query.to_string();
+        let mut params: Vec<_> = self
+            .query_string_parameters
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        params.sort();
+        for (k, v) in params {
+            if !query.is_empty() {
+                query.push('&');
+            }
+            query.push_str(&format!(
+                "{}={}",
+                percent_encoding::utf8_percent_encode(&k, percent_encoding::NON_ALPHANUMERIC),
+                percent_encoding::utf8_percent_encode(&v, percent_encoding::NON_ALPHANUMERIC)
+            ));
+        }
+
+        Ok(LambdaHttpEvent::synthetic(SyntheticRequest {
+            method: route.method.clone(),
+            path: path.to_string(),
+            query,
+            headers,
+            body: self.body,
+            is_base64_encoded: self.is_base64_encoded.unwrap_or(false),
+            source_ip: context
+                .identity
+                .and_then(|identity| identity.source_ip)
+                .unwrap_or_else(|| "127.0.0.1".to_string()),
+            request_id: context.request_id,
+            stage: context.stage,
+            websocket: true,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Text body for WebSocket messages, binary bodies stay in base64
+pub(crate) fn finish_websocket_response(response: &mut LambdaResponse) {
+    if !response.is_base64 {
+        return;
+    }
+    if let Some(text) = base64::decode(&response.body)
+        .ok()
+        .and_then(|body| String::from_utf8(body).ok())
+    {
+        response.body = text;
+        response.is_base64 = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::InvocationEvent;
+    use crate::response::ResponseFormat;
+    use crate::test_consts::*;
+
+    fn routes() -> WebSocketRoutes {
+        WebSocketRoutes::new()
+            .route("$connect", "post", "/ws/connections?from=connect")
+            .route("sendmessage", "POST", "/ws/messages")
+    }
+
+    fn websocket_event(event_str: &str) -> WebSocketEvent {
+        match serde_json::from_str(event_str).unwrap() {
+            InvocationEvent::WebSocket(event) => event,
+            event => panic!("not a WebSocket event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_connect() {
+        let event = websocket_event(WEBSOCKET_CONNECT)
+            .into_http(Some(&routes()))
+            .unwrap();
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/ws/connections?from=connect&token=abc");
+        assert_eq!(
+            event.hostname(),
+            Some("abcdef1234.execute-api.ap-northeast-1.amazonaws.com")
+        );
+        assert_eq!(
+            event.header(WEBSOCKET_CONNECTION_ID_HEADER),
+            Some("L0SM9cOFvHcCIhw=")
+        );
+        assert_eq!(event.header(WEBSOCKET_EVENT_TYPE_HEADER), Some("CONNECT"));
+        assert_eq!(event.header("sec-websocket-version"), Some("13"));
+        assert!(event.is_websocket());
+    }
+
+    #[test]
+    fn test_message() {
+        let event = websocket_event(WEBSOCKET_MESSAGE)
+            .into_http(Some(&routes()))
+            .unwrap();
+        assert_eq!(event.method(), "POST");
+        assert_eq!(event.path_query(), "/ws/messages");
+        assert_eq!(
+            event.header(WEBSOCKET_ROUTE_KEY_HEADER),
+            Some("sendmessage")
+        );
+        assert_eq!(
+            event.body().unwrap(),
+            br#"{"action":"sendmessage","data":"hello"}"#
+        );
+
+        // Route key without mapping
+        let event = websocket_event(WEBSOCKET_MESSAGE);
+        assert!(event.into_http(Some(&WebSocketRoutes::new())).is_err());
+    }
+
+    #[test]
+    fn test_finish_websocket_response() {
+        let mut response = LambdaResponse::new(200, ResponseFormat::HttpApiV2);
+        response.body = base64::encode("hello");
+        response.is_base64 = true;
+        finish_websocket_response(&mut response);
+        assert_eq!(response.body, "hello");
+        assert!(!response.is_base64);
+
+        // Binary body
+        response.body = base64::encode([0xffu8, 0xfe]);
+        response.is_base64 = true;
+        finish_websocket_response(&mut response);
+        assert!(response.is_base64);
+    }
+}